@@ -26,6 +26,8 @@ use bitcoin::secp256k1::rand::thread_rng;
 use bitcoin::secp256k1::rand::RngCore;
 
 use clap::Parser;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use farcaster_node::Error;
 use farcaster_node::ServiceConfig;
@@ -62,8 +64,22 @@ fn main() -> Result<(), Error> {
         Err(e) => warn!("Failed to set new session id: {}", e),
     };
 
+    // the supervisor forwards the reload requested on SIGHUP to farcasterd
+    let reload_requested = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&reload_requested);
+    // SAFETY: the handler only stores an atomic flag, which is async-signal-safe
+    let sighup = unsafe {
+        signal_hook_registry::register(nix::libc::SIGHUP, move || {
+            flag.store(true, Ordering::SeqCst)
+        })
+    };
+    if let Err(e) = sighup {
+        warn!("Failed to install the SIGHUP handler: {}", e);
+    }
+
     debug!("Starting runtime ...");
-    farcasterd::run(service_config, config, opts, token).expect("Error running farcasterd runtime");
+    farcasterd::run(service_config, config, opts, token, reload_requested)
+        .expect("Error running farcasterd runtime");
 
     unreachable!()
 }
@@ -38,7 +38,15 @@ fn main() {
     debug!("CTL RPC socket {}", &service_config.ctl_endpoint);
 
     debug!("Starting runtime ...");
-    grpcd::run(service_config, opts.grpc_port, opts.grpc_ip).expect("Error running grpcd runtime");
+    let data_dir = opts.absolute_data_dir_path();
+    grpcd::run(
+        service_config,
+        opts.grpc_port,
+        opts.grpc_ip,
+        data_dir,
+        !opts.no_auth,
+    )
+    .expect("Error running grpcd runtime");
 
     unreachable!()
 }
@@ -33,7 +33,7 @@
 //! The first one will open TCP socket in listening mode and wait for incoming
 //! connections, forking on each one of them, passing the accepted TCP socket to
 //! the child and continuing on listening to the new connections. (In
-//! multi-thread mode, differentiated with `--threaded` argument, instead of
+//! multi-thread mode, differentiated with `--use-threads` argument, instead of
 //! forking damon will launch a new thread).
 //!
 //! The second one will be launched by some control process and then commanded
@@ -55,7 +55,8 @@
 //!   forking itself.
 //! - If `--listen` argument is present, start a listening version as described
 //!   above and open TCP port in listening mode; wait for incoming connections
-//! - If `--connect` argument is present, connect to the remote TCP peer
+//! - If `--connect` argument is present, connect to the remote TCP peer, through
+//!   the SOCKS5 proxy given with `--tor-proxy` if any
 //!
 //! In forked/spawned version:
 //! - Acquire connected TCP socket from the parent
@@ -76,7 +77,8 @@
 //! --------
 //!
 //! Node key, used for node identification and in generation of the encryption
-//! keys, is read from the file specified in `--peer-secret-key` parameter.
+//! keys, is given with the `--peer-secret-key` parameter, handed over by farcasterd on stdin
+//! to keep it out of the process command line.
 
 #[macro_use]
 extern crate log;
@@ -85,7 +87,9 @@ extern crate amplify_derive;
 
 use clap::Parser;
 use internet2::addr::InetSocketAddr;
-use nix::unistd::{fork, ForkResult};
+use nix::sys::wait::{waitpid, WaitPidFlag};
+use nix::unistd::{fork, ForkResult, Pid};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::net::TcpListener;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
@@ -104,7 +108,8 @@ use microservices::peer::PeerConnection;
 pub enum PeerSocket {
     /// The service should listen for incoming connections on a certain
     /// TCP socket, which may be IPv4- or IPv6-based. For Tor hidden services
-    /// use IPv4 TCP port proxied as a Tor hidden service in `torrc`.
+    /// use IPv4 TCP port proxied as a Tor hidden service, either in `torrc` or
+    /// by farcasterd through the Tor control port.
     #[display("--listen={0}")]
     Listen(InetSocketAddr),
 
@@ -134,7 +139,7 @@ impl From<Opts> for PeerSocket {
 }
 
 fn main() {
-    let mut opts = Opts::parse();
+    let mut opts = Opts::parse_from(farcaster_node::opts::service_args());
     trace!("Command-line arguments: {:?}", &opts);
     opts.process();
     trace!("Processed arguments: {:?}", &opts);
@@ -145,6 +150,8 @@ fn main() {
     debug!("CTL RPC socket {}", &service_config.ctl_endpoint);
 
     let local_node = opts.peer_key_opts.local_node();
+    let local_init = opts.local_init();
+    let limits = opts.peer_limits();
     info!(
         "{}: {}, {}: {}",
         "Local node id".bright_green_bold(),
@@ -164,10 +171,26 @@ fn main() {
 
             local_socket = Some(inet_addr);
 
+            if opts.use_threads {
+                debug!("Spawning a thread for each incoming connection");
+                peerd::run_threaded_listener(
+                    service_config,
+                    inet_addr,
+                    local_node,
+                    local_init,
+                    limits,
+                    Default::default(),
+                )
+                .expect("Error running peerd listener");
+                return;
+            }
+
             debug!("Binding TCP socket {}", inet_addr);
             if let Ok(listener) = TcpListener::bind(
                 SocketAddr::try_from(inet_addr).expect("Tor is not yet supported"),
             ) {
+                // remote IP of the connection handled by every running child
+                let mut children: HashMap<Pid, IpAddr> = HashMap::new();
                 debug!("Running TCP listener event loop");
                 loop {
                     debug!("Awaiting for incoming connections...");
@@ -176,32 +199,43 @@ fn main() {
                         .expect("Error accepting incoming peer connection");
                     debug!("New connection from {}", remote_socket_addr);
 
-                    // TODO: Support multithread mode
-                    debug!("Forking child process");
-                    if let ForkResult::Child =
-                        unsafe { fork().expect("Unable to fork child process") }
-                    {
-                        stream
-                            .set_read_timeout(Some(Duration::from_secs(30)))
-                            .expect("Unable to set up timeout for TCP connection");
-
-                        debug!("Establishing session with the remote");
-                        let session = session::BrontozaurSession::with(
-                            stream,
-                            local_node.private_key(),
-                            inet_addr,
-                        )
-                        .expect("Unable to establish session with the remote peer");
-
-                        debug!(
-                            "Session successfully established with {}",
-                            remote_socket_addr
+                    reap_children(&mut children);
+                    let remote_ip = remote_socket_addr.ip();
+                    let connections = children.values().filter(|ip| **ip == remote_ip).count();
+                    if connections as u32 >= limits.max_connections_per_ip {
+                        warn!(
+                            "Refusing connection from {}: more than {} connections from this IP",
+                            remote_socket_addr, limits.max_connections_per_ip
                         );
-
-                        break PeerConnection::with(session);
+                        continue;
                     }
+
+                    debug!("Forking child process");
+                    let child = match unsafe { fork().expect("Unable to fork child process") } {
+                        ForkResult::Parent { child } => child,
+                        ForkResult::Child => {
+                            stream
+                                .set_read_timeout(Some(Duration::from_secs(30)))
+                                .expect("Unable to set up timeout for TCP connection");
+
+                            debug!("Establishing session with the remote");
+                            let session = session::BrontozaurSession::with(
+                                stream,
+                                local_node.private_key(),
+                                inet_addr,
+                            )
+                            .expect("Unable to establish session with the remote peer");
+
+                            debug!(
+                                "Session successfully established with {}",
+                                remote_socket_addr
+                            );
+
+                            break PeerConnection::with(session);
+                        }
+                    };
+                    children.insert(child, remote_ip);
                     debug!("Child forked; returning into main listener event loop");
-                    continue;
                 }
             } else {
                 error!("Unable to bind to {} socket", inet_addr.red_bold());
@@ -211,8 +245,16 @@ fn main() {
         PeerSocket::Connect(remote_node) => {
             debug!("Peerd running in CONNECT mode");
             debug!("Connecting to {}", &remote_node.addr());
-            peerd::run_from_connect(service_config, remote_node, local_socket, local_node)
-                .expect("Error running peerd runtime");
+            peerd::run_from_connect(
+                service_config,
+                remote_node,
+                opts.shared.tor_proxy,
+                local_socket,
+                local_node,
+                local_init,
+                limits,
+            )
+            .expect("Error running peerd runtime");
             unreachable!()
         }
     };
@@ -234,8 +276,23 @@ fn main() {
         remote_node_addr,
         local_socket,
         local_node,
+        local_init,
+        limits,
     )
     .expect("Error running peerd runtime");
 
     unreachable!()
 }
+
+/// Forget the children whose connection ended, reaping the terminated processes
+fn reap_children(children: &mut HashMap<Pid, IpAddr>) {
+    while let Ok(status) = waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+        match status.pid() {
+            Some(pid) => {
+                children.remove(&pid);
+            }
+            // no more terminated child
+            None => break,
+        }
+    }
+}
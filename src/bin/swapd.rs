@@ -27,7 +27,7 @@ use farcaster_node::swapd::{self, Opts};
 use farcaster_node::ServiceConfig;
 
 fn main() {
-    let mut opts = Opts::parse();
+    let mut opts = Opts::parse_from(farcaster_node::opts::service_args());
     trace!("Command-line arguments: {:?}", &opts);
     opts.process();
     trace!("Processed arguments: {:?}", &opts);
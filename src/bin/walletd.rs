@@ -23,14 +23,14 @@ extern crate log;
 
 use clap::Parser;
 
-use farcaster_node::ServiceConfig;
 use farcaster_node::{
     bus::ctl::Token,
     walletd::{self, NodeSecrets, Opts},
 };
+use farcaster_node::{Error, ServiceConfig};
 
-fn main() {
-    let mut opts = Opts::parse();
+fn main() -> Result<(), Error> {
+    let mut opts = Opts::parse_from(farcaster_node::opts::service_args());
     trace!("Command-line arguments: {:?}", &opts);
     opts.process();
     trace!("Processed arguments: {:?}", &opts);
@@ -42,11 +42,25 @@ fn main() {
 
     let wallet_token = Token(opts.wallet_token.token);
 
-    let node_secrets = NodeSecrets::new(opts.key_opts.key_file.clone());
+    let passphrase = opts.passphrase()?;
+    let node_secrets = if opts.restore_from_mnemonic {
+        let mnemonic = walletd::read_mnemonic()?;
+        let node_secrets =
+            NodeSecrets::restore(opts.key_opts.key_file.clone(), &mnemonic, passphrase)?;
+        info!("Key file {} restored from mnemonic", opts.key_opts.key_file);
+        node_secrets
+    } else {
+        NodeSecrets::new(opts.key_opts.key_file.clone(), passphrase)?
+    };
 
     debug!("Starting runtime ...");
-    walletd::run(service_config, wallet_token, node_secrets)
-        .expect("Error running walletd runtime");
+    walletd::run(
+        service_config,
+        wallet_token,
+        node_secrets,
+        opts.hot_wallet_network,
+    )
+    .expect("Error running walletd runtime");
 
     unreachable!()
 }
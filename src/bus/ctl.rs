@@ -7,6 +7,7 @@
 use std::fmt::{self, Debug};
 use std::io;
 use std::str::FromStr;
+use std::time::Duration;
 
 use farcaster_core::blockchain::Network;
 use farcaster_core::consensus::{self, Decodable, Encodable};
@@ -23,10 +24,14 @@ use bitcoin::Transaction;
 use internet2::addr::{InetSocketAddr, NodeAddr};
 use strict_encoding::{NetworkDecode, NetworkEncode};
 
-use crate::bus::p2p::{PeerMsg, TakerCommit};
+use crate::bus::p2p::{PeerMsg, Receipt, TakerCommit};
 use crate::bus::{
     AddressSecretKey, CheckpointEntry, DealInfo, Failure, OptionDetails, Outcome, Progress,
+    SwapParams,
 };
+use crate::farcasterd::metrics::SyncerMetrics;
+use crate::farcasterd::stats::StatsEvent;
+use crate::grpcd::auth::Permission;
 use crate::swapd::CheckpointSwapd;
 use crate::syncerd::{Health, SweepAddressAddendum};
 use crate::{Error, ServiceId};
@@ -151,6 +156,14 @@ pub enum CtlMsg {
     #[display("set_deal_history({0})")]
     SetDealInfo(DealInfo),
 
+    /// Records a swap event in the persistent statistics of databased
+    #[display("record_stats({0})")]
+    RecordStats(StatsEvent),
+
+    /// Reports the height, task count and RPC latency of a syncer to farcasterd
+    #[display("syncer_metrics({0})")]
+    SyncerMetrics(SyncerMetrics),
+
     #[display("keys({0})")]
     Keys(Keys),
 
@@ -173,14 +186,109 @@ pub enum CtlMsg {
     #[display("failed peer message")]
     FailedPeerMessage(PeerMsg),
 
+    /// A message sent from swapd to databased to keep an outgoing protocol message until the
+    /// counterparty acknowledges it
+    #[display("store_peer_message({0})")]
+    StorePeerMessage(PeerMsg),
+
+    /// A message sent from peerd to databased once the counterparty acknowledged a protocol
+    /// message, the message is not resent anymore
+    #[display("peer_message_acknowledged({0})")]
+    PeerMessageAcknowledged(Receipt),
+
+    /// A message sent from swapd to databased with the id of a protocol message received from the
+    /// counterparty, used to drop the messages resent after a restore
+    #[display("peer_message_received({0})")]
+    PeerMessageReceived(Receipt),
+
     #[display("connect failed")]
     ConnectFailed,
 
+    /// A message sent from farcaster to databased to update the peer address book on a
+    /// connection or a swap with the peer
+    #[display("peer_seen({0})")]
+    PeerSeen(PeerSeen),
+
+    /// A message sent from the farcasterd supervisor to farcasterd once the delay before the
+    /// next reconnection attempt to a counterparty of a restored swap elapsed
+    #[display("reconnect_peer({0})")]
+    ReconnectPeer(NodeId),
+
+    /// A message sent from peerd to farcasterd with the number of violations of the peer limits
+    /// by the remote since the last report
+    #[display("peer_limit_violations({0})")]
+    PeerLimitViolations(u32),
+
+    /// A message sent from peerd to farcasterd with the round-trip time of a ping answered by
+    /// the remote
+    #[display("peer_round_trip({0:?})")]
+    PeerRoundTrip(Duration),
+
     #[display("health_check({0} {1})")]
     HealthCheck(Blockchain, Network),
 
     #[display("health_result({0})")]
     HealthResult(Health),
+
+    /// A message sent from a client to farcaster to stop the node, draining the running swaps
+    /// first if set.
+    #[display("shutdown(drain: {0})")]
+    Shutdown(bool),
+
+    /// A message sent from a client or the SIGHUP handler to farcaster to re-read its
+    /// configuration file and apply the settings that can change at runtime.
+    #[display("reload_config()")]
+    ReloadConfig,
+
+    /// A message sent from farcaster to swapd to be notified once the swap reached a safe point
+    /// to be terminated at.
+    #[display("drain()")]
+    Drain,
+
+    /// A message sent from swapd to farcaster once drained: the swap is checkpointed, has no
+    /// broadcast in flight and stops progressing until terminated.
+    #[display("drained()")]
+    Drained,
+
+    /// A message sent from the farcasterd supervisor to farcasterd once the swaps took too long
+    /// to drain.
+    #[display("drain_timeout()")]
+    DrainTimeout,
+
+    /// A message sent from the farcasterd supervisor to farcasterd when a supervised process
+    /// exited.
+    #[display("service_exited({0})")]
+    ServiceExited(ServiceExit),
+
+    /// A message sent from farcaster to walletd once the Bitcoin syncer of the hot wallet is up,
+    /// walletd (re)subscribes to the hot wallet addresses and transactions.
+    #[display("hot_wallet_syncer({0})")]
+    HotWalletSyncer(ServiceId),
+
+    /// A message sent from farcaster to walletd to fund a swap from the hot wallet.
+    #[display("fund_from_hot_wallet({0})")]
+    FundFromHotWallet(BitcoinFundingInfo),
+
+    /// A message sent from walletd to farcaster when a swap could not be funded from the hot
+    /// wallet.
+    #[display("hot_wallet_funding_failed({0}, {1})")]
+    HotWalletFundingFailed(SwapId, String),
+
+    /// A message sent from a client to walletd to send funds from the hot wallet.
+    #[display("hot_wallet_send({0})")]
+    HotWalletSend(HotWalletSend),
+
+    /// A message sent from a client to walletd to encrypt the key file with a new passphrase.
+    #[display("change_key_passphrase(..)")]
+    ChangeKeyPassphrase(ChangeKeyPassphrase),
+
+    /// A message sent from a client to grpcd to issue a new token granting the permissions.
+    #[display("create_grpc_token(..)")]
+    CreateGrpcToken(Vec<Permission>),
+
+    /// A message sent from a client to grpcd to revoke the token with the identifier.
+    #[display("revoke_grpc_token({0})")]
+    RevokeGrpcToken(String),
 }
 
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
@@ -198,6 +306,7 @@ pub struct ProtoDeal {
     pub public_addr: InetSocketAddr,
     pub arbitrating_addr: bitcoin::Address,
     pub accordant_addr: monero::Address,
+    pub swap_params: SwapParams,
 }
 
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
@@ -206,8 +315,32 @@ pub struct PubDeal {
     pub deal: Deal,
     pub bitcoin_address: bitcoin::Address,
     pub monero_address: monero::Address,
+    pub swap_params: SwapParams,
 }
 
+#[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{amount} to {address}")]
+pub struct HotWalletSend {
+    pub address: bitcoin::Address,
+    pub amount: bitcoin::Amount,
+}
+
+/// Passphrases are not shown in logs
+#[derive(Clone, Display, NetworkEncode, NetworkDecode)]
+#[display("..")]
+pub struct ChangeKeyPassphrase {
+    /// Current passphrase, none if the key file is not encrypted yet
+    pub current: Option<String>,
+    pub new: String,
+}
+
+impl std::fmt::Debug for ChangeKeyPassphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ChangeKeyPassphrase(..)")
+    }
+}
+
+/// A peer to connect to and the node identity to connect with, the node identity if none
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
 #[display("{0}, ..")]
 pub struct ReconnectPeer(pub NodeAddr, pub Option<SecretKey>);
@@ -220,11 +353,33 @@ pub struct Token(pub String);
 #[display("token({0})")]
 pub struct GetKeys(pub Token);
 
+#[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{name} with PID {pid} exited with {status}")]
+pub struct ServiceExit {
+    pub name: String,
+    pub service: Option<ServiceId>,
+    pub pid: u32,
+    pub status: String,
+    pub crashed: bool,
+    pub relaunching: bool,
+}
+
+#[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{node_id}")]
+pub struct PeerSeen {
+    pub node_id: NodeId,
+    /// Address the peer was reached at, None for incoming connections
+    pub address: Option<InetSocketAddr>,
+    /// Swap started or restored with the peer
+    pub swap_id: Option<SwapId>,
+}
+
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
 #[display("{deal}, ..")]
 pub struct SwapKeys {
     pub key_manager: WrappedKeyManager,
     pub deal: Deal,
+    pub sweep_bitcoin_address: Option<bitcoin::Address>,
 }
 
 #[derive(Clone, Debug)]
@@ -258,6 +413,7 @@ pub struct InitTakerSwap {
     pub key_manager: WrappedKeyManager,
     pub target_bitcoin_address: bitcoin::Address,
     pub target_monero_address: monero::Address,
+    pub sweep_bitcoin_address: Option<bitcoin::Address>,
 }
 
 #[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
@@ -269,6 +425,7 @@ pub struct InitMakerSwap {
     pub key_manager: WrappedKeyManager,
     pub target_bitcoin_address: bitcoin::Address,
     pub target_monero_address: monero::Address,
+    pub sweep_bitcoin_address: Option<bitcoin::Address>,
     pub commit: Commit,
 }
 
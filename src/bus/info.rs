@@ -10,22 +10,32 @@ use std::time::Duration;
 use amplify::ToYamlString;
 use farcaster_core::role::{SwapRole, TradeRole};
 use farcaster_core::trade::DealId;
-use farcaster_core::{blockchain::Blockchain, swap::btcxmr::Deal, swap::SwapId};
+use farcaster_core::{
+    blockchain::{Blockchain, Network},
+    swap::btcxmr::Deal,
+    swap::SwapId,
+};
 use internet2::addr::{InetSocketAddr, NodeAddr, NodeId};
 #[cfg(feature = "serde")]
-use serde_with::{DisplayFromStr, DurationSeconds};
+use serde_with::{DisplayFromStr, DurationMilliSeconds, DurationSeconds};
 use strict_encoding::{NetworkDecode, NetworkEncode};
 
 use crate::bus::{
-    AddressSecretKey, CheckpointEntry, DealInfo, Failure, List, OptionDetails, Progress,
+    AddressSecretKey, CheckpointEntry, DealInfo, Failure, List, OptionDetails, Outcome, Progress,
 };
 use crate::cli::DealSelector;
-use crate::farcasterd::stats::Stats;
+use crate::farcasterd::acceptance_policy::AcceptancePolicyReport;
+use crate::farcasterd::deal_validation::DealValidationReport;
+use crate::farcasterd::stats::{Stats, StatsBucket, StatsQuery};
+use crate::farcasterd::supervisor::CrashReport;
+use crate::grpcd::auth::Permission;
 use crate::swapd::StateReport;
 use crate::syncerd::runtime::SyncerdTask;
+use crate::syncerd::Health;
 use crate::Error;
 
 use super::ctl::FundingInfo;
+use super::p2p::{FeatureSet, PeerMsg};
 use super::StateTransition;
 
 #[derive(Clone, Debug, Display, From, NetworkEncode, NetworkDecode)]
@@ -40,6 +50,11 @@ pub enum InfoMsg {
     #[display("list_peers()")]
     ListPeers,
 
+    /// Forwarded by farcasterd to the peerd connected with the node address, which replies with
+    /// a PeerInfo message
+    #[display("get_peer_info({0})")]
+    GetPeerInfo(NodeAddr),
+
     #[display("list_swaps()")]
     ListSwaps,
 
@@ -67,6 +82,35 @@ pub enum InfoMsg {
     #[display("get_checkpoint_entry({0})")]
     GetCheckpointEntry(SwapId),
 
+    /// Returns the address book entry of a peer, a PeerEntry message
+    #[display("get_peer_entry({0})")]
+    GetPeerEntry(NodeId),
+
+    /// Returns the protocol messages of a swap kept by databased, a PeerMessages message
+    #[display("get_peer_messages({0})")]
+    GetPeerMessages(SwapId),
+
+    #[display("get_acceptance_policy()")]
+    GetAcceptancePolicy,
+
+    #[display("validate_deal({0})")]
+    ValidateDeal(Deal),
+
+    #[display("get_hot_wallet_balance()")]
+    GetHotWalletBalance,
+
+    #[display("get_hot_wallet_address()")]
+    GetHotWalletAddress,
+
+    #[display("export_mnemonic({0})")]
+    ExportMnemonic(ExportMnemonic),
+
+    #[display("get_stats({0})")]
+    GetStats(StatsQuery),
+
+    #[display("list_grpc_tokens()")]
+    ListGrpcTokens,
+
     // Progress functionalities
     // ----------------
     // Returns a SwapProgress message
@@ -82,6 +126,18 @@ pub enum InfoMsg {
     #[display("unsubscribe_progress({0})")]
     UnsubscribeProgress(SwapId),
 
+    // Node events functionalities
+    // ----------------
+    // Pushes a NodeEvent message on every node event until unsubscribed
+    #[display("subscribe_events()")]
+    SubscribeEvents,
+
+    #[display("unsubscribe_events()")]
+    UnsubscribeEvents,
+
+    #[display(inner)]
+    NodeEvent(NodeEvent),
+
     #[display(inner)]
     Progress(Progress),
 
@@ -174,11 +230,73 @@ pub enum InfoMsg {
     #[display("checkpoint_entry({0})")]
     CheckpointEntry(CheckpointEntry),
     // - End GetCheckpointEntry section
+    #[display("peer_entry({0})")]
+    PeerEntry(PeerEntry),
+
+    #[display("peer_messages({0})")]
+    PeerMessages(PeerMessages),
+
     #[display("{0}")]
     FundingInfos(FundingInfos),
 
     #[display("{0}")]
     AddressBalance(AddressBalance),
+
+    #[display("{0}")]
+    AcceptancePolicy(AcceptancePolicyReport),
+
+    #[display("{0}")]
+    DealValidationReport(DealValidationReport),
+
+    #[display(inner)]
+    StatsList(List<StatsBucket>),
+
+    #[display("{0}")]
+    GrpcToken(GrpcToken),
+
+    #[display(inner)]
+    GrpcTokenList(List<GrpcToken>),
+
+    #[display("{0}")]
+    HotWalletBalance(HotWalletBalance),
+
+    #[display("{0}")]
+    HotWalletAddress(bitcoin::Address),
+
+    #[display("{0}")]
+    WalletMnemonic(WalletMnemonic),
+}
+
+/// Event of the node pushed by farcasterd to the clients subscribed with `SubscribeEvents`
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+pub enum NodeEvent {
+    #[display("deal_made({0})")]
+    DealMade(Deal),
+
+    #[display("funding_required({0})")]
+    FundingRequired(FundingInfo),
+
+    #[display("swap_started({0})")]
+    SwapStarted(SwapId),
+
+    #[display("swap_ended({0}, {1})")]
+    SwapEnded(SwapId, Outcome),
+
+    #[display("peer_connected({0})")]
+    PeerConnected(NodeAddr),
+
+    #[display("peer_disconnected({0})")]
+    PeerDisconnected(NodeAddr),
+
+    #[display("syncer_connected({0}, {1})")]
+    SyncerConnected(Blockchain, Network),
+
+    #[display("syncer_disconnected({0}, {1})")]
+    SyncerDisconnected(Blockchain, Network),
+
+    /// Result of a health check differing from the previous one of the syncer
+    #[display("syncer_health({0}, {1}, {2})")]
+    SyncerHealth(Blockchain, Network, Health),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
@@ -267,6 +385,7 @@ pub struct NodeInfo {
     pub deals: Vec<Deal>,
     #[serde(alias = "statistics")]
     pub stats: Stats,
+    pub crashes: Vec<CrashReport>,
 }
 
 #[cfg_attr(feature = "serde", serde_as)]
@@ -287,10 +406,33 @@ pub struct PeerInfo {
     #[serde_as(as = "DurationSeconds")]
     pub uptime: Duration,
     pub since: u64,
+    /// Time since the connection was established or last re-established
+    #[serde_as(as = "DurationSeconds")]
+    pub connection_uptime: Duration,
+    /// Number of times a connecting peerd re-established the connection, a listening peerd is
+    /// replaced by a new one when the remote reconnects
+    pub reconnects: u32,
     pub messages_sent: usize,
     pub messages_received: usize,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Round-trip time of the last ping answered by the remote, None until a pong is received
+    #[serde_as(as = "Option<DurationMilliSeconds>")]
+    pub rtt: Option<Duration>,
+    /// Average round-trip time of the pings answered by the remote
+    #[serde_as(as = "Option<DurationMilliSeconds>")]
+    pub average_rtt: Option<Duration>,
+    /// Violations of the peer limits by the remote, the offending messages are dropped
+    pub limit_violations: u64,
     pub forked_from_listener: bool,
     pub awaits_pong: bool,
+    /// Peer protocol version of the remote, None until the handshake completed
+    pub protocol_version: Option<u16>,
+    /// Features supported by both nodes
+    #[serde_as(as = "DisplayFromStr")]
+    pub features: FeatureSet,
+    /// Networks the remote swaps on
+    pub networks: Vec<Network>,
 }
 
 #[cfg_attr(feature = "serde", serde_as)]
@@ -361,6 +503,16 @@ pub enum ProgressEvent {
     Failure(Failure),
 }
 
+impl From<Progress> for ProgressEvent {
+    fn from(p: Progress) -> ProgressEvent {
+        match p {
+            Progress::Message(m) => ProgressEvent::Message(m),
+            Progress::StateUpdate(su) => ProgressEvent::StateUpdate(su),
+            Progress::StateTransition(st) => ProgressEvent::StateTransition(st),
+        }
+    }
+}
+
 #[cfg_attr(feature = "serde", serde_as)]
 #[derive(Eq, PartialEq, Clone, Debug, Display, Hash, NetworkDecode, NetworkEncode)]
 #[cfg_attr(
@@ -402,6 +554,102 @@ pub struct AddressBalance {
     pub balance: u64,
 }
 
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Eq, PartialEq, Clone, Debug, Display, NetworkDecode, NetworkEncode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(GrpcToken::to_yaml_string)]
+pub struct GrpcToken {
+    pub id: String,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub permissions: Vec<Permission>,
+    /// The bearer token, only given on creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Eq, PartialEq, Clone, Debug, Display, NetworkDecode, NetworkEncode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(HotWalletBalance::to_yaml_string)]
+pub struct HotWalletBalance {
+    #[serde_as(as = "DisplayFromStr")]
+    pub network: Network,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub confirmed: bitcoin::Amount,
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub unconfirmed: bitcoin::Amount,
+    pub utxos: u32,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Display, NetworkDecode, NetworkEncode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(WalletMnemonic::to_yaml_string)]
+pub struct WalletMnemonic {
+    pub mnemonic: String,
+    /// Whether restoring from the mnemonic keeps the node id, false for key files created
+    /// before mnemonics were introduced
+    pub node_id_restorable: bool,
+}
+
+/// Passphrases are not shown in logs
+#[derive(Clone, Display, NetworkEncode, NetworkDecode)]
+#[display("..")]
+pub struct ExportMnemonic {
+    /// Key file passphrase, none if the key file is not encrypted
+    pub passphrase: Option<String>,
+}
+
+impl std::fmt::Debug for ExportMnemonic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ExportMnemonic(..)")
+    }
+}
+
+/// Entry of the peer address book kept by databased
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(PeerEntry::to_yaml_string)]
+pub struct PeerEntry {
+    pub node_id: NodeId,
+    /// Last known addresses of the peer, the most recent first. Empty for peers which only
+    /// connected to us
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub addresses: Vec<InetSocketAddr>,
+    /// Unix timestamp of the last connection with the peer
+    pub last_seen: i64,
+    /// Swaps run with the peer
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub swaps: Vec<SwapId>,
+}
+
+/// Protocol messages of a swap kept by databased
+#[derive(Clone, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{swap_id}")]
+pub struct PeerMessages {
+    pub swap_id: SwapId,
+    /// Messages sent to the counterparty and not acknowledged yet
+    pub outbox: Vec<PeerMsg>,
+    /// Types of the messages received from the counterparty
+    pub received: Vec<internet2::TypeId>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Display, NetworkEncode, NetworkDecode)]
 pub enum DealStatusSelector {
     #[display("Open")]
@@ -478,3 +726,11 @@ impl ToYamlString for ProgressEvent {}
 impl ToYamlString for FundingInfos {}
 #[cfg(feature = "serde")]
 impl ToYamlString for AddressBalance {}
+#[cfg(feature = "serde")]
+impl ToYamlString for HotWalletBalance {}
+#[cfg(feature = "serde")]
+impl ToYamlString for GrpcToken {}
+#[cfg(feature = "serde")]
+impl ToYamlString for WalletMnemonic {}
+#[cfg(feature = "serde")]
+impl ToYamlString for PeerEntry {}
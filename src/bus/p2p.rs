@@ -4,7 +4,10 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::str::FromStr;
+
 use farcaster_core::{
+    blockchain::Network,
     protocol::message::Abort,
     swap::btcxmr::message::{
         BuyProcedureSignature, CommitAliceParameters, CommitBobParameters, CoreArbitratingSetup,
@@ -13,7 +16,7 @@ use farcaster_core::{
     swap::btcxmr::Deal,
     swap::SwapId,
 };
-use internet2::Api;
+use internet2::{Api, TypedEnum};
 use strict_encoding::{StrictDecode, StrictEncode};
 
 #[derive(Clone, Debug, Display, Api, StrictDecode, StrictEncode)]
@@ -33,6 +36,10 @@ pub enum PeerMsg {
     #[display("{0} deal not found")]
     DealNotFound(SwapId),
 
+    #[api(type = 33705)]
+    #[display(inner)]
+    DealRefused(DealRefused),
+
     #[api(type = 33704)]
     #[display("reveal {0}")]
     Reveal(Reveal),
@@ -76,6 +83,14 @@ pub enum PeerMsg {
     #[api(type = 33802)]
     #[display("msg_receipt {0}")]
     MsgReceipt(Receipt),
+
+    #[api(type = 33803)]
+    #[display("init({0})")]
+    Init(Init),
+
+    #[api(type = 33804)]
+    #[display("limit_violations({0})")]
+    LimitViolations(u32),
 }
 
 impl PeerMsg {
@@ -84,6 +99,7 @@ impl PeerMsg {
             PeerMsg::MakerCommit(c) => c.swap_id(),
             PeerMsg::TakerCommit(c) => c.swap_id(),
             PeerMsg::DealNotFound(swap_id) => *swap_id,
+            PeerMsg::DealRefused(DealRefused { swap_id, .. }) => *swap_id,
             PeerMsg::Reveal(r) => r.swap_id(),
             PeerMsg::RefundProcedureSignatures(RefundProcedureSignatures { swap_id, .. }) => {
                 *swap_id
@@ -96,14 +112,25 @@ impl PeerMsg {
             | PeerMsg::Pong(_)
             | PeerMsg::PingPeer
             | PeerMsg::PeerReceiverRuntimeShutdown
-            | PeerMsg::Identity(_) => {
+            | PeerMsg::Identity(_)
+            | PeerMsg::Init(_)
+            | PeerMsg::LimitViolations(_) => {
                 unreachable!(
-                    "Ping, Pong, PingPeer, PeerdShutdown and Identity do not contain swapid"
+                    "Ping, Pong, PingPeer, PeerdShutdown, Identity, Init and LimitViolations do not contain swapid"
                 )
             }
         }
     }
 
+    /// Identifier of a protocol message, carried by the receipt of the remote. A swap sends every
+    /// protocol message type at most once, so a resent message keeps its identifier.
+    pub fn msg_id(&self) -> Receipt {
+        Receipt {
+            swap_id: self.swap_id(),
+            msg_type: self.get_type(),
+        }
+    }
+
     pub fn on_receiver_whitelist(&self) -> bool {
         matches!(
             self,
@@ -117,6 +144,7 @@ impl PeerMsg {
                 | PeerMsg::Pong(_)
                 | PeerMsg::MsgReceipt(_)
                 | PeerMsg::DealNotFound(_)
+                | PeerMsg::DealRefused(_)
         )
     }
 
@@ -130,6 +158,7 @@ impl PeerMsg {
                 | PeerMsg::CoreArbitratingSetup(_)
                 | PeerMsg::BuyProcedureSignature(_)
                 | PeerMsg::DealNotFound(_)
+                | PeerMsg::DealRefused(_)
         )
     }
 }
@@ -181,9 +210,206 @@ impl Commit {
     }
 }
 
+/// Sent by a maker to a taker whose commit does not satisfy the maker acceptance policy
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
-#[display("receipt")]
+#[display("{swap_id} deal refused: {reason}")]
+pub struct DealRefused {
+    pub swap_id: SwapId,
+    pub reason: RefusalReason,
+}
+
+/// Reason given by a maker for refusing a taker commit
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+pub enum RefusalReason {
+    #[display("node id denied")]
+    Denied,
+    #[display("node id not allowed")]
+    NotAllowed,
+    #[display("outside of trading hours")]
+    OutsideTradingHours,
+    #[display("too many concurrent swaps")]
+    MaxConcurrentSwaps,
+    #[display("too many concurrent swaps with this peer")]
+    MaxConcurrentSwapsPerPeer,
+    #[display("bitcoin exposure limit reached")]
+    BitcoinExposure,
+    #[display("monero exposure limit reached")]
+    MoneroExposure,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[display("{swap_id}:{msg_type}")]
 pub struct Receipt {
     pub swap_id: SwapId,
     pub msg_type: internet2::TypeId,
 }
+
+/// Version of the peer protocol, increased on every change of the peer messages breaking the
+/// compatibility with the previous version
+pub const PROTOCOL_VERSION: u16 = 1;
+/// Oldest version of the peer protocol this node can still swap with
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// Optional capabilities of a node, announced in the handshake
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+pub enum Feature {
+    #[display("fee_bumping")]
+    FeeBumping,
+    #[display("deal_gossip")]
+    DealGossip,
+    #[display("watchtowers")]
+    Watchtowers,
+    #[display("receipts")]
+    Receipts,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 4] = [
+        Feature::FeeBumping,
+        Feature::DealGossip,
+        Feature::Watchtowers,
+        Feature::Receipts,
+    ];
+
+    fn bit(self) -> u64 {
+        match self {
+            Feature::FeeBumping => 1 << 0,
+            Feature::DealGossip => 1 << 1,
+            Feature::Watchtowers => 1 << 2,
+            Feature::Receipts => 1 << 3,
+        }
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Feature::ALL
+            .iter()
+            .copied()
+            .find(|feature| feature.to_string() == s)
+            .ok_or_else(|| format!("Unknown feature {}", s))
+    }
+}
+
+/// Features supported by this node
+pub const LOCAL_FEATURES: [Feature; 1] = [Feature::Receipts];
+/// Features the remote peer must support, messages are re-sent until their receipt is received
+pub const REQUIRED_FEATURES: [Feature; 1] = [Feature::Receipts];
+
+/// A set of features encoded as bits, the bits unknown to this node are preserved
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, StrictEncode, StrictDecode)]
+pub struct FeatureSet(u64);
+
+impl FeatureSet {
+    pub fn with(features: &[Feature]) -> Self {
+        FeatureSet(
+            features
+                .iter()
+                .fold(0, |bits, feature| bits | feature.bit()),
+        )
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.0 & feature.bit() != 0
+    }
+
+    /// Features supported by both sets
+    pub fn intersection(&self, other: &FeatureSet) -> FeatureSet {
+        FeatureSet(self.0 & other.0)
+    }
+
+    /// Known features of the set
+    pub fn features(&self) -> Vec<Feature> {
+        Feature::ALL
+            .iter()
+            .copied()
+            .filter(|feature| self.supports(*feature))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for FeatureSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let features: Vec<String> = self.features().iter().map(Feature::to_string).collect();
+        if features.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", features.join(","))
+        }
+    }
+}
+
+impl FromStr for FeatureSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(FeatureSet::default());
+        }
+        let features = s
+            .split(',')
+            .map(Feature::from_str)
+            .collect::<Result<Vec<Feature>, String>>()?;
+        Ok(FeatureSet::with(&features))
+    }
+}
+
+/// Sent by both nodes right after the taker identity, a node closes the connection if the remote
+/// is not compatible before any deal is taken
+#[derive(Clone, PartialEq, Eq, Debug, Display, StrictEncode, StrictDecode)]
+#[display("v{protocol_version}, features {features}")]
+pub struct Init {
+    pub protocol_version: u16,
+    pub min_protocol_version: u16,
+    pub features: FeatureSet,
+    /// Networks the node swaps on
+    pub networks: Vec<Network>,
+}
+
+impl Init {
+    /// The handshake data of this node
+    pub fn local(networks: Vec<Network>) -> Self {
+        Init {
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            features: FeatureSet::with(&LOCAL_FEATURES),
+            networks,
+        }
+    }
+
+    /// Check the remote handshake data against ours, returns the features supported by both
+    /// nodes or the reason of the incompatibility
+    pub fn negotiate(&self, remote: &Init) -> Result<FeatureSet, String> {
+        if remote.protocol_version < self.min_protocol_version {
+            return Err(format!(
+                "remote protocol version {} is older than the minimum supported version {}",
+                remote.protocol_version, self.min_protocol_version
+            ));
+        }
+        if self.protocol_version < remote.min_protocol_version {
+            return Err(format!(
+                "remote requires protocol version {} or newer, local version is {}",
+                remote.min_protocol_version, self.protocol_version
+            ));
+        }
+        if let Some(feature) = REQUIRED_FEATURES
+            .iter()
+            .find(|feature| !remote.features.supports(**feature))
+        {
+            return Err(format!("remote does not support the {} feature", feature));
+        }
+        if !self
+            .networks
+            .iter()
+            .any(|network| remote.networks.contains(network))
+        {
+            return Err(format!(
+                "no common network, remote swaps on {:?}",
+                remote.networks
+            ));
+        }
+        Ok(self.features.intersection(&remote.features))
+    }
+}
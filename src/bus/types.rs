@@ -19,12 +19,12 @@ use amplify::{ToYamlString, Wrapper};
 use internet2::addr::NodeId;
 use microservices::rpc;
 use serde_with::DisplayFromStr;
-use strict_encoding::{NetworkDecode, NetworkEncode};
+use strict_encoding::{NetworkDecode, NetworkEncode, StrictDecode};
 
 use crate::swapd::StateReport;
 use crate::syncerd::Health;
 
-#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode)]
 #[display("{swap_id}, {deal}")]
 #[cfg_attr(
     feature = "serde",
@@ -37,8 +37,46 @@ pub struct CheckpointEntry {
     pub deal: Deal,
     pub trade_role: TradeRole,
     pub expected_counterparty_node_id: Option<NodeId>,
+    pub swap_params: SwapParams,
 }
 
+// Entries checkpointed before per-swap parameters were introduced use the swap configuration
+impl StrictDecode for CheckpointEntry {
+    fn strict_decode<D: std::io::Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
+        Ok(CheckpointEntry {
+            swap_id: StrictDecode::strict_decode(&mut d)?,
+            deal: StrictDecode::strict_decode(&mut d)?,
+            trade_role: StrictDecode::strict_decode(&mut d)?,
+            expected_counterparty_node_id: StrictDecode::strict_decode(&mut d)?,
+            swap_params: SwapParams::strict_decode(&mut d).unwrap_or_default(),
+        })
+    }
+}
+
+/// Per-swap finality and safety parameters, unset values fall back on the swap configuration
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(SwapParams::to_yaml_string)]
+pub struct SwapParams {
+    pub arb_finality: Option<u8>,
+    pub arb_safety: Option<u8>,
+    pub acc_finality: Option<u8>,
+}
+
+impl SwapParams {
+    /// Returns true if no parameter is overridden
+    pub fn is_empty(&self) -> bool {
+        self.arb_finality.is_none() && self.arb_safety.is_none() && self.acc_finality.is_none()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ToYamlString for SwapParams {}
+
 #[derive(Clone, Debug, Display, Eq, PartialEq, Hash, NetworkDecode, NetworkEncode)]
 #[cfg_attr(
     feature = "serde",
@@ -117,6 +155,8 @@ pub struct DealInfo {
     pub serialized_deal: String,
     pub status: DealStatus,
     pub local_trade_role: TradeRole,
+    /// Whether the deal was made with a node id derived for it instead of the node identity
+    pub ephemeral_node_id: bool,
 }
 
 #[cfg(feature = "serde")]
@@ -24,15 +24,16 @@ use farcaster_core::{
 use super::Command;
 use crate::bus::{
     ctl::{self, CtlMsg},
-    info::{Address, InfoMsg},
+    info::{self, Address, InfoMsg},
     AddressSecretKey,
 };
 use crate::bus::{
     BusMsg, CompleteHealthReport, DefaultHealthReport, Failure, FailureCode, HealthCheckSelector,
-    ReducedHealthReport,
+    ReducedHealthReport, SwapParams,
 };
-use crate::cli::opts::CheckpointSelector;
+use crate::cli::opts::{CheckpointSelector, GrpcTokenCommand, WalletCommand};
 use crate::client::Client;
+use crate::farcasterd::stats::StatsQuery;
 use crate::syncerd::{Health, SweepAddressAddendum, SweepBitcoinAddress, SweepMoneroAddress};
 use crate::{Error, LogStyle, ServiceId};
 
@@ -57,8 +58,11 @@ impl Exec for Command {
                     1 => {
                         let subj = subject.get(0).expect("vec of lenght 1");
                         if let Ok(node_addr) = NodeAddr::from_str(subj) {
-                            runtime
-                                .request_info(ServiceId::Peer(0, node_addr), InfoMsg::GetInfo)?;
+                            // farcasterd forwards the request to the peerd with this address
+                            runtime.request_info(
+                                ServiceId::Farcasterd,
+                                InfoMsg::GetPeerInfo(node_addr),
+                            )?;
                             ServiceId::Peer(0, node_addr)
                         } else if let Ok(swap_id) = Uuid::from_str(subj).map(SwapId) {
                             runtime.request_info(ServiceId::Swap(swap_id), InfoMsg::GetInfo)?;
@@ -274,6 +278,9 @@ impl Exec for Command {
                 maker_role,
                 public_ip_addr,
                 public_port,
+                arb_finality,
+                arb_safety,
+                acc_finality,
             } => {
                 let deal_parameters = DealParameters {
                     uuid: Uuid::new().into(),
@@ -293,6 +300,11 @@ impl Exec for Command {
                     public_addr,
                     arbitrating_addr,
                     accordant_addr,
+                    swap_params: SwapParams {
+                        arb_finality,
+                        arb_safety,
+                        acc_finality,
+                    },
                 };
                 runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::MakeDeal(proto_deal))?;
                 // report success or failure of the request to cli
@@ -309,6 +321,9 @@ impl Exec for Command {
                 bitcoin_address,
                 monero_address,
                 without_validation,
+                arb_finality,
+                arb_safety,
+                acc_finality,
             } => {
                 let Deal {
                     node_id,
@@ -322,6 +337,20 @@ impl Exec for Command {
                     );
                     println!("Trade counterparty: {}@{}\n", &node_id, peer_address);
                     println!("{}", serde_yaml::to_string(&deal).expect("already parsed"));
+                    // run the configured deal validation policy and show the report
+                    runtime
+                        .request_info(ServiceId::Farcasterd, InfoMsg::ValidateDeal(deal.clone()))?;
+                    if let BusMsg::Info(InfoMsg::DealValidationReport(report)) =
+                        runtime.report_failure()?
+                    {
+                        println!("Validation report:\n{}", report);
+                        if !report.passed() {
+                            println!(
+                                "{}",
+                                "Some validation checks failed, review the deal carefully!".err()
+                            );
+                        }
+                    }
                 }
                 if without_validation || take_deal() {
                     // pass deal to farcasterd to initiate the swap
@@ -331,6 +360,11 @@ impl Exec for Command {
                             deal,
                             bitcoin_address,
                             monero_address,
+                            swap_params: SwapParams {
+                                arb_finality,
+                                arb_safety,
+                                acc_finality,
+                            },
                         }),
                     )?;
                     // report success of failure of the request to cli
@@ -455,6 +489,89 @@ impl Exec for Command {
                 }
             }
 
+            Command::PeerEntry { node_id } => {
+                runtime.request_info(ServiceId::Database, InfoMsg::GetPeerEntry(node_id))?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::Stats { since, group_by } => {
+                runtime.request_info(
+                    ServiceId::Database,
+                    InfoMsg::GetStats(StatsQuery { since, group_by }),
+                )?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::Shutdown { drain } => {
+                runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::Shutdown(drain))?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::ReloadConfig => {
+                runtime.request_ctl(ServiceId::Farcasterd, CtlMsg::ReloadConfig)?;
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::Wallet { command } => {
+                match command {
+                    WalletCommand::Balance => {
+                        runtime.request_info(ServiceId::Wallet, InfoMsg::GetHotWalletBalance)?
+                    }
+                    WalletCommand::Receive => {
+                        runtime.request_info(ServiceId::Wallet, InfoMsg::GetHotWalletAddress)?
+                    }
+                    WalletCommand::ExportMnemonic => {
+                        let passphrase = rpassword::prompt_password(
+                            "Key file passphrase (empty if the key file is not encrypted): ",
+                        )?;
+                        runtime.request_info(
+                            ServiceId::Wallet,
+                            InfoMsg::ExportMnemonic(info::ExportMnemonic {
+                                passphrase: Some(passphrase)
+                                    .filter(|passphrase| !passphrase.is_empty()),
+                            }),
+                        )?
+                    }
+                    WalletCommand::ChangePassphrase => {
+                        let current = rpassword::prompt_password(
+                            "Current passphrase (empty if the key file is not encrypted): ",
+                        )?;
+                        let new = rpassword::prompt_password("New passphrase: ")?;
+                        if rpassword::prompt_password("Repeat the new passphrase: ")? != new {
+                            return Err(Error::Farcaster(
+                                "The new passphrases do not match".to_string(),
+                            ));
+                        }
+                        runtime.request_ctl(
+                            ServiceId::Wallet,
+                            CtlMsg::ChangeKeyPassphrase(ctl::ChangeKeyPassphrase {
+                                current: Some(current).filter(|current| !current.is_empty()),
+                                new,
+                            }),
+                        )?
+                    }
+                    WalletCommand::Send { address, amount } => runtime.request_ctl(
+                        ServiceId::Wallet,
+                        CtlMsg::HotWalletSend(ctl::HotWalletSend { address, amount }),
+                    )?,
+                }
+                runtime.report_response_or_fail()?;
+            }
+
+            Command::GrpcToken { command } => {
+                match command {
+                    GrpcTokenCommand::Create { permissions } => runtime
+                        .request_ctl(ServiceId::Grpcd, CtlMsg::CreateGrpcToken(permissions))?,
+                    GrpcTokenCommand::List => {
+                        runtime.request_info(ServiceId::Grpcd, InfoMsg::ListGrpcTokens)?
+                    }
+                    GrpcTokenCommand::Revoke { id } => {
+                        runtime.request_ctl(ServiceId::Grpcd, CtlMsg::RevokeGrpcToken(id))?
+                    }
+                }
+                runtime.report_response_or_fail()?;
+            }
+
             Command::Completion { shell } => {
                 let mut app = super::Opts::command();
                 let name = app.get_name().to_string();
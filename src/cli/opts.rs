@@ -6,6 +6,7 @@
 
 use bitcoin::Address as BtcAddress;
 use clap_complete::shells::Shell;
+use internet2::addr::NodeId;
 use monero::Address as XmrAddress;
 use std::net::IpAddr;
 use std::str::FromStr;
@@ -19,6 +20,8 @@ use farcaster_core::{
 
 use crate::bus::info::Address;
 use crate::bus::HealthCheckSelector;
+use crate::farcasterd::stats::{StatsDay, StatsGrouping};
+use crate::grpcd::auth::Permission;
 
 /// Command-line tool for working with Farcaster node
 #[derive(Parser, Clone, PartialEq, Eq, Debug)]
@@ -211,6 +214,20 @@ pub enum Command {
         /// {farcasterd.bind_ip}:{farcasterd.bind_port}
         #[clap(short = 'p', long, default_value = "7067")]
         public_port: u16,
+
+        /// Override the number of confirmations required to consider an arbitrating transaction
+        /// final for this swap.
+        #[clap(long)]
+        arb_finality: Option<u8>,
+
+        /// Override the arbitrating safety margin, in blocks, used for this swap.
+        #[clap(long)]
+        arb_safety: Option<u8>,
+
+        /// Override the number of confirmations required to consider an accordant transaction
+        /// final for this swap.
+        #[clap(long)]
+        acc_finality: Option<u8>,
     },
 
     /// Taker accepts deal and connects to maker's daemon to start the trade.
@@ -227,9 +244,23 @@ pub enum Command {
         #[clap(short = 'D', long = "deal")]
         deal: Deal,
 
-        /// Accept the deal without validation.
+        /// Accept the deal without showing the deal and its validation report.
         #[clap(short, long)]
         without_validation: bool,
+
+        /// Override the number of confirmations required to consider an arbitrating transaction
+        /// final for this swap.
+        #[clap(long)]
+        arb_finality: Option<u8>,
+
+        /// Override the arbitrating safety margin, in blocks, used for this swap.
+        #[clap(long)]
+        arb_safety: Option<u8>,
+
+        /// Override the number of confirmations required to consider an accordant transaction
+        /// final for this swap.
+        #[clap(long)]
+        acc_finality: Option<u8>,
     },
 
     /// Revoke deal accepts a deal and revokes it within the runtime.
@@ -271,6 +302,14 @@ pub enum Command {
         blockchain: Blockchain,
     },
 
+    /// Shows the address book entry of a peer: its last known addresses, when it was last seen
+    /// and the swaps run with it
+    #[display("peer-entry<{node_id}>")]
+    PeerEntry {
+        /// The node id of the peer
+        node_id: NodeId,
+    },
+
     /// Attempts to sweep any funds on a given bitcoin funding address
     #[display("sweep-bitcoin-address<{source_address} {destination_address}>")]
     SweepBitcoinAddress {
@@ -296,6 +335,57 @@ pub enum Command {
         address: Address,
     },
 
+    /// Shows the swap statistics recorded by the node, split by network and swap role
+    #[display("stats<{group_by}>")]
+    Stats {
+        /// First day (YYYY-MM-DD, UTC) included in the statistics, defaults to all recorded days
+        #[clap(long)]
+        since: Option<StatsDay>,
+
+        /// Period the statistics are aggregated over
+        #[clap(
+            long,
+            default_value = "day",
+            possible_values = &["day", "month", "total"],
+        )]
+        group_by: StatsGrouping,
+    },
+
+    /// Stop the node and all its services. Running swaps are resumed from their checkpoint on
+    /// next start if auto restore is enabled.
+    #[display("shutdown<{drain}>")]
+    Shutdown {
+        /// Stop accepting new deals, revoke the open ones and wait for every running swap to
+        /// reach a safe point before shutting down. Swaps that did not checkpoint yet, e.g. swaps
+        /// awaiting funding, are aborted. The node shuts down anyway if the swaps did not drain
+        /// within ten minutes.
+        #[clap(long)]
+        drain: bool,
+    },
+
+    /// Reload the node configuration file. Swap parameters, auto-funding, acceptance policy,
+    /// deal validation and notifications apply to the next swaps. Syncer servers, fee policy and
+    /// peer limits only apply to newly launched syncers and peerds, the running ones keep their
+    /// values until restart. Other changes are reported as requiring a restart.
+    #[display("reload-config")]
+    ReloadConfig,
+
+    /// Manage the node wallet: the hot wallet used to fund swaps and receive swept funds, and the
+    /// mnemonic backup of the node secrets
+    #[display("wallet<{command}>")]
+    Wallet {
+        #[clap(subcommand)]
+        command: WalletCommand,
+    },
+
+    /// Manage the tokens authenticating the grpc clients, the default tokens are stored in the
+    /// grpc folder of the data directory
+    #[display("grpc-token<{command}>")]
+    GrpcToken {
+        #[clap(subcommand)]
+        command: GrpcTokenCommand,
+    },
+
     /// Output shell completion code for the specified shell (bash, zsh or fish)
     ///
     /// The shell code must be evaluated to provide interactive completion of swap-cli commands.
@@ -314,6 +404,61 @@ pub enum Command {
     },
 }
 
+/// Hot wallet commands:
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum WalletCommand {
+    /// Returns the confirmed and unconfirmed balance of the hot wallet
+    #[display("balance")]
+    Balance,
+
+    /// Returns a fresh hot wallet address to receive funds on
+    #[display("receive")]
+    Receive,
+
+    /// Prints the 24 words mnemonic backing up the node secrets, swap keys and hot wallet. Keep
+    /// it offline, anyone knowing it can spend the node funds.
+    #[display("export-mnemonic")]
+    ExportMnemonic,
+
+    /// Encrypts the walletd key file with a new passphrase, prompted on the terminal together
+    /// with the current one. Unencrypted key files are encrypted for the first time.
+    #[display("change-passphrase")]
+    ChangePassphrase,
+
+    /// Sends bitcoin from the hot wallet to an address
+    #[display("send<{amount} to {address}>")]
+    Send {
+        /// The destination address.
+        address: BtcAddress,
+        /// Amount to send, including the denomination, e.g. "0.01 BTC".
+        amount: bitcoin::Amount,
+    },
+}
+
+/// Grpc token commands:
+#[derive(Subcommand, Clone, PartialEq, Eq, Debug, Display)]
+pub enum GrpcTokenCommand {
+    /// Issues a new token granting the permissions and prints it, the token is not stored by the
+    /// node and cannot be retrieved later
+    #[display("create")]
+    Create {
+        /// Permissions granted by the token: read, trade or funds
+        #[clap(required = true)]
+        permissions: Vec<Permission>,
+    },
+
+    /// Lists the identifiers and permissions of the issued tokens
+    #[display("list")]
+    List,
+
+    /// Revokes the token with the identifier, the grpc requests using it are rejected from now on
+    #[display("revoke<{id}>")]
+    Revoke {
+        /// The identifier of the token, as listed
+        id: String,
+    },
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, From)]
 pub enum DealSelector {
     #[display("Open")]
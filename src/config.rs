@@ -4,11 +4,13 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use chrono::NaiveTime;
 use config::ConfigError::Message;
+use farcaster_core::bitcoin::fee::SatPerKvB;
 use farcaster_core::blockchain::Network;
 use farcaster_core::role::{SwapRole, TradeRole};
 use farcaster_core::swap::btcxmr::DealParameters;
-use internet2::addr::InetSocketAddr;
+use internet2::addr::{InetSocketAddr, NodeId};
 use serde::{Deserialize, Serialize};
 use serde_with::DisplayFromStr;
 
@@ -16,9 +18,13 @@ use std::convert::TryInto;
 use std::fmt::Display;
 use std::fs::File;
 use std::io::prelude::*;
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
+use crate::bus::SwapParams;
 use crate::{AccordantBlockchain, ArbitratingBlockchain, Error};
 
 pub const FARCASTER_MAINNET_ELECTRUM_SERVER: &str = "ssl://blockstream.info:700";
@@ -31,9 +37,39 @@ pub const FARCASTER_TESTNET_MONERO_RPC_WALLET: &str = "http://localhost:38083";
 
 pub const FARCASTER_BIND_PORT: u16 = 7067;
 pub const FARCASTER_BIND_IP: &str = "0.0.0.0";
+pub const FARCASTER_EPHEMERAL_MIN_PORT: u16 = 7100;
+pub const FARCASTER_EPHEMERAL_MAX_PORT: u16 = 7199;
 
 pub const GRPC_BIND_IP_ADDRESS: &str = "127.0.0.1";
 
+pub const METRICS_BIND_IP_ADDRESS: &str = "127.0.0.1";
+pub const METRICS_BIND_PORT: u16 = 9467;
+
+pub const TOR_CONTROL_ADDRESS: &str = "127.0.0.1:9051";
+pub const TOR_SOCKS_PROXY: &str = "127.0.0.1:9050";
+
+/// Configuration sections only read when the node starts, changing them requires a restart
+pub const RESTART_REQUIRED_SECTIONS: [&str; 4] = ["grpc", "hot_wallet", "metrics", "tor"];
+
+/// Configuration sections passed to the services when launched, the running syncers and peerds
+/// keep their values, also when relaunched after a crash, until the node restarts
+pub const LAUNCH_ARGS_SECTIONS: [&str; 3] = ["fee_policy", "peer_limits", "syncers"];
+
+pub const FEE_POLICY_LOCK_TARGET: u16 = 2;
+pub const FEE_POLICY_SWEEP_TARGET: u16 = 2;
+pub const FEE_POLICY_MIN_FEE_RATE: u64 = 1;
+pub const FEE_POLICY_MAX_FEE_RATE: u64 = 1000;
+pub const FEE_POLICY_FALLBACK_FEE_RATE: u64 = 20;
+
+pub const PEER_LIMIT_MAX_MESSAGES_PER_SEC: u32 = 20;
+pub const PEER_LIMIT_MAX_PONG_SIZE: u16 = 1024;
+pub const PEER_LIMIT_MAX_CONNECTIONS_PER_IP: u32 = 8;
+pub const PEER_LIMIT_IDLE_TIMEOUT: u64 = 300;
+pub const PEER_LIMIT_MAX_INFLIGHT_SWAPS_PER_PEER: u32 = 10;
+
+pub const PEER_ALERT_MAX_RTT: u64 = 5000;
+pub const PEER_ALERT_MAX_RECONNECTS: u32 = 3;
+
 pub const SWAP_MAINNET_BITCOIN_SAFETY: u8 = 7;
 pub const SWAP_MAINNET_BITCOIN_FINALITY: u8 = 6;
 pub const SWAP_MAINNET_BITCOIN_MIN_BTC_AMOUNT: f64 = 0.00001;
@@ -55,11 +91,44 @@ pub struct Config {
     pub swap: Option<SwapConfig>,
     /// Sets the grpc server port, if none is given, no grpc server is run
     pub grpc: Option<GrpcConfig>,
+    /// Sets the prometheus metrics endpoint, if none is given no metrics are exported
+    pub metrics: Option<MetricsConfig>,
+    /// Sets where swap events are notified, if none is given no notification is sent
+    pub notifications: Option<NotificationsConfig>,
+    /// Fee policy of the Bitcoin transactions, if none is given the default policy is used
+    pub fee_policy: Option<FeePolicyConfig>,
+    /// Limits applied to the remote peers, if none is given the default limits are used
+    pub peer_limits: Option<PeerLimitsConfig>,
+    /// Thresholds of the peer connection alerts, if none is given the default thresholds are used
+    pub peer_alerts: Option<PeerAlertsConfig>,
+    /// Bitcoin hot wallet managed by walletd, if none is given no hot wallet is used
+    pub hot_wallet: Option<HotWalletConfig>,
+    /// Tor onion service and proxy, if none is given the node only uses clear addresses
+    pub tor: Option<TorConfig>,
     /// Syncer configuration
     pub syncers: Option<Networked<Option<SyncerServers>>>,
 }
 
 impl Config {
+    /// Returns the top-level sections that differ between the two configurations
+    pub fn changed_sections(&self, other: &Config) -> Result<Vec<String>, Error> {
+        let to_table = |config: &Config| match toml::Value::try_from(config) {
+            Ok(toml::Value::Table(table)) => Ok(table),
+            Ok(_) => Err(Message(s!("Configuration is not a table"))),
+            Err(err) => Err(Message(err.to_string())),
+        };
+        let (old, new) = (to_table(self)?, to_table(other)?);
+        let mut sections: Vec<String> = old
+            .keys()
+            .chain(new.keys())
+            .filter(|section| old.get(*section) != new.get(*section))
+            .cloned()
+            .collect();
+        sections.sort();
+        sections.dedup();
+        Ok(sections)
+    }
+
     /// Returns if auto-funding functionality is enabled
     pub fn is_auto_funding_enable(&self) -> bool {
         match &self.farcasterd {
@@ -79,6 +148,16 @@ impl Config {
         }
     }
 
+    /// Returns if the grpc clients must authenticate with a token, enabled by default
+    pub fn is_grpc_auth_enable(&self) -> bool {
+        match &self.grpc {
+            Some(GrpcConfig {
+                auth: Some(auth), ..
+            }) => *auth,
+            _ => true,
+        }
+    }
+
     /// Returns the Grcp bind ip address, if not set return the default value
     pub fn grpc_bind_ip(&self) -> String {
         match &self.grpc {
@@ -90,6 +169,157 @@ impl Config {
         }
     }
 
+    /// Returns the address the metrics endpoint listens on if enabled, if not set return the
+    /// default values '127.0.0.1:9467'
+    pub fn get_metrics_bind_addr(&self) -> Result<Option<SocketAddr>, Error> {
+        match &self.metrics {
+            Some(MetricsConfig {
+                enable: true,
+                bind_ip,
+                bind_port,
+            }) => {
+                let addr = format!(
+                    "{}:{}",
+                    bind_ip
+                        .as_ref()
+                        .unwrap_or(&METRICS_BIND_IP_ADDRESS.to_string()),
+                    bind_port.unwrap_or(METRICS_BIND_PORT)
+                );
+                SocketAddr::from_str(&addr)
+                    .map(Some)
+                    .map_err(|err| Message(format!("Invalid metrics bind address: {}", err)).into())
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the notifications configuration if at least one target is configured
+    pub fn get_notifications_config(&self) -> Option<NotificationsConfig> {
+        match &self.notifications {
+            Some(notifications)
+                if !notifications.webhooks.is_empty() || !notifications.commands.is_empty() =>
+            {
+                Some(notifications.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the fee policy with the default values for the unset fields
+    pub fn get_fee_policy(&self) -> Result<FeePolicy, Error> {
+        let mut policy = FeePolicy::default();
+        if let Some(config) = &self.fee_policy {
+            policy.lock_target = config.lock_target.unwrap_or(policy.lock_target);
+            policy.sweep_target = config.sweep_target.unwrap_or(policy.sweep_target);
+            policy.min_fee_rate = config.min_fee_rate.unwrap_or(policy.min_fee_rate);
+            policy.max_fee_rate = config.max_fee_rate.unwrap_or(policy.max_fee_rate);
+            policy.fallback_fee_rate = config.fallback_fee_rate.unwrap_or(policy.fallback_fee_rate);
+        }
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Returns the peer limits with the default values for the unset fields
+    pub fn get_peer_limits(&self) -> Result<PeerLimits, Error> {
+        let mut limits = PeerLimits::default();
+        if let Some(config) = &self.peer_limits {
+            limits.max_messages_per_sec = config
+                .max_messages_per_sec
+                .unwrap_or(limits.max_messages_per_sec);
+            limits.max_pong_size = config.max_pong_size.unwrap_or(limits.max_pong_size);
+            limits.max_connections_per_ip = config
+                .max_connections_per_ip
+                .unwrap_or(limits.max_connections_per_ip);
+            limits.idle_timeout = config.idle_timeout.unwrap_or(limits.idle_timeout);
+            limits.max_inflight_swaps_per_peer = config
+                .max_inflight_swaps_per_peer
+                .unwrap_or(limits.max_inflight_swaps_per_peer);
+        }
+        limits.validate()?;
+        Ok(limits)
+    }
+
+    /// Returns the thresholds of the peer connection alerts, the defaults are used for the
+    /// thresholds not set
+    pub fn get_peer_alerts(&self) -> PeerAlerts {
+        let mut alerts = PeerAlerts::default();
+        if let Some(config) = &self.peer_alerts {
+            alerts.max_rtt = config.max_rtt.unwrap_or(alerts.max_rtt);
+            alerts.max_reconnects = config.max_reconnects.unwrap_or(alerts.max_reconnects);
+        }
+        alerts
+    }
+
+    /// Returns the network of the hot wallet if enabled
+    pub fn get_hot_wallet_network(&self) -> Option<Network> {
+        match &self.hot_wallet {
+            Some(HotWalletConfig {
+                enable: true,
+                network,
+                ..
+            }) => Some(*network),
+            _ => None,
+        }
+    }
+
+    /// Returns if the swaps on the given network are funded from the hot wallet. Default to true
+    /// when the hot wallet is enabled on the network
+    pub fn is_hot_wallet_auto_funding(&self, network: Network) -> bool {
+        match &self.hot_wallet {
+            Some(HotWalletConfig {
+                enable: true,
+                network: wallet_network,
+                auto_funding,
+            }) if *wallet_network == network => auto_funding.unwrap_or(true),
+            _ => false,
+        }
+    }
+
+    /// Returns the range of ports bound by the per deal listeners if deals are made with
+    /// ephemeral node ids, None if deals are made with the node identity
+    pub fn get_ephemeral_ports(&self) -> Result<Option<RangeInclusive<u16>>, Error> {
+        let ephemeral = match &self.farcasterd {
+            Some(FarcasterdConfig {
+                ephemeral_node_ids: Some(ephemeral),
+                ..
+            }) if ephemeral.enable => ephemeral,
+            _ => return Ok(None),
+        };
+        let min_port = ephemeral.min_port.unwrap_or(FARCASTER_EPHEMERAL_MIN_PORT);
+        let max_port = ephemeral.max_port.unwrap_or(FARCASTER_EPHEMERAL_MAX_PORT);
+        if min_port == 0 || min_port > max_port {
+            return Err(Message(s!("Ephemeral node ids require a non-empty port range")).into());
+        }
+        // all the deals would share the onion address and be linked anyway
+        if self.get_onion_service_config().is_some() {
+            return Err(Message(s!(
+                "Ephemeral node ids cannot be used with the onion service"
+            ))
+            .into());
+        }
+        Ok(Some(min_port..=max_port))
+    }
+
+    /// Returns the Tor configuration if the listening peerd is advertised as an onion service
+    pub fn get_onion_service_config(&self) -> Option<TorConfig> {
+        match &self.tor {
+            Some(tor) if tor.onion_service => Some(tor.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the Tor SOCKS proxy used to reach makers advertising an onion address, if not set
+    /// return the default value '127.0.0.1:9050'
+    pub fn get_tor_socks_proxy(&self) -> Result<SocketAddr, Error> {
+        let addr = self
+            .tor
+            .as_ref()
+            .and_then(|tor| tor.socks_proxy.clone())
+            .unwrap_or_else(|| TOR_SOCKS_PROXY.to_string());
+        SocketAddr::from_str(&addr)
+            .map_err(|err| Message(format!("Invalid Tor SOCKS proxy address: {}", err)).into())
+    }
+
     /// Returns if auto restore is enabled. Default to true
     pub fn auto_restore_enable(&self) -> bool {
         match &self.farcasterd {
@@ -101,6 +331,28 @@ impl Config {
         }
     }
 
+    /// Returns the acceptance policy configuration, if none is set all takers are accepted
+    pub fn get_acceptance_policy_config(&self) -> AcceptancePolicyConfig {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                acceptance_policy: Some(policy),
+                ..
+            }) => policy.clone(),
+            _ => AcceptancePolicyConfig::default(),
+        }
+    }
+
+    /// Returns the deal validation configuration, if none is set only timelocks are checked
+    pub fn get_deal_validation_config(&self) -> DealValidationConfig {
+        match &self.farcasterd {
+            Some(FarcasterdConfig {
+                deal_validation: Some(validation),
+                ..
+            }) => validation.clone(),
+            _ => DealValidationConfig::default(),
+        }
+    }
+
     /// Returns the auto-funding configuration for a given network if enable, if None no
     /// configuration is found
     pub fn get_auto_funding_config(&self, network: Network) -> Option<AutoFundingServers> {
@@ -197,6 +449,22 @@ impl Config {
         }
     }
 
+    /// Returns the networks with a swap configuration, announced to the peers in the handshake
+    pub fn get_swap_networks(&self) -> Vec<Network> {
+        [Network::Mainnet, Network::Testnet, Network::Local]
+            .iter()
+            .copied()
+            .filter(|network| {
+                self.get_swap_config(
+                    ArbitratingBlockchain::Bitcoin,
+                    AccordantBlockchain::Monero,
+                    *network,
+                )
+                .is_ok()
+            })
+            .collect()
+    }
+
     /// Validate a deal against user configuration (farcasterd.toml) and user provided addresses
     pub fn validate_deal_parameters(
         &self,
@@ -329,6 +597,13 @@ impl Default for Config {
             farcasterd: Some(FarcasterdConfig::default()),
             swap: Some(SwapConfig::default()),
             grpc: None,
+            metrics: None,
+            notifications: None,
+            fee_policy: None,
+            peer_limits: None,
+            peer_alerts: None,
+            hot_wallet: None,
+            tor: None,
             syncers: Some(Networked {
                 mainnet: Some(SyncerServers {
                     electrum_server: FARCASTER_MAINNET_ELECTRUM_SERVER.into(),
@@ -361,6 +636,141 @@ pub struct FarcasterdConfig {
     pub bind_ip: Option<String>,
     /// Whether checkpoints should be auto restored at start-up, or not
     pub auto_restore: Option<bool>,
+    /// Sets the policy applied on incoming taker commits, default to accept all takers
+    pub acceptance_policy: Option<AcceptancePolicyConfig>,
+    /// Sets the checks run on deals before taking them
+    pub deal_validation: Option<DealValidationConfig>,
+    /// Sets the per deal node identities, default to all deals made with the node identity
+    pub ephemeral_node_ids: Option<EphemeralNodeIdsConfig>,
+}
+
+/// Defines the node identities made per deal. Every deal is made with its own node id derived
+/// from the node key and the deal id, and the peerd listening with that identity binds a port of
+/// the range, advertised in the deal
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "serde_crate")]
+pub struct EphemeralNodeIdsConfig {
+    /// Make the deals with a node id derived per deal
+    #[serde(default)]
+    pub enable: bool,
+    /// First port of the range bound by the per deal listeners
+    pub min_port: Option<u16>,
+    /// Last port of the range bound by the per deal listeners
+    pub max_port: Option<u16>,
+}
+
+/// Defines the checks a taker runs on a deal before taking it. Timelock checks always run when a
+/// swap configuration exists for the deal's network, all other checks are optional
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "serde_crate")]
+pub struct DealValidationConfig {
+    /// Refuse to take deals failing a check, otherwise the report is only informative
+    #[serde(default)]
+    pub enforce: bool,
+    /// Minimum number of blocks the cancel timelock must exceed safety plus finality by
+    pub min_cancel_margin: Option<u32>,
+    /// Minimum number of blocks the punish timelock must exceed safety plus finality by
+    pub min_punish_margin: Option<u32>,
+    /// Maximum fee rate accepted for the arbitrating transactions
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub max_fee_rate: Option<SatPerKvB>,
+    /// If specified, only deals on these networks are accepted
+    #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+    #[serde(default)]
+    pub networks: Option<Vec<Network>>,
+    /// If specified, only deals from these maker node ids are accepted
+    #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+    #[serde(default)]
+    pub maker_node_ids: Option<Vec<NodeId>>,
+    /// Minimum acceptable price in BTC per XMR
+    pub min_price: Option<f64>,
+    /// Maximum acceptable price in BTC per XMR
+    pub max_price: Option<f64>,
+    /// Command printing the reference price in BTC per XMR on its standard output
+    pub price_oracle_command: Option<String>,
+    /// Maximum deviation from the oracle price, in percent. Default to 5
+    pub max_price_deviation: Option<f64>,
+}
+
+/// Defines the limits a maker applies before accepting a taker commit on one of its deals, all
+/// limits are optional
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "serde_crate")]
+pub struct AcceptancePolicyConfig {
+    /// Maximum number of swaps running at the same time
+    pub max_concurrent_swaps: Option<u32>,
+    /// Maximum number of swaps running at the same time with the same counterparty
+    pub max_concurrent_swaps_per_peer: Option<u32>,
+    /// Maximum amount of bitcoin locked by this node across all running swaps
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub max_btc_exposure: Option<bitcoin::Amount>,
+    /// Maximum amount of monero locked by this node across all running swaps
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub max_xmr_exposure: Option<monero::Amount>,
+    /// If specified, only these node ids can take deals
+    #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+    #[serde(default)]
+    pub allowed_node_ids: Option<Vec<NodeId>>,
+    /// Node ids that can never take deals
+    #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+    #[serde(default)]
+    pub denied_node_ids: Option<Vec<NodeId>>,
+    /// If specified, takers are only accepted during these UTC time windows, e.g. "08:00-18:00"
+    #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+    #[serde(default)]
+    pub trading_hours: Option<Vec<TimeWindow>>,
+}
+
+/// A daily time window in UTC, the window wraps around midnight if it ends before it starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Returns true if the time is within the window, start inclusive and end exclusive
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl Display for TimeWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| Error::Farcaster(format!("Invalid time window {}", s)))?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|e| Error::Farcaster(format!("Invalid time window {}: {}", s, e)))
+        };
+        Ok(TimeWindow {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
 }
 
 /// This struct holds all swap config, for all chains and all networks
@@ -401,6 +811,22 @@ pub struct ParsedSwapConfig {
     pub accordant: AccConfig,
 }
 
+impl ParsedSwapConfig {
+    /// Override the configured finality and safety with the per-swap parameters that are set
+    pub fn with_params(mut self, params: &SwapParams) -> Self {
+        if let Some(finality) = params.arb_finality {
+            self.arbitrating.finality = finality;
+        }
+        if let Some(safety) = params.arb_safety {
+            self.arbitrating.safety = safety;
+        }
+        if let Some(finality) = params.acc_finality {
+            self.accordant.finality = finality;
+        }
+        self
+    }
+}
+
 /// Holds the parameters needed for an arbitrating asset in a swap, e.g. Bitcoin
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(crate = "serde_crate")]
@@ -555,6 +981,281 @@ pub struct GrpcConfig {
     pub bind_port: u16,
     /// Grpc listening ip address
     pub bind_ip: Option<String>,
+    /// Require the grpc clients to authenticate with a token, default to true
+    pub auth: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct MetricsConfig {
+    /// Export prometheus metrics
+    pub enable: bool,
+    /// Metrics port configuration, default to 9467
+    pub bind_port: Option<u16>,
+    /// Metrics listening ip address, default to 127.0.0.1
+    pub bind_ip: Option<String>,
+}
+
+/// Fee policy of the Bitcoin transactions whose fee is chosen by this node, i.e. the lock
+/// transaction funded by Bob and the address sweeps. The fee of the other arbitrating transactions
+/// is fixed by the deal's fee strategy.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "serde_crate")]
+pub struct FeePolicyConfig {
+    /// Confirmation target in blocks of the lock transaction, default to 2
+    pub lock_target: Option<u16>,
+    /// Confirmation target in blocks of the sweep transactions, default to 2
+    pub sweep_target: Option<u16>,
+    /// Minimum fee rate in sat/vB, default to 1
+    pub min_fee_rate: Option<u64>,
+    /// Maximum fee rate in sat/vB, default to 1000
+    pub max_fee_rate: Option<u64>,
+    /// Fee rate in sat/vB used when no fee can be estimated, default to 20
+    pub fallback_fee_rate: Option<u64>,
+}
+
+/// Fee policy resolved from the configuration, see [`FeePolicyConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeePolicy {
+    pub lock_target: u16,
+    pub sweep_target: u16,
+    pub min_fee_rate: u64,
+    pub max_fee_rate: u64,
+    pub fallback_fee_rate: u64,
+}
+
+impl Default for FeePolicy {
+    fn default() -> Self {
+        FeePolicy {
+            lock_target: FEE_POLICY_LOCK_TARGET,
+            sweep_target: FEE_POLICY_SWEEP_TARGET,
+            min_fee_rate: FEE_POLICY_MIN_FEE_RATE,
+            max_fee_rate: FEE_POLICY_MAX_FEE_RATE,
+            fallback_fee_rate: FEE_POLICY_FALLBACK_FEE_RATE,
+        }
+    }
+}
+
+impl FeePolicy {
+    /// Validate the confirmation targets are set and the fee rates are coherent
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.lock_target == 0 || self.sweep_target == 0 {
+            return Err(Message(s!(
+                "Fee policy confirmation targets must be at least 1 block"
+            ))
+            .into());
+        }
+        if self.min_fee_rate > self.max_fee_rate {
+            return Err(Message(s!("Fee policy minimum fee rate is above the maximum")).into());
+        }
+        if self.fallback_fee_rate < self.min_fee_rate || self.fallback_fee_rate > self.max_fee_rate
+        {
+            return Err(Message(s!(
+                "Fee policy fallback fee rate is outside of the minimum and maximum fee rates"
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Limits protecting the node against remote peers flooding it or holding connections open.
+/// Peer daemons get the limits when launched, a running listener keeps its limits until restarted
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "serde_crate")]
+pub struct PeerLimitsConfig {
+    /// Maximum number of messages per second received on a connection, default to 20
+    pub max_messages_per_sec: Option<u32>,
+    /// Maximum size in bytes of the pong a remote ping can request, default to 1024
+    pub max_pong_size: Option<u16>,
+    /// Maximum number of simultaneous incoming connections from the same IP, default to 8
+    pub max_connections_per_ip: Option<u32>,
+    /// Seconds after which an incoming connection on which no swap started is closed, default
+    /// to 300
+    pub idle_timeout: Option<u64>,
+    /// Maximum number of swaps in flight with the same node id, default to 10
+    pub max_inflight_swaps_per_peer: Option<u32>,
+}
+
+/// Peer limits resolved from the configuration, see [`PeerLimitsConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerLimits {
+    pub max_messages_per_sec: u32,
+    pub max_pong_size: u16,
+    pub max_connections_per_ip: u32,
+    pub idle_timeout: u64,
+    pub max_inflight_swaps_per_peer: u32,
+}
+
+impl Default for PeerLimits {
+    fn default() -> Self {
+        PeerLimits {
+            max_messages_per_sec: PEER_LIMIT_MAX_MESSAGES_PER_SEC,
+            max_pong_size: PEER_LIMIT_MAX_PONG_SIZE,
+            max_connections_per_ip: PEER_LIMIT_MAX_CONNECTIONS_PER_IP,
+            idle_timeout: PEER_LIMIT_IDLE_TIMEOUT,
+            max_inflight_swaps_per_peer: PEER_LIMIT_MAX_INFLIGHT_SWAPS_PER_PEER,
+        }
+    }
+}
+
+impl PeerLimits {
+    /// Validate that no limit blocks every peer
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.max_messages_per_sec == 0
+            || self.max_connections_per_ip == 0
+            || self.idle_timeout == 0
+            || self.max_inflight_swaps_per_peer == 0
+        {
+            return Err(Message(s!(
+                "Peer limits must allow at least one message, connection, swap and second"
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout)
+    }
+}
+
+/// Thresholds above which farcasterd logs an alert on the connection with the counterparty of a
+/// swap in its critical phase, once funds are locked and until the swap no longer relies on the
+/// counterparty
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "serde_crate")]
+pub struct PeerAlertsConfig {
+    /// Maximum round-trip time in milliseconds of the pings answered by the counterparty,
+    /// default to 5000
+    pub max_rtt: Option<u64>,
+    /// Maximum number of reconnections with the counterparty during a swap, default to 3
+    pub max_reconnects: Option<u32>,
+}
+
+/// Peer alert thresholds resolved from the configuration, see [`PeerAlertsConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerAlerts {
+    pub max_rtt: u64,
+    pub max_reconnects: u32,
+}
+
+impl Default for PeerAlerts {
+    fn default() -> Self {
+        PeerAlerts {
+            max_rtt: PEER_ALERT_MAX_RTT,
+            max_reconnects: PEER_ALERT_MAX_RECONNECTS,
+        }
+    }
+}
+
+impl PeerAlerts {
+    pub fn max_rtt(&self) -> Duration {
+        Duration::from_millis(self.max_rtt)
+    }
+}
+
+/// Defines the Bitcoin hot wallet managed by walletd. The wallet is a BIP84 wallet derived from
+/// the node wallet seed, its funds are used to fund Bob's swaps and the swept funds return to it
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct HotWalletConfig {
+    /// Use the hot wallet
+    pub enable: bool,
+    /// Network of the hot wallet, a single network is supported at a time
+    #[serde_as(as = "DisplayFromStr")]
+    pub network: Network,
+    /// Fund the swaps on the hot wallet network from the hot wallet instead of the auto-funding
+    /// bitcoin node, default to true
+    pub auto_funding: Option<bool>,
+}
+
+/// Defines the Tor onion service farcasterd creates through the Tor control port for the
+/// listening peerd, and the SOCKS proxy used when taking deals advertising an onion address
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "serde_crate")]
+pub struct TorConfig {
+    /// Advertise the listening peerd as an onion service in the deals instead of the public
+    /// address given to make
+    pub onion_service: bool,
+    /// Address of the Tor control port, default to 127.0.0.1:9051
+    pub control_address: Option<String>,
+    /// Password of the Tor control port, if none is given the cookie or no authentication is used
+    pub control_password: Option<String>,
+    /// Keep the same onion address across restarts, its key is stored in the data directory.
+    /// Default to false, a new ephemeral onion address is created on every start
+    pub persistent: Option<bool>,
+    /// Tor SOCKS proxy used to reach onion makers, default to 127.0.0.1:9050
+    pub socks_proxy: Option<String>,
+}
+
+impl TorConfig {
+    /// Returns the Tor control port address, if not set return the default value
+    /// '127.0.0.1:9051'
+    pub fn get_control_addr(&self) -> Result<SocketAddr, Error> {
+        let addr = self
+            .control_address
+            .clone()
+            .unwrap_or_else(|| TOR_CONTROL_ADDRESS.to_string());
+        SocketAddr::from_str(&addr)
+            .map_err(|err| Message(format!("Invalid Tor control address: {}", err)).into())
+    }
+}
+
+/// Defines the targets notified on swap events, every target receives all the events unless it
+/// lists the ones it wants
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "serde_crate")]
+pub struct NotificationsConfig {
+    /// Webhooks receiving the events as JSON in a POST request
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Local commands receiving the events as JSON on their standard input
+    #[serde(default)]
+    pub commands: Vec<NotificationCommandConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct WebhookConfig {
+    /// Url of the webhook, only plain http urls are supported
+    pub url: String,
+    /// If specified, the payload is signed with HMAC-SHA256 using this secret and the signature
+    /// is sent in the `X-Farcaster-Signature` header
+    pub secret: Option<String>,
+    /// If specified, only these events are delivered
+    pub events: Option<Vec<NotificationKind>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "serde_crate")]
+pub struct NotificationCommandConfig {
+    /// Command run with `sh -c`
+    pub command: String,
+    /// If specified, only these events are delivered
+    pub events: Option<Vec<NotificationKind>>,
+}
+
+/// The swap events operators can be notified of
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[serde(crate = "serde_crate", rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// A swap waits for its funding
+    #[display("funding_required")]
+    FundingRequired,
+    /// A swap lock transaction is seen on chain
+    #[display("locked")]
+    Locked,
+    /// A swap is canceled
+    #[display("canceled")]
+    Canceled,
+    /// A swap ended with the punishment of a participant
+    #[display("punished")]
+    Punished,
+    /// A swap ended, whatever its outcome
+    #[display("ended")]
+    Ended,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -664,6 +1365,9 @@ impl Default for FarcasterdConfig {
             // write the default port and ip in the generated config
             bind_port: Some(FARCASTER_BIND_PORT),
             bind_ip: Some(FARCASTER_BIND_IP.to_string()),
+            acceptance_policy: None,
+            deal_validation: None,
+            ephemeral_node_ids: None,
         }
     }
 }
@@ -689,11 +1393,130 @@ pub fn parse_config(path: &str) -> Result<Config, Error> {
 
 #[cfg(test)]
 mod tests {
-    use super::parse_config;
+    use super::{
+        parse_config, AccConfig, ArbConfig, EphemeralNodeIdsConfig, FarcasterdConfig,
+        ParsedSwapConfig, PeerAlerts, PeerAlertsConfig, PeerLimits, PeerLimitsConfig, SwapParams,
+        TimeWindow, FARCASTER_EPHEMERAL_MAX_PORT,
+    };
+    use chrono::NaiveTime;
+    use std::str::FromStr;
 
     #[test]
     fn config_example_parse() {
         let config = parse_config("./farcasterd.toml").expect("correct config example");
         dbg!(config);
     }
+
+    #[test]
+    fn config_changed_sections() {
+        let config = parse_config("./farcasterd.toml").expect("correct config example");
+        let mut changed = config.clone();
+        assert!(config.changed_sections(&changed).unwrap().is_empty());
+        changed.metrics = None;
+        changed.farcasterd.as_mut().unwrap().bind_port = Some(9736);
+        assert_eq!(
+            config.changed_sections(&changed).unwrap(),
+            vec![s!("farcasterd"), s!("metrics")]
+        );
+    }
+
+    #[test]
+    fn swap_config_with_params() {
+        let swap_config = ParsedSwapConfig {
+            arbitrating: ArbConfig::btc_testnet_default(),
+            accordant: AccConfig::xmr_testnet_default(),
+        };
+        let default_safety = swap_config.arbitrating.safety;
+        let overridden = swap_config.with_params(&SwapParams {
+            arb_finality: Some(3),
+            arb_safety: None,
+            acc_finality: Some(5),
+        });
+        assert_eq!(overridden.arbitrating.finality, 3);
+        assert_eq!(overridden.arbitrating.safety, default_safety);
+        assert_eq!(overridden.accordant.finality, 5);
+    }
+
+    #[test]
+    fn peer_limits_defaults() {
+        let mut config = parse_config("./farcasterd.toml").expect("correct config example");
+        assert_eq!(config.get_peer_limits().unwrap(), PeerLimits::default());
+        config.peer_limits = Some(PeerLimitsConfig {
+            max_pong_size: Some(64),
+            ..Default::default()
+        });
+        let limits = config.get_peer_limits().unwrap();
+        assert_eq!(limits.max_pong_size, 64);
+        assert_eq!(
+            limits.max_messages_per_sec,
+            PeerLimits::default().max_messages_per_sec
+        );
+        config.peer_limits = Some(PeerLimitsConfig {
+            max_connections_per_ip: Some(0),
+            ..Default::default()
+        });
+        assert!(config.get_peer_limits().is_err());
+    }
+
+    #[test]
+    fn peer_alerts_defaults() {
+        let mut config = parse_config("./farcasterd.toml").expect("correct config example");
+        assert_eq!(config.get_peer_alerts(), PeerAlerts::default());
+        config.peer_alerts = Some(PeerAlertsConfig {
+            max_rtt: Some(800),
+            ..Default::default()
+        });
+        let alerts = config.get_peer_alerts();
+        assert_eq!(alerts.max_rtt().as_millis(), 800);
+        assert_eq!(alerts.max_reconnects, PeerAlerts::default().max_reconnects);
+    }
+
+    #[test]
+    fn ephemeral_ports_range() {
+        let mut config = parse_config("./farcasterd.toml").expect("correct config example");
+        assert_eq!(config.get_ephemeral_ports().unwrap(), None);
+        config.farcasterd = Some(FarcasterdConfig {
+            ephemeral_node_ids: Some(EphemeralNodeIdsConfig {
+                enable: true,
+                min_port: Some(8000),
+                max_port: None,
+            }),
+            ..Default::default()
+        });
+        assert_eq!(
+            config.get_ephemeral_ports().unwrap(),
+            Some(8000..=FARCASTER_EPHEMERAL_MAX_PORT)
+        );
+        config.farcasterd = Some(FarcasterdConfig {
+            ephemeral_node_ids: Some(EphemeralNodeIdsConfig {
+                enable: true,
+                min_port: Some(8000),
+                max_port: Some(7999),
+            }),
+            ..Default::default()
+        });
+        assert!(config.get_ephemeral_ports().is_err());
+    }
+
+    #[test]
+    fn grpc_auth_enabled_by_default() {
+        let mut config = parse_config("./farcasterd.toml").expect("correct config example");
+        assert!(config.is_grpc_auth_enable());
+        config.grpc.as_mut().unwrap().auth = None;
+        assert!(config.is_grpc_auth_enable());
+        config.grpc.as_mut().unwrap().auth = Some(false);
+        assert!(!config.is_grpc_auth_enable());
+    }
+
+    #[test]
+    fn time_window_wraps_around_midnight() {
+        let at = |h, m| NaiveTime::from_hms(h, m, 0);
+        let window = TimeWindow::from_str("22:00-02:00").expect("valid window");
+        assert!(window.contains(at(23, 30)));
+        assert!(window.contains(at(1, 0)));
+        assert!(!window.contains(at(2, 0)));
+        assert!(!window.contains(at(12, 0)));
+        assert_eq!(window.to_string(), "22:00-02:00");
+        assert!(TimeWindow::from_str("8h-18h").is_err());
+    }
 }
@@ -4,21 +4,27 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use farcaster_core::blockchain::{Blockchain, Network};
+use farcaster_core::role::{SwapRole, TradeRole};
 use farcaster_core::swap::btcxmr::Deal;
 use farcaster_core::swap::SwapId;
-use farcaster_core::{blockchain::Blockchain, role::TradeRole};
+use internet2::addr::{InetSocketAddr, NodeId};
 use lmdb::{Cursor, Transaction as LMDBTransaction};
+use std::collections::HashMap;
 use std::io::Cursor as IoCursor;
 use std::path::PathBuf;
 use strict_encoding::{StrictDecode, StrictEncode};
 
 use crate::bus::{
-    ctl::{Checkpoint, CtlMsg},
-    info::{Address, InfoMsg},
+    ctl::{Checkpoint, CtlMsg, PeerSeen},
+    info::{Address, InfoMsg, PeerEntry, PeerMessages},
     info::{BitcoinAddressSwapIdPair, DealStatusSelector, MoneroAddressSwapIdPair},
+    p2p::{PeerMsg, Receipt},
     AddressSecretKey, BitcoinSecretKeyInfo, BusMsg, CheckpointEntry, DealInfo, DealStatus, Failure,
     FailureCode, MoneroSecretKeyInfo, Outcome, ServiceBus,
 };
+use crate::farcasterd::stats::{StatsBucket, StatsDay, StatsEvent, StatsEventKind, StatsQuery};
+use crate::service::exit_service;
 use crate::{swapd::CheckpointSwapd, Endpoints};
 use crate::{CtlServer, Error, LogStyle, Service, ServiceConfig, ServiceId};
 use microservices::esb::{self, Handler};
@@ -86,12 +92,18 @@ impl Runtime {
                 debug!("Received Hello from {}", source);
             }
 
+            CtlMsg::Terminate if source == ServiceId::Farcasterd => {
+                info!("Terminating {}", self.identity());
+                exit_service(0);
+            }
+
             CtlMsg::Checkpoint(Checkpoint { swap_id, state }) => {
                 let info = CheckpointEntry {
                     swap_id,
                     deal: state.deal.clone(),
                     trade_role: state.local_trade_role,
                     expected_counterparty_node_id: state.connected_counterparty_node_id,
+                    swap_params: state.temporal_safety.swap_params(),
                 };
                 debug!("{} | setting checkpoint info entry", swap_id.swap_id());
                 self.database.set_checkpoint_info(&swap_id, &info)?;
@@ -149,6 +161,9 @@ impl Runtime {
                 if let Err(err) = self.database.delete_checkpoint_info(swap_id) {
                     debug!("{} | Did not delete checkpoint info: {}", swap_id, err);
                 }
+                if let Err(err) = self.database.delete_peer_messages(swap_id) {
+                    debug!("{} | Did not delete peer messages: {}", swap_id, err);
+                }
             }
 
             CtlMsg::SetAddressSecretKey(AddressSecretKey::Bitcoin {
@@ -171,6 +186,7 @@ impl Runtime {
                 deal,
                 status,
                 local_trade_role,
+                ephemeral_node_id,
                 ..
             }) => {
                 self.database.set_deal(
@@ -178,10 +194,41 @@ impl Runtime {
                     &DealValue {
                         status,
                         local_trade_role,
+                        ephemeral_node_id,
                     },
                 )?;
             }
 
+            CtlMsg::RecordStats(event) => {
+                debug!(
+                    "{} | recording stats: {}",
+                    event.swap_id.swap_id(),
+                    event.kind
+                );
+                self.database.record_stats(&event)?;
+            }
+
+            CtlMsg::PeerSeen(PeerSeen {
+                node_id,
+                address,
+                swap_id,
+            }) => {
+                self.database.set_peer_seen(node_id, address, swap_id)?;
+            }
+
+            CtlMsg::StorePeerMessage(msg) => {
+                debug!("{} | storing peer message {}", msg.swap_id().swap_id(), msg);
+                self.database.store_peer_message(msg)?;
+            }
+
+            CtlMsg::PeerMessageAcknowledged(receipt) => {
+                self.database.acknowledge_peer_message(&receipt)?;
+            }
+
+            CtlMsg::PeerMessageReceived(receipt) => {
+                self.database.set_peer_message_received(&receipt)?;
+            }
+
             CtlMsg::CleanDanglingDeals => {
                 let checkpointed_pub_deals: Vec<Deal> = self
                     .database
@@ -196,17 +243,18 @@ impl Runtime {
                     .chain(in_progress.drain(..))
                     .filter_map(|o| {
                         if !checkpointed_pub_deals.contains(&o.deal) {
-                            Some((o.deal, o.local_trade_role))
+                            Some((o.deal, o.local_trade_role, o.ephemeral_node_id))
                         } else {
                             None
                         }
                     })
-                    .try_for_each(|(deal, local_trade_role)| {
+                    .try_for_each(|(deal, local_trade_role, ephemeral_node_id)| {
                         self.database.set_deal(
                             &deal,
                             &DealValue {
                                 status: DealStatus::Ended(Outcome::FailureAbort),
                                 local_trade_role,
+                                ephemeral_node_id,
                             },
                         )
                     })?;
@@ -277,6 +325,45 @@ impl Runtime {
                 }
             }
 
+            InfoMsg::GetPeerEntry(node_id) => match self.database.get_peer_entry(&node_id) {
+                Ok(entry) => {
+                    self.send_client_info(endpoints, source, InfoMsg::PeerEntry(entry))?;
+                }
+                Err(err) => {
+                    debug!("No address book entry for peer {}: {}", node_id, err);
+                    self.send_client_ctl(
+                        endpoints,
+                        source,
+                        CtlMsg::Failure(Failure {
+                            code: FailureCode::Unknown,
+                            info: format!("Could not retrieve address book entry for {}", node_id),
+                        }),
+                    )?;
+                }
+            },
+
+            InfoMsg::GetPeerMessages(swap_id) => {
+                let messages = self.database.get_peer_messages(swap_id)?;
+                self.send_client_info(endpoints, source, InfoMsg::PeerMessages(messages))?;
+            }
+
+            InfoMsg::GetStats(query) => match self.database.get_stats(&query) {
+                Ok(buckets) => {
+                    self.send_client_info(endpoints, source, InfoMsg::StatsList(buckets.into()))?;
+                }
+                Err(err) => {
+                    error!("Failed to retrieve stats: {}", err);
+                    self.send_client_ctl(
+                        endpoints,
+                        source,
+                        CtlMsg::Failure(Failure {
+                            code: FailureCode::Unknown,
+                            info: "Failed to retrieve stats".to_string(),
+                        }),
+                    )?;
+                }
+            },
+
             InfoMsg::GetAddressSecretKey(Address::Monero(address)) => {
                 match self.database.get_monero_address_secret_key(&address) {
                     Err(_) => {
@@ -387,10 +474,112 @@ struct CheckpointKey {
     service_id: ServiceId,
 }
 
-#[derive(Debug, Clone, StrictEncode, StrictDecode)]
+#[derive(Debug, Clone, StrictEncode)]
 struct DealValue {
     status: DealStatus,
     local_trade_role: TradeRole,
+    ephemeral_node_id: bool,
+}
+
+// Deals recorded before ephemeral node ids were introduced were made with the node identity
+impl StrictDecode for DealValue {
+    fn strict_decode<D: std::io::Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
+        Ok(DealValue {
+            status: StrictDecode::strict_decode(&mut d)?,
+            local_trade_role: StrictDecode::strict_decode(&mut d)?,
+            ephemeral_node_id: bool::strict_decode(&mut d).unwrap_or(false),
+        })
+    }
+}
+
+#[derive(Debug, Clone, StrictEncode, StrictDecode)]
+struct StatsKey {
+    day: StatsDay,
+    network: Network,
+    swap_role: SwapRole,
+}
+
+/// Sum of the durations of the swaps ending with a given outcome
+#[derive(Debug, Clone, Copy, Default, StrictEncode, StrictDecode)]
+struct CompletionTime {
+    total_secs: u64,
+    swaps: u64,
+}
+
+impl CompletionTime {
+    fn add(&mut self, secs: u64) {
+        self.total_secs += secs;
+        self.swaps += 1;
+    }
+
+    fn merge(&mut self, other: &CompletionTime) {
+        self.total_secs += other.total_secs;
+        self.swaps += other.swaps;
+    }
+
+    fn mean(&self) -> Option<u64> {
+        self.total_secs.checked_div(self.swaps)
+    }
+}
+
+/// Counters of a daily stats bucket, amounts are in satoshis and piconeros
+#[derive(Debug, Clone, Default, StrictEncode, StrictDecode)]
+struct StatsCounters {
+    initiated: u64,
+    success: u64,
+    refund: u64,
+    punish: u64,
+    abort: u64,
+    funding_canceled_btc: u64,
+    funding_canceled_xmr: u64,
+    volume_btc: u64,
+    volume_xmr: u64,
+    fees_btc: u64,
+    success_time: CompletionTime,
+    refund_time: CompletionTime,
+    punish_time: CompletionTime,
+    abort_time: CompletionTime,
+}
+
+impl StatsCounters {
+    fn merge(&mut self, other: &StatsCounters) {
+        self.initiated += other.initiated;
+        self.success += other.success;
+        self.refund += other.refund;
+        self.punish += other.punish;
+        self.abort += other.abort;
+        self.funding_canceled_btc += other.funding_canceled_btc;
+        self.funding_canceled_xmr += other.funding_canceled_xmr;
+        self.volume_btc += other.volume_btc;
+        self.volume_xmr += other.volume_xmr;
+        self.fees_btc += other.fees_btc;
+        self.success_time.merge(&other.success_time);
+        self.refund_time.merge(&other.refund_time);
+        self.punish_time.merge(&other.punish_time);
+        self.abort_time.merge(&other.abort_time);
+    }
+
+    fn into_bucket(self, period: String, network: Network, swap_role: SwapRole) -> StatsBucket {
+        StatsBucket {
+            period,
+            network,
+            swap_role,
+            initiated: self.initiated,
+            success: self.success,
+            refund: self.refund,
+            punish: self.punish,
+            abort: self.abort,
+            funding_canceled_btc: self.funding_canceled_btc,
+            funding_canceled_xmr: self.funding_canceled_xmr,
+            volume_btc: bitcoin::Amount::from_sat(self.volume_btc),
+            volume_xmr: monero::Amount::from_pico(self.volume_xmr),
+            fees_btc: bitcoin::Amount::from_sat(self.fees_btc),
+            mean_success_secs: self.success_time.mean(),
+            mean_refund_secs: self.refund_time.mean(),
+            mean_punish_secs: self.punish_time.mean(),
+            mean_abort_secs: self.abort_time.mean(),
+        }
+    }
 }
 
 struct Database(lmdb::Environment);
@@ -400,6 +589,13 @@ const LMDB_CHECKPOINT_INFOS: &str = "checkpoint_infos";
 const LMDB_BITCOIN_ADDRESSES: &str = "bitcoin_addresses";
 const LMDB_MONERO_ADDRESSES: &str = "monero_addresses";
 const LMDB_DEAL_HISTORY: &str = "deal_history";
+const LMDB_STATS: &str = "stats";
+const LMDB_SWAP_STARTS: &str = "swap_starts";
+const LMDB_PEERS: &str = "peers";
+const LMDB_PEER_MESSAGES: &str = "peer_messages";
+
+/// Number of last known addresses kept per peer in the address book
+const PEER_ADDRESSES_KEPT: usize = 5;
 
 impl Database {
     fn new(path: PathBuf) -> Result<Database, lmdb::Error> {
@@ -412,9 +608,129 @@ impl Database {
         env.create_db(Some(LMDB_BITCOIN_ADDRESSES), lmdb::DatabaseFlags::empty())?;
         env.create_db(Some(LMDB_DEAL_HISTORY), lmdb::DatabaseFlags::empty())?;
         env.create_db(Some(LMDB_MONERO_ADDRESSES), lmdb::DatabaseFlags::empty())?;
+        env.create_db(Some(LMDB_STATS), lmdb::DatabaseFlags::empty())?;
+        env.create_db(Some(LMDB_SWAP_STARTS), lmdb::DatabaseFlags::empty())?;
+        env.create_db(Some(LMDB_PEERS), lmdb::DatabaseFlags::empty())?;
+        env.create_db(Some(LMDB_PEER_MESSAGES), lmdb::DatabaseFlags::empty())?;
         Ok(Database(env))
     }
 
+    fn record_stats(&mut self, event: &StatsEvent) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_STATS))?;
+        let starts_db = self.0.open_db(Some(LMDB_SWAP_STARTS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        StatsKey {
+            day: event.day(),
+            network: event.network,
+            swap_role: event.swap_role,
+        }
+        .strict_encode(&mut key)?;
+        let mut counters = match tx.get(db, &key) {
+            Ok(val) => StatsCounters::strict_decode(val)?,
+            Err(_) => StatsCounters::default(),
+        };
+        let mut swap_key = vec![];
+        event.swap_id.strict_encode(&mut swap_key)?;
+
+        match &event.kind {
+            StatsEventKind::Initiated => {
+                counters.initiated += 1;
+                let mut val = vec![];
+                event.timestamp.strict_encode(&mut val)?;
+                tx.put(starts_db, &swap_key, &val, lmdb::WriteFlags::empty())?;
+            }
+            StatsEventKind::FundingFee(fee) => counters.fees_btc += fee.as_sat(),
+            StatsEventKind::FundingCanceled(Blockchain::Bitcoin) => {
+                counters.funding_canceled_btc += 1
+            }
+            StatsEventKind::FundingCanceled(Blockchain::Monero) => {
+                counters.funding_canceled_xmr += 1
+            }
+            StatsEventKind::Ended {
+                outcome,
+                volume_btc,
+                volume_xmr,
+            } => {
+                // swaps started before stats were recorded have no known start
+                let started = match tx.get(starts_db, &swap_key) {
+                    Ok(val) => Some(i64::strict_decode(val)?),
+                    Err(_) => None,
+                };
+                if started.is_some() {
+                    tx.del(starts_db, &swap_key, None)?;
+                }
+                let completion_time = match outcome {
+                    Outcome::SuccessSwap => {
+                        counters.success += 1;
+                        counters.volume_btc += volume_btc.as_sat();
+                        counters.volume_xmr += volume_xmr.as_pico();
+                        &mut counters.success_time
+                    }
+                    Outcome::FailureRefund => {
+                        counters.refund += 1;
+                        &mut counters.refund_time
+                    }
+                    Outcome::FailurePunish => {
+                        counters.punish += 1;
+                        &mut counters.punish_time
+                    }
+                    Outcome::FailureAbort => {
+                        counters.abort += 1;
+                        &mut counters.abort_time
+                    }
+                };
+                if let Some(started) = started {
+                    completion_time.add(event.timestamp.saturating_sub(started).max(0) as u64);
+                }
+            }
+        }
+
+        let mut val = vec![];
+        counters.strict_encode(&mut val)?;
+        tx.put(db, &key, &val, lmdb::WriteFlags::empty())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_stats(&mut self, query: &StatsQuery) -> Result<Vec<StatsBucket>, Error> {
+        let db = self.0.open_db(Some(LMDB_STATS))?;
+        let tx = self.0.begin_ro_txn()?;
+        let mut cursor = tx.open_ro_cursor(db)?;
+        let mut groups: HashMap<(String, Network, SwapRole), StatsCounters> = HashMap::new();
+        for (key, val) in cursor.iter() {
+            let StatsKey {
+                day,
+                network,
+                swap_role,
+            } = StatsKey::strict_decode(IoCursor::new(key.to_vec()))?;
+            if matches!(query.since, Some(since) if day < since) {
+                continue;
+            }
+            let counters = StatsCounters::strict_decode(IoCursor::new(val.to_vec()))?;
+            groups
+                .entry((day.period(query.group_by), network, swap_role))
+                .or_default()
+                .merge(&counters);
+        }
+        drop(cursor);
+        tx.abort();
+        let mut buckets: Vec<StatsBucket> = groups
+            .into_iter()
+            .map(|((period, network, swap_role), counters)| {
+                counters.into_bucket(period, network, swap_role)
+            })
+            .collect();
+        buckets.sort_by_key(|bucket| {
+            (
+                bucket.period.clone(),
+                bucket.network.to_string(),
+                bucket.swap_role.to_string(),
+            )
+        });
+        Ok(buckets)
+    }
+
     fn set_deal(&mut self, deal: &Deal, value: &DealValue) -> Result<(), Error> {
         let db = self.0.open_db(Some(LMDB_DEAL_HISTORY))?;
         let mut tx = self.0.begin_rw_txn()?;
@@ -440,6 +756,7 @@ impl Database {
                 let DealValue {
                     local_trade_role,
                     status,
+                    ephemeral_node_id,
                 } = match DealValue::strict_decode(IoCursor::new(val.to_vec())) {
                     Err(err) => {
                         return Some(Err(Error::from(err)));
@@ -464,6 +781,7 @@ impl Database {
                             deal,
                             status: filtered_status,
                             local_trade_role,
+                            ephemeral_node_id,
                         })
                         .map_err(Error::from),
                 )
@@ -471,6 +789,146 @@ impl Database {
             .collect()
     }
 
+    fn set_peer_seen(
+        &mut self,
+        node_id: NodeId,
+        address: Option<InetSocketAddr>,
+        swap_id: Option<SwapId>,
+    ) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_PEERS))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        node_id.strict_encode(&mut key)?;
+        let mut entry = match tx.get(db, &key) {
+            Ok(val) => PeerEntry::strict_decode(IoCursor::new(val.to_vec()))?,
+            Err(_) => PeerEntry {
+                node_id,
+                addresses: vec![],
+                last_seen: 0,
+                swaps: vec![],
+            },
+        };
+        if let Some(address) = address {
+            entry.addresses.retain(|known| *known != address);
+            entry.addresses.insert(0, address);
+            entry.addresses.truncate(PEER_ADDRESSES_KEPT);
+        }
+        if let Some(swap_id) = swap_id {
+            if !entry.swaps.contains(&swap_id) {
+                entry.swaps.push(swap_id);
+            }
+        }
+        entry.last_seen = chrono::Utc::now().timestamp();
+        if tx.get(db, &key).is_ok() {
+            tx.del(db, &key, None)?;
+        }
+        let mut val = vec![];
+        entry.strict_encode(&mut val)?;
+        tx.put(db, &key, &val, lmdb::WriteFlags::empty())?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_peer_entry(&mut self, node_id: &NodeId) -> Result<PeerEntry, Error> {
+        let db = self.0.open_db(Some(LMDB_PEERS))?;
+        let tx = self.0.begin_ro_txn()?;
+        let mut key = vec![];
+        node_id.strict_encode(&mut key)?;
+        let val = tx.get(db, &key)?.to_vec();
+        tx.abort();
+        Ok(PeerEntry::strict_decode(IoCursor::new(val))?)
+    }
+
+    fn store_peer_message(&mut self, msg: PeerMsg) -> Result<(), Error> {
+        let msg_type = msg.msg_id().msg_type;
+        self.update_peer_messages(msg.swap_id(), |messages| {
+            messages
+                .outbox
+                .retain(|stored| stored.msg_id().msg_type != msg_type);
+            messages.outbox.push(msg);
+        })
+    }
+
+    fn acknowledge_peer_message(&mut self, receipt: &Receipt) -> Result<(), Error> {
+        self.update_peer_messages(receipt.swap_id, |messages| {
+            messages
+                .outbox
+                .retain(|stored| stored.msg_id().msg_type != receipt.msg_type);
+        })
+    }
+
+    fn set_peer_message_received(&mut self, receipt: &Receipt) -> Result<(), Error> {
+        self.update_peer_messages(receipt.swap_id, |messages| {
+            if !messages.received.contains(&receipt.msg_type) {
+                messages.received.push(receipt.msg_type);
+            }
+        })
+    }
+
+    /// Apply the update to the peer messages of the swap, an entry left empty is not stored
+    fn update_peer_messages(
+        &mut self,
+        swap_id: SwapId,
+        update: impl FnOnce(&mut PeerMessages),
+    ) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_PEER_MESSAGES))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        swap_id.strict_encode(&mut key)?;
+        let (mut messages, exists) = match tx.get(db, &key) {
+            Ok(val) => (
+                PeerMessages::strict_decode(IoCursor::new(val.to_vec()))?,
+                true,
+            ),
+            Err(_) => (
+                PeerMessages {
+                    swap_id,
+                    outbox: vec![],
+                    received: vec![],
+                },
+                false,
+            ),
+        };
+        update(&mut messages);
+        if exists {
+            tx.del(db, &key, None)?;
+        }
+        if !messages.outbox.is_empty() || !messages.received.is_empty() {
+            let mut val = vec![];
+            messages.strict_encode(&mut val)?;
+            tx.put(db, &key, &val, lmdb::WriteFlags::empty())?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_peer_messages(&mut self, swap_id: SwapId) -> Result<PeerMessages, Error> {
+        let db = self.0.open_db(Some(LMDB_PEER_MESSAGES))?;
+        let tx = self.0.begin_ro_txn()?;
+        let mut key = vec![];
+        swap_id.strict_encode(&mut key)?;
+        let messages = match tx.get(db, &key) {
+            Ok(val) => PeerMessages::strict_decode(IoCursor::new(val.to_vec()))?,
+            Err(_) => PeerMessages {
+                swap_id,
+                outbox: vec![],
+                received: vec![],
+            },
+        };
+        tx.abort();
+        Ok(messages)
+    }
+
+    fn delete_peer_messages(&mut self, swap_id: SwapId) -> Result<(), Error> {
+        let db = self.0.open_db(Some(LMDB_PEER_MESSAGES))?;
+        let mut tx = self.0.begin_rw_txn()?;
+        let mut key = vec![];
+        swap_id.strict_encode(&mut key)?;
+        tx.del(db, &key, None)?;
+        tx.commit()?;
+        Ok(())
+    }
+
     fn set_bitcoin_address(
         &mut self,
         address: &bitcoin::Address,
@@ -678,6 +1136,8 @@ impl Database {
 #[test]
 fn test_lmdb_state() {
     use crate::bus::Outcome;
+    use crate::bus::SwapParams;
+    use crate::farcasterd::stats::StatsGrouping;
     use bitcoin::secp256k1::SecretKey;
     use farcaster_core::role::TradeRole;
     use farcaster_core::Uuid;
@@ -717,12 +1177,30 @@ fn test_lmdb_state() {
         trade_role: TradeRole::Maker,
         deal: Deal::from_str("Deal:Cke4ftrP5A781Vq85dgBQJNwYgBS4nuUV1LQM2fvVdFMNR4h5TrWhRR11111uMFuZTAsNgpdK8DiK11111TB9zym113GTvtvqfD1111114A4TTfFfmZoWyvpcjDBtTZCdWFSUWcRKYfEC3Y17hqaXZ3dWz11111111111111111111111111111111111111111AfZ113SEBTEspU3a").unwrap(),
         expected_counterparty_node_id: None,
+        swap_params: SwapParams {
+            arb_finality: Some(1),
+            arb_safety: Some(3),
+            acc_finality: Some(1),
+        },
     };
     database.set_checkpoint_info(&key_info, &val_info).unwrap();
     let res = database.get_checkpoint_info(&key_info).unwrap();
     assert_eq!(val_info, res);
     database.delete_checkpoint_info(key_info).unwrap();
 
+    // entries written before the per-swap parameters were added still decode
+    let mut old_info = vec![];
+    val_info.swap_id.strict_encode(&mut old_info).unwrap();
+    val_info.deal.strict_encode(&mut old_info).unwrap();
+    val_info.trade_role.strict_encode(&mut old_info).unwrap();
+    val_info
+        .expected_counterparty_node_id
+        .strict_encode(&mut old_info)
+        .unwrap();
+    let res = CheckpointEntry::strict_decode(IoCursor::new(old_info)).unwrap();
+    assert_eq!(res.deal, val_info.deal);
+    assert!(res.swap_params.is_empty());
+
     let sk = SecretKey::new(&mut bitcoin::secp256k1::rand::thread_rng());
     let private_key =
         bitcoin::PrivateKey::from_slice(&sk.secret_bytes(), bitcoin::Network::Testnet).unwrap();
@@ -770,6 +1248,7 @@ fn test_lmdb_state() {
             &DealValue {
                 status: DealStatus::Open,
                 local_trade_role: TradeRole::Taker,
+                ephemeral_node_id: false,
             },
         )
         .unwrap();
@@ -785,6 +1264,7 @@ fn test_lmdb_state() {
             &DealValue {
                 status: DealStatus::InProgress,
                 local_trade_role: TradeRole::Maker,
+                ephemeral_node_id: false,
             },
         )
         .unwrap();
@@ -797,6 +1277,7 @@ fn test_lmdb_state() {
             &DealValue {
                 status: DealStatus::Ended(Outcome::SuccessSwap),
                 local_trade_role: TradeRole::Maker,
+                ephemeral_node_id: false,
             },
         )
         .unwrap();
@@ -809,6 +1290,7 @@ fn test_lmdb_state() {
             &DealValue {
                 status: DealStatus::Open,
                 local_trade_role: TradeRole::Maker,
+                ephemeral_node_id: true,
             },
         )
         .unwrap();
@@ -818,14 +1300,137 @@ fn test_lmdb_state() {
         deal: deal_1,
         status: DealStatus::Ended(Outcome::SuccessSwap),
         local_trade_role: TradeRole::Maker,
+        ephemeral_node_id: false,
     };
     let status_2 = DealInfo {
         serialized_deal: deal_2.to_string(),
         deal: deal_2,
         status: DealStatus::Open,
         local_trade_role: TradeRole::Maker,
+        ephemeral_node_id: true,
     };
     assert!(deals_retrieved.len() == 2);
     assert!(deals_retrieved.contains(&status_1));
     assert!(deals_retrieved.contains(&status_2));
+
+    // a deal recorded without the ephemeral node id flag
+    let mut legacy_value = vec![];
+    DealStatus::Open.strict_encode(&mut legacy_value).unwrap();
+    TradeRole::Maker.strict_encode(&mut legacy_value).unwrap();
+    let value = DealValue::strict_decode(IoCursor::new(legacy_value)).unwrap();
+    assert!(!value.ephemeral_node_id);
+
+    let swap_id = SwapId(Uuid::new());
+    let started = chrono::Utc::now().timestamp();
+    let query = StatsQuery {
+        since: Some(StatsDay::from_timestamp(started)),
+        group_by: StatsGrouping::Day,
+    };
+    let bucket = |database: &mut Database| {
+        database
+            .get_stats(&query)
+            .unwrap()
+            .into_iter()
+            .find(|bucket| {
+                bucket.network == Network::Testnet
+                    && bucket.swap_role == SwapRole::Bob
+                    && bucket.period == StatsDay::from_timestamp(started).to_string()
+            })
+    };
+    let before = bucket(&mut database);
+    let event = |timestamp, kind| StatsEvent {
+        swap_id,
+        network: Network::Testnet,
+        swap_role: SwapRole::Bob,
+        timestamp,
+        kind,
+    };
+    database
+        .record_stats(&event(started, StatsEventKind::Initiated))
+        .unwrap();
+    database
+        .record_stats(&event(
+            started,
+            StatsEventKind::FundingFee(bitcoin::Amount::from_sat(1000)),
+        ))
+        .unwrap();
+    database
+        .record_stats(&event(
+            started,
+            StatsEventKind::Ended {
+                outcome: Outcome::SuccessSwap,
+                volume_btc: bitcoin::Amount::from_sat(100_000),
+                volume_xmr: monero::Amount::from_pico(1_000_000),
+            },
+        ))
+        .unwrap();
+    let after = bucket(&mut database).unwrap();
+    let (initiated, success, fees_btc, volume_xmr) = before
+        .map(|b| (b.initiated, b.success, b.fees_btc, b.volume_xmr))
+        .unwrap_or((0, 0, bitcoin::Amount::ZERO, monero::Amount::ZERO));
+    assert_eq!(after.initiated, initiated + 1);
+    assert_eq!(after.success, success + 1);
+    assert_eq!(after.fees_btc, fees_btc + bitcoin::Amount::from_sat(1000));
+    assert_eq!(
+        after.volume_xmr,
+        volume_xmr + monero::Amount::from_pico(1_000_000)
+    );
+    assert!(after.mean_success_secs.is_some());
+
+    let secp = bitcoin::secp256k1::Secp256k1::new();
+    let node_id = NodeId::from(bitcoin::secp256k1::PublicKey::from_secret_key(
+        &secp,
+        &SecretKey::new(&mut bitcoin::secp256k1::rand::thread_rng()),
+    ));
+    assert!(database.get_peer_entry(&node_id).is_err());
+    let address_1 = InetSocketAddr::from_str("127.0.0.1:7067").unwrap();
+    let address_2 = InetSocketAddr::from_str("127.0.0.1:7068").unwrap();
+    database
+        .set_peer_seen(node_id, Some(address_1), Some(swap_id))
+        .unwrap();
+    database
+        .set_peer_seen(node_id, Some(address_2), None)
+        .unwrap();
+    database
+        .set_peer_seen(node_id, Some(address_1), Some(swap_id))
+        .unwrap();
+    let entry = database.get_peer_entry(&node_id).unwrap();
+    assert_eq!(entry.addresses, vec![address_1, address_2]);
+    assert_eq!(entry.swaps, vec![swap_id]);
+    assert!(entry.last_seen >= started);
+
+    let abort = |error_body: &str| {
+        PeerMsg::Abort(farcaster_core::protocol::message::Abort {
+            swap_id,
+            error_body: Some(error_body.to_string()),
+        })
+    };
+    let not_found = PeerMsg::DealNotFound(swap_id);
+    let messages = database.get_peer_messages(swap_id).unwrap();
+    assert!(messages.outbox.is_empty() && messages.received.is_empty());
+    database.store_peer_message(abort("first")).unwrap();
+    database.store_peer_message(not_found.clone()).unwrap();
+    database.store_peer_message(abort("second")).unwrap();
+    let messages = database.get_peer_messages(swap_id).unwrap();
+    assert_eq!(messages.outbox.len(), 2);
+    assert!(matches!(
+        &messages.outbox[1],
+        PeerMsg::Abort(farcaster_core::protocol::message::Abort { error_body: Some(body), .. })
+            if body == "second"
+    ));
+    database
+        .acknowledge_peer_message(&not_found.msg_id())
+        .unwrap();
+    database
+        .set_peer_message_received(&not_found.msg_id())
+        .unwrap();
+    database
+        .set_peer_message_received(&not_found.msg_id())
+        .unwrap();
+    let messages = database.get_peer_messages(swap_id).unwrap();
+    assert_eq!(messages.outbox.len(), 1);
+    assert_eq!(messages.received, vec![not_found.msg_id().msg_type]);
+    database.delete_peer_messages(swap_id).unwrap();
+    let messages = database.get_peer_messages(swap_id).unwrap();
+    assert!(messages.outbox.is_empty() && messages.received.is_empty());
 }
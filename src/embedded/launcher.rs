@@ -0,0 +1,235 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::any::Any;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use clap::Parser;
+use internet2::addr::InetSocketAddr;
+use lazy_static::lazy_static;
+
+use crate::bus::ctl::Token;
+use crate::farcasterd::supervisor::ServiceStatus;
+use crate::opts::Opts;
+use crate::service::{mark_service_thread, ServiceExitCode};
+use crate::{databased, grpcd, peerd, swapd, syncerd, walletd};
+use crate::{Error, ServiceConfig};
+
+lazy_static! {
+    /// Shared options of the running embedded node, forwarded to every launched service
+    static ref SHARED_OPTS: Mutex<Option<Opts>> = Mutex::new(None);
+}
+
+/// Identifier of the next service thread, reported in place of a PID
+static NEXT_THREAD_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Returns the shared options of the embedded node if one is running in this process
+pub fn shared_opts() -> Option<Opts> {
+    SHARED_OPTS
+        .lock()
+        .expect("embedded node lock poisoned")
+        .clone()
+}
+
+pub(super) fn set_shared_opts(opts: Option<Opts>) {
+    *SHARED_OPTS.lock().expect("embedded node lock poisoned") = opts;
+}
+
+/// A service running as a thread of the embedded node
+pub struct ServiceThread {
+    id: u32,
+    status: Arc<Mutex<Option<ServiceStatus>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ServiceThread {
+    /// Run the service in a new thread, the thread is stopped by [`crate::service::exit_service`]
+    pub(super) fn spawn(
+        name: &str,
+        service: impl FnOnce(Arc<AtomicBool>) -> Result<(), Error> + Send + 'static,
+    ) -> io::Result<Self> {
+        let id = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+        let status = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_status = Arc::clone(&status);
+        let thread_stop = Arc::clone(&stop);
+        let label = name.to_string();
+        thread::Builder::new()
+            .name(format!("{}-{}", name, id))
+            .spawn(move || {
+                mark_service_thread();
+                let res = panic::catch_unwind(AssertUnwindSafe(|| service(thread_stop)));
+                let status = match res {
+                    Ok(Ok(())) => ServiceStatus {
+                        success: true,
+                        description: s!("exit code: 0"),
+                    },
+                    Ok(Err(err)) => {
+                        error!("Error running {}: {}", label, err);
+                        ServiceStatus {
+                            success: false,
+                            description: format!("error: {}", err),
+                        }
+                    }
+                    Err(payload) => unwind_status(payload),
+                };
+                *thread_status.lock().expect("service status lock poisoned") = Some(status);
+            })?;
+
+        Ok(ServiceThread { id, status, stop })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the exit status once the thread stopped
+    pub fn try_wait(&self) -> Option<ServiceStatus> {
+        self.status
+            .lock()
+            .expect("service status lock poisoned")
+            .clone()
+    }
+
+    /// Ask the service to stop, only honored by the listening peerd
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn unwind_status(payload: Box<dyn Any + Send>) -> ServiceStatus {
+    if let Some(ServiceExitCode(code)) = payload.downcast_ref::<ServiceExitCode>() {
+        ServiceStatus {
+            success: *code == 0,
+            description: format!("exit code: {}", code),
+        }
+    } else {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .map(|msg| msg.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| s!("unknown"));
+        ServiceStatus {
+            success: false,
+            description: format!("panic: {}", msg),
+        }
+    }
+}
+
+/// Launch a service as a thread, parsing the same arguments as its binary
+pub fn spawn_service(name: &str, shared: &Opts, args: Vec<String>) -> io::Result<ServiceThread> {
+    let mut argv = vec![
+        name.to_string(),
+        s!("-d"),
+        shared.data_dir.to_string_lossy().to_string(),
+        s!("-m"),
+        shared.msg_socket.to_string(),
+        s!("-x"),
+        shared.ctl_socket.to_string(),
+        s!("-i"),
+        shared.info_socket.to_string(),
+        s!("-S"),
+        shared.sync_socket.to_string(),
+    ];
+    if let Some(tor_proxy) = shared.tor_proxy {
+        argv.extend([s!("-T"), tor_proxy.to_string()]);
+    }
+    argv.extend(args);
+
+    // the arguments may carry secrets handed over on stdin to the service binaries
+    debug!("Launching {} as a thread", name);
+    let service = name.to_string();
+    ServiceThread::spawn(name, move |stop| run_service(&service, argv, stop))
+}
+
+fn run_service(name: &str, argv: Vec<String>, stop: Arc<AtomicBool>) -> Result<(), Error> {
+    match name {
+        "walletd" => {
+            let mut opts = walletd::Opts::try_parse_from(argv).map_err(parse_error)?;
+            opts.process();
+            let node_secrets =
+                walletd::NodeSecrets::new(opts.key_opts.key_file.clone(), opts.passphrase()?)?;
+            walletd::run(
+                opts.shared.into(),
+                Token(opts.wallet_token.token),
+                node_secrets,
+                opts.hot_wallet_network,
+            )
+        }
+        "databased" => {
+            let mut opts = databased::Opts::try_parse_from(argv).map_err(parse_error)?;
+            opts.process();
+            let data_dir = opts.absolute_data_dir_path();
+            databased::run(opts.shared.into(), data_dir)
+        }
+        "grpcd" => {
+            let mut opts = grpcd::Opts::try_parse_from(argv).map_err(parse_error)?;
+            opts.process();
+            let data_dir = opts.absolute_data_dir_path();
+            grpcd::run(
+                opts.shared.into(),
+                opts.grpc_port,
+                opts.grpc_ip,
+                data_dir,
+                !opts.no_auth,
+            )
+        }
+        "syncerd" => {
+            let mut opts = syncerd::Opts::try_parse_from(argv).map_err(parse_error)?;
+            opts.process();
+            syncerd::run(opts.shared.clone().into(), opts)
+        }
+        "swapd" => {
+            let mut opts = swapd::Opts::try_parse_from(argv).map_err(parse_error)?;
+            opts.process();
+            swapd::run(opts.shared.clone().into(), opts)
+        }
+        "peerd" => {
+            let mut opts = peerd::Opts::try_parse_from(argv).map_err(parse_error)?;
+            opts.process();
+            let service_config: ServiceConfig = opts.shared.clone().into();
+            let local_node = opts.peer_key_opts.local_node();
+            let local_init = opts.local_init();
+            let limits = opts.peer_limits();
+            // the listener always runs in threaded mode, forking would duplicate the whole
+            // application embedding the node
+            match (opts.connect, opts.listen) {
+                (Some(remote_node), _) => peerd::run_from_connect(
+                    service_config,
+                    remote_node,
+                    opts.shared.tor_proxy,
+                    None,
+                    local_node,
+                    local_init,
+                    limits,
+                ),
+                (None, Some(bind_addr)) => peerd::run_threaded_listener(
+                    service_config,
+                    InetSocketAddr::socket(
+                        bind_addr.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+                        opts.port,
+                    ),
+                    local_node,
+                    local_init,
+                    limits,
+                    stop,
+                ),
+                (None, None) => unreachable!("Clap requires either `connect` or `listen`"),
+            }
+        }
+        _ => Err(Error::Other(format!("Unknown service {}", name))),
+    }
+}
+
+fn parse_error(err: clap::Error) -> Error {
+    Error::Other(err.to_string())
+}
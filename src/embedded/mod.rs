@@ -0,0 +1,450 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Farcaster node embedded in another application: every service runs as a thread of the
+//! current process and the services talk over `inproc://` ZMQ endpoints. The node is driven
+//! through the typed methods of [`Node`], mirroring the `swap-cli` commands. The hot wallet and
+//! grpc token commands are left out, they are served by walletd and grpcd to their own clients.
+//!
+//! Only one embedded node can run per process at a time.
+
+mod launcher;
+
+use std::net::SocketAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+
+use amplify::Wrapper;
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::secp256k1::rand::{thread_rng, RngCore};
+use clap::Parser;
+use farcaster_core::blockchain::{Blockchain, Network};
+use farcaster_core::swap::btcxmr::Deal;
+use farcaster_core::swap::SwapId;
+use internet2::addr::{NodeAddr, NodeId};
+use uuid::Uuid;
+
+pub use launcher::{shared_opts, spawn_service, ServiceThread};
+
+use crate::bus::ctl::{CtlMsg, ProtoDeal, PubDeal, Token};
+use crate::bus::info::{
+    Address, AddressBalance, DealStatusSelector, FundingInfos, InfoMsg, MadeDeal, NodeInfo,
+    PeerEntry, PeerInfo, SwapProgress, TookDeal,
+};
+use crate::bus::{AddressSecretKey, BusMsg, CheckpointEntry, DealInfo, OptionDetails};
+use crate::client::Client;
+use crate::config::parse_config;
+use crate::farcasterd::acceptance_policy::AcceptancePolicyReport;
+use crate::farcasterd::deal_validation::DealValidationReport;
+use crate::farcasterd::stats::{StatsBucket, StatsQuery};
+use crate::farcasterd::{self, supervisor::ServiceStatus};
+use crate::syncerd::runtime::SyncerdTask;
+use crate::syncerd::{Health, SweepAddressAddendum, SweepBitcoinAddress, SweepMoneroAddress};
+use crate::{Config, Error, ServiceConfig, ServiceId};
+
+/// Delay given to farcasterd to bind the endpoints before connecting the client
+const STARTUP_DELAY: Duration = Duration::from_secs(1);
+
+/// Configuration of an embedded node
+#[derive(Clone, Debug)]
+pub struct NodeConfig {
+    /// Directory holding the databases, the node key and the configuration file
+    pub data_dir: PathBuf,
+    /// Node configuration, read from (or created as) `farcasterd.toml` in the data directory
+    /// when absent
+    pub config: Option<Config>,
+    /// SOCKS5 proxy used for Tor connectivity
+    pub tor_proxy: Option<SocketAddr>,
+}
+
+impl NodeConfig {
+    pub fn with_data_dir(data_dir: impl Into<PathBuf>) -> Self {
+        NodeConfig {
+            data_dir: data_dir.into(),
+            config: None,
+            tor_proxy: None,
+        }
+    }
+}
+
+/// Handle on a running embedded node
+pub struct Node {
+    client: Client,
+    farcasterd: ServiceThread,
+}
+
+impl Node {
+    /// Start farcasterd and the services it launches as threads of the current process
+    pub fn start(node_config: NodeConfig) -> Result<Self, Error> {
+        if shared_opts().is_some() {
+            return Err(Error::Farcaster(s!(
+                "An embedded node is already running in this process"
+            )));
+        }
+
+        // unique endpoint names, a previous node of this process may still hold its sockets
+        let instance = Uuid::new_v4().simple().to_string();
+        let mut args = vec![
+            s!("farcasterd"),
+            s!("-d"),
+            node_config.data_dir.to_string_lossy().to_string(),
+            s!("-m"),
+            format!("inproc://farcaster-{}-msg", instance),
+            s!("-x"),
+            format!("inproc://farcaster-{}-ctl", instance),
+            s!("-i"),
+            format!("inproc://farcaster-{}-info", instance),
+            s!("-S"),
+            format!("inproc://farcaster-{}-sync", instance),
+        ];
+        if let Some(tor_proxy) = node_config.tor_proxy {
+            args.extend([s!("-T"), tor_proxy.to_string()]);
+        }
+        let mut opts =
+            farcasterd::Opts::try_parse_from(args).map_err(|err| Error::Other(err.to_string()))?;
+        opts.process();
+
+        let service_config: ServiceConfig = opts.shared.clone().into();
+        let config = match node_config.config {
+            Some(config) => config,
+            None => parse_config(&opts.config)?,
+        };
+
+        let mut dest = [0u8; 16];
+        thread_rng().fill_bytes(&mut dest);
+        let token = Token(dest.to_hex());
+
+        launcher::set_shared_opts(Some(opts.shared.clone()));
+        let farcasterd_config = service_config.clone();
+        let farcasterd = ServiceThread::spawn("farcasterd", move |_| {
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                // the embedding application does not reload the configuration with a signal
+                farcasterd::run(farcasterd_config, config, opts, token, Default::default())
+            }));
+            launcher::set_shared_opts(None);
+            res.unwrap_or_else(|payload| panic::resume_unwind(payload))
+        })
+        .map_err(|err| {
+            launcher::set_shared_opts(None);
+            err
+        })?;
+
+        sleep(STARTUP_DELAY);
+        if let Some(status) = farcasterd.try_wait() {
+            return Err(Error::Farcaster(format!(
+                "farcasterd failed to start: {}",
+                status
+            )));
+        }
+        let client = Client::with(service_config)?;
+        Ok(Node { client, farcasterd })
+    }
+
+    /// Returns the exit status of farcasterd once the node stopped
+    pub fn status(&self) -> Option<ServiceStatus> {
+        self.farcasterd.try_wait()
+    }
+
+    /// General information about the running node
+    pub fn info(&mut self) -> Result<NodeInfo, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::GetInfo)? {
+            InfoMsg::NodeInfo(info) => Ok(info),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Lists existing peer connections
+    pub fn peers(&mut self) -> Result<Vec<NodeAddr>, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::ListPeers)? {
+            InfoMsg::PeerList(list) => Ok(list.into_inner()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Connection statistics of a connected peer
+    pub fn peer_info(&mut self, node_addr: NodeAddr) -> Result<PeerInfo, Error> {
+        // farcasterd forwards the request to the peerd with this address
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::GetPeerInfo(node_addr))? {
+            InfoMsg::PeerInfo(info) => Ok(info),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Returns the address book entry of a peer
+    pub fn peer_entry(&mut self, node_id: NodeId) -> Result<PeerEntry, Error> {
+        match self.info_request(ServiceId::Database, InfoMsg::GetPeerEntry(node_id))? {
+            InfoMsg::PeerEntry(entry) => Ok(entry),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Lists running swaps
+    pub fn list_swaps(&mut self) -> Result<Vec<SwapId>, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::ListSwaps)? {
+            InfoMsg::SwapList(list) => Ok(list.into_inner()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Lists deals created by the node
+    pub fn list_deals(&mut self, select: DealStatusSelector) -> Result<Vec<DealInfo>, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::ListDeals(select))? {
+            InfoMsg::DealList(list) => Ok(list.into_inner()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Lists the tasks of a syncer
+    pub fn list_tasks(
+        &mut self,
+        blockchain: Blockchain,
+        network: Network,
+    ) -> Result<Vec<SyncerdTask>, Error> {
+        match self.info_request(ServiceId::Syncer(blockchain, network), InfoMsg::ListTasks)? {
+            InfoMsg::TaskList(list) => Ok(list.into_inner()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Lists the addresses the node listens on for peer connections
+    pub fn list_listens(&mut self) -> Result<Vec<String>, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::ListListens)? {
+            InfoMsg::ListenList(list) => Ok(list.into_inner()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Lists all saved checkpoints
+    pub fn list_checkpoints(&mut self) -> Result<Vec<CheckpointEntry>, Error> {
+        match self.info_request(ServiceId::Database, InfoMsg::RetrieveAllCheckpointInfo)? {
+            InfoMsg::CheckpointList(list) => Ok(list.into_inner()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Returns the recorded swap statistics aggregated per period, network and swap role
+    pub fn stats(&mut self, query: StatsQuery) -> Result<Vec<StatsBucket>, Error> {
+        match self.info_request(ServiceId::Database, InfoMsg::GetStats(query))? {
+            InfoMsg::StatsList(list) => Ok(list.into_inner()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Returns the limits of the maker acceptance policy together with their current usage
+    pub fn acceptance_policy(&mut self) -> Result<AcceptancePolicyReport, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::GetAcceptancePolicy)? {
+            InfoMsg::AcceptancePolicy(report) => Ok(report),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Lists the checkpoints of the swaps that are not running and can be restored
+    pub fn list_restorable_checkpoints(&mut self) -> Result<Vec<CheckpointEntry>, Error> {
+        let list = self.list_checkpoints()?;
+        match self.info_request(
+            ServiceId::Farcasterd,
+            InfoMsg::CheckpointList(list.into_iter().collect()),
+        )? {
+            InfoMsg::CheckpointList(list) => Ok(list.into_inner()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Restore a swap from its latest checkpoint
+    pub fn restore_checkpoint(&mut self, swap_id: SwapId) -> Result<(), Error> {
+        match self.info_request(ServiceId::Database, InfoMsg::GetCheckpointEntry(swap_id))? {
+            InfoMsg::CheckpointEntry(entry) => self
+                .ctl_request(ServiceId::Farcasterd, CtlMsg::RestoreCheckpoint(entry))
+                .map(|_| ()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Attempt to reconnect the peer of a running swap
+    pub fn connect_swap(&mut self, swap_id: SwapId) -> Result<(), Error> {
+        self.ctl_request(ServiceId::Farcasterd, CtlMsg::Connect(swap_id))
+            .map(|_| ())
+    }
+
+    /// Check the health of a syncer
+    pub fn health_check(
+        &mut self,
+        blockchain: Blockchain,
+        network: Network,
+    ) -> Result<Health, Error> {
+        match self.ctl_request(
+            ServiceId::Farcasterd,
+            CtlMsg::HealthCheck(blockchain, network),
+        )? {
+            BusMsg::Ctl(CtlMsg::HealthResult(health)) => Ok(health),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Make a new deal and start listening for takers
+    pub fn make_deal(&mut self, proto_deal: ProtoDeal) -> Result<MadeDeal, Error> {
+        match self.ctl_request(ServiceId::Farcasterd, CtlMsg::MakeDeal(proto_deal))? {
+            BusMsg::Info(InfoMsg::MadeDeal(made_deal)) => Ok(made_deal),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Run the configured deal validation policy against a deal before taking it
+    pub fn validate_deal(&mut self, deal: Deal) -> Result<DealValidationReport, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::ValidateDeal(deal))? {
+            InfoMsg::DealValidationReport(report) => Ok(report),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Take a deal and start the swap
+    pub fn take_deal(&mut self, pub_deal: PubDeal) -> Result<TookDeal, Error> {
+        match self.ctl_request(ServiceId::Farcasterd, CtlMsg::TakeDeal(pub_deal))? {
+            BusMsg::Info(InfoMsg::TookDeal(took_deal)) => Ok(took_deal),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Revoke a deal made by the node
+    pub fn revoke_deal(&mut self, deal: Deal) -> Result<(), Error> {
+        self.ctl_request(ServiceId::Farcasterd, CtlMsg::RevokeDeal(deal))
+            .map(|_| ())
+    }
+
+    /// Abort a running swap, only possible before funding
+    pub fn abort_swap(&mut self, swap_id: SwapId) -> Result<(), Error> {
+        self.ctl_request(ServiceId::Swap(swap_id), CtlMsg::AbortSwap)
+            .map(|_| ())
+    }
+
+    /// Read the progress of a swap
+    pub fn progress(&mut self, swap_id: SwapId) -> Result<SwapProgress, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::ReadProgress(swap_id))? {
+            InfoMsg::SwapProgress(progress) => Ok(progress),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Lists the swaps waiting for funding on a blockchain
+    pub fn needs_funding(&mut self, blockchain: Blockchain) -> Result<FundingInfos, Error> {
+        match self.info_request(ServiceId::Farcasterd, InfoMsg::NeedsFunding(blockchain))? {
+            InfoMsg::FundingInfos(infos) => Ok(infos),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Sweep the funds of a former swap funding address
+    pub fn sweep_bitcoin_address(
+        &mut self,
+        source_address: bitcoin::Address,
+        destination_address: bitcoin::Address,
+    ) -> Result<(), Error> {
+        match self.address_secret_key(Address::Bitcoin(source_address.clone()))? {
+            AddressSecretKey::Bitcoin {
+                secret_key_info, ..
+            } => self
+                .ctl_request(
+                    ServiceId::Farcasterd,
+                    CtlMsg::SweepAddress(SweepAddressAddendum::Bitcoin(SweepBitcoinAddress {
+                        source_address,
+                        source_secret_key: secret_key_info.secret_key,
+                        destination_address,
+                    })),
+                )
+                .map(|_| ()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Sweep the funds of a former swap funding address
+    pub fn sweep_monero_address(
+        &mut self,
+        source_address: monero::Address,
+        destination_address: monero::Address,
+    ) -> Result<(), Error> {
+        match self.address_secret_key(Address::Monero(source_address))? {
+            AddressSecretKey::Monero {
+                secret_key_info, ..
+            } => self
+                .ctl_request(
+                    ServiceId::Farcasterd,
+                    CtlMsg::SweepAddress(SweepAddressAddendum::Monero(SweepMoneroAddress {
+                        source_spend_key: secret_key_info.spend,
+                        source_view_key: secret_key_info.view,
+                        destination_address,
+                        minimum_balance: monero::Amount::from_pico(0),
+                        from_height: Some(secret_key_info.creation_height),
+                    })),
+                )
+                .map(|_| ()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Get the balance of a former swap funding address
+    pub fn get_balance(&mut self, address: Address) -> Result<AddressBalance, Error> {
+        let address_secret_key = self.address_secret_key(address)?;
+        match self.ctl_request(
+            ServiceId::Farcasterd,
+            CtlMsg::GetBalance(address_secret_key),
+        )? {
+            BusMsg::Info(InfoMsg::AddressBalance(balance)) => Ok(balance),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// Stop the node, once the running swaps reached a safe point if `drain` is set. The node is
+    /// stopped once [`Node::status`] returns the exit status of farcasterd.
+    pub fn shutdown(&mut self, drain: bool) -> Result<(), Error> {
+        self.ctl_request(ServiceId::Farcasterd, CtlMsg::Shutdown(drain))
+            .map(|_| ())
+    }
+
+    /// Reload the configuration file of the data directory, returns the applied settings and the
+    /// ones requiring a restart
+    pub fn reload_config(&mut self) -> Result<String, Error> {
+        match self.ctl_request(ServiceId::Farcasterd, CtlMsg::ReloadConfig)? {
+            BusMsg::Ctl(CtlMsg::Success(OptionDetails(Some(report)))) => Ok(report),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn address_secret_key(&mut self, address: Address) -> Result<AddressSecretKey, Error> {
+        match self.info_request(ServiceId::Database, InfoMsg::GetAddressSecretKey(address))? {
+            InfoMsg::AddressSecretKey(address_secret_key) => Ok(address_secret_key),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn info_request(&mut self, service: ServiceId, req: InfoMsg) -> Result<InfoMsg, Error> {
+        self.client.request_info(service, req)?;
+        match self.response()? {
+            BusMsg::Info(info) => Ok(info),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    fn ctl_request(&mut self, service: ServiceId, req: CtlMsg) -> Result<BusMsg, Error> {
+        self.client.request_ctl(service, req)?;
+        self.response()
+    }
+
+    // Wait for the response and turn failures into errors
+    fn response(&mut self) -> Result<BusMsg, Error> {
+        match self.client.response()? {
+            BusMsg::Ctl(CtlMsg::Failure(failure)) | BusMsg::Info(InfoMsg::Failure(failure)) => {
+                Err(Error::Farcaster(failure.info))
+            }
+            resp => Ok(resp),
+        }
+    }
+}
+
+fn unexpected_response() -> Error {
+    Error::Farcaster(s!("Received unexpected response"))
+}
@@ -114,6 +114,16 @@ pub enum Error {
     #[from]
     BitcoinAmount(bitcoin::util::amount::ParseAmountError),
 
+    /// Bitcoin BIP32 key derivation errors
+    #[display(inner)]
+    #[from]
+    BitcoinBip32(bitcoin::util::bip32::Error),
+
+    /// BIP39 mnemonic errors
+    #[display(inner)]
+    #[from]
+    Bip39(bip39::Error),
+
     /// Monero address errors
     #[display(inner)]
     #[from]
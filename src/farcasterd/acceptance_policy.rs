@@ -0,0 +1,464 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use amplify::ToYamlString;
+use chrono::{DateTime, Utc};
+use farcaster_core::role::{SwapRole, TradeRole};
+use farcaster_core::swap::btcxmr::Deal;
+use internet2::addr::NodeId;
+#[cfg(feature = "serde")]
+use serde_with::DisplayFromStr;
+use strict_encoding::{NetworkDecode, NetworkEncode};
+
+use crate::bus::p2p::RefusalReason;
+use crate::config::{AcceptancePolicyConfig, TimeWindow};
+
+/// A swap counted against the acceptance limits, i.e. any swap for which the deal is consumed
+/// and not yet ended.
+pub struct InProgressSwap {
+    /// The counterparty of the swap, if known
+    pub counterparty: Option<NodeId>,
+    pub deal: Deal,
+    pub trade_role: TradeRole,
+}
+
+/// An incoming taker commit to evaluate against the policy
+pub struct Candidate<'a> {
+    pub node_id: NodeId,
+    pub deal: &'a Deal,
+    pub time: DateTime<Utc>,
+}
+
+/// A rule of the maker acceptance policy. Rules are evaluated in order and the first refusal
+/// stops the evaluation.
+pub trait AcceptanceRule {
+    /// Returns a refusal reason if the candidate must be rejected given the swaps currently in
+    /// progress
+    fn check(&self, candidate: &Candidate, swaps: &[InProgressSwap]) -> Result<(), RefusalReason>;
+}
+
+struct DenyList(Vec<NodeId>);
+
+impl AcceptanceRule for DenyList {
+    fn check(&self, candidate: &Candidate, _: &[InProgressSwap]) -> Result<(), RefusalReason> {
+        if self.0.contains(&candidate.node_id) {
+            Err(RefusalReason::Denied)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct AllowList(Vec<NodeId>);
+
+impl AcceptanceRule for AllowList {
+    fn check(&self, candidate: &Candidate, _: &[InProgressSwap]) -> Result<(), RefusalReason> {
+        if self.0.contains(&candidate.node_id) {
+            Ok(())
+        } else {
+            Err(RefusalReason::NotAllowed)
+        }
+    }
+}
+
+struct TradingHours(Vec<TimeWindow>);
+
+impl AcceptanceRule for TradingHours {
+    fn check(&self, candidate: &Candidate, _: &[InProgressSwap]) -> Result<(), RefusalReason> {
+        let time = candidate.time.time();
+        if self.0.iter().any(|window| window.contains(time)) {
+            Ok(())
+        } else {
+            Err(RefusalReason::OutsideTradingHours)
+        }
+    }
+}
+
+struct MaxConcurrentSwaps(u32);
+
+impl AcceptanceRule for MaxConcurrentSwaps {
+    fn check(&self, _: &Candidate, swaps: &[InProgressSwap]) -> Result<(), RefusalReason> {
+        if swaps.len() as u32 >= self.0 {
+            Err(RefusalReason::MaxConcurrentSwaps)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct MaxConcurrentSwapsPerPeer(u32);
+
+impl AcceptanceRule for MaxConcurrentSwapsPerPeer {
+    fn check(&self, candidate: &Candidate, swaps: &[InProgressSwap]) -> Result<(), RefusalReason> {
+        let with_peer = swaps
+            .iter()
+            .filter(|swap| swap.counterparty == Some(candidate.node_id))
+            .count();
+        if with_peer as u32 >= self.0 {
+            Err(RefusalReason::MaxConcurrentSwapsPerPeer)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct MaxBitcoinExposure(bitcoin::Amount);
+
+impl AcceptanceRule for MaxBitcoinExposure {
+    fn check(&self, candidate: &Candidate, swaps: &[InProgressSwap]) -> Result<(), RefusalReason> {
+        let (exposure, _) = exposure(swaps);
+        let (added, _) = locked_amounts(candidate.deal, TradeRole::Maker);
+        if exposure + added > self.0 {
+            Err(RefusalReason::BitcoinExposure)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct MaxMoneroExposure(monero::Amount);
+
+impl AcceptanceRule for MaxMoneroExposure {
+    fn check(&self, candidate: &Candidate, swaps: &[InProgressSwap]) -> Result<(), RefusalReason> {
+        let (_, exposure) = exposure(swaps);
+        let (_, added) = locked_amounts(candidate.deal, TradeRole::Maker);
+        if exposure + added > self.0 {
+            Err(RefusalReason::MoneroExposure)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Amounts locked by the local node in a swap: Bob locks bitcoin, Alice locks monero
+fn locked_amounts(deal: &Deal, trade_role: TradeRole) -> (bitcoin::Amount, monero::Amount) {
+    match deal.swap_role(&trade_role) {
+        SwapRole::Bob => (deal.parameters.arbitrating_amount, monero::Amount::ZERO),
+        SwapRole::Alice => (bitcoin::Amount::ZERO, deal.parameters.accordant_amount),
+    }
+}
+
+// Total amounts locked by the local node across the swaps in progress
+fn exposure(swaps: &[InProgressSwap]) -> (bitcoin::Amount, monero::Amount) {
+    swaps.iter().fold(
+        (bitcoin::Amount::ZERO, monero::Amount::ZERO),
+        |(btc, xmr), swap| {
+            let (swap_btc, swap_xmr) = locked_amounts(&swap.deal, swap.trade_role);
+            (btc + swap_btc, xmr + swap_xmr)
+        },
+    )
+}
+
+/// Acceptance policy applied by the maker on incoming taker commits, built from the
+/// `[farcasterd.acceptance_policy]` configuration section. Without configuration every taker is
+/// accepted.
+#[derive(Default)]
+pub struct AcceptancePolicy {
+    config: AcceptancePolicyConfig,
+    rules: Vec<Box<dyn AcceptanceRule>>,
+    refused: u64,
+}
+
+impl From<AcceptancePolicyConfig> for AcceptancePolicy {
+    fn from(config: AcceptancePolicyConfig) -> Self {
+        let mut rules: Vec<Box<dyn AcceptanceRule>> = vec![];
+        if let Some(denied) = &config.denied_node_ids {
+            rules.push(Box::new(DenyList(denied.clone())));
+        }
+        if let Some(allowed) = &config.allowed_node_ids {
+            rules.push(Box::new(AllowList(allowed.clone())));
+        }
+        if let Some(windows) = &config.trading_hours {
+            rules.push(Box::new(TradingHours(windows.clone())));
+        }
+        if let Some(max) = config.max_concurrent_swaps {
+            rules.push(Box::new(MaxConcurrentSwaps(max)));
+        }
+        if let Some(max) = config.max_concurrent_swaps_per_peer {
+            rules.push(Box::new(MaxConcurrentSwapsPerPeer(max)));
+        }
+        if let Some(max) = config.max_btc_exposure {
+            rules.push(Box::new(MaxBitcoinExposure(max)));
+        }
+        if let Some(max) = config.max_xmr_exposure {
+            rules.push(Box::new(MaxMoneroExposure(max)));
+        }
+        AcceptancePolicy {
+            config,
+            rules,
+            refused: 0,
+        }
+    }
+}
+
+impl AcceptancePolicy {
+    /// Replace the rules with the ones of a new configuration, keeping the refusal count
+    pub fn reconfigure(&mut self, config: AcceptancePolicyConfig) {
+        let refused = self.refused;
+        *self = config.into();
+        self.refused = refused;
+    }
+
+    /// Evaluate a candidate against all rules, counting refusals
+    pub fn evaluate(
+        &mut self,
+        candidate: &Candidate,
+        swaps: &[InProgressSwap],
+    ) -> Result<(), RefusalReason> {
+        let res = self
+            .rules
+            .iter()
+            .try_for_each(|rule| rule.check(candidate, swaps));
+        if res.is_err() {
+            self.refused += 1;
+        }
+        res
+    }
+
+    /// Summarize the configured limits together with the current usage
+    pub fn report(&self, swaps: &[InProgressSwap]) -> AcceptancePolicyReport {
+        let (btc_exposure, xmr_exposure) = exposure(swaps);
+        AcceptancePolicyReport {
+            max_concurrent_swaps: self.config.max_concurrent_swaps,
+            max_concurrent_swaps_per_peer: self.config.max_concurrent_swaps_per_peer,
+            max_btc_exposure: self.config.max_btc_exposure,
+            max_xmr_exposure: self.config.max_xmr_exposure,
+            allowed_node_ids: self.config.allowed_node_ids.clone(),
+            denied_node_ids: self.config.denied_node_ids.clone().unwrap_or_default(),
+            trading_hours: self
+                .config
+                .trading_hours
+                .iter()
+                .flatten()
+                .map(|window| window.to_string())
+                .collect(),
+            concurrent_swaps: swaps.len() as u32,
+            btc_exposure,
+            xmr_exposure,
+            refused: self.refused,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(AcceptancePolicyReport::to_yaml_string)]
+pub struct AcceptancePolicyReport {
+    pub max_concurrent_swaps: Option<u32>,
+    pub max_concurrent_swaps_per_peer: Option<u32>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub max_btc_exposure: Option<bitcoin::Amount>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub max_xmr_exposure: Option<monero::Amount>,
+    #[serde_as(as = "Option<Vec<DisplayFromStr>>")]
+    pub allowed_node_ids: Option<Vec<NodeId>>,
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub denied_node_ids: Vec<NodeId>,
+    pub trading_hours: Vec<String>,
+    pub concurrent_swaps: u32,
+    #[serde_as(as = "DisplayFromStr")]
+    pub btc_exposure: bitcoin::Amount,
+    #[serde_as(as = "DisplayFromStr")]
+    pub xmr_exposure: monero::Amount,
+    pub refused: u64,
+}
+
+#[cfg(feature = "serde")]
+impl ToYamlString for AcceptancePolicyReport {}
+
+#[cfg(test)]
+fn test_deal(maker_role: SwapRole) -> Deal {
+    use std::str::FromStr;
+    let mut deal = Deal::from_str("Deal:Cke4ftrP5A781Vq85dgBQJNwYgBS4nuUV1LQM2fvVdFMNR4h5TrWhRR11111uMFuZTAsNgpdK8DiK11111TB9zym113GTvtvqfD1111114A4TTfFfmZoWyvpcjDBtTZCdWFSUWcRKYfEC3Y17hqaXZ3dWz11111111111111111111111111111111111111111AfZ113SEBTEspU3a").unwrap();
+    deal.parameters.maker_role = maker_role;
+    deal
+}
+
+#[cfg(test)]
+fn test_node_id() -> NodeId {
+    use bitcoin::secp256k1::{rand::thread_rng, PublicKey, SecretKey, SECP256K1};
+    NodeId::from(PublicKey::from_secret_key(
+        SECP256K1,
+        &SecretKey::new(&mut thread_rng()),
+    ))
+}
+
+#[cfg(test)]
+fn test_swap(counterparty: NodeId, maker_role: SwapRole, trade_role: TradeRole) -> InProgressSwap {
+    InProgressSwap {
+        counterparty: Some(counterparty),
+        deal: test_deal(maker_role),
+        trade_role,
+    }
+}
+
+#[cfg(test)]
+fn test_candidate<'a>(node_id: NodeId, deal: &'a Deal, time: &str) -> Candidate<'a> {
+    Candidate {
+        node_id,
+        deal,
+        time: format!("2022-01-01T{}:00Z", time).parse().unwrap(),
+    }
+}
+
+#[test]
+fn acceptance_policy_deny_list() {
+    let (denied, other) = (test_node_id(), test_node_id());
+    let deal = test_deal(SwapRole::Bob);
+    let mut policy = AcceptancePolicy::from(AcceptancePolicyConfig {
+        denied_node_ids: Some(vec![denied]),
+        ..Default::default()
+    });
+    assert_eq!(
+        policy.evaluate(&test_candidate(denied, &deal, "12:00"), &[]),
+        Err(RefusalReason::Denied)
+    );
+    assert_eq!(
+        policy.evaluate(&test_candidate(other, &deal, "12:00"), &[]),
+        Ok(())
+    );
+    assert_eq!(policy.report(&[]).refused, 1);
+}
+
+#[test]
+fn acceptance_policy_allow_list() {
+    let (allowed, other) = (test_node_id(), test_node_id());
+    let deal = test_deal(SwapRole::Bob);
+    let mut policy = AcceptancePolicy::from(AcceptancePolicyConfig {
+        allowed_node_ids: Some(vec![allowed]),
+        ..Default::default()
+    });
+    assert_eq!(
+        policy.evaluate(&test_candidate(allowed, &deal, "12:00"), &[]),
+        Ok(())
+    );
+    assert_eq!(
+        policy.evaluate(&test_candidate(other, &deal, "12:00"), &[]),
+        Err(RefusalReason::NotAllowed)
+    );
+}
+
+#[test]
+fn acceptance_policy_trading_hours_wrap_past_midnight() {
+    use std::str::FromStr;
+    let node_id = test_node_id();
+    let deal = test_deal(SwapRole::Bob);
+    let mut policy = AcceptancePolicy::from(AcceptancePolicyConfig {
+        trading_hours: Some(vec![TimeWindow::from_str("22:00-06:00").unwrap()]),
+        ..Default::default()
+    });
+    for time in ["22:00", "23:30", "00:00", "05:59"] {
+        assert_eq!(
+            policy.evaluate(&test_candidate(node_id, &deal, time), &[]),
+            Ok(())
+        );
+    }
+    for time in ["06:00", "12:00", "21:59"] {
+        assert_eq!(
+            policy.evaluate(&test_candidate(node_id, &deal, time), &[]),
+            Err(RefusalReason::OutsideTradingHours)
+        );
+    }
+}
+
+#[test]
+fn acceptance_policy_max_concurrent_swaps() {
+    let deal = test_deal(SwapRole::Bob);
+    let candidate = test_candidate(test_node_id(), &deal, "12:00");
+    let mut policy = AcceptancePolicy::from(AcceptancePolicyConfig {
+        max_concurrent_swaps: Some(2),
+        ..Default::default()
+    });
+    let mut swaps = vec![test_swap(test_node_id(), SwapRole::Bob, TradeRole::Maker)];
+    assert_eq!(policy.evaluate(&candidate, &swaps), Ok(()));
+    swaps.push(test_swap(test_node_id(), SwapRole::Bob, TradeRole::Taker));
+    assert_eq!(
+        policy.evaluate(&candidate, &swaps),
+        Err(RefusalReason::MaxConcurrentSwaps)
+    );
+}
+
+#[test]
+fn acceptance_policy_max_concurrent_swaps_per_peer() {
+    let (busy, other) = (test_node_id(), test_node_id());
+    let deal = test_deal(SwapRole::Bob);
+    let mut policy = AcceptancePolicy::from(AcceptancePolicyConfig {
+        max_concurrent_swaps_per_peer: Some(1),
+        ..Default::default()
+    });
+    let swaps = vec![test_swap(busy, SwapRole::Bob, TradeRole::Maker)];
+    assert_eq!(
+        policy.evaluate(&test_candidate(busy, &deal, "12:00"), &swaps),
+        Err(RefusalReason::MaxConcurrentSwapsPerPeer)
+    );
+    assert_eq!(
+        policy.evaluate(&test_candidate(other, &deal, "12:00"), &swaps),
+        Ok(())
+    );
+}
+
+#[test]
+fn acceptance_policy_max_btc_exposure() {
+    let node_id = test_node_id();
+    let bob_deal = test_deal(SwapRole::Bob);
+    let alice_deal = test_deal(SwapRole::Alice);
+    let amount = bob_deal.parameters.arbitrating_amount;
+    let mut policy = AcceptancePolicy::from(AcceptancePolicyConfig {
+        max_btc_exposure: Some(amount + amount),
+        ..Default::default()
+    });
+    // the maker locks bitcoin as Bob, and so does the taker of a deal made as Alice
+    let mut swaps = vec![test_swap(test_node_id(), SwapRole::Bob, TradeRole::Maker)];
+    assert_eq!(
+        policy.evaluate(&test_candidate(node_id, &bob_deal, "12:00"), &swaps),
+        Ok(())
+    );
+    swaps.push(test_swap(test_node_id(), SwapRole::Alice, TradeRole::Taker));
+    assert_eq!(policy.report(&swaps).btc_exposure, amount + amount);
+    assert_eq!(
+        policy.evaluate(&test_candidate(node_id, &bob_deal, "12:00"), &swaps),
+        Err(RefusalReason::BitcoinExposure)
+    );
+    // as Alice the maker locks no bitcoin
+    assert_eq!(
+        policy.evaluate(&test_candidate(node_id, &alice_deal, "12:00"), &swaps),
+        Ok(())
+    );
+}
+
+#[test]
+fn acceptance_policy_max_xmr_exposure() {
+    let node_id = test_node_id();
+    let bob_deal = test_deal(SwapRole::Bob);
+    let alice_deal = test_deal(SwapRole::Alice);
+    let amount = alice_deal.parameters.accordant_amount;
+    let mut policy = AcceptancePolicy::from(AcceptancePolicyConfig {
+        max_xmr_exposure: Some(amount + amount),
+        ..Default::default()
+    });
+    // the maker locks monero as Alice, and so does the taker of a deal made as Bob
+    let mut swaps = vec![test_swap(test_node_id(), SwapRole::Alice, TradeRole::Maker)];
+    assert_eq!(
+        policy.evaluate(&test_candidate(node_id, &alice_deal, "12:00"), &swaps),
+        Ok(())
+    );
+    swaps.push(test_swap(test_node_id(), SwapRole::Bob, TradeRole::Taker));
+    assert_eq!(policy.report(&swaps).xmr_exposure, amount + amount);
+    assert_eq!(
+        policy.evaluate(&test_candidate(node_id, &alice_deal, "12:00"), &swaps),
+        Err(RefusalReason::MoneroExposure)
+    );
+    // as Bob the maker locks no monero
+    assert_eq!(
+        policy.evaluate(&test_candidate(node_id, &bob_deal, "12:00"), &swaps),
+        Ok(())
+    );
+}
@@ -0,0 +1,380 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::convert::TryInto;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use amplify::ToYamlString;
+use farcaster_core::blockchain::FeeStrategy;
+use farcaster_core::swap::btcxmr::Deal;
+use farcaster_core::trade::DealId;
+use internet2::addr::NodeId;
+use strict_encoding::{NetworkDecode, NetworkEncode};
+
+use crate::config::DealValidationConfig;
+use crate::{Config, Error};
+
+/// Default maximum deviation, in percent, between the deal price and the oracle price
+pub const PRICE_ORACLE_MAX_DEVIATION: f64 = 5.0;
+
+/// Interval between two runs of the price oracle command
+pub const PRICE_ORACLE_REFRESH: Duration = Duration::from_secs(60);
+
+/// Time after which a price oracle command still running is killed
+pub const PRICE_ORACLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Age after which the oracle price is no longer used to validate deals
+pub const PRICE_ORACLE_MAX_AGE: Duration = Duration::from_secs(300);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum CheckStatus {
+    #[display("passed")]
+    #[serde(rename = "passed")]
+    Passed,
+    #[display("failed")]
+    #[serde(rename = "failed")]
+    Failed,
+    #[display("skipped")]
+    #[serde(rename = "skipped")]
+    Skipped,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display("{check}: {status} ({details})")]
+pub struct ValidationCheck {
+    pub check: String,
+    pub status: CheckStatus,
+    pub details: String,
+}
+
+impl ValidationCheck {
+    fn new(check: &str, passed: bool, details: String) -> Self {
+        ValidationCheck {
+            check: check.to_string(),
+            status: if passed {
+                CheckStatus::Passed
+            } else {
+                CheckStatus::Failed
+            },
+            details,
+        }
+    }
+
+    fn skipped(check: &str, details: &str) -> Self {
+        ValidationCheck {
+            check: check.to_string(),
+            status: CheckStatus::Skipped,
+            details: details.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(DealValidationReport::to_yaml_string)]
+pub struct DealValidationReport {
+    pub deal_id: DealId,
+    pub enforced: bool,
+    pub checks: Vec<ValidationCheck>,
+}
+
+impl DealValidationReport {
+    /// Returns true if no check failed
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Failed)
+    }
+
+    /// Returns the failed checks
+    pub fn failures(&self) -> Vec<&ValidationCheck> {
+        self.checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Failed)
+            .collect()
+    }
+}
+
+struct OracleQuote {
+    price: Result<f64, String>,
+    fetched: Instant,
+}
+
+/// Handle on the price oracle thread, which runs the oracle command periodically off the event
+/// loop. Every validation reads the latest price, dropping the handle stops the thread.
+pub struct PriceOracle {
+    command: String,
+    quote: Arc<Mutex<Option<OracleQuote>>>,
+    _stop: Sender<()>,
+}
+
+impl PriceOracle {
+    /// Start the thread running the oracle command, a first price is fetched right away
+    pub fn start(command: String) -> Result<Self, Error> {
+        let quote = Arc::new(Mutex::new(None));
+        let (stop, rx) = mpsc::channel();
+        let thread_command = command.clone();
+        let thread_quote = Arc::clone(&quote);
+        thread::Builder::new()
+            .name(s!("price-oracle"))
+            .spawn(move || refresh(rx, thread_command, thread_quote))?;
+        Ok(PriceOracle {
+            command,
+            quote,
+            _stop: stop,
+        })
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The latest oracle price in BTC per XMR, if fetched recently enough
+    pub fn price(&self) -> Result<f64, Error> {
+        match &*self.quote.lock().expect("price oracle lock poisoned") {
+            None => Err(Error::Farcaster(s!("price oracle returned no price yet"))),
+            Some(quote) if quote.fetched.elapsed() > PRICE_ORACLE_MAX_AGE => {
+                Err(Error::Farcaster(format!(
+                    "price oracle returned no price for the last {} seconds",
+                    quote.fetched.elapsed().as_secs()
+                )))
+            }
+            Some(OracleQuote { price, .. }) => price.clone().map_err(Error::Farcaster),
+        }
+    }
+}
+
+fn refresh(stop: Receiver<()>, command: String, quote: Arc<Mutex<Option<OracleQuote>>>) {
+    loop {
+        let price = oracle_price(&command).map_err(|err| err.to_string());
+        if let Err(err) = &price {
+            warn!("Price oracle failed: {}", err);
+        }
+        *quote.lock().expect("price oracle lock poisoned") = Some(OracleQuote {
+            price,
+            fetched: Instant::now(),
+        });
+        // the handle is dropped on shutdown or when the oracle is reconfigured
+        if let Err(RecvTimeoutError::Disconnected) = stop.recv_timeout(PRICE_ORACLE_REFRESH) {
+            break;
+        }
+    }
+}
+
+/// Run all the taker-side checks configured in `[farcasterd.deal_validation]` against a deal, the
+/// price is compared against the latest price of the oracle if one is configured
+pub fn validate_deal(
+    config: &Config,
+    deal: &Deal,
+    price_oracle: Option<&PriceOracle>,
+) -> DealValidationReport {
+    let policy = config.get_deal_validation_config();
+    let mut checks = vec![];
+    checks.push(check_network(&policy, deal));
+    checks.push(check_maker(&policy, deal));
+    checks.extend(check_timelocks(config, &policy, deal));
+    checks.push(check_fee_rate(&policy, deal));
+    checks.push(check_price_range(&policy, deal));
+    checks.push(check_price_oracle(&policy, deal, price_oracle));
+    DealValidationReport {
+        deal_id: deal.id(),
+        enforced: policy.enforce,
+        checks,
+    }
+}
+
+fn check_network(policy: &DealValidationConfig, deal: &Deal) -> ValidationCheck {
+    let network = deal.parameters.network;
+    match &policy.networks {
+        Some(networks) => ValidationCheck::new(
+            "network",
+            networks.contains(&network),
+            format!("deal is on {}", network),
+        ),
+        None => ValidationCheck::skipped("network", "no network restriction"),
+    }
+}
+
+fn check_maker(policy: &DealValidationConfig, deal: &Deal) -> ValidationCheck {
+    match &policy.maker_node_ids {
+        Some(node_ids) => ValidationCheck::new(
+            "maker node id",
+            node_ids.contains(&NodeId::from(deal.node_id)),
+            format!("maker is {}", deal.node_id),
+        ),
+        None => ValidationCheck::skipped("maker node id", "no maker pinned"),
+    }
+}
+
+fn check_timelocks(
+    config: &Config,
+    policy: &DealValidationConfig,
+    deal: &Deal,
+) -> Vec<ValidationCheck> {
+    let swap_config = match (
+        deal.parameters.arbitrating_blockchain.try_into(),
+        deal.parameters.accordant_blockchain.try_into(),
+    ) {
+        (Ok(arb), Ok(acc)) => config
+            .get_swap_config(arb, acc, deal.parameters.network)
+            .ok(),
+        _ => None,
+    };
+    let arb = match swap_config {
+        Some(swap_config) => swap_config.arbitrating,
+        None => {
+            let details = "no swap configuration for this network";
+            return vec![
+                ValidationCheck::skipped("cancel timelock", details),
+                ValidationCheck::skipped("punish timelock", details),
+            ];
+        }
+    };
+    let cancel = deal.parameters.cancel_timelock.as_u32();
+    let punish = deal.parameters.punish_timelock.as_u32();
+    let min_cancel =
+        arb.safety as u32 + arb.finality as u32 + policy.min_cancel_margin.unwrap_or(0);
+    let min_punish =
+        arb.safety as u32 + arb.finality as u32 + policy.min_punish_margin.unwrap_or(0);
+    vec![
+        ValidationCheck::new(
+            "cancel timelock",
+            cancel >= min_cancel,
+            format!("{} blocks, at least {} required", cancel, min_cancel),
+        ),
+        ValidationCheck::new(
+            "punish timelock",
+            punish >= min_punish && punish > cancel,
+            format!(
+                "{} blocks, at least {} and more than cancel required",
+                punish, min_punish
+            ),
+        ),
+    ]
+}
+
+fn check_fee_rate(policy: &DealValidationConfig, deal: &Deal) -> ValidationCheck {
+    match (&policy.max_fee_rate, &deal.parameters.fee_strategy) {
+        (Some(max), FeeStrategy::Fixed(fee)) => ValidationCheck::new(
+            "fee rate",
+            fee.as_sat() <= max.as_sat(),
+            format!("{}, at most {} accepted", fee, max),
+        ),
+        // the fee rate may end up anywhere in the range
+        (Some(max), FeeStrategy::Range { min_inc, max_inc }) => ValidationCheck::new(
+            "fee rate",
+            max_inc.as_sat() <= max.as_sat(),
+            format!("{} to {}, at most {} accepted", min_inc, max_inc, max),
+        ),
+        (None, _) => ValidationCheck::skipped("fee rate", "no maximum fee rate"),
+    }
+}
+
+// Price of the deal expressed in BTC per XMR
+fn deal_price(deal: &Deal) -> f64 {
+    deal.parameters.arbitrating_amount.as_btc() / deal.parameters.accordant_amount.as_xmr()
+}
+
+fn check_price_range(policy: &DealValidationConfig, deal: &Deal) -> ValidationCheck {
+    if policy.min_price.is_none() && policy.max_price.is_none() {
+        return ValidationCheck::skipped("price range", "no price range");
+    }
+    let price = deal_price(deal);
+    let above_min = policy.min_price.map(|min| price >= min).unwrap_or(true);
+    let below_max = policy.max_price.map(|max| price <= max).unwrap_or(true);
+    ValidationCheck::new(
+        "price range",
+        above_min && below_max,
+        format!("{:.8} BTC/XMR", price),
+    )
+}
+
+fn check_price_oracle(
+    policy: &DealValidationConfig,
+    deal: &Deal,
+    price_oracle: Option<&PriceOracle>,
+) -> ValidationCheck {
+    let price_oracle = match price_oracle {
+        Some(price_oracle) => price_oracle,
+        None => return ValidationCheck::skipped("price oracle", "no price oracle"),
+    };
+    match price_oracle.price() {
+        Ok(reference) => {
+            let price = deal_price(deal);
+            let deviation = (price - reference).abs() / reference * 100.0;
+            let max_deviation = policy
+                .max_price_deviation
+                .unwrap_or(PRICE_ORACLE_MAX_DEVIATION);
+            ValidationCheck::new(
+                "price oracle",
+                deviation <= max_deviation,
+                format!(
+                    "{:.8} BTC/XMR is {:.2}% away from {:.8} BTC/XMR, at most {}% accepted",
+                    price, deviation, reference, max_deviation
+                ),
+            )
+        }
+        Err(err) => ValidationCheck::new("price oracle", false, err.to_string()),
+    }
+}
+
+// Run the oracle command and parse its output as a price in BTC per XMR, the command is killed
+// if it does not exit in time
+fn oracle_price(command: &str) -> Result<f64, Error> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let deadline = Instant::now() + PRICE_ORACLE_TIMEOUT;
+    while child.try_wait()?.is_none() {
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Err(Error::Farcaster(format!(
+                "price oracle did not exit within {} seconds",
+                PRICE_ORACLE_TIMEOUT.as_secs()
+            )));
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(Error::Farcaster(format!(
+            "price oracle failed with {}",
+            output.status
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.trim().parse::<f64>() {
+        Ok(price) if price > 0.0 => Ok(price),
+        _ => Err(Error::Farcaster(format!(
+            "price oracle returned an invalid price: {}",
+            stdout.trim()
+        ))),
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ToYamlString for DealValidationReport {}
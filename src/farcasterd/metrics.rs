@@ -0,0 +1,535 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Prometheus metrics served by farcasterd on `/metrics` when the `[metrics]` configuration
+//! section is enabled.
+//!
+//! farcasterd keeps the metrics up to date while handling bus messages, the HTTP thread renders
+//! them in the Prometheus text format only when the endpoint is scraped.
+//! Syncers measure the latency of their RPC calls themselves and report it, along with their
+//! height and task count, with a [`SyncerMetrics`] message on every new block.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use farcaster_core::blockchain::{Blockchain, Network};
+use farcaster_core::swap::SwapId;
+use strict_encoding::{NetworkDecode, NetworkEncode};
+
+use crate::bus::ServiceBus;
+use crate::farcasterd::stats::Stats;
+use crate::Error;
+
+/// Upper bounds of the swap phase duration buckets, in seconds
+const PHASE_DURATION_BUCKETS: [u64; 10] = [10, 30, 60, 300, 600, 1800, 3600, 7200, 21600, 86400];
+
+/// Upper bounds of the syncer RPC latency buckets, in milliseconds
+const RPC_LATENCY_BUCKETS: [u64; 10] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 10000];
+
+/// Time allowed to a scraper to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Histogram of integer observations, rendered in seconds
+#[derive(Clone, PartialEq, Eq, Debug, NetworkEncode, NetworkDecode)]
+pub struct Histogram {
+    /// Inclusive upper bounds of the buckets
+    bounds: Vec<u64>,
+    /// Observations per bucket, the last one counts the observations above every bound
+    counts: Vec<u64>,
+    sum: u64,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &[u64]) -> Self {
+        Histogram {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+            sum: 0,
+            count: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: u64) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(self.bounds.len());
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Render the series, `units_per_sec` converts the observed unit to seconds
+    fn render(&self, out: &mut String, name: &str, labels: &[(&str, String)], units_per_sec: u64) {
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter()) {
+            cumulative += count;
+            let le = (*bound as f64 / units_per_sec as f64).to_string();
+            let _ = writeln!(
+                out,
+                "{}_bucket{} {}",
+                name,
+                format_labels(labels, Some(&le)),
+                cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{} {}",
+            name,
+            format_labels(labels, Some("+Inf")),
+            self.count
+        );
+        let _ = writeln!(
+            out,
+            "{}_sum{} {}",
+            name,
+            format_labels(labels, None),
+            self.sum as f64 / units_per_sec as f64
+        );
+        let _ = writeln!(
+            out,
+            "{}_count{} {}",
+            name,
+            format_labels(labels, None),
+            self.count
+        );
+    }
+}
+
+/// Latency of the RPC calls made by a syncer, shared with its worker threads
+#[derive(Clone, Debug)]
+pub struct RpcLatency(Arc<Mutex<Histogram>>);
+
+impl Default for RpcLatency {
+    fn default() -> Self {
+        RpcLatency(Arc::new(Mutex::new(Histogram::new(&RPC_LATENCY_BUCKETS))))
+    }
+}
+
+impl RpcLatency {
+    pub fn observe(&self, elapsed: Duration) {
+        self.0
+            .lock()
+            .expect("rpc latency lock poisoned")
+            .observe(elapsed.as_millis() as u64);
+    }
+
+    pub fn snapshot(&self) -> Histogram {
+        self.0.lock().expect("rpc latency lock poisoned").clone()
+    }
+}
+
+/// Report sent by a syncer to farcasterd on every new block
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("height {height}, {tasks} tasks")]
+pub struct SyncerMetrics {
+    pub height: u64,
+    pub tasks: u64,
+    pub rpc_latency: Histogram,
+}
+
+/// Metrics tracked by farcasterd, shared with the thread serving the metrics endpoint
+pub struct Metrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+/// Metrics tracked by farcasterd in addition to its [`Stats`], and a copy of the latter
+#[derive(Default)]
+struct MetricsState {
+    stats: Stats,
+    peer_connections: usize,
+    bus_messages: HashMap<ServiceBus, u64>,
+    swap_states: HashMap<SwapId, (String, Instant)>,
+    phase_durations: BTreeMap<String, Histogram>,
+    syncers: HashMap<(Blockchain, Network), SyncerMetrics>,
+}
+
+impl Metrics {
+    /// Bind the metrics endpoint and serve it from a dedicated thread
+    pub fn start(addr: SocketAddr) -> Result<Self, Error> {
+        let listener = TcpListener::bind(addr)?;
+        let state = Arc::new(Mutex::new(MetricsState::default()));
+        let served = Arc::clone(&state);
+        thread::Builder::new()
+            .name(s!("metrics"))
+            .spawn(move || serve(listener, served))?;
+        info!("Serving metrics on http://{}/metrics", addr);
+        Ok(Metrics { state })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MetricsState> {
+        self.state.lock().expect("metrics lock poisoned")
+    }
+
+    pub fn record_message(&mut self, bus: ServiceBus) {
+        *self.lock().bus_messages.entry(bus).or_insert(0) += 1;
+    }
+
+    /// Track the current state of a swap, the time spent in the previous state is recorded as a
+    /// phase duration
+    pub fn record_swap_state(&mut self, swap_id: SwapId, state: &str) {
+        let now = Instant::now();
+        let mut metrics = self.lock();
+        let metrics = &mut *metrics;
+        match metrics.swap_states.get_mut(&swap_id) {
+            Some((current, _)) if current == state => {}
+            Some((current, since)) => {
+                metrics
+                    .phase_durations
+                    .entry(current.clone())
+                    .or_insert_with(|| Histogram::new(&PHASE_DURATION_BUCKETS))
+                    .observe(now.duration_since(*since).as_secs());
+                *current = state.to_string();
+                *since = now;
+            }
+            None => {
+                metrics
+                    .swap_states
+                    .insert(swap_id, (state.to_string(), now));
+            }
+        }
+    }
+
+    pub fn remove_swap(&mut self, swap_id: &SwapId) {
+        self.lock().swap_states.remove(swap_id);
+    }
+
+    pub fn record_syncer(
+        &mut self,
+        blockchain: Blockchain,
+        network: Network,
+        report: SyncerMetrics,
+    ) {
+        self.lock().syncers.insert((blockchain, network), report);
+    }
+
+    /// Copy the node stats, they are rendered when the endpoint is next scraped
+    pub fn record_node(&mut self, stats: &Stats, peer_connections: usize) {
+        let mut metrics = self.lock();
+        metrics.stats.clone_from(stats);
+        metrics.peer_connections = peer_connections;
+    }
+}
+
+impl MetricsState {
+    fn render(&self) -> String {
+        let stats = &self.stats;
+        let peer_connections = self.peer_connections;
+        let mut out = String::new();
+
+        family(
+            &mut out,
+            "farcaster_swaps_initiated_total",
+            "counter",
+            "Swaps launched or restored since farcasterd started",
+        );
+        sample(
+            &mut out,
+            "farcaster_swaps_initiated_total",
+            &[],
+            stats.initialized,
+        );
+
+        family(
+            &mut out,
+            "farcaster_swaps_ended_total",
+            "counter",
+            "Swaps ended since farcasterd started, by outcome",
+        );
+        for (outcome, value) in [
+            ("success", stats.success),
+            ("refund", stats.refund),
+            ("punish", stats.punish),
+            ("abort", stats.abort),
+        ] {
+            sample(
+                &mut out,
+                "farcaster_swaps_ended_total",
+                &[("outcome", s!(outcome))],
+                value,
+            );
+        }
+
+        family(
+            &mut out,
+            "farcaster_swaps_awaiting_funding",
+            "gauge",
+            "Swaps awaiting funding, by blockchain",
+        );
+        family(
+            &mut out,
+            "farcaster_swaps_funded_total",
+            "counter",
+            "Swaps funded since farcasterd started, by blockchain",
+        );
+        family(
+            &mut out,
+            "farcaster_swaps_funding_canceled_total",
+            "counter",
+            "Swaps whose funding was canceled since farcasterd started, by blockchain",
+        );
+        for (blockchain, awaiting, funded, canceled) in [
+            (
+                Blockchain::Bitcoin,
+                stats.awaiting_funding_btc.len() as u64,
+                stats.funded_btc,
+                stats.funding_canceled_btc,
+            ),
+            (
+                Blockchain::Monero,
+                stats.awaiting_funding_xmr.len() as u64,
+                stats.funded_xmr,
+                stats.funding_canceled_xmr,
+            ),
+        ] {
+            let labels = [("blockchain", blockchain.to_string())];
+            sample(
+                &mut out,
+                "farcaster_swaps_awaiting_funding",
+                &labels,
+                awaiting,
+            );
+            sample(&mut out, "farcaster_swaps_funded_total", &labels, funded);
+            sample(
+                &mut out,
+                "farcaster_swaps_funding_canceled_total",
+                &labels,
+                canceled,
+            );
+        }
+
+        family(
+            &mut out,
+            "farcaster_swaps_running",
+            "gauge",
+            "Running swaps, by swap state",
+        );
+        let mut by_state: BTreeMap<&str, u64> = BTreeMap::new();
+        for (state, _) in self.swap_states.values() {
+            *by_state.entry(state).or_insert(0) += 1;
+        }
+        for (state, count) in by_state {
+            sample(
+                &mut out,
+                "farcaster_swaps_running",
+                &[("state", s!(state))],
+                count,
+            );
+        }
+
+        family(
+            &mut out,
+            "farcaster_swap_phase_duration_seconds",
+            "histogram",
+            "Time spent by swaps in each state",
+        );
+        for (phase, histogram) in &self.phase_durations {
+            histogram.render(
+                &mut out,
+                "farcaster_swap_phase_duration_seconds",
+                &[("phase", phase.clone())],
+                1,
+            );
+        }
+
+        family(
+            &mut out,
+            "farcaster_syncer_height",
+            "gauge",
+            "Last block height seen by the syncer",
+        );
+        family(
+            &mut out,
+            "farcaster_syncer_tasks",
+            "gauge",
+            "Tasks received by the syncer",
+        );
+        let mut syncers: Vec<_> = self.syncers.iter().collect();
+        syncers.sort_by_key(|((blockchain, network), _)| {
+            (blockchain.to_string(), network.to_string())
+        });
+        for ((blockchain, network), report) in &syncers {
+            let labels = [
+                ("blockchain", blockchain.to_string()),
+                ("network", network.to_string()),
+            ];
+            sample(&mut out, "farcaster_syncer_height", &labels, report.height);
+            sample(&mut out, "farcaster_syncer_tasks", &labels, report.tasks);
+        }
+        family(
+            &mut out,
+            "farcaster_syncer_rpc_latency_seconds",
+            "histogram",
+            "Latency of the RPC calls made by the syncer",
+        );
+        for ((blockchain, network), report) in &syncers {
+            report.rpc_latency.render(
+                &mut out,
+                "farcaster_syncer_rpc_latency_seconds",
+                &[
+                    ("blockchain", blockchain.to_string()),
+                    ("network", network.to_string()),
+                ],
+                1000,
+            );
+        }
+
+        family(
+            &mut out,
+            "farcaster_peer_connections",
+            "gauge",
+            "Open peer connections",
+        );
+        sample(
+            &mut out,
+            "farcaster_peer_connections",
+            &[],
+            peer_connections as u64,
+        );
+
+        family(
+            &mut out,
+            "farcaster_peer_limit_violations_total",
+            "counter",
+            "Violations of the peer limits by remote peers since farcasterd started",
+        );
+        sample(
+            &mut out,
+            "farcaster_peer_limit_violations_total",
+            &[],
+            stats.peer_limit_violations,
+        );
+
+        family(
+            &mut out,
+            "farcaster_bus_messages_total",
+            "counter",
+            "Messages handled by farcasterd, by service bus",
+        );
+        let mut buses: Vec<_> = self.bus_messages.iter().collect();
+        buses.sort_by_key(|(bus, _)| bus.to_string());
+        for (bus, count) in buses {
+            sample(
+                &mut out,
+                "farcaster_bus_messages_total",
+                &[("bus", bus.to_string())],
+                *count,
+            );
+        }
+
+        out
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, String)], value: u64) {
+    let _ = writeln!(out, "{}{} {}", name, format_labels(labels, None), value);
+}
+
+fn format_labels(labels: &[(&str, String)], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}=\"{}\"",
+                name,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        })
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        s!("")
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn serve(listener: TcpListener, state: Arc<Mutex<MetricsState>>) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(err) = respond(stream, &state) {
+                    debug!("Failed to answer metrics request: {}", err);
+                }
+            }
+            Err(err) => warn!("Failed to accept metrics connection: {}", err),
+        }
+    }
+}
+
+fn respond(mut stream: TcpStream, state: &Mutex<MetricsState>) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // skip the headers, the request has no body
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? <= 2 {
+            break;
+        }
+    }
+
+    let mut request = request_line.split_whitespace();
+    let path = request
+        .nth(1)
+        .and_then(|target| target.split('?').next())
+        .unwrap_or_default();
+    let (status, body) = match (request_line.starts_with("GET "), path) {
+        (true, "/metrics") => (
+            "200 OK",
+            state.lock().expect("metrics lock poisoned").render(),
+        ),
+        _ => ("404 Not Found", s!("Not Found\n")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[test]
+fn test_histogram_render() {
+    let mut histogram = Histogram::new(&[10, 100]);
+    histogram.observe(5);
+    histogram.observe(10);
+    histogram.observe(50);
+    histogram.observe(500);
+    let mut out = String::new();
+    histogram.render(
+        &mut out,
+        "latency_seconds",
+        &[("network", s!("Testnet"))],
+        1000,
+    );
+    assert_eq!(
+        out,
+        "latency_seconds_bucket{network=\"Testnet\",le=\"0.01\"} 2\n\
+         latency_seconds_bucket{network=\"Testnet\",le=\"0.1\"} 3\n\
+         latency_seconds_bucket{network=\"Testnet\",le=\"+Inf\"} 4\n\
+         latency_seconds_sum{network=\"Testnet\"} 0.565\n\
+         latency_seconds_count{network=\"Testnet\"} 4\n"
+    );
+}
@@ -4,11 +4,19 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+pub mod acceptance_policy;
+pub mod deal_validation;
+pub mod metrics;
+pub mod notifications;
 #[cfg(feature = "shell")]
 mod opts;
+mod peer_alerts;
+mod peer_reconnect;
 mod runtime;
 pub mod stats;
+pub mod supervisor;
 mod syncer_state_machine;
+mod tor;
 mod trade_state_machine;
 
 #[cfg(feature = "shell")]
@@ -0,0 +1,411 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Notifications sent by farcasterd on swap events, configured in the `[notifications]` section.
+//!
+//! farcasterd hands the events to a [`Notifier`] which delivers them from a dedicated thread,
+//! so slow or unreachable targets never block the bus. Each event is serialized once as JSON and
+//! sent to every webhook and command accepting its kind. Webhook deliveries that fail are queued
+//! and retried with an exponential backoff, commands are run once.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use chrono::Utc;
+use farcaster_core::blockchain::Blockchain;
+use farcaster_core::role::TradeRole;
+use farcaster_core::swap::btcxmr::Deal;
+use farcaster_core::swap::SwapId;
+
+use crate::bus::ctl::FundingInfo;
+use crate::bus::Outcome;
+use crate::config::{NotificationKind, NotificationsConfig};
+use crate::Error;
+
+/// Time allowed to a webhook to accept the connection and answer
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before the first retry of a failed webhook delivery, doubled on every attempt
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Number of attempts after which a webhook delivery is dropped
+const MAX_ATTEMPTS: u32 = 10;
+
+/// A swap event as delivered to the notification targets
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+pub struct Notification {
+    pub swap_id: SwapId,
+    pub trade_role: TradeRole,
+    /// Unix timestamp of the event, in seconds
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub event: NotificationEvent,
+}
+
+impl Notification {
+    pub fn new(swap_id: SwapId, trade_role: TradeRole, event: NotificationEvent) -> Self {
+        Notification {
+            swap_id,
+            trade_role,
+            timestamp: Utc::now().timestamp(),
+            event,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate", tag = "event", rename_all = "snake_case")]
+pub enum NotificationEvent {
+    FundingRequired {
+        blockchain: Blockchain,
+        address: String,
+        amount: String,
+    },
+    Locked {
+        blockchain: Blockchain,
+    },
+    Canceled,
+    Punished,
+    Ended {
+        outcome: Outcome,
+        #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+        btc_amount: bitcoin::Amount,
+        #[serde(with = "monero::util::amount::serde::as_xmr")]
+        xmr_amount: monero::Amount,
+    },
+}
+
+impl NotificationEvent {
+    pub fn funding_required(info: &FundingInfo) -> Self {
+        match info {
+            FundingInfo::Bitcoin(info) => NotificationEvent::FundingRequired {
+                blockchain: Blockchain::Bitcoin,
+                address: info.address.to_string(),
+                amount: info.amount.to_string(),
+            },
+            FundingInfo::Monero(info) => NotificationEvent::FundingRequired {
+                blockchain: Blockchain::Monero,
+                address: info.address.to_string(),
+                amount: info.amount.to_string(),
+            },
+        }
+    }
+
+    pub fn ended(deal: &Deal, outcome: Outcome) -> Self {
+        NotificationEvent::Ended {
+            outcome,
+            btc_amount: deal.parameters.arbitrating_amount,
+            xmr_amount: deal.parameters.accordant_amount,
+        }
+    }
+
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            NotificationEvent::FundingRequired { .. } => NotificationKind::FundingRequired,
+            NotificationEvent::Locked { .. } => NotificationKind::Locked,
+            NotificationEvent::Canceled => NotificationKind::Canceled,
+            NotificationEvent::Punished => NotificationKind::Punished,
+            NotificationEvent::Ended { .. } => NotificationKind::Ended,
+        }
+    }
+}
+
+/// Handle on the notification thread, dropping it stops the thread once pending notifications
+/// are handed over
+pub struct Notifier {
+    tx: Sender<Notification>,
+}
+
+impl Notifier {
+    /// Validate the configured targets and start the delivery thread
+    pub fn start(config: NotificationsConfig) -> Result<Self, Error> {
+        let mut targets = vec![];
+        for webhook in config.webhooks {
+            let url = WebhookUrl::parse(&webhook.url)?;
+            targets.push(Target {
+                events: webhook.events,
+                kind: TargetKind::Webhook {
+                    url,
+                    secret: webhook.secret,
+                },
+            });
+        }
+        for command in config.commands {
+            targets.push(Target {
+                events: command.events,
+                kind: TargetKind::Command(command.command),
+            });
+        }
+
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name(s!("notifications"))
+            .spawn(move || deliver(rx, targets))?;
+        Ok(Notifier { tx })
+    }
+
+    pub fn notify(&self, notification: Notification) {
+        if self.tx.send(notification).is_err() {
+            error!("Notification thread stopped, dropping notification");
+        }
+    }
+}
+
+struct Target {
+    events: Option<Vec<NotificationKind>>,
+    kind: TargetKind,
+}
+
+impl Target {
+    fn accepts(&self, kind: NotificationKind) -> bool {
+        self.events
+            .as_ref()
+            .map(|events| events.contains(&kind))
+            .unwrap_or(true)
+    }
+}
+
+enum TargetKind {
+    Webhook {
+        url: WebhookUrl,
+        secret: Option<String>,
+    },
+    Command(String),
+}
+
+/// An http url split into what is needed to send the request
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct WebhookUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl WebhookUrl {
+    fn parse(url: &str) -> Result<Self, Error> {
+        let invalid =
+            |reason: &str| Error::Farcaster(format!("Invalid webhook url {}: {}", url, reason));
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("only http urls are supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(pos) => (&rest[..pos], &rest[pos..]),
+            None => (rest, "/"),
+        };
+        // the port follows the last colon unless it is part of an IPv6 address
+        let (host, port) = match authority.rfind(':') {
+            Some(pos) if !authority[pos..].contains(']') => (
+                &authority[..pos],
+                authority[pos + 1..]
+                    .parse::<u16>()
+                    .map_err(|_| invalid("invalid port"))?,
+            ),
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        Ok(WebhookUrl {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// A webhook delivery waiting for its next attempt
+struct PendingDelivery {
+    target: usize,
+    payload: String,
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+fn deliver(rx: Receiver<Notification>, targets: Vec<Target>) {
+    let mut retries: VecDeque<PendingDelivery> = none!();
+    loop {
+        // wait for a new notification until the next retry is due
+        let received = match retries.iter().map(|pending| pending.next_attempt).min() {
+            Some(next) => rx.recv_timeout(next.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(notification) => {
+                let payload = match serde_json::to_string(&notification) {
+                    Ok(payload) => payload,
+                    Err(err) => {
+                        error!("Failed to serialize notification: {}", err);
+                        continue;
+                    }
+                };
+                let kind = notification.event.kind();
+                for (index, target) in targets.iter().enumerate() {
+                    if !target.accepts(kind) {
+                        continue;
+                    }
+                    match &target.kind {
+                        TargetKind::Webhook { url, secret } => {
+                            if let Err(err) = post(url, secret.as_deref(), &payload) {
+                                warn!(
+                                    "Failed to notify {} of {} for swap {}, will retry: {}",
+                                    url.host, kind, notification.swap_id, err
+                                );
+                                retries.push_back(PendingDelivery {
+                                    target: index,
+                                    payload: payload.clone(),
+                                    attempts: 1,
+                                    next_attempt: Instant::now() + RETRY_DELAY,
+                                });
+                            }
+                        }
+                        TargetKind::Command(command) => {
+                            if let Err(err) = run_command(command, &payload) {
+                                warn!(
+                                    "Notification command for {} of swap {} failed: {}",
+                                    kind, notification.swap_id, err
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                debug!("Stopping notification thread");
+                return;
+            }
+        }
+
+        // retry the due webhook deliveries, the ones failing again are queued back
+        let now = Instant::now();
+        for mut pending in retries.split_off(0) {
+            if pending.next_attempt > now {
+                retries.push_back(pending);
+                continue;
+            }
+            if let TargetKind::Webhook { url, secret } = &targets[pending.target].kind {
+                match post(url, secret.as_deref(), &pending.payload) {
+                    Ok(()) => {}
+                    Err(err) if pending.attempts + 1 >= MAX_ATTEMPTS => {
+                        error!(
+                            "Dropping notification to {} after {} attempts: {}",
+                            url.host, MAX_ATTEMPTS, err
+                        );
+                    }
+                    Err(err) => {
+                        debug!("Failed to notify {}: {}", url.host, err);
+                        pending.next_attempt = now + RETRY_DELAY * 2u32.pow(pending.attempts);
+                        pending.attempts += 1;
+                        retries.push_back(pending);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Signature of the payload sent in the `X-Farcaster-Signature` header
+fn sign(secret: &str, payload: &str) -> String {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(payload.as_bytes());
+    Hmac::<sha256::Hash>::from_engine(engine).to_hex()
+}
+
+fn post(url: &WebhookUrl, secret: Option<&str>, payload: &str) -> io::Result<()> {
+    let addr = (url.host.trim_matches(|c| c == '[' || c == ']'), url.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host not found"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, WEBHOOK_TIMEOUT)?;
+    stream.set_read_timeout(Some(WEBHOOK_TIMEOUT))?;
+    stream.set_write_timeout(Some(WEBHOOK_TIMEOUT))?;
+
+    let signature = secret
+        .map(|secret| {
+            format!(
+                "X-Farcaster-Signature: sha256={}\r\n",
+                sign(secret, payload)
+            )
+        })
+        .unwrap_or_default();
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        url.path,
+        url.host,
+        url.port,
+        payload.len(),
+        signature,
+        payload
+    )?;
+    stream.flush()?;
+
+    let mut status_line = String::new();
+    BufReader::new(&stream).read_line(&mut status_line)?;
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("unexpected response: {}", status_line.trim()),
+        )),
+    }
+}
+
+fn run_command(command: &str, payload: &str) -> io::Result<()> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("exited with {}", status),
+        ))
+    }
+}
+
+#[test]
+fn test_webhook_url_and_signature() {
+    assert_eq!(
+        WebhookUrl::parse("http://localhost:8080/hooks/farcaster").unwrap(),
+        WebhookUrl {
+            host: s!("localhost"),
+            port: 8080,
+            path: s!("/hooks/farcaster"),
+        }
+    );
+    assert_eq!(
+        WebhookUrl::parse("http://[::1]").unwrap(),
+        WebhookUrl {
+            host: s!("[::1]"),
+            port: 80,
+            path: s!("/"),
+        }
+    );
+    assert!(WebhookUrl::parse("https://example.com").is_err());
+    assert!(WebhookUrl::parse("http://example.com:port/").is_err());
+    // RFC 4231 test case 2
+    assert_eq!(
+        sign("Jefe", "what do ya want for nothing?"),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
@@ -30,11 +30,16 @@ pub struct Opts {
         value_hint = ValueHint::FilePath
     )]
     pub config: String,
+
+    /// Walletd key file, its passphrase is read once and handed over to walletd
+    #[clap(flatten)]
+    pub key_opts: crate::walletd::KeyOpts,
 }
 
 impl Opts {
     pub fn process(&mut self) {
         self.shared.process();
         self.shared.process_dir(&mut self.config);
+        self.key_opts.process(&self.shared);
     }
 }
@@ -0,0 +1,80 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Alerts on the connection with the counterparty of a swap in its critical phase.
+//!
+//! A swap is in its critical phase once funds are locked and until it no longer relies on its
+//! counterparty, i.e. the buy transaction is seen or the swap is canceled. A slow or unstable
+//! connection during that phase may delay the swap until its timelocks expire, so farcasterd logs
+//! an alert when the peerd of the swap reports a round-trip time above the threshold, or when the
+//! swap reconnected to its counterparty more often than allowed. See
+//! [`crate::config::PeerAlerts`] for the configured thresholds.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use farcaster_core::swap::SwapId;
+
+use crate::config::PeerAlerts;
+use crate::swapd::StateReport;
+use crate::LogStyle;
+
+/// Connection with the counterparty of a running swap
+#[derive(Clone, Debug, Default)]
+struct SwapConnection {
+    critical: bool,
+    reconnects: u32,
+}
+
+/// Connections with the counterparties of the running swaps
+#[derive(Debug, Default)]
+pub struct SwapConnections(HashMap<SwapId, SwapConnection>);
+
+impl SwapConnections {
+    /// Update the phase of a swap from its latest state report
+    pub fn record_state(&mut self, swap_id: SwapId, report: &StateReport) {
+        self.0.entry(swap_id).or_default().critical = is_critical(report);
+    }
+
+    /// Count a reconnection with the counterparty of a swap, alerts if the swap is in its
+    /// critical phase and reconnected more often than allowed
+    pub fn record_reconnect(&mut self, swap_id: SwapId, alerts: &PeerAlerts) {
+        let connection = self.0.entry(swap_id).or_default();
+        connection.reconnects += 1;
+        if connection.critical && connection.reconnects > alerts.max_reconnects {
+            warn!(
+                "{} | Reconnected {} times to the counterparty, above the maximum of {}, while funds are locked",
+                swap_id.swap_id(),
+                connection.reconnects,
+                alerts.max_reconnects
+            );
+        }
+    }
+
+    /// Alerts if the round-trip time with the counterparty of a swap in its critical phase is
+    /// above the maximum
+    pub fn record_rtt(&self, swap_id: SwapId, rtt: Duration, alerts: &PeerAlerts) {
+        if let Some(SwapConnection { critical: true, .. }) = self.0.get(&swap_id) {
+            if rtt > alerts.max_rtt() {
+                warn!(
+                    "{} | Round-trip time of {} ms with the counterparty, above the maximum of {} ms, while funds are locked",
+                    swap_id.swap_id(),
+                    rtt.as_millis(),
+                    alerts.max_rtt
+                );
+            }
+        }
+    }
+
+    pub fn remove_swap(&mut self, swap_id: &SwapId) {
+        self.0.remove(swap_id);
+    }
+}
+
+/// Funds are locked and the swap still relies on the counterparty to complete
+fn is_critical(report: &StateReport) -> bool {
+    (report.arb_locked || report.acc_locked) && !report.buy_seen && !report.canceled
+}
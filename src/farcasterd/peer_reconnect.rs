@@ -0,0 +1,88 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Reconnection to the counterparty of a restored swap.
+//!
+//! A restored swap starts without a connection with its counterparty. farcasterd dials the
+//! addresses of the peer address book kept by databased, and the address of the deal for a taker,
+//! until a connection with the counterparty is registered. The attempts are scheduled by the
+//! supervisor thread with an exponential backoff and a random jitter, and given up after
+//! [`MAX_RECONNECT_ATTEMPTS`]. A maker dials with the node identity its deal was made with.
+
+use std::time::Duration;
+
+use bitcoin::secp256k1::rand::{thread_rng, Rng};
+use bitcoin::secp256k1::SecretKey;
+use internet2::addr::InetSocketAddr;
+
+/// Number of connection attempts before giving up on a peer
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+/// Delay before the first attempt, doubled after every attempt
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(2);
+/// Upper bound of the delay between two attempts, before the jitter
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(600);
+
+/// Reconnection state of a peer
+#[derive(Clone, Debug, Default)]
+pub struct PeerReconnect {
+    addresses: Vec<InetSocketAddr>,
+    attempts: u32,
+    secret_key: Option<SecretKey>,
+}
+
+impl PeerReconnect {
+    pub fn new(addresses: Vec<InetSocketAddr>, secret_key: Option<SecretKey>) -> Self {
+        PeerReconnect {
+            addresses,
+            attempts: 0,
+            secret_key,
+        }
+    }
+
+    /// The node identity to dial with, the node identity if none
+    pub fn secret_key(&self) -> Option<SecretKey> {
+        self.secret_key
+    }
+
+    /// Add the addresses not known yet, tried after the already known ones
+    pub fn add_addresses(&mut self, addresses: &[InetSocketAddr]) {
+        for address in addresses {
+            if !self.addresses.contains(address) {
+                self.addresses.push(*address);
+            }
+        }
+    }
+
+    /// Register a new attempt and return the address to dial, rotating through the known
+    /// addresses. Returns None if no address is known.
+    pub fn next_address(&mut self) -> Option<InetSocketAddr> {
+        if self.addresses.is_empty() {
+            return None;
+        }
+        let address = self.addresses[self.attempts as usize % self.addresses.len()];
+        self.attempts += 1;
+        Some(address)
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns true once the maximum number of attempts is reached
+    pub fn exhausted(&self) -> bool {
+        self.attempts >= MAX_RECONNECT_ATTEMPTS
+    }
+
+    /// Delay before the next attempt, with a jitter of up to a quarter of the delay so that both
+    /// sides of several restored swaps do not dial in lockstep
+    pub fn delay(&self) -> Duration {
+        let delay = INITIAL_RECONNECT_DELAY
+            .checked_mul(2u32.saturating_pow(self.attempts))
+            .map_or(MAX_RECONNECT_DELAY, |delay| delay.min(MAX_RECONNECT_DELAY));
+        let jitter_ms = thread_rng().gen_range(0, delay.as_millis() as u64 / 4 + 1);
+        delay + Duration::from_millis(jitter_ms)
+    }
+}
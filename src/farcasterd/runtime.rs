@@ -4,69 +4,153 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use crate::bus::ctl::{CtlMsg, FundingInfo, GetKeys, SwapKeys};
-use crate::bus::info::FundingInfos;
-use crate::bus::p2p::{PeerMsg, TakerCommit};
+use crate::bus::ctl::{
+    CtlMsg, FundingInfo, GetKeys, PeerSeen, ReconnectPeer, ServiceExit, SwapKeys,
+};
+use crate::bus::info::{FundingInfos, NodeEvent};
+use crate::bus::p2p::{PeerMsg, RefusalReason, TakerCommit};
 use crate::bus::sync::SyncMsg;
-use crate::bus::{BusMsg, DealInfo, DealStatus, List, ServiceBus};
+use crate::bus::{BusMsg, CheckpointEntry, DealInfo, DealStatus, List, OptionDetails, ServiceBus};
 use crate::event::StateMachineExecutor;
+use crate::farcasterd::acceptance_policy::{AcceptancePolicy, Candidate, InProgressSwap};
+use crate::farcasterd::deal_validation::{validate_deal, PriceOracle};
+use crate::farcasterd::metrics::Metrics;
+use crate::farcasterd::notifications::{Notification, NotificationEvent, Notifier};
+use crate::farcasterd::peer_alerts::SwapConnections;
+use crate::farcasterd::peer_reconnect::{PeerReconnect, MAX_RECONNECT_ATTEMPTS};
 use crate::farcasterd::stats::Stats;
+use crate::farcasterd::supervisor::{RestartPolicy, ServiceHandle, Supervisor};
 use crate::farcasterd::syncer_state_machine::{SyncerStateMachine, SyncerStateMachineExecutor};
+use crate::farcasterd::tor::{self, TorControl};
 use crate::farcasterd::trade_state_machine::{TradeStateMachine, TradeStateMachineExecutor};
 use crate::farcasterd::Opts;
-use crate::syncerd::{AddressBalance, TaskAborted};
+use crate::opts::ARGS_FROM_STDIN;
+use crate::service::exit_service;
+use crate::syncerd::{AddressBalance, Health, TaskAborted};
 use crate::syncerd::{Event as SyncerEvent, HealthResult, SweepSuccess, TaskId};
+use crate::walletd::FARCASTER_KEY_PASSPHRASE;
 use crate::{
     bus::ctl::{Keys, ProgressStack, Token},
-    bus::info::{DealStatusSelector, InfoMsg, NodeInfo, ProgressEvent, SwapProgress},
-    bus::{Failure, FailureCode, Progress},
+    bus::info::{DealStatusSelector, InfoMsg, NodeInfo, PeerEntry, ProgressEvent, SwapProgress},
+    bus::{Failure, FailureCode, Progress, StateTransition},
     clap::Parser,
-    config::ParsedSwapConfig,
+    config::{parse_config, ParsedSwapConfig, LAUNCH_ARGS_SECTIONS, RESTART_REQUIRED_SECTIONS},
     error::SyncerError,
     service::Endpoints,
 };
+use crate::{AccordantBlockchain, ArbitratingBlockchain};
 use crate::{Config, CtlServer, Error, LogStyle, Service, ServiceConfig, ServiceId};
 
 use std::collections::VecDeque;
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
-use std::io;
+use std::io::{self, Write};
 use std::iter::FromIterator;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::process;
+use std::str::FromStr;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
-use bitcoin::{secp256k1::PublicKey, secp256k1::SecretKey};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{PublicKey, SecretKey, SECP256K1};
+use chrono::Utc;
 use clap::IntoApp;
 use farcaster_core::{
     blockchain::{Blockchain, Network},
     role::TradeRole,
     swap::btcxmr::Deal,
     swap::SwapId,
+    trade::DealId,
 };
 use internet2::addr::NodeId;
 use internet2::{addr::InetSocketAddr, addr::NodeAddr};
 use microservices::esb::{self, Handler};
+use microservices::ZMQ_CONTEXT;
+use strict_encoding::StrictEncode;
+
+/// Time given to the running swaps to reach a safe point on a drain shutdown, the node shuts
+/// down anyway once elapsed
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(600);
 
 pub fn run(
     service_config: ServiceConfig,
     config: Config,
-    _opts: Opts,
+    opts: Opts,
     wallet_token: Token,
+    reload_requested: Arc<AtomicBool>,
 ) -> Result<(), Error> {
-    let _walletd = launch("walletd", ["--token", &wallet_token.to_string()])?;
+    let supervisor = Supervisor::new(reload_requested);
+    let tx_exit = ZMQ_CONTEXT.socket(zmq::PAIR)?;
+    let rx_exit = ZMQ_CONTEXT.socket(zmq::PAIR)?;
+    rx_exit.bind("inproc://farcasterdbridge")?;
+    tx_exit.connect("inproc://farcasterdbridge")?;
+
+    // a wrong passphrase fails here instead of walletd being relaunched in a loop
+    let key_passphrase = opts.key_opts.passphrase()?;
+    opts.key_opts.check_passphrase(key_passphrase.as_deref())?;
+
+    let hot_wallet_network = config.get_hot_wallet_network();
+    let mut walletd_args = vec!["--key-file".to_string(), opts.key_opts.key_file.clone()];
+    if let Some(network) = hot_wallet_network {
+        walletd_args.push("--hot-wallet-network".to_string());
+        walletd_args.push(network.to_string());
+    }
+    walletd_args.extend([
+        ARGS_FROM_STDIN.to_string(),
+        "--token".to_string(),
+        wallet_token.to_string(),
+    ]);
+    if let Some(passphrase) = key_passphrase {
+        walletd_args.extend(["--key-passphrase".to_string(), passphrase]);
+    }
+    let walletd = launch("walletd", &walletd_args)?;
+    supervisor.supervise(
+        "walletd",
+        walletd_args,
+        Some(ServiceId::Wallet),
+        RestartPolicy::Relaunch,
+        walletd,
+    );
     if config.is_grpc_enable() {
-        let _grpcd = launch(
+        let mut grpcd_args = vec![
+            "--grpc-port".to_string(),
+            config.grpc.clone().unwrap().bind_port.to_string(),
+            "--grpc-ip".to_string(),
+            config.grpc_bind_ip(),
+        ];
+        if !config.is_grpc_auth_enable() {
+            grpcd_args.push("--no-auth".to_string());
+        }
+        let grpcd = launch("grpcd", &grpcd_args)?;
+        supervisor.supervise(
             "grpcd",
-            [
-                "--grpc-port",
-                &config.grpc.clone().unwrap().bind_port.to_string(),
-                "--grpc-ip",
-                &config.grpc_bind_ip(),
-            ],
-        )?;
+            grpcd_args,
+            Some(ServiceId::Grpcd),
+            RestartPolicy::Relaunch,
+            grpcd,
+        );
+    }
+    let databased = launch("databased", Vec::<String>::new())?;
+    supervisor.supervise(
+        "databased",
+        vec![],
+        Some(ServiceId::Database),
+        RestartPolicy::Relaunch,
+        databased,
+    );
+    supervisor.run(tx_exit)?;
+
+    if let Some(network) = hot_wallet_network {
+        info!(
+            "{} manages a Bitcoin {} hot wallet",
+            "walletd".label(),
+            network.label()
+        );
     }
-    let empty: Vec<String> = vec![];
-    let _databased = launch("databased", empty)?;
 
     if config.is_auto_funding_enable() {
         info!(
@@ -76,6 +160,25 @@ pub fn run(
         );
     }
 
+    config.get_peer_limits()?;
+    config.get_ephemeral_ports()?;
+
+    let metrics = config
+        .get_metrics_bind_addr()?
+        .map(Metrics::start)
+        .transpose()?;
+
+    let notifier = config
+        .get_notifications_config()
+        .map(Notifier::start)
+        .transpose()?;
+
+    let price_oracle = config
+        .get_deal_validation_config()
+        .price_oracle_command
+        .map(PriceOracle::start)
+        .transpose()?;
+
     let runtime = Runtime {
         identity: ServiceId::Farcasterd,
         node_secret_key: None,
@@ -89,15 +192,36 @@ pub fn run(
         wallet_token,
         progress: none!(),
         progress_subscriptions: none!(),
+        event_subscriptions: none!(),
         stats: none!(),
+        metrics,
+        notifier,
+        price_oracle,
+        acceptance_policy: config.get_acceptance_policy_config().into(),
+        supervisor,
+        draining: false,
+        drained_swaps: none!(),
+        shutting_down: false,
+        hot_wallet_network,
         config,
+        config_path: opts.config,
+        data_dir: opts.shared.data_dir,
+        forwarded_tor_proxy: opts.shared.tor_proxy,
+        tor_proxy: None,
+        tor_control: None,
+        onion_address: None,
+        peer_reconnects: none!(),
+        swap_connections: none!(),
+        syncer_health: none!(),
         syncer_task_counter: 0,
         trade_state_machines: vec![],
         syncer_state_machines: none!(),
     };
 
-    let broker = true;
-    Service::run(service_config, runtime, broker)
+    let mut service = Service::broker(service_config, runtime)?;
+    service.add_bridge_service_bus(rx_exit)?;
+    service.run_loop()?;
+    unreachable!()
 }
 
 pub struct Runtime {
@@ -113,8 +237,27 @@ pub struct Runtime {
     pub deals: HashSet<Deal>, // The set of all known deals. Includes open, consumed and ended deals includes open, consumed and ended deals
     progress: HashMap<ServiceId, VecDeque<ProgressStack>>, // A mapping from Swap ServiceId to its sent and received progress messages (Progress, Success, Failure)
     progress_subscriptions: HashMap<ServiceId, HashSet<ServiceId>>, // A mapping from a Client ServiceId to its subsribed swap progresses
-    pub stats: Stats,             // Some stats about deals and swaps
-    pub config: Config,           // The complete node configuration
+    event_subscriptions: HashSet<ServiceId>, // Clients subscribed to the node events
+    pub stats: Stats,                        // Some stats about deals and swaps
+    metrics: Option<Metrics>, // Exported prometheus metrics, set from config on Runtime instantiation
+    notifier: Option<Notifier>, // Delivers swap events to the configured targets, set from config on Runtime instantiation
+    pub price_oracle: Option<PriceOracle>, // Runs the configured price oracle command off the event loop, set from config on Runtime instantiation
+    pub acceptance_policy: AcceptancePolicy, // Policy applied to incoming taker commits, set from config on Runtime instantiation
+    pub supervisor: Supervisor, // Tracks the launched processes, set on Runtime instantiation
+    pub draining: bool, // Set by a drain shutdown request, no new deal or taker is accepted anymore
+    drained_swaps: HashSet<SwapId>, // Swaps that reached a safe point while draining
+    shutting_down: bool, // Set once services are being terminated
+    pub hot_wallet_network: Option<Network>, // Network of the walletd hot wallet, set from config on Runtime instantiation
+    pub config: Config,                      // The complete node configuration
+    config_path: String, // Path of the configuration file, re-read on ReloadConfig
+    data_dir: PathBuf,   // Data directory of the node, set on Runtime instantiation
+    forwarded_tor_proxy: Option<SocketAddr>, // Tor proxy given to farcasterd, forwarded to every launched service
+    pub tor_proxy: Option<SocketAddr>, // Tor proxy given to the services launched once an onion deal is taken
+    tor_control: Option<TorControl>, // Tor control connection keeping the onion service alive, set by the first listen
+    onion_address: Option<InetSocketAddr>, // Onion address of the listening peerd, advertised in the deals
+    peer_reconnects: HashMap<NodeId, PeerReconnect>, // Counterparties of restored swaps being reconnected to
+    swap_connections: SwapConnections, // Connections with the counterparties of the running swaps, checked against the peer alert thresholds
+    syncer_health: HashMap<ServiceId, Health>, // Latest health check result of the running syncers, a change is pushed as node event
     pub syncer_task_counter: u32, // A strictly incrementing counter of issued syncer tasks
     pub trade_state_machines: Vec<TradeStateMachine>, // New trade state machines are inserted on creation and destroyed upon state machine end transitions
     syncer_state_machines: HashMap<TaskId, SyncerStateMachine>, // New syncer state machines are inserted by their syncer task id when sending a syncer request and destroyed upon matching syncer request receival
@@ -137,7 +280,10 @@ impl esb::Handler<ServiceBus> for Runtime {
         source: ServiceId,
         request: BusMsg,
     ) -> Result<(), Self::Error> {
-        match (bus, request) {
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record_message(bus);
+        }
+        let res = match (bus, request) {
             // Peer-to-peer message bus, only accept Peer message
             (ServiceBus::Msg, BusMsg::P2p(req)) => self.handle_msg(endpoints, source, req),
             // Control bus for issuing control commands, only accept Ctl message
@@ -146,9 +292,13 @@ impl esb::Handler<ServiceBus> for Runtime {
             (ServiceBus::Info, BusMsg::Info(req)) => self.handle_info(endpoints, source, req),
             // Syncer event bus for blockchain tasks and events, only accept Sync message
             (ServiceBus::Sync, BusMsg::Sync(req)) => self.handle_sync(endpoints, source, req),
+            // Internal bridge for process exits detected by the supervisor, only accept Ctl message
+            (ServiceBus::Bridge, BusMsg::Ctl(req)) => self.handle_bridge(endpoints, source, req),
             // All other pairs are not supported
             (_, request) => Err(Error::NotSupported(bus, request.to_string())),
-        }
+        };
+        self.update_metrics();
+        res
     }
 
     fn handle_err(
@@ -252,6 +402,7 @@ impl Runtime {
                             source.clone(),
                             BusMsg::Ctl(CtlMsg::GetKeys(wallet_token)),
                         )?;
+                        self.hot_wallet_syncer_up(endpoints)?;
                     }
                     ServiceId::Peer(_, addr) => {
                         // If this is a connecting peerd, only process the
@@ -266,11 +417,24 @@ impl Runtime {
                         if !awaiting_swaps.is_empty() {
                             debug!("Received hello from awaited peerd connection {}, will continue processing once swaps {:?} are connected.", source, awaiting_swaps);
                         } else {
-                            self.handle_new_connection(source.clone());
+                            self.handle_new_connection(endpoints, source.clone());
+                        }
+                        // connections forked from a listener are identified by our own address
+                        if self.listens.contains(&addr.addr) {
+                            self.record_peer_seen(endpoints, addr.id, None, None)?;
                         }
                     }
                     ServiceId::Swap(_) => {
-                        // nothing to do, we register swapd instances on a by-swap basis
+                        // nothing to do, we register swapd instances on a by-swap basis, except
+                        // requesting a launched or restored swapd to drain as well
+                        if self.draining {
+                            endpoints.send_to(
+                                ServiceBus::Ctl,
+                                self.identity(),
+                                source.clone(),
+                                BusMsg::Ctl(CtlMsg::Drain),
+                            )?;
+                        }
                     }
                     ServiceId::Syncer(_, _) => {
                         if self.spawning_services.remove(&source) {
@@ -280,6 +444,12 @@ impl Runtime {
                                 source,
                                 self.count_syncers().bright_blue_bold()
                             );
+                            if let ServiceId::Syncer(blockchain, network) = source {
+                                self.notify_node_event(
+                                    endpoints,
+                                    NodeEvent::SyncerConnected(blockchain, network),
+                                );
+                            }
                         } else {
                             error!(
                                 "Syncer {} was already registered; the service probably was relaunched\\
@@ -287,6 +457,9 @@ impl Runtime {
                                 source
                             );
                         }
+                        if Some(&source) == self.hot_wallet_syncer().as_ref() {
+                            self.hot_wallet_syncer_up(endpoints)?;
+                        }
                     }
                     _ => {
                         // Ignoring the rest of daemon/client types
@@ -346,11 +519,22 @@ impl Runtime {
 
             // Notify all swapds in case of disconnect
             req @ (CtlMsg::Disconnected | CtlMsg::Reconnected) => {
+                if let Some(node_addr) = source.node_addr() {
+                    let event = match req {
+                        CtlMsg::Reconnected => NodeEvent::PeerConnected(node_addr),
+                        _ => NodeEvent::PeerDisconnected(node_addr),
+                    };
+                    self.notify_node_event(endpoints, event);
+                }
+                let alerts = self.config.get_peer_alerts();
                 for swap_id in self
                     .trade_state_machines
                     .iter()
                     .filter_map(|tsm| tsm.get_swap_id_with_matching_connection(&source))
                 {
+                    if matches!(req, CtlMsg::Reconnected) {
+                        self.swap_connections.record_reconnect(swap_id, &alerts);
+                    }
                     endpoints.send_to(
                         ServiceBus::Ctl,
                         self.identity(),
@@ -360,14 +544,58 @@ impl Runtime {
                 }
             }
 
+            CtlMsg::PeerLimitViolations(count) if matches!(source, ServiceId::Peer(..)) => {
+                debug!(
+                    "{} | {} violations of the peer limits by the remote",
+                    source.label(),
+                    count
+                );
+                self.stats.incr_peer_limit_violations(count);
+            }
+
+            CtlMsg::PeerRoundTrip(rtt) if matches!(source, ServiceId::Peer(..)) => {
+                trace!("{} | Round-trip time: {:?}", source.label(), rtt);
+                let alerts = self.config.get_peer_alerts();
+                for swap_id in self
+                    .trade_state_machines
+                    .iter()
+                    .filter_map(|tsm| tsm.get_swap_id_with_matching_connection(&source))
+                {
+                    self.swap_connections.record_rtt(swap_id, rtt, &alerts);
+                }
+            }
+
+            // No address book entry for a peer we reconnect to, only its deal address is dialed
+            CtlMsg::Failure(Failure { info, .. }) if source == ServiceId::Database => {
+                debug!("{}", info);
+            }
+
             // Add progress in queues and forward to subscribed clients
             event @ (CtlMsg::Progress(..) | CtlMsg::Success(..) | CtlMsg::Failure(..)) => {
+                if let (
+                    CtlMsg::Progress(Progress::StateTransition(transition)),
+                    ServiceId::Swap(swap_id),
+                ) = (&event, &source)
+                {
+                    self.notify_state_transition(*swap_id, transition);
+                }
                 if !self.progress.contains_key(&source) {
                     self.progress.insert(source.clone(), none!());
                 };
                 let queue = self.progress.get_mut(&source).expect("checked/added above");
                 let prog = match event {
                     CtlMsg::Progress(p) => {
+                        let report = match &p {
+                            Progress::StateUpdate(report) => Some(report),
+                            Progress::StateTransition(transition) => Some(&transition.new_state),
+                            Progress::Message(_) => None,
+                        };
+                        if let (Some(report), ServiceId::Swap(swap_id)) = (report, &source) {
+                            if let Some(metrics) = self.metrics.as_mut() {
+                                metrics.record_swap_state(*swap_id, &report.state);
+                            }
+                            self.swap_connections.record_state(*swap_id, report);
+                        }
                         // Replace the latest state update message in the queue
                         if let Progress::StateUpdate(_) = p {
                             if let Some(ProgressStack::Progress(Progress::StateUpdate(_))) =
@@ -388,11 +616,126 @@ impl Runtime {
                 self.notify_subscribed_clients(endpoints, &source, prog.1);
             }
 
+            CtlMsg::SyncerMetrics(report) => {
+                if let (Some(metrics), ServiceId::Syncer(blockchain, network)) =
+                    (self.metrics.as_mut(), &source)
+                {
+                    metrics.record_syncer(*blockchain, *network, report);
+                }
+            }
+
+            CtlMsg::Shutdown(drain) => {
+                if drain {
+                    self.draining = true;
+                    self.revoke_open_deals(endpoints)?;
+                    let swap_ids: Vec<SwapId> = self
+                        .trade_state_machines
+                        .iter()
+                        .filter_map(|tsm| tsm.swap_id())
+                        .collect();
+                    for swap_id in swap_ids.iter() {
+                        // swapd instances not launched yet are requested to drain on Hello
+                        let _ = endpoints.send_to(
+                            ServiceBus::Ctl,
+                            self.identity(),
+                            ServiceId::Swap(*swap_id),
+                            BusMsg::Ctl(CtlMsg::Drain),
+                        );
+                    }
+                    self.supervisor.schedule_drain_timeout(DRAIN_TIMEOUT);
+                    info!(
+                        "{} {} running swaps before shutdown",
+                        "Draining".label(),
+                        swap_ids.len()
+                    );
+                    self.send_client_ctl(
+                        endpoints,
+                        source,
+                        CtlMsg::Success(OptionDetails::with(format!(
+                            "Draining {} running swaps before shutdown, swaps not checkpointed yet are aborted and the node shuts down anyway after {} seconds",
+                            swap_ids.len(),
+                            DRAIN_TIMEOUT.as_secs()
+                        ))),
+                    )?;
+                } else {
+                    self.send_client_ctl(
+                        endpoints,
+                        source,
+                        CtlMsg::Success(OptionDetails::with("Shutting down")),
+                    )?;
+                    self.shutdown(endpoints)?;
+                }
+            }
+
+            CtlMsg::ReloadConfig => match self.reload_config() {
+                Ok(report) => {
+                    info!("{}", report);
+                    self.send_client_ctl(
+                        endpoints,
+                        source,
+                        CtlMsg::Success(OptionDetails::with(report)),
+                    )?;
+                }
+                Err(err) => {
+                    self.send_client_ctl(
+                        endpoints,
+                        source,
+                        CtlMsg::Failure(Failure {
+                            code: FailureCode::Unknown,
+                            info: format!("Failed to reload the configuration: {}", err),
+                        }),
+                    )?;
+                }
+            },
+
+            // The swap reached a safe point, terminate it and resume it from its checkpoint on
+            // next start
+            CtlMsg::Drained => {
+                if let ServiceId::Swap(swap_id) = source {
+                    info!("{} | Drained", swap_id.swap_id());
+                    self.drained_swaps.insert(swap_id);
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        source,
+                        BusMsg::Ctl(CtlMsg::Terminate),
+                    )?;
+                }
+            }
+
+            CtlMsg::ConnectSuccess => {
+                if let Some(node_addr) = source.node_addr() {
+                    self.record_peer_seen(endpoints, node_addr.id, Some(node_addr.addr), None)?;
+                }
+                self.process_request_with_state_machines(
+                    BusMsg::Ctl(CtlMsg::ConnectSuccess),
+                    source,
+                    endpoints,
+                )?;
+            }
+
+            CtlMsg::MakeDeal(..) | CtlMsg::TakeDeal(..) if self.draining => {
+                self.send_client_ctl(
+                    endpoints,
+                    source,
+                    CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: "Farcaster is draining before shutdown, no new deal is accepted"
+                            .to_string(),
+                    }),
+                )?;
+            }
+
             req => {
                 self.process_request_with_state_machines(BusMsg::Ctl(req), source, endpoints)?;
             }
         }
 
+        // Swaps may end or drain with any control message, check if the shutdown can proceed
+        if self.draining && !self.shutting_down && self.all_swaps_drained() {
+            self.shutdown(endpoints)?;
+        }
+
         Ok(())
     }
 
@@ -431,10 +774,21 @@ impl Runtime {
                             .filter_map(|tsm| tsm.open_deal())
                             .collect(),
                         stats: self.stats.clone(),
+                        crashes: self.supervisor.crash_reports(),
                     }),
                 )?;
             }
 
+            InfoMsg::GetAcceptancePolicy => {
+                let report = self.acceptance_policy.report(&self.in_progress_swaps());
+                self.send_client_info(endpoints, source, InfoMsg::AcceptancePolicy(report))?;
+            }
+
+            InfoMsg::ValidateDeal(deal) => {
+                let report = validate_deal(&self.config, &deal, self.price_oracle.as_ref());
+                self.send_client_info(endpoints, source, InfoMsg::DealValidationReport(report))?;
+            }
+
             InfoMsg::ListPeers => {
                 self.send_client_info(
                     endpoints,
@@ -443,6 +797,35 @@ impl Runtime {
                 )?;
             }
 
+            // The peerd replies to the client directly
+            InfoMsg::GetPeerInfo(node_addr) => {
+                match self
+                    .registered_services
+                    .iter()
+                    .find(|service| service.node_addr() == Some(node_addr))
+                    .cloned()
+                {
+                    Some(peerd) => {
+                        endpoints.send_to(
+                            ServiceBus::Info,
+                            source,
+                            peerd,
+                            BusMsg::Info(InfoMsg::GetInfo),
+                        )?;
+                    }
+                    None => {
+                        self.send_client_ctl(
+                            endpoints,
+                            source,
+                            CtlMsg::Failure(Failure {
+                                code: FailureCode::TargetServiceNotFound,
+                                info: format!("No connected peerd with address {}", node_addr),
+                            }),
+                        )?;
+                    }
+                }
+            }
+
             InfoMsg::ListSwaps => {
                 self.send_client_info(
                     endpoints,
@@ -465,6 +848,7 @@ impl Runtime {
                             .filter_map(|tsm| tsm.open_deal())
                             .map(|deal| DealInfo {
                                 serialized_deal: deal.to_string(),
+                                ephemeral_node_id: self.deal_secret_key(&deal).is_some(),
                                 deal,
                                 status: DealStatus::Open,
                                 local_trade_role: TradeRole::Maker,
@@ -479,6 +863,7 @@ impl Runtime {
                             .filter_map(|tsm| tsm.consumed_deal())
                             .map(|(deal, trade_role)| DealInfo {
                                 serialized_deal: deal.to_string(),
+                                ephemeral_node_id: self.deal_secret_key(&deal).is_some(),
                                 deal,
                                 status: DealStatus::InProgress,
                                 local_trade_role: trade_role,
@@ -572,15 +957,25 @@ impl Runtime {
                     )?;
                 } else {
                     for checkpoint in list.drain(..) {
-                        self.handle_ctl(
-                            endpoints,
-                            source.clone(),
-                            CtlMsg::RestoreCheckpoint(checkpoint),
-                        )?;
+                        self.restore_checkpoint(endpoints, checkpoint)?;
                     }
                 }
             }
 
+            // Checkpoint of a crashed swap requested on swapd exit, restore it
+            InfoMsg::CheckpointEntry(entry) if source == ServiceId::Database => {
+                self.restore_checkpoint(endpoints, entry)?;
+            }
+
+            // Address book entry of a peer we reconnect to
+            InfoMsg::PeerEntry(PeerEntry {
+                node_id, addresses, ..
+            }) if source == ServiceId::Database => {
+                if let Some(reconnect) = self.peer_reconnects.get_mut(&node_id) {
+                    reconnect.add_addresses(&addresses);
+                }
+            }
+
             // Add the request's source to the subscription list for later progress notifications
             // and send all notifications already in the queue
             InfoMsg::SubscribeProgress(swap_id) => {
@@ -649,6 +1044,19 @@ impl Runtime {
                 // if no swap service exists no subscription need to be removed
             }
 
+            // Add the request's source to the subscription list of node events
+            InfoMsg::SubscribeEvents => {
+                // double subscribe is not a problem as we manage the list in a set
+                self.event_subscriptions.insert(source.clone());
+                trace!("{} has been added to node events subscription", source);
+            }
+
+            // Remove the request's source from the subscription list of node events
+            InfoMsg::UnsubscribeEvents => {
+                self.event_subscriptions.remove(&source);
+                trace!("{} has been removed from node events subscription", source);
+            }
+
             // Filter tsm by funding needs by blockchain and return the funding infos
             InfoMsg::NeedsFunding(blockchain) => {
                 let swaps_need_funding: Vec<FundingInfo> = self
@@ -663,6 +1071,11 @@ impl Runtime {
                 )?;
             }
 
+            // reply of a swap aborted by a drain as it was not checkpointed yet
+            InfoMsg::String(msg) if self.draining && matches!(source, ServiceId::Swap(_)) => {
+                info!("{} | {}", source, msg);
+            }
+
             req => {
                 warn!("Ignoring request: {}", req.err());
             }
@@ -692,6 +1105,225 @@ impl Runtime {
         self.process_request_with_state_machines(BusMsg::Sync(request), source, endpoints)
     }
 
+    fn handle_bridge(
+        &mut self,
+        endpoints: &mut Endpoints,
+        _source: ServiceId,
+        request: CtlMsg,
+    ) -> Result<(), Error> {
+        match request {
+            CtlMsg::ServiceExited(exit) => self.handle_service_exit(endpoints, exit),
+            CtlMsg::ReconnectPeer(node_id) => self.handle_peer_reconnect(node_id),
+            CtlMsg::DrainTimeout => {
+                if self.draining && !self.shutting_down {
+                    let pending: Vec<String> = self
+                        .trade_state_machines
+                        .iter()
+                        .filter_map(|tsm| match tsm.swap_id() {
+                            Some(swap_id) if self.drained_swaps.contains(&swap_id) => None,
+                            Some(swap_id) => Some(swap_id.to_string()),
+                            None => Some("…".to_string()),
+                        })
+                        .collect();
+                    error!(
+                        "{} within {} seconds, swaps {:?} did not reach a safe point; shutting down anyway, they resume from their latest checkpoint on next start",
+                        "Failed to drain".err(),
+                        DRAIN_TIMEOUT.as_secs(),
+                        pending
+                    );
+                    self.shutdown(endpoints)?;
+                }
+                Ok(())
+            }
+            CtlMsg::ReloadConfig => {
+                match self.reload_config() {
+                    Ok(report) => info!("{}", report),
+                    Err(err) => error!("Failed to reload the configuration: {}", err),
+                }
+                Ok(())
+            }
+            req => {
+                warn!("Ignoring request: {}", req.err());
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_service_exit(
+        &mut self,
+        endpoints: &mut Endpoints,
+        exit: ServiceExit,
+    ) -> Result<(), Error> {
+        if exit.crashed {
+            error!("{}", format!("Process {} crashed", exit).err());
+        } else {
+            debug!("Process {}", exit);
+        }
+        if self.shutting_down && self.supervisor.is_idle() {
+            info!("All services terminated, {}", "exiting".label());
+            exit_service(0);
+        }
+        // the last swap saved its checkpoints, databased can stop
+        if self.shutting_down
+            && matches!(exit.service, Some(ServiceId::Swap(_)))
+            && !self.is_swapd_running()
+        {
+            self.terminate(endpoints, ServiceId::Database);
+        }
+        let service = match exit.service {
+            Some(service) => service,
+            None => return Ok(()),
+        };
+
+        match &service {
+            ServiceId::Swap(swap_id) => {
+                // swapd exited before the swap ended, restore the swap from its latest checkpoint
+                if self.running_swaps_contain(swap_id)
+                    && !self.shutting_down
+                    && !self.drained_swaps.contains(swap_id)
+                {
+                    self.trade_state_machines
+                        .retain(|tsm| tsm.swap_id() != Some(*swap_id));
+                    if self.supervisor.register_swap_restore(*swap_id) {
+                        info!(
+                            "{} | {} swap from its latest checkpoint",
+                            swap_id.swap_id(),
+                            "Restoring".label()
+                        );
+                        endpoints.send_to(
+                            ServiceBus::Info,
+                            self.identity(),
+                            ServiceId::Database,
+                            BusMsg::Info(InfoMsg::GetCheckpointEntry(*swap_id)),
+                        )?;
+                    } else {
+                        error!(
+                            "{} | Swap exited too many times, not restoring it automatically anymore; use restore-checkpoint once the problem is fixed",
+                            swap_id.swap_id()
+                        );
+                    }
+                }
+            }
+            ServiceId::Peer(_, node_addr) => {
+                if exit.crashed {
+                    self.spawning_services.remove(&service);
+                    self.registered_services
+                        .retain(|registered| registered.node_addr() != Some(*node_addr));
+                }
+            }
+            _ => {
+                self.registered_services.remove(&service);
+                if let ServiceId::Syncer(blockchain, network) = service {
+                    self.syncer_health.remove(&service);
+                    self.notify_node_event(
+                        endpoints,
+                        NodeEvent::SyncerDisconnected(blockchain, network),
+                    );
+                }
+                // the subscriptions of the grpc clients do not outlive grpcd
+                if service == ServiceId::Grpcd {
+                    let is_grpc_client = |sub: &ServiceId| matches!(sub, ServiceId::GrpcdClient(_));
+                    self.event_subscriptions.retain(|sub| !is_grpc_client(sub));
+                    for subs in self.progress_subscriptions.values_mut() {
+                        subs.retain(|sub| !is_grpc_client(sub));
+                    }
+                    self.progress_subscriptions
+                        .retain(|_, subs| !subs.is_empty());
+                }
+                if exit.relaunching {
+                    self.spawning_services.insert(service.clone());
+                } else {
+                    self.spawning_services.remove(&service);
+                }
+                // The syncer tasks are lost with the syncer, restart the swaps using it so they
+                // register their tasks again once restored
+                if matches!(service, ServiceId::Syncer(..)) && (exit.crashed || exit.relaunching) {
+                    let swap_ids: Vec<SwapId> = self
+                        .trade_state_machines
+                        .iter()
+                        .filter(|tsm| tsm.syncers().contains(&service))
+                        .filter_map(|tsm| tsm.swap_id())
+                        .collect();
+                    for swap_id in swap_ids {
+                        warn!(
+                            "{} | Restarting swap after {} exited",
+                            swap_id.swap_id(),
+                            service
+                        );
+                        if endpoints
+                            .send_to(
+                                ServiceBus::Ctl,
+                                self.identity(),
+                                ServiceId::Swap(swap_id),
+                                BusMsg::Ctl(CtlMsg::Terminate),
+                            )
+                            .is_err()
+                        {
+                            warn!("{} | Failed to terminate swapd", swap_id.swap_id());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_peer_reconnect(&mut self, node_id: NodeId) -> Result<(), Error> {
+        let awaited = self
+            .trade_state_machines
+            .iter()
+            .any(|tsm| tsm.awaiting_reconnect_of() == Some(node_id));
+        let connected = self
+            .registered_services
+            .iter()
+            .any(|service| service.node_id() == Some(node_id));
+        if !awaited || connected {
+            self.peer_reconnects.remove(&node_id);
+            return Ok(());
+        }
+        let reconnect = match self.peer_reconnects.get_mut(&node_id) {
+            Some(reconnect) => reconnect,
+            None => return Ok(()),
+        };
+        if reconnect.exhausted() {
+            warn!(
+                "Giving up reconnecting to {} after {} attempts, waiting for the counterparty to connect; use connect to retry",
+                node_id, MAX_RECONNECT_ATTEMPTS
+            );
+            self.peer_reconnects.remove(&node_id);
+            return Ok(());
+        }
+        let address = match reconnect.next_address() {
+            Some(address) => address,
+            None => {
+                info!(
+                    "No known address of {}, waiting for the counterparty to connect",
+                    node_id
+                );
+                self.peer_reconnects.remove(&node_id);
+                return Ok(());
+            }
+        };
+        let attempts = reconnect.attempts();
+        let delay = reconnect.delay();
+        info!(
+            "{} to {} at {} (attempt {}/{})",
+            "Reconnecting".label(),
+            node_id,
+            address,
+            attempts,
+            MAX_RECONNECT_ATTEMPTS
+        );
+        let secret_key = reconnect.secret_key();
+        if let Err(err) =
+            self.connect_peer_as(&ReconnectPeer(NodeAddr::new(node_id, address), secret_key))
+        {
+            warn!("Failed to reconnect to {}: {}", node_id, err);
+        }
+        self.supervisor.schedule_peer_reconnect(node_id, delay);
+        Ok(())
+    }
+
     fn handle_auto_restore(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
         if self.config.auto_restore_enable()
             && self.services_ready().is_ok()
@@ -715,6 +1347,118 @@ impl Runtime {
         Ok(())
     }
 
+    // Restore a checkpoint on farcasterd's own initiative, no client is waiting for the reply
+    fn restore_checkpoint(
+        &mut self,
+        endpoints: &mut Endpoints,
+        entry: CheckpointEntry,
+    ) -> Result<(), Error> {
+        self.process_request_with_state_machines(
+            BusMsg::Ctl(CtlMsg::RestoreCheckpoint(entry)),
+            self.identity(),
+            endpoints,
+        )
+    }
+
+    // Revoke all open deals, the same way a client would
+    fn revoke_open_deals(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        let open_deals: Vec<Deal> = self
+            .trade_state_machines
+            .iter()
+            .filter_map(|tsm| tsm.open_deal())
+            .collect();
+        self.trade_state_machines
+            .retain(|tsm| tsm.open_deal().is_none());
+        for deal in open_deals {
+            info!("Revoked deal {}", deal.label());
+            endpoints.send_to(
+                ServiceBus::Ctl,
+                self.identity(),
+                ServiceId::Database,
+                BusMsg::Ctl(CtlMsg::SetDealInfo(DealInfo {
+                    deal: deal.clone(),
+                    serialized_deal: deal.to_string(),
+                    status: DealStatus::Revoked,
+                    local_trade_role: TradeRole::Maker,
+                    ephemeral_node_id: self.deal_secret_key(&deal).is_some(),
+                })),
+            )?;
+        }
+        Ok(())
+    }
+
+    // True once every running swap reached a safe point, swaps which did not launch their swapd
+    // yet are awaited too
+    fn all_swaps_drained(&self) -> bool {
+        self.trade_state_machines.iter().all(|tsm| {
+            tsm.swap_id()
+                .map_or(false, |swap_id| self.drained_swaps.contains(&swap_id))
+        })
+    }
+
+    /// Terminate all services in order: swaps first, then peers and syncers, and the wallet and
+    /// grpc daemons. The database daemon is terminated once no swapd is running anymore, the
+    /// swaps save their checkpoints until they exit. farcasterd exits once the supervisor reports
+    /// all processes gone.
+    fn shutdown(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        info!("{} farcaster node", "Shutting down".label());
+        self.shutting_down = true;
+        self.supervisor.shutdown();
+
+        let mut services: Vec<ServiceId> = self
+            .trade_state_machines
+            .iter()
+            .filter_map(|tsm| tsm.swap_id())
+            .filter(|swap_id| !self.drained_swaps.contains(swap_id))
+            .map(ServiceId::Swap)
+            .collect();
+        services.extend(
+            self.registered_services
+                .iter()
+                .filter(|service| matches!(service, ServiceId::Peer(..)))
+                .cloned(),
+        );
+        services.extend(
+            self.registered_services
+                .iter()
+                .filter(|service| matches!(service, ServiceId::Syncer(..)))
+                .cloned(),
+        );
+        services.push(ServiceId::Wallet);
+        if self.config.is_grpc_enable() {
+            services.push(ServiceId::Grpcd);
+        }
+        if !self.is_swapd_running() {
+            services.push(ServiceId::Database);
+        }
+
+        for service in services {
+            self.terminate(endpoints, service);
+        }
+        Ok(())
+    }
+
+    fn terminate(&self, endpoints: &mut Endpoints, service: ServiceId) {
+        debug!("Terminating {}", service);
+        if endpoints
+            .send_to(
+                ServiceBus::Ctl,
+                self.identity(),
+                service.clone(),
+                BusMsg::Ctl(CtlMsg::Terminate),
+            )
+            .is_err()
+        {
+            warn!("Failed to terminate {}", service);
+        }
+    }
+
+    // True while a swapd, drained or not, did not exit yet
+    fn is_swapd_running(&self) -> bool {
+        self.supervisor
+            .is_supervising(|service| matches!(service, ServiceId::Swap(_)))
+    }
+
     pub fn services_ready(&self) -> Result<(), Error> {
         if !self.registered_services.contains(&ServiceId::Wallet) {
             Err(Error::Farcaster(
@@ -737,7 +1481,7 @@ impl Runtime {
         }
     }
 
-    pub fn handle_new_connection(&mut self, connection: ServiceId) {
+    pub fn handle_new_connection(&mut self, endpoints: &mut Endpoints, connection: ServiceId) {
         if let Some(node_addr) = connection.node_addr() {
             self.spawning_services
                 .remove(&ServiceId::dummy_peer_service_id(node_addr));
@@ -748,6 +1492,9 @@ impl Runtime {
                 connection.bright_blue_italic(),
                 self.count_connections().bright_blue_bold(),
             );
+            if let Some(node_addr) = connection.node_addr() {
+                self.notify_node_event(endpoints, NodeEvent::PeerConnected(node_addr));
+            }
         } else {
             warn!(
                 "Connection {} was already registered; the service probably was relaunched",
@@ -756,6 +1503,58 @@ impl Runtime {
         }
     }
 
+    /// Update the peer address book of databased on a connection or a swap with a peer
+    pub fn record_peer_seen(
+        &self,
+        endpoints: &mut Endpoints,
+        node_id: NodeId,
+        address: Option<InetSocketAddr>,
+        swap_id: Option<SwapId>,
+    ) -> Result<(), Error> {
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(CtlMsg::PeerSeen(PeerSeen {
+                node_id,
+                address,
+                swap_id,
+            })),
+        )?;
+        Ok(())
+    }
+
+    /// Count a reconnection with the counterparty of a swap, logs an alert above the threshold
+    pub fn record_swap_reconnect(&mut self, swap_id: SwapId) {
+        let alerts = self.config.get_peer_alerts();
+        self.swap_connections.record_reconnect(swap_id, &alerts);
+    }
+
+    /// Reconnect to the counterparty of a restored swap through the addresses of the address
+    /// book, and the deal address if given, until it is connected
+    pub fn start_peer_reconnect(
+        &mut self,
+        endpoints: &mut Endpoints,
+        node_id: NodeId,
+        deal_address: Option<InetSocketAddr>,
+        secret_key: Option<SecretKey>,
+    ) -> Result<(), Error> {
+        if self.peer_reconnects.contains_key(&node_id) {
+            return Ok(());
+        }
+        let reconnect = PeerReconnect::new(deal_address.into_iter().collect(), secret_key);
+        self.supervisor
+            .schedule_peer_reconnect(node_id, reconnect.delay());
+        self.peer_reconnects.insert(node_id, reconnect);
+        endpoints.send_to(
+            ServiceBus::Info,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Info(InfoMsg::GetPeerEntry(node_id)),
+        )?;
+        Ok(())
+    }
+
     pub fn handle_failed_connection(
         &mut self,
         endpoints: &mut Endpoints,
@@ -769,7 +1568,11 @@ impl Runtime {
             self.spawning_services
                 .remove(&ServiceId::dummy_peer_service_id(node_addr));
         }
-        self.registered_services.remove(&connection);
+        if self.registered_services.remove(&connection) {
+            if let Some(node_addr) = connection.node_addr() {
+                self.notify_node_event(endpoints, NodeEvent::PeerDisconnected(node_addr));
+            }
+        }
         endpoints.send_to(
             ServiceBus::Ctl,
             self.identity(),
@@ -784,6 +1587,10 @@ impl Runtime {
         swap_id: &SwapId,
         endpoints: &mut Endpoints,
     ) -> Result<(), Error> {
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.remove_swap(swap_id);
+        }
+        self.swap_connections.remove_swap(swap_id);
         endpoints.send_to(
             ServiceBus::Ctl,
             self.identity(),
@@ -881,15 +1688,162 @@ impl Runtime {
     }
 
     pub fn syncer_has_client(&self, syncerd: &ServiceId) -> bool {
-        self.trade_state_machines.iter().any(|tsm| {
-            tsm.syncers()
-                .iter()
-                .any(|client_syncer| client_syncer == syncerd)
-        }) || self
-            .syncer_state_machines
-            .values()
-            .filter_map(|ssm| ssm.syncer())
-            .any(|client_syncer| client_syncer == *syncerd)
+        Some(syncerd) == self.hot_wallet_syncer().as_ref()
+            || self.trade_state_machines.iter().any(|tsm| {
+                tsm.syncers()
+                    .iter()
+                    .any(|client_syncer| client_syncer == syncerd)
+            })
+            || self
+                .syncer_state_machines
+                .values()
+                .filter_map(|ssm| ssm.syncer())
+                .any(|client_syncer| client_syncer == *syncerd)
+    }
+
+    /// Returns the Bitcoin syncer used by the walletd hot wallet, kept up for the node lifetime
+    fn hot_wallet_syncer(&self) -> Option<ServiceId> {
+        self.hot_wallet_network
+            .map(|network| ServiceId::Syncer(Blockchain::Bitcoin, network))
+    }
+
+    /// Launches the hot wallet syncer if needed and lets walletd know once it is up. Called on
+    /// walletd and syncer hellos, this way walletd watches again after any of them restarted.
+    fn hot_wallet_syncer_up(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        let network = match self.hot_wallet_network {
+            Some(network) if self.registered_services.contains(&ServiceId::Wallet) => network,
+            _ => return Ok(()),
+        };
+        if let Some(syncer) = syncer_up(
+            &mut self.spawning_services,
+            &mut self.registered_services,
+            Blockchain::Bitcoin,
+            network,
+            &self.config,
+            &self.supervisor,
+            self.tor_proxy,
+        )? {
+            endpoints.send_to(
+                ServiceBus::Ctl,
+                self.identity(),
+                ServiceId::Wallet,
+                BusMsg::Ctl(CtlMsg::HotWalletSyncer(syncer)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Re-read the configuration file and apply the settings that can change at runtime, the
+    /// current configuration is kept if the new one is invalid. Swap parameters, auto-funding,
+    /// acceptance policy and deal validation apply to the next swaps, syncer servers, fee policy
+    /// and peer limits to the syncers and peerds launched from now on; running swaps, syncers and
+    /// peerds are left untouched and reported as such.
+    fn reload_config(&mut self) -> Result<String, Error> {
+        info!("Reloading configuration from {}", self.config_path);
+        let config = parse_config(&self.config_path)?;
+        config.get_bind_addr()?;
+        config.get_metrics_bind_addr()?;
+        config.get_fee_policy()?;
+        config.get_peer_limits()?;
+        config.get_ephemeral_ports()?;
+        for network in [Network::Mainnet, Network::Testnet, Network::Local] {
+            config.get_swap_config(
+                ArbitratingBlockchain::Bitcoin,
+                AccordantBlockchain::Monero,
+                network,
+            )?;
+        }
+
+        let changed = self.config.changed_sections(&config)?;
+        if changed.iter().any(|section| section == "notifications") {
+            self.notifier = config
+                .get_notifications_config()
+                .map(Notifier::start)
+                .transpose()?;
+        }
+        let price_oracle_command = config.get_deal_validation_config().price_oracle_command;
+        if price_oracle_command.as_deref() != self.price_oracle.as_ref().map(PriceOracle::command) {
+            self.price_oracle = price_oracle_command.map(PriceOracle::start).transpose()?;
+        }
+        self.acceptance_policy
+            .reconfigure(config.get_acceptance_policy_config());
+        self.config = config;
+
+        let (restart, changed): (Vec<String>, Vec<String>) = changed
+            .into_iter()
+            .partition(|section| RESTART_REQUIRED_SECTIONS.contains(&section.as_str()));
+        let (launched, applied): (Vec<String>, Vec<String>) = changed
+            .into_iter()
+            .partition(|section| LAUNCH_ARGS_SECTIONS.contains(&section.as_str()));
+        let mut report = if applied.is_empty() {
+            s!("Configuration reloaded, no change applied")
+        } else {
+            format!("Configuration reloaded, applied: {}", applied.join(", "))
+        };
+        if !launched.is_empty() {
+            report.push_str(&format!(
+                "; applied to newly launched services only, running syncers and peerds keep \
+                 their values until restart for: {}",
+                launched.join(", ")
+            ));
+        }
+        if !restart.is_empty() {
+            report.push_str(&format!("; restart required for: {}", restart.join(", ")));
+        }
+        Ok(report)
+    }
+
+    /// Deliver a swap event to the configured notification targets, if any
+    pub fn notify(&self, swap_id: SwapId, trade_role: TradeRole, event: NotificationEvent) {
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(Notification::new(swap_id, trade_role, event));
+        }
+    }
+
+    // Notify the locks and the cancel seen by a swap
+    fn notify_state_transition(&self, swap_id: SwapId, transition: &StateTransition) {
+        let trade_role = match self
+            .trade_state_machines
+            .iter()
+            .find(|tsm| tsm.swap_id() == Some(swap_id))
+            .and_then(|tsm| tsm.trade_role())
+        {
+            Some(trade_role) => trade_role,
+            None => return,
+        };
+        let StateTransition {
+            old_state,
+            new_state,
+        } = transition;
+        if new_state.arb_locked && !old_state.arb_locked {
+            self.notify(
+                swap_id,
+                trade_role,
+                NotificationEvent::Locked {
+                    blockchain: Blockchain::Bitcoin,
+                },
+            );
+        }
+        if new_state.acc_locked && !old_state.acc_locked {
+            self.notify(
+                swap_id,
+                trade_role,
+                NotificationEvent::Locked {
+                    blockchain: Blockchain::Monero,
+                },
+            );
+        }
+        if new_state.canceled && !old_state.canceled {
+            self.notify(swap_id, trade_role, NotificationEvent::Canceled);
+        }
+    }
+
+    // Copy the node stats served on the metrics endpoint, if enabled
+    fn update_metrics(&mut self) {
+        let peer_connections = self.get_open_connections().len();
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record_node(&self.stats, peer_connections);
+        }
     }
 
     fn count_syncers(&self) -> usize {
@@ -913,6 +1867,51 @@ impl Runtime {
             .count()
     }
 
+    /// Returns the swaps counted against the acceptance policy limits
+    pub fn in_progress_swaps(&self) -> Vec<InProgressSwap> {
+        self.trade_state_machines
+            .iter()
+            .filter_map(|tsm| {
+                tsm.consumed_deal()
+                    .map(|(deal, trade_role)| InProgressSwap {
+                        counterparty: tsm.counterparty_node_id(),
+                        deal,
+                        trade_role,
+                    })
+            })
+            .collect()
+    }
+
+    /// Evaluates a taker committing to one of our deals against the acceptance policy
+    pub fn evaluate_taker(&mut self, node_id: NodeId, deal: &Deal) -> Result<(), RefusalReason> {
+        let swaps = self.in_progress_swaps();
+        // the peer limit applies whatever the acceptance policy, it protects the node against a
+        // single taker locking all its deals
+        let max_inflight = self
+            .config
+            .get_peer_limits()
+            .unwrap_or_default()
+            .max_inflight_swaps_per_peer;
+        let inflight = swaps
+            .iter()
+            .filter(|swap| swap.counterparty == Some(node_id))
+            .count();
+        if inflight as u32 >= max_inflight {
+            warn!(
+                "Peer {} reached the limit of {} swaps in flight",
+                node_id, max_inflight
+            );
+            self.stats.incr_peer_limit_violations(1);
+            return Err(RefusalReason::MaxConcurrentSwapsPerPeer);
+        }
+        let candidate = Candidate {
+            node_id,
+            deal,
+            time: Utc::now(),
+        };
+        self.acceptance_policy.evaluate(&candidate, &swaps)
+    }
+
     fn get_open_connections(&self) -> Vec<NodeAddr> {
         self.registered_services
             .iter()
@@ -1015,17 +2014,15 @@ impl Runtime {
             (BusMsg::Ctl(CtlMsg::ConnectSuccess), ServiceId::Peer(_, addr))
             | (BusMsg::Ctl(CtlMsg::ConnectFailed), ServiceId::Peer(_, addr)) => {
                 Ok(dummy_drain_filter(&mut self.trade_state_machines, |tsm| {
-                    if let Some(tsm_addr) = tsm.awaiting_connect_from() {
-                        tsm_addr == *addr
-                    } else {
-                        false
-                    }
+                    tsm.awaiting_connect_from() == Some(*addr)
+                        || tsm.awaiting_reconnect_of() == Some(addr.id)
                 }))
             }
             (BusMsg::Ctl(CtlMsg::PeerdUnreachable(..)), ServiceId::Swap(swap_id))
             | (BusMsg::Ctl(CtlMsg::FundingInfo(..)), ServiceId::Swap(swap_id))
             | (BusMsg::Ctl(CtlMsg::FundingCanceled(..)), ServiceId::Swap(swap_id))
             | (BusMsg::Ctl(CtlMsg::FundingCompleted(..)), ServiceId::Swap(swap_id))
+            | (BusMsg::Ctl(CtlMsg::HotWalletFundingFailed(swap_id, _)), ServiceId::Wallet)
             | (BusMsg::Ctl(CtlMsg::Connect(swap_id)), _)
             | (BusMsg::Ctl(CtlMsg::SwapOutcome(..)), ServiceId::Swap(swap_id)) => {
                 Ok(dummy_drain_filter(&mut self.trade_state_machines, |tsm| {
@@ -1096,6 +2093,15 @@ impl Runtime {
                     )?;
                     Ok(())
                 }
+                BusMsg::P2p(PeerMsg::TakerCommit(TakerCommit { commit, .. }))
+                    if self.running_swaps_contain(&commit.swap_id()) =>
+                {
+                    debug!(
+                        "{} | Ignoring the taker commit resent by the counterparty, the swap is already running",
+                        commit.swap_id(),
+                    );
+                    Ok(())
+                }
                 BusMsg::P2p(PeerMsg::TakerCommit(TakerCommit { commit, deal })) => {
                     debug!(
                         "{} | Deal {} already taken or aborted, replying with deal not found to the counterparty",
@@ -1119,9 +2125,130 @@ impl Runtime {
     }
 
     pub fn listen(&mut self, bind_addr: InetSocketAddr) -> Result<NodeId, Error> {
+        let (peer_secret_key, _) = self.peer_keys_ready()?;
+        self.listen_as(bind_addr, peer_secret_key)
+    }
+
+    /// Launches the listening peerd of a deal: with the ephemeral node id of the deal on the port
+    /// advertised in the deal, or with the node identity on the configured bind address
+    pub fn listen_for_deal(&mut self, deal: &Deal) -> Result<NodeId, Error> {
+        match self.deal_secret_key(deal) {
+            Some(secret_key) => {
+                let bind_addr = self.deal_bind_addr(deal)?;
+                self.listen_as(bind_addr, secret_key)
+            }
+            None => {
+                let bind_addr = self.config.get_bind_addr()?;
+                self.listen(bind_addr)
+            }
+        }
+    }
+
+    /// Launches the listening peerd of a new deal made with an ephemeral node id on a free port
+    /// of the range, returns the node id of the deal and the bound port
+    pub fn listen_ephemeral(
+        &mut self,
+        deal_id: DealId,
+        ports: RangeInclusive<u16>,
+    ) -> Result<(NodeId, u16), Error> {
+        let (secret_key, _) = self.deal_peer_keys(deal_id)?;
+        let ip = ip_address(self.config.get_bind_addr()?)?;
+        let port = ports
+            .into_iter()
+            .find(|port| {
+                !self.listens.contains(&InetSocketAddr::socket(ip, *port))
+                    && TcpListener::bind(SocketAddr::new(ip, *port)).is_ok()
+            })
+            .ok_or_else(|| {
+                Error::Farcaster("No free port left for the ephemeral node ids".to_string())
+            })?;
+        let node_id = self.listen_as(InetSocketAddr::socket(ip, port), secret_key)?;
+        Ok((node_id, port))
+    }
+
+    /// Stops the listening peerd of a deal made with an ephemeral node id once the deal is revoked
+    /// or its swap ended, its port is free for new deals. The listener of the node identity is
+    /// shared by the other deals and keeps running.
+    pub fn stop_listening_for_deal(&mut self, deal: &Deal) -> Result<(), Error> {
+        if self.deal_secret_key(deal).is_none() {
+            return Ok(());
+        }
+        let bind_addr = self.deal_bind_addr(deal)?;
+        if !self.listens.remove(&bind_addr) {
+            return Ok(());
+        }
+        let port = bind_addr
+            .port()
+            .ok_or_else(|| Error::Farcaster("listen requires the port to listen on".to_string()))?;
+        let listen_args = [
+            "--listen".to_string(),
+            format!("{}", bind_addr.address()),
+            "--port".to_string(),
+            port.to_string(),
+        ];
+        self.supervisor
+            .stop("peerd", |args| args.starts_with(&listen_args));
+        info!(
+            "Connection daemon for deal {} {} on {}",
+            deal.id(),
+            "stopped listening".bright_blue_bold(),
+            bind_addr
+        );
+        Ok(())
+    }
+
+    /// Address the listening peerd of a deal binds: the configured bind ip with the port
+    /// advertised in the deal for an ephemeral node id, the configured bind address otherwise
+    pub fn deal_bind_addr(&self, deal: &Deal) -> Result<InetSocketAddr, Error> {
+        let bind_addr = self.config.get_bind_addr()?;
+        if self.deal_secret_key(deal).is_none() {
+            return Ok(bind_addr);
+        }
+        let port = deal.peer_address.port().ok_or_else(|| {
+            Error::Farcaster(format!(
+                "The deal address {} has no port",
+                deal.peer_address
+            ))
+        })?;
+        Ok(InetSocketAddr::socket(ip_address(bind_addr)?, port))
+    }
+
+    /// Keys of the ephemeral node id of a deal, derived from the node key and the deal id. The
+    /// node key is derived from the wallet seed, so restoring the seed restores the identities of
+    /// the deals too
+    pub fn deal_peer_keys(&self, deal_id: DealId) -> Result<(SecretKey, PublicKey), Error> {
+        let (node_secret_key, _) = self.peer_keys_ready()?;
+        let mut encoded_deal_id = vec![];
+        deal_id.strict_encode(&mut encoded_deal_id)?;
+        let mut engine = sha256::Hash::engine();
+        engine.input(b"farcaster:deal_peerd_secret_key");
+        engine.input(&node_secret_key.secret_bytes());
+        engine.input(&encoded_deal_id);
+        let secret_key = SecretKey::from_slice(&sha256::Hash::from_engine(engine)[..])
+            .expect("a hash is a valid secret key with overwhelming probability");
+        Ok((
+            secret_key,
+            PublicKey::from_secret_key(SECP256K1, &secret_key),
+        ))
+    }
+
+    /// Secret key of the ephemeral node id a deal was made with, None if the deal was made with
+    /// the node identity or by another node
+    pub fn deal_secret_key(&self, deal: &Deal) -> Option<SecretKey> {
+        match self.deal_peer_keys(deal.id()) {
+            Ok((secret_key, public_key)) if public_key == deal.node_id => Some(secret_key),
+            _ => None,
+        }
+    }
+
+    /// Launches a listening peerd with the given node identity
+    fn listen_as(
+        &mut self,
+        bind_addr: InetSocketAddr,
+        peer_secret_key: SecretKey,
+    ) -> Result<NodeId, Error> {
         self.services_ready()?;
-        let (peer_secret_key, peer_public_key) = self.peer_keys_ready()?;
-        let node_id = NodeId::from(peer_public_key);
+        let node_id = NodeId::from(PublicKey::from_secret_key(SECP256K1, &peer_secret_key));
         if self.listens.iter().any(|a| a == &bind_addr) {
             let msg = format!("Already listening on {}", &bind_addr);
             debug!("{}", &msg);
@@ -1138,20 +2265,27 @@ impl Runtime {
             .port()
             .ok_or_else(|| Error::Farcaster("listen requires the port to listen on".to_string()))?;
 
+        if self.config.get_onion_service_config().is_some() && self.onion_address.is_none() {
+            self.start_onion_service(&address.to_string(), port)?;
+        }
+
         debug!("Instantiating peerd...");
-        let child = launch(
-            "peerd",
-            [
-                "--listen",
-                &format!("{}", address),
-                "--port",
-                &port.to_string(),
-                "--peer-secret-key",
-                &format!("{}", peer_secret_key.display_secret()),
-                "--token",
-                &self.wallet_token.clone().to_string(),
-            ],
-        );
+        let mut args = vec![
+            "--listen".to_string(),
+            format!("{}", address),
+            "--port".to_string(),
+            port.to_string(),
+        ];
+        args.extend(network_args(&self.config));
+        args.extend(peer_limits_args(&self.config));
+        args.extend([
+            ARGS_FROM_STDIN.to_string(),
+            "--peer-secret-key".to_string(),
+            format!("{}", peer_secret_key.display_secret()),
+            "--token".to_string(),
+            self.wallet_token.clone().to_string(),
+        ]);
+        let child = launch("peerd", &args);
 
         // in case it can't connect wait for it to crash
         std::thread::sleep(Duration::from_secs_f32(0.1));
@@ -1164,6 +2298,8 @@ impl Runtime {
 
         self.listens.insert(bind_addr);
         debug!("New instance of peerd launched with PID {}", child.id());
+        self.supervisor
+            .supervise("peerd", args, None, RestartPolicy::Relaunch, child);
         info!(
             "Connection daemon {} for incoming peer connections on {}",
             "listens".bright_green_bold(),
@@ -1173,8 +2309,19 @@ impl Runtime {
     }
 
     pub fn connect_peer(&mut self, node_addr: &NodeAddr) -> Result<(bool, ServiceId), Error> {
+        self.connect_peer_as(&ReconnectPeer(*node_addr, None))
+    }
+
+    /// Connects to a peer with the given node identity, or the node identity if none is given
+    pub fn connect_peer_as(
+        &mut self,
+        ReconnectPeer(node_addr, secret_key): &ReconnectPeer,
+    ) -> Result<(bool, ServiceId), Error> {
         self.services_ready()?;
-        let (peer_secret_key, _) = self.peer_keys_ready()?;
+        let peer_secret_key = match secret_key {
+            Some(secret_key) => *secret_key,
+            None => self.peer_keys_ready()?.0,
+        };
         if let Some(spawning_peer) = self.spawning_services.iter().find(|service| {
             if let Some(registered_node_addr) = service.node_addr() {
                 registered_node_addr.id == node_addr.id
@@ -1204,18 +2351,23 @@ impl Runtime {
 
         debug!("{} to remote peer {}", "Connecting", node_addr);
 
+        if is_onion(&node_addr.addr) {
+            self.route_through_tor()?;
+        }
+
         // Start peerd
-        let child = launch(
-            "peerd",
-            [
-                "--connect",
-                &node_addr.to_string(),
-                "--peer-secret-key",
-                &format!("{}", peer_secret_key.display_secret()),
-                "--token",
-                &self.wallet_token.clone().to_string(),
-            ],
-        );
+        let mut args = vec!["--connect".to_string(), node_addr.to_string()];
+        args.extend(tor_proxy_args(self.tor_proxy));
+        args.extend(network_args(&self.config));
+        args.extend(peer_limits_args(&self.config));
+        args.extend([
+            ARGS_FROM_STDIN.to_string(),
+            "--peer-secret-key".to_string(),
+            format!("{}", peer_secret_key.display_secret()),
+            "--token".to_string(),
+            self.wallet_token.clone().to_string(),
+        ]);
+        let child = launch("peerd", &args);
 
         // status is Some if peerd returns because it crashed
         let (child, status) = child.and_then(|mut c| c.try_wait().map(|s| (c, s)))?;
@@ -1225,6 +2377,14 @@ impl Runtime {
         }
 
         debug!("New instance of peerd launched with PID {}", child.id());
+        // A connecting peerd is not relaunched, the swaps reconnect on restore
+        self.supervisor.supervise(
+            "peerd",
+            args,
+            Some(ServiceId::dummy_peer_service_id(*node_addr)),
+            RestartPolicy::Notify,
+            child,
+        );
 
         self.spawning_services
             .insert(ServiceId::dummy_peer_service_id(*node_addr));
@@ -1233,6 +2393,101 @@ impl Runtime {
         Ok((false, ServiceId::dummy_peer_service_id(*node_addr)))
     }
 
+    /// Onion address of the listening peerd to advertise in the deals, if the onion service is
+    /// enabled
+    pub fn onion_address(&self) -> Option<InetSocketAddr> {
+        self.onion_address
+    }
+
+    /// Creates the onion service forwarding to the listening peerd through the Tor control port,
+    /// the control connection is kept open for tor to keep the service
+    fn start_onion_service(&mut self, address: &str, port: u16) -> Result<(), Error> {
+        if !cfg!(feature = "tor") {
+            return Err(Error::Farcaster(
+                "farcasterd must be built with the tor feature to advertise an onion address"
+                    .to_string(),
+            ));
+        }
+        let tor_config = match self.config.get_onion_service_config() {
+            Some(tor_config) => tor_config,
+            None => return Ok(()),
+        };
+        // tor forwards to the loopback interface if the listener binds all the interfaces
+        let ip = match IpAddr::from_str(address) {
+            Ok(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            Ok(ip) => ip,
+            Err(_) => {
+                return Err(Error::Farcaster(format!(
+                    "Cannot forward an onion service to {}",
+                    address
+                )))
+            }
+        };
+        let persistent = tor_config.persistent.unwrap_or(false);
+        let mut control = TorControl::connect(
+            tor_config.get_control_addr()?,
+            tor_config.control_password.as_deref(),
+        )?;
+        let stored_key = if persistent {
+            tor::read_onion_service_key(&self.data_dir)?
+        } else {
+            None
+        };
+        let service = control.add_onion(stored_key.as_deref(), port, SocketAddr::new(ip, port))?;
+        if let (true, Some(private_key)) = (persistent, &service.private_key) {
+            tor::write_onion_service_key(&self.data_dir, private_key)?;
+        }
+        let onion_address =
+            InetSocketAddr::from_str(&format!("{}.onion:{}", service.service_id, port))?;
+        info!(
+            "{} for the listening peerd at {}",
+            "Onion service created".bright_green_bold(),
+            onion_address.bright_yellow_bold()
+        );
+        self.tor_control = Some(control);
+        self.onion_address = Some(onion_address);
+        Ok(())
+    }
+
+    /// Routes the peer connections and syncers launched from now on through the Tor SOCKS
+    /// proxy, used once a deal pointing at an onion address is taken. Nothing changes if the
+    /// proxy was already given to farcasterd as it is forwarded to every service
+    fn route_through_tor(&mut self) -> Result<(), Error> {
+        if self.forwarded_tor_proxy.is_some() || self.tor_proxy.is_some() {
+            return Ok(());
+        }
+        if !cfg!(feature = "tor") {
+            return Err(Error::Farcaster(
+                "farcasterd must be built with the tor feature to connect to an onion address"
+                    .to_string(),
+            ));
+        }
+        let proxy = self.config.get_tor_socks_proxy()?;
+        info!(
+            "{} through the Tor proxy {}",
+            "Routing peer and syncer traffic".bright_blue_bold(),
+            proxy.bright_blue_bold()
+        );
+        self.tor_proxy = Some(proxy);
+        // the running syncers were launched without the proxy, the swaps using them are restored
+        // once they exit
+        let running_syncers: HashSet<ServiceId> = self
+            .registered_services
+            .iter()
+            .chain(self.spawning_services.iter())
+            .filter(|service| matches!(service, ServiceId::Syncer(..)))
+            .cloned()
+            .collect();
+        for syncer in running_syncers {
+            if let ServiceId::Syncer(blockchain, network) = syncer {
+                info!("Relaunching {} through the Tor proxy", syncer);
+                let args = syncer_args(&self.config, blockchain, network, self.tor_proxy)?;
+                self.supervisor.relaunch_with(&syncer, args);
+            }
+        }
+        Ok(())
+    }
+
     /// Notify(forward to) the subscribed clients still online with the given request
     fn notify_subscribed_clients(
         &mut self,
@@ -1241,19 +2496,50 @@ impl Runtime {
         request: InfoMsg,
     ) {
         // if subs exists for the source (swap_id), forward the request to every subs
-        if let Some(subs) = self.progress_subscriptions.get_mut(source) {
+        if let Some(subs) = self.progress_subscriptions.remove(source) {
             // if the sub is no longer reachable, i.e. the process terminated without calling
             // unsub, remove it from sub list
-            subs.retain(|sub| {
-                endpoints
-                    .send_to(
-                        ServiceBus::Info,
-                        ServiceId::Farcasterd,
-                        sub.clone(),
-                        BusMsg::Info(request.clone()),
-                    )
+            let subs: HashSet<ServiceId> = subs
+                .into_iter()
+                .filter(|sub| {
+                    self.send_client_info(endpoints, sub.clone(), request.clone())
+                        .is_ok()
+                })
+                .collect();
+            self.progress_subscriptions.insert(source.clone(), subs);
+        }
+    }
+
+    /// Push a node event to every client subscribed to the node events
+    pub fn notify_node_event(&mut self, endpoints: &mut Endpoints, event: NodeEvent) {
+        debug!("Node event: {}", event);
+        // as for progress, a sub no longer reachable is removed from the sub list
+        let subs: HashSet<ServiceId> = std::mem::take(&mut self.event_subscriptions)
+            .into_iter()
+            .filter(|sub| {
+                self.send_client_info(endpoints, sub.clone(), InfoMsg::NodeEvent(event.clone()))
                     .is_ok()
-            });
+            })
+            .collect();
+        self.event_subscriptions = subs;
+    }
+
+    /// Record the result of a syncer health check, pushed as node event if it differs from the
+    /// previous result of the syncer
+    pub fn record_syncer_health(
+        &mut self,
+        endpoints: &mut Endpoints,
+        syncer: ServiceId,
+        health: Health,
+    ) {
+        if let ServiceId::Syncer(blockchain, network) = syncer {
+            if self.syncer_health.get(&syncer) != Some(&health) {
+                self.syncer_health.insert(syncer, health.clone());
+                self.notify_node_event(
+                    endpoints,
+                    NodeEvent::SyncerHealth(blockchain, network, health),
+                );
+            }
         }
     }
 }
@@ -1264,20 +2550,23 @@ pub fn syncer_up(
     blockchain: Blockchain,
     network: Network,
     config: &Config,
+    supervisor: &Supervisor,
+    tor_proxy: Option<SocketAddr>,
 ) -> Result<Option<ServiceId>, Error> {
     let syncer_service = ServiceId::Syncer(blockchain, network);
     if !registered_services.contains(&syncer_service)
         && !spawning_services.contains(&syncer_service)
     {
-        let mut args = vec![
-            "--blockchain".to_string(),
-            blockchain.to_string(),
-            "--network".to_string(),
-            network.to_string(),
-        ];
-        args.append(&mut syncer_servers_args(config, blockchain, network)?);
+        let args = syncer_args(config, blockchain, network, tor_proxy)?;
         debug!("launching syncer with: {:?}", args);
-        launch("syncerd", args)?;
+        let child = launch("syncerd", &args)?;
+        supervisor.supervise(
+            "syncerd",
+            args,
+            Some(syncer_service.clone()),
+            RestartPolicy::Relaunch,
+            child,
+        );
         spawning_services.insert(syncer_service.clone());
     }
     if registered_services.contains(&syncer_service) {
@@ -1287,6 +2576,72 @@ pub fn syncer_up(
     }
 }
 
+/// Return the launch arguments of a syncer
+fn syncer_args(
+    config: &Config,
+    blockchain: Blockchain,
+    network: Network,
+    tor_proxy: Option<SocketAddr>,
+) -> Result<Vec<String>, Error> {
+    let mut args = vec![
+        "--blockchain".to_string(),
+        blockchain.to_string(),
+        "--network".to_string(),
+        network.to_string(),
+    ];
+    args.append(&mut syncer_servers_args(config, blockchain, network)?);
+    args.extend(tor_proxy_args(tor_proxy));
+    Ok(args)
+}
+
+/// Returns the socket address with another port, fails for onion addresses
+pub fn with_port(addr: InetSocketAddr, port: u16) -> Result<InetSocketAddr, Error> {
+    Ok(InetSocketAddr::socket(ip_address(addr)?, port))
+}
+
+/// Returns the IP address of a socket address, fails for onion addresses
+fn ip_address(addr: InetSocketAddr) -> Result<IpAddr, Error> {
+    IpAddr::from_str(&addr.address().to_string())
+        .map_err(|_| Error::Farcaster(format!("{} is not an IP socket address", addr)))
+}
+
+/// Returns if the address is an onion address, reachable only through Tor
+fn is_onion(addr: &InetSocketAddr) -> bool {
+    addr.to_string()
+        .rsplit_once(':')
+        .map_or(false, |(host, _)| host.ends_with(".onion"))
+}
+
+/// Return the arguments setting the Tor proxy of a launched service, if any
+fn tor_proxy_args(tor_proxy: Option<SocketAddr>) -> Vec<String> {
+    tor_proxy.map_or(vec![], |proxy| vec!["-T".to_string(), proxy.to_string()])
+}
+
+/// Return the arguments setting the networks a launched peerd announces in the handshake
+fn network_args(config: &Config) -> Vec<String> {
+    config
+        .get_swap_networks()
+        .iter()
+        .flat_map(|network| vec!["--network".to_string(), network.to_string()])
+        .collect()
+}
+
+/// Return the arguments setting the peer limits of a launched peerd, the limits are validated
+/// when the configuration is loaded
+fn peer_limits_args(config: &Config) -> Vec<String> {
+    let limits = config.get_peer_limits().unwrap_or_default();
+    vec![
+        "--max-messages-per-sec".to_string(),
+        limits.max_messages_per_sec.to_string(),
+        "--max-pong-size".to_string(),
+        limits.max_pong_size.to_string(),
+        "--max-connections-per-ip".to_string(),
+        limits.max_connections_per_ip.to_string(),
+        "--idle-timeout".to_string(),
+        limits.idle_timeout.to_string(),
+    ]
+}
+
 /// Launch a swapd instance with all the necessary paramters for: swap id, deal to use, trade role
 /// to execute, temporal safety arguments.
 pub fn launch_swapd(
@@ -1294,26 +2649,34 @@ pub fn launch_swapd(
     deal: Deal,
     swap_id: SwapId,
     swap_config: ParsedSwapConfig,
+    supervisor: &Supervisor,
 ) -> Result<(), Error> {
     debug!("Instantiating swapd...");
-    let child = launch(
-        "swapd",
-        [
-            "--arb-finality".to_string(),
-            swap_config.arbitrating.finality.to_string(),
-            "--arb-safety".to_string(),
-            swap_config.arbitrating.safety.to_string(),
-            "--acc-finality".to_string(),
-            swap_config.accordant.finality.to_string(),
-            "--id".to_string(),
-            swap_id.to_string(),
-            "--deal".to_string(),
-            deal.to_string(),
-            "--trade-role".to_string(),
-            local_trade_role.to_string(),
-        ],
-    )?;
+    let args = vec![
+        "--arb-finality".to_string(),
+        swap_config.arbitrating.finality.to_string(),
+        "--arb-safety".to_string(),
+        swap_config.arbitrating.safety.to_string(),
+        "--acc-finality".to_string(),
+        swap_config.accordant.finality.to_string(),
+        "--id".to_string(),
+        swap_id.to_string(),
+        "--trade-role".to_string(),
+        local_trade_role.to_string(),
+        ARGS_FROM_STDIN.to_string(),
+        "--deal".to_string(),
+        deal.to_string(),
+    ];
+    let child = launch("swapd", &args)?;
     debug!("New instance of swapd launched with PID {}", child.id());
+    // A crashed swapd is restored from its checkpoint by farcasterd, not relaunched
+    supervisor.supervise(
+        "swapd",
+        args,
+        Some(ServiceId::Swap(swap_id)),
+        RestartPolicy::Notify,
+        child,
+    );
     debug!("Awaiting for swapd to connect...");
     Ok(())
 }
@@ -1327,10 +2690,23 @@ fn syncer_servers_args(
 ) -> Result<Vec<String>, Error> {
     match config.get_syncer_servers(net) {
         Some(servers) => match blockchain {
-            Blockchain::Bitcoin => Ok(vec![
-                "--electrum-server".to_string(),
-                servers.electrum_server,
-            ]),
+            Blockchain::Bitcoin => {
+                let fee_policy = config.get_fee_policy()?;
+                Ok(vec![
+                    "--electrum-server".to_string(),
+                    servers.electrum_server,
+                    "--lock-fee-target".to_string(),
+                    fee_policy.lock_target.to_string(),
+                    "--sweep-fee-target".to_string(),
+                    fee_policy.sweep_target.to_string(),
+                    "--min-fee-rate".to_string(),
+                    fee_policy.min_fee_rate.to_string(),
+                    "--max-fee-rate".to_string(),
+                    fee_policy.max_fee_rate.to_string(),
+                    "--fallback-fee-rate".to_string(),
+                    fee_policy.fallback_fee_rate.to_string(),
+                ])
+            }
             Blockchain::Monero => {
                 let mut args: Vec<String> = vec![
                     "--monero-daemon".to_string(),
@@ -1358,7 +2734,26 @@ fn syncer_servers_args(
 pub fn launch(
     name: &str,
     args: impl IntoIterator<Item = impl AsRef<OsStr>>,
-) -> io::Result<process::Child> {
+) -> io::Result<ServiceHandle> {
+    let mut args: Vec<String> = args
+        .into_iter()
+        .map(|arg| arg.as_ref().to_string_lossy().to_string())
+        .collect();
+    // arguments carrying secrets, written on the service stdin
+    let secret_args = match args.iter().position(|arg| arg == ARGS_FROM_STDIN) {
+        Some(pos) => args.split_off(pos + 1),
+        None => vec![],
+    };
+
+    // services of an embedded node run as threads of the current process, no command line is
+    // exposed
+    #[cfg(feature = "embedded")]
+    if let Some(shared) = crate::embedded::shared_opts() {
+        args.retain(|arg| arg != ARGS_FROM_STDIN);
+        args.extend(secret_args);
+        return crate::embedded::spawn_service(name, &shared, args).map(ServiceHandle::Thread);
+    }
+
     let app = Opts::command();
     let mut bin_path = std::env::current_exe().map_err(|err| {
         error!("Unable to detect binary directory: {}", err);
@@ -1411,10 +2806,22 @@ pub fn launch(
 
     // Given specialized args in launch
     cmd.args(args);
+    // the key file passphrase is only handed over to walletd, on stdin
+    cmd.env_remove(FARCASTER_KEY_PASSPHRASE);
+    if !secret_args.is_empty() {
+        cmd.stdin(process::Stdio::piped());
+    }
 
     debug!("Executing `{:?}`", cmd);
-    cmd.spawn().map_err(|err| {
+    let mut child = cmd.spawn().map_err(|err| {
         error!("Error launching {}: {}", name, err);
         err
-    })
+    })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // the service reads its arguments until stdin is closed, when dropped here
+        for arg in secret_args {
+            writeln!(stdin, "{}", arg)?;
+        }
+    }
+    Ok(ServiceHandle::Process(child))
 }
@@ -5,8 +5,13 @@
 // https://opensource.org/licenses/MIT.
 
 use std::collections::HashSet;
+use std::str::FromStr;
 
-use farcaster_core::{blockchain::Blockchain, swap::SwapId};
+use amplify::ToYamlString;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Utc};
+use farcaster_core::blockchain::{Blockchain, Network};
+use farcaster_core::role::{SwapRole, TradeRole};
+use farcaster_core::swap::{btcxmr::Deal, SwapId};
 use strict_encoding::{NetworkDecode, NetworkEncode};
 
 use crate::bus::Outcome;
@@ -31,6 +36,7 @@ pub struct Stats {
     pub funded_btc: u64,
     pub funding_canceled_xmr: u64,
     pub funding_canceled_btc: u64,
+    pub peer_limit_violations: u64,
 }
 
 impl Stats {
@@ -103,6 +109,10 @@ impl Stats {
         }
     }
 
+    pub fn incr_peer_limit_violations(&mut self, count: u32) {
+        self.peer_limit_violations += count as u64;
+    }
+
     pub fn success_rate(&self) -> f64 {
         let Stats {
             success,
@@ -116,6 +126,7 @@ impl Stats {
             funded_xmr,
             funding_canceled_xmr,
             funding_canceled_btc,
+            peer_limit_violations: _,
         } = self;
         let total = success + refund + punish + abort;
         let rate = *success as f64 / (total as f64);
@@ -141,3 +152,186 @@ impl Stats {
         rate
     }
 }
+
+/// Event recorded in the persistent statistics kept by databased
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{kind} for swap {swap_id}")]
+pub struct StatsEvent {
+    pub swap_id: SwapId,
+    pub network: Network,
+    pub swap_role: SwapRole,
+    /// Unix timestamp of the event, in seconds
+    pub timestamp: i64,
+    pub kind: StatsEventKind,
+}
+
+impl StatsEvent {
+    pub fn new(swap_id: SwapId, deal: &Deal, trade_role: TradeRole, kind: StatsEventKind) -> Self {
+        StatsEvent {
+            swap_id,
+            network: deal.parameters.network,
+            swap_role: deal.swap_role(&trade_role),
+            timestamp: Utc::now().timestamp(),
+            kind,
+        }
+    }
+
+    pub fn day(&self) -> StatsDay {
+        StatsDay::from_timestamp(self.timestamp)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+pub enum StatsEventKind {
+    /// A new swap was launched
+    #[display("initiated")]
+    Initiated,
+    /// The Bitcoin funding of the swap completed, the amount is the fee spent on top of the
+    /// arbitrating amount
+    #[display("funding fee of {0}")]
+    FundingFee(bitcoin::Amount),
+    #[display("{0} funding canceled")]
+    FundingCanceled(Blockchain),
+    /// The swap ended, the amounts are the traded volume
+    #[display("ended with {outcome}")]
+    Ended {
+        outcome: Outcome,
+        volume_btc: bitcoin::Amount,
+        volume_xmr: monero::Amount,
+    },
+}
+
+impl StatsEventKind {
+    pub fn ended(deal: &Deal, outcome: Outcome) -> Self {
+        StatsEventKind::Ended {
+            outcome,
+            volume_btc: deal.parameters.arbitrating_amount,
+            volume_xmr: deal.parameters.accordant_amount,
+        }
+    }
+}
+
+/// A calendar day (UTC) used to bucket statistics, stored as the number of days since the common
+/// era
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, From, NetworkEncode, NetworkDecode,
+)]
+pub struct StatsDay(u32);
+
+impl StatsDay {
+    pub fn today() -> Self {
+        Self::from_date(Utc::now().date_naive())
+    }
+
+    pub fn from_timestamp(timestamp: i64) -> Self {
+        NaiveDateTime::from_timestamp_opt(timestamp, 0)
+            .map(|datetime| Self::from_date(datetime.date()))
+            .unwrap_or_else(Self::today)
+    }
+
+    pub fn from_date(date: NaiveDate) -> Self {
+        StatsDay(date.num_days_from_ce().max(0) as u32)
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        NaiveDate::from_num_days_from_ce_opt(self.0 as i32).unwrap_or(NaiveDate::MIN)
+    }
+
+    /// Label of the period containing this day for the given grouping
+    pub fn period(&self, group_by: StatsGrouping) -> String {
+        match group_by {
+            StatsGrouping::Day => self.to_string(),
+            StatsGrouping::Month => self.date().format("%Y-%m").to_string(),
+            StatsGrouping::Total => s!("total"),
+        }
+    }
+}
+
+impl std::fmt::Display for StatsDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.date().format("%Y-%m-%d"))
+    }
+}
+
+impl FromStr for StatsDay {
+    type Err = chrono::ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").map(Self::from_date)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+pub enum StatsGrouping {
+    #[display("day")]
+    Day,
+    #[display("month")]
+    Month,
+    #[display("total")]
+    Total,
+}
+
+impl FromStr for StatsGrouping {
+    type Err = StatsGroupingParseError;
+    fn from_str(input: &str) -> Result<StatsGrouping, Self::Err> {
+        match input {
+            "day" | "Day" => Ok(StatsGrouping::Day),
+            "month" | "Month" => Ok(StatsGrouping::Month),
+            "total" | "Total" => Ok(StatsGrouping::Total),
+            _ => Err(StatsGroupingParseError::Invalid),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum StatsGroupingParseError {
+    /// The provided value can't be parsed as a stats grouping, expected day, month or total
+    Invalid,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[display("{group_by}")]
+pub struct StatsQuery {
+    /// First day included in the result, all recorded days if absent
+    pub since: Option<StatsDay>,
+    pub group_by: StatsGrouping,
+}
+
+/// Aggregated statistics of a period for a network and a swap role
+#[cfg_attr(feature = "serde", serde_as)]
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(StatsBucket::to_yaml_string)]
+pub struct StatsBucket {
+    pub period: String,
+    pub network: Network,
+    pub swap_role: SwapRole,
+    pub initiated: u64,
+    pub success: u64,
+    pub refund: u64,
+    pub punish: u64,
+    pub abort: u64,
+    pub funding_canceled_btc: u64,
+    pub funding_canceled_xmr: u64,
+    /// Volume of the successful swaps
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub volume_btc: bitcoin::Amount,
+    #[serde(with = "monero::util::amount::serde::as_xmr")]
+    pub volume_xmr: monero::Amount,
+    /// Fees spent on top of the arbitrating amount when funding swaps
+    #[serde(with = "bitcoin::util::amount::serde::as_btc")]
+    pub fees_btc: bitcoin::Amount,
+    /// Mean time-to-completion in seconds per outcome, absent when no swap of this outcome has
+    /// a known start
+    pub mean_success_secs: Option<u64>,
+    pub mean_refund_secs: Option<u64>,
+    pub mean_punish_secs: Option<u64>,
+    pub mean_abort_secs: Option<u64>,
+}
+
+#[cfg(feature = "serde")]
+impl ToYamlString for StatsBucket {}
@@ -0,0 +1,478 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::HashMap;
+use std::io;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use farcaster_core::swap::SwapId;
+use internet2::addr::NodeId;
+use internet2::session::LocalSession;
+use internet2::zeromq::ZmqSocketType;
+use internet2::{SendRecvMessage, TypedEnum};
+use strict_encoding::{NetworkDecode, NetworkEncode};
+
+use crate::bus::ctl::{CtlMsg, ServiceExit};
+use crate::bus::BusMsg;
+use crate::farcasterd::runtime::launch;
+use crate::{Error, ServiceId};
+
+/// Interval between two checks of the supervised processes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Delay before relaunching a crashed process, doubled on every consecutive crash
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Upper bound of the relaunch delay
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// A process running for longer than this before crashing gets its backoff reset
+const STABLE_UPTIME: Duration = Duration::from_secs(300);
+
+/// Maximum number of automatic checkpoint restores of the same swap
+pub const MAX_SWAP_RESTORES: u32 = 3;
+
+/// Defines how the supervisor reacts to a crashed process
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RestartPolicy {
+    /// Relaunch the process with the same arguments after the backoff delay
+    Relaunch,
+    /// Only notify farcasterd, which is responsible for the recovery, e.g. swapd is restored from
+    /// its latest checkpoint
+    Notify,
+}
+
+/// Exit status of a launched service
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display("{description}")]
+pub struct ServiceStatus {
+    pub success: bool,
+    pub description: String,
+}
+
+/// A launched service, running as a child process or, when the node is embedded, as a thread
+pub enum ServiceHandle {
+    Process(process::Child),
+    #[cfg(feature = "embedded")]
+    Thread(crate::embedded::ServiceThread),
+}
+
+impl ServiceHandle {
+    /// Returns the PID of the process or the identifier of the thread
+    pub fn id(&self) -> u32 {
+        match self {
+            ServiceHandle::Process(child) => child.id(),
+            #[cfg(feature = "embedded")]
+            ServiceHandle::Thread(thread) => thread.id(),
+        }
+    }
+
+    /// Returns the exit status if the service exited, without blocking
+    pub fn try_wait(&mut self) -> io::Result<Option<ServiceStatus>> {
+        match self {
+            ServiceHandle::Process(child) => Ok(child.try_wait()?.map(|status| ServiceStatus {
+                success: status.success(),
+                description: status.to_string(),
+            })),
+            #[cfg(feature = "embedded")]
+            ServiceHandle::Thread(thread) => Ok(thread.try_wait()),
+        }
+    }
+
+    /// Kill the process, a thread is only asked to stop
+    pub fn kill(&mut self) -> io::Result<()> {
+        match self {
+            ServiceHandle::Process(child) => child.kill(),
+            #[cfg(feature = "embedded")]
+            ServiceHandle::Thread(thread) => {
+                thread.stop();
+                Ok(())
+            }
+        }
+    }
+}
+
+struct SupervisedProcess {
+    name: String,
+    args: Vec<String>,
+    service: Option<ServiceId>,
+    policy: RestartPolicy,
+    child: Option<ServiceHandle>,
+    started: Instant,
+    backoff: Duration,
+    relaunch_at: Option<Instant>,
+    stopped: bool,
+    restarting: bool,
+}
+
+impl SupervisedProcess {
+    fn label(&self) -> String {
+        self.service
+            .as_ref()
+            .map_or_else(|| self.name.clone(), |service| service.to_string())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, NetworkEncode, NetworkDecode)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display("{service}: {crashes} crashes, last exit {last_exit_status}")]
+pub struct CrashReport {
+    pub service: String,
+    pub crashes: u64,
+    pub restarts: u64,
+    pub last_exit_status: String,
+}
+
+#[derive(Default)]
+struct SupervisorState {
+    processes: Vec<SupervisedProcess>,
+    crashes: HashMap<String, CrashReport>,
+    swap_restores: HashMap<SwapId, u32>,
+    peer_reconnects: Vec<(Instant, NodeId)>,
+    drain_deadline: Option<Instant>,
+    shutdown: bool,
+}
+
+/// Tracks the processes launched by farcasterd and detects their exit from a dedicated thread.
+/// Crashed processes are relaunched with an exponential backoff according to their
+/// [`RestartPolicy`] and every exit is reported to farcasterd over the bridge bus.
+#[derive(Clone, Default)]
+pub struct Supervisor {
+    state: Arc<Mutex<SupervisorState>>,
+    // Set from a signal handler to request a configuration reload, kept out of the locked state
+    // since a signal handler cannot take a lock
+    reload_requested: Arc<AtomicBool>,
+}
+
+impl Supervisor {
+    /// Create a supervisor forwarding a configuration reload to farcasterd whenever the flag is
+    /// set, e.g. by the SIGHUP handler
+    pub fn new(reload_requested: Arc<AtomicBool>) -> Self {
+        Supervisor {
+            reload_requested,
+            ..Default::default()
+        }
+    }
+
+    /// Start tracking a launched process
+    pub fn supervise(
+        &self,
+        name: &str,
+        args: Vec<String>,
+        service: Option<ServiceId>,
+        policy: RestartPolicy,
+        child: ServiceHandle,
+    ) {
+        debug!("Supervising {} with PID {}", name, child.id());
+        self.lock().processes.push(SupervisedProcess {
+            name: name.to_string(),
+            args,
+            service,
+            policy,
+            child: Some(child),
+            started: Instant::now(),
+            backoff: INITIAL_BACKOFF,
+            relaunch_at: None,
+            stopped: false,
+            restarting: false,
+        });
+    }
+
+    /// Spawn the supervising thread, exits and configuration reload requests are sent to
+    /// farcasterd through the bridge socket
+    pub fn run(&self, tx: zmq::Socket) -> Result<(), Error> {
+        let supervisor = self.clone();
+        thread::Builder::new()
+            .name("supervisor".to_string())
+            .spawn(move || {
+                let mut session = LocalSession::with_zmq_socket(ZmqSocketType::Push, tx);
+                let identity: Vec<u8> = ServiceId::Farcasterd.into();
+                loop {
+                    let mut requests: Vec<BusMsg> = supervisor
+                        .poll()
+                        .into_iter()
+                        .map(|exit| BusMsg::Ctl(CtlMsg::ServiceExited(exit)))
+                        .collect();
+                    requests.extend(
+                        supervisor
+                            .due_peer_reconnects()
+                            .into_iter()
+                            .map(|node_id| BusMsg::Ctl(CtlMsg::ReconnectPeer(node_id))),
+                    );
+                    if supervisor.is_drain_timed_out() {
+                        requests.push(BusMsg::Ctl(CtlMsg::DrainTimeout));
+                    }
+                    if supervisor.reload_requested.swap(false, Ordering::SeqCst) {
+                        requests.push(BusMsg::Ctl(CtlMsg::ReloadConfig));
+                    }
+                    for request in requests {
+                        trace!("sending request over farcasterd bridge: {}", request);
+                        if let Err(err) = session.send_routed_message(
+                            &identity,
+                            &identity,
+                            &identity,
+                            &request.serialize(),
+                        ) {
+                            error!("Failed to send {} to farcasterd: {}", request, err);
+                        }
+                    }
+                    // nothing left to supervise once the node shut down
+                    if supervisor.lock().shutdown && supervisor.is_idle() {
+                        break;
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+            })?;
+        Ok(())
+    }
+
+    /// Return the crash count and last exit status of every service that crashed at least once
+    pub fn crash_reports(&self) -> Vec<CrashReport> {
+        let mut reports: Vec<CrashReport> = self.lock().crashes.values().cloned().collect();
+        reports.sort_by(|a, b| a.service.cmp(&b.service));
+        reports
+    }
+
+    /// Register an automatic restore of a crashed swap, returns false once the swap has been
+    /// restored too many times
+    pub fn register_swap_restore(&self, swap_id: SwapId) -> bool {
+        let mut state = self.lock();
+        let restores = state.swap_restores.entry(swap_id).or_insert(0);
+        *restores += 1;
+        *restores <= MAX_SWAP_RESTORES
+    }
+
+    /// Schedule a reconnection attempt to a peer, farcasterd receives a ReconnectPeer request
+    /// once the delay elapsed
+    pub fn schedule_peer_reconnect(&self, node_id: NodeId, delay: Duration) {
+        self.lock()
+            .peer_reconnects
+            .push((Instant::now() + delay, node_id));
+    }
+
+    /// Schedule the end of a drain, farcasterd receives a DrainTimeout request once the delay
+    /// elapsed
+    pub fn schedule_drain_timeout(&self, delay: Duration) {
+        self.lock().drain_deadline = Some(Instant::now() + delay);
+    }
+
+    /// Stop relaunching processes on node shutdown and kill the supervised processes not
+    /// reachable over the bus, i.e. the listening peerd
+    pub fn shutdown(&self) {
+        let mut state = self.lock();
+        state.shutdown = true;
+        for process in state.processes.iter_mut() {
+            if process.service.is_none() {
+                if let Some(child) = process.child.as_mut() {
+                    if let Err(err) = child.kill() {
+                        warn!("Unable to stop {}: {}", process.name, err);
+                    }
+                }
+            }
+            process.relaunch_at = None;
+        }
+        state.peer_reconnects.clear();
+        state.processes.retain(|process| process.child.is_some());
+    }
+
+    /// Stop the supervised processes not reachable over the bus, i.e. a listening peerd, whose
+    /// launch arguments match. They are not relaunched.
+    pub fn stop(&self, name: &str, matches: impl Fn(&[String]) -> bool) {
+        let mut state = self.lock();
+        for process in state.processes.iter_mut() {
+            if process.service.is_some()
+                || process.name != name
+                || !matches(process.args.as_slice())
+            {
+                continue;
+            }
+            process.stopped = true;
+            process.relaunch_at = None;
+            if let Some(child) = process.child.as_mut() {
+                if let Err(err) = child.kill() {
+                    warn!("Unable to stop {}: {}", process.name, err);
+                }
+            }
+        }
+        state
+            .processes
+            .retain(|process| process.child.is_some() || process.relaunch_at.is_some());
+    }
+
+    /// Relaunch a running service with new launch arguments, it is stopped and relaunched right
+    /// away without being considered crashed. A pending relaunch uses the new arguments.
+    pub fn relaunch_with(&self, service: &ServiceId, args: Vec<String>) {
+        let mut state = self.lock();
+        if let Some(process) = state
+            .processes
+            .iter_mut()
+            .find(|process| process.service.as_ref() == Some(service))
+        {
+            process.args = args;
+            if let Some(child) = process.child.as_mut() {
+                process.restarting = true;
+                if let Err(err) = child.kill() {
+                    warn!("Unable to stop {}: {}", process.label(), err);
+                }
+            }
+        }
+    }
+
+    /// Returns true if a supervised service matching is running or about to be relaunched
+    pub fn is_supervising(&self, matches: impl Fn(&ServiceId) -> bool) -> bool {
+        self.lock()
+            .processes
+            .iter()
+            .any(|process| process.service.as_ref().map_or(false, &matches))
+    }
+
+    /// Returns true once no supervised process is running anymore
+    pub fn is_idle(&self) -> bool {
+        self.lock().processes.is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<SupervisorState> {
+        self.state.lock().expect("supervisor state lock poisoned")
+    }
+
+    // True once when the drain deadline elapsed
+    fn is_drain_timed_out(&self) -> bool {
+        let mut state = self.lock();
+        match state.drain_deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                state.drain_deadline = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Remove and return the peers whose reconnection delay elapsed
+    fn due_peer_reconnects(&self) -> Vec<NodeId> {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let (due, pending): (Vec<_>, Vec<_>) = state
+            .peer_reconnects
+            .drain(..)
+            .partition(|(reconnect_at, _)| now >= *reconnect_at);
+        state.peer_reconnects = pending;
+        due.into_iter().map(|(_, node_id)| node_id).collect()
+    }
+
+    // Check all supervised processes, relaunch the pending ones and return the detected exits
+    fn poll(&self) -> Vec<ServiceExit> {
+        let mut state = self.lock();
+        let SupervisorState {
+            processes,
+            crashes,
+            shutdown,
+            ..
+        } = &mut *state;
+        let now = Instant::now();
+        let mut exits = vec![];
+
+        for process in processes.iter_mut() {
+            if let Some(relaunch_at) = process.relaunch_at {
+                if now >= relaunch_at {
+                    relaunch(process, crashes, now);
+                }
+                continue;
+            }
+
+            let status = match process.child.as_mut().map(|child| child.try_wait()) {
+                Some(Ok(Some(status))) => status,
+                Some(Err(err)) => {
+                    warn!("Unable to check the status of {}: {}", process.name, err);
+                    continue;
+                }
+                _ => continue,
+            };
+            let pid = process.child.take().map_or(0, |child| child.id());
+            // processes stopped during shutdown or on purpose are not considered crashed
+            let restarting = process.restarting && !*shutdown;
+            process.restarting = false;
+            let crashed = !status.success && !*shutdown && !process.stopped && !restarting;
+            let relaunching = restarting || (crashed && process.policy == RestartPolicy::Relaunch);
+
+            if crashed {
+                let label = process.label();
+                let report = crashes.entry(label.clone()).or_insert_with(|| CrashReport {
+                    service: label,
+                    crashes: 0,
+                    restarts: 0,
+                    last_exit_status: s!(""),
+                });
+                report.crashes += 1;
+                report.last_exit_status = status.to_string();
+            }
+
+            if restarting {
+                info!(
+                    "{} stopped, relaunching with new arguments",
+                    process.label()
+                );
+                process.relaunch_at = Some(now);
+            } else if relaunching {
+                if now.duration_since(process.started) > STABLE_UPTIME {
+                    process.backoff = INITIAL_BACKOFF;
+                }
+                warn!(
+                    "{} crashed with {}, relaunching in {}s",
+                    process.label(),
+                    status,
+                    process.backoff.as_secs()
+                );
+                process.relaunch_at = Some(now + process.backoff);
+                process.backoff = (process.backoff * 2).min(MAX_BACKOFF);
+            }
+
+            exits.push(ServiceExit {
+                name: process.name.clone(),
+                service: process.service.clone(),
+                pid,
+                status: status.to_string(),
+                crashed,
+                relaunching,
+            });
+        }
+
+        processes.retain(|process| process.child.is_some() || process.relaunch_at.is_some());
+        exits
+    }
+}
+
+fn relaunch(
+    process: &mut SupervisedProcess,
+    crashes: &mut HashMap<String, CrashReport>,
+    now: Instant,
+) {
+    match launch(&process.name, &process.args) {
+        Ok(child) => {
+            info!("Relaunched {} with PID {}", process.label(), child.id());
+            if let Some(report) = crashes.get_mut(&process.label()) {
+                report.restarts += 1;
+            }
+            process.child = Some(child);
+            process.started = now;
+            process.relaunch_at = None;
+        }
+        Err(err) => {
+            error!(
+                "Failed to relaunch {}: {}, retrying in {}s",
+                process.label(),
+                err,
+                process.backoff.as_secs()
+            );
+            process.relaunch_at = Some(now + process.backoff);
+            process.backoff = (process.backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
@@ -171,6 +171,8 @@ fn attempt_transition_to_awaiting_syncer_or_awaiting_syncer_request(
                 blockchain,
                 network,
                 &runtime.config,
+                &runtime.supervisor,
+                runtime.tor_proxy,
             )? {
                 event.complete_sync_service(service_id, SyncMsg::Task(syncer_task))?;
                 Ok(Some(SyncerStateMachine::AwaitingSyncerRequest(
@@ -212,6 +214,8 @@ fn attempt_transition_to_awaiting_syncer_or_awaiting_syncer_request(
                 blockchain,
                 network,
                 &runtime.config,
+                &runtime.supervisor,
+                runtime.tor_proxy,
             )? {
                 event.complete_sync_service(service_id, SyncMsg::Task(syncer_task))?;
                 Ok(Some(SyncerStateMachine::AwaitingSyncerRequest(
@@ -242,6 +246,8 @@ fn attempt_transition_to_awaiting_syncer_or_awaiting_syncer_request(
                 blockchain,
                 network,
                 &runtime.config,
+                &runtime.supervisor,
+                runtime.tor_proxy,
             ) {
                 Ok(Some(service_id)) => {
                     event.complete_sync_service(service_id, SyncMsg::Task(syncer_task))?;
@@ -399,6 +405,7 @@ fn attempt_transition_to_end(
         (BusMsg::Sync(SyncMsg::Event(SyncerEvent::HealthResult(res))), syncer_id)
             if syncer == syncer_id && res.id == syncer_task_id =>
         {
+            runtime.record_syncer_health(event.endpoints, syncer, res.health.clone());
             event.send_client_ctl(source, CtlMsg::HealthResult(res.health))?;
             runtime.clean_up_after_syncer_usage(event.endpoints)?;
             Ok(None)
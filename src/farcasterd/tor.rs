@@ -0,0 +1,225 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Onion service of the listening peerd, configured in the `[tor]` section.
+//!
+//! farcasterd speaks the Tor control protocol with a local tor daemon to create a v3 onion
+//! service forwarding to the listening peerd. The onion service is created without the `Detach`
+//! flag, tor removes it when the control connection closes, so the [`TorControl`] connection
+//! lives as long as farcasterd. A persistent onion service reuses the key stored in the data
+//! directory, an ephemeral one gets a new address on every start.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use bitcoin::hashes::hex::ToHex;
+
+use crate::Error;
+
+/// Name of the file storing the key of a persistent onion service, in the data directory
+pub const ONION_SERVICE_KEY_FILE: &str = "onion_service_key";
+
+/// Time allowed to the tor daemon to answer a control command
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An authenticated connection to the Tor control port
+pub struct TorControl {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+/// An onion service created with `ADD_ONION`
+#[derive(Clone, Debug)]
+pub struct OnionService {
+    /// The onion address without the `.onion` suffix
+    pub service_id: String,
+    /// The key of the onion service, only returned by tor for a newly generated key
+    pub private_key: Option<String>,
+}
+
+impl TorControl {
+    /// Connects to the control port and authenticates with the password if given, otherwise
+    /// with the authentication methods advertised by tor
+    pub fn connect(addr: SocketAddr, password: Option<&str>) -> Result<Self, Error> {
+        let stream = TcpStream::connect_timeout(&addr, CONTROL_TIMEOUT).map_err(|err| {
+            Error::Farcaster(format!(
+                "Unable to connect to the Tor control port {}: {}",
+                addr, err
+            ))
+        })?;
+        stream.set_read_timeout(Some(CONTROL_TIMEOUT))?;
+        let mut control = TorControl {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        control.authenticate(password)?;
+        Ok(control)
+    }
+
+    fn authenticate(&mut self, password: Option<&str>) -> Result<(), Error> {
+        let command = match password {
+            Some(password) => format!("AUTHENTICATE {}", quote(password)),
+            None => {
+                let protocol_info = self.command("PROTOCOLINFO 1")?;
+                let (methods, cookie_file) = parse_auth_line(&protocol_info)?;
+                if methods.iter().any(|method| method == "NULL") {
+                    s!("AUTHENTICATE")
+                } else if methods.iter().any(|method| method == "COOKIE") {
+                    let cookie_file = cookie_file.ok_or_else(|| {
+                        Error::Farcaster("Tor did not advertise its cookie file".to_string())
+                    })?;
+                    let cookie = fs::read(&cookie_file).map_err(|err| {
+                        Error::Farcaster(format!(
+                            "Unable to read the Tor cookie file {}: {}",
+                            cookie_file, err
+                        ))
+                    })?;
+                    format!("AUTHENTICATE {}", cookie.to_hex())
+                } else {
+                    return Err(Error::Farcaster(format!(
+                        "No supported Tor control authentication among {}, set the control \
+                         password in the tor configuration section",
+                        methods.join(",")
+                    )));
+                }
+            }
+        };
+        self.command(&command)?;
+        Ok(())
+    }
+
+    /// Creates an onion service forwarding the virtual port to the target, with the given key or
+    /// a new one if none
+    pub fn add_onion(
+        &mut self,
+        private_key: Option<&str>,
+        virtual_port: u16,
+        target: SocketAddr,
+    ) -> Result<OnionService, Error> {
+        let key = private_key.unwrap_or("NEW:ED25519-V3");
+        let reply = self.command(&format!(
+            "ADD_ONION {} Port={},{}",
+            key, virtual_port, target
+        ))?;
+        let value = |name: &str| {
+            reply
+                .iter()
+                .find_map(|line| line.strip_prefix(&format!("{}=", name)))
+                .map(str::to_string)
+        };
+        let service_id = value("ServiceID")
+            .ok_or_else(|| Error::Farcaster("Tor did not return the onion address".to_string()))?;
+        Ok(OnionService {
+            service_id,
+            private_key: value("PrivateKey"),
+        })
+    }
+
+    /// Sends a command and returns the reply lines without their status code, fails if tor
+    /// does not reply with a success
+    fn command(&mut self, command: &str) -> Result<Vec<String>, Error> {
+        self.writer
+            .write_all(format!("{}\r\n", command).as_bytes())?;
+        let mut lines = vec![];
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(Error::Farcaster(
+                    "Tor closed the control connection".to_string(),
+                ));
+            }
+            let line = line.trim_end();
+            if line.len() < 4 {
+                return Err(Error::Farcaster(format!(
+                    "Invalid Tor control reply: {}",
+                    line
+                )));
+            }
+            let (code, rest) = line.split_at(3);
+            if code != "250" {
+                return Err(Error::Farcaster(format!("Tor control error: {}", line)));
+            }
+            lines.push(rest[1..].to_string());
+            // a space after the code marks the last line of the reply
+            if rest.starts_with(' ') {
+                return Ok(lines);
+            }
+        }
+    }
+}
+
+/// Returns the key of the persistent onion service stored in the data directory, if any
+pub fn read_onion_service_key(data_dir: &Path) -> Result<Option<String>, Error> {
+    let path = data_dir.join(ONION_SERVICE_KEY_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(fs::read_to_string(path)?.trim().to_string()))
+}
+
+/// Stores the key of the persistent onion service in the data directory, readable only by the
+/// owner
+pub fn write_onion_service_key(data_dir: &Path, private_key: &str) -> Result<(), Error> {
+    let path = data_dir.join(ONION_SERVICE_KEY_FILE);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(private_key.as_bytes())?;
+    Ok(())
+}
+
+/// Returns the authentication methods and the cookie file of a `PROTOCOLINFO` reply
+fn parse_auth_line(reply: &[String]) -> Result<(Vec<String>, Option<String>), Error> {
+    let auth = reply
+        .iter()
+        .find_map(|line| line.strip_prefix("AUTH "))
+        .ok_or_else(|| Error::Farcaster("Tor did not advertise its authentication".to_string()))?;
+    let methods = auth
+        .split(' ')
+        .find_map(|field| field.strip_prefix("METHODS="))
+        .map(|methods| methods.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+    let cookie_file = auth
+        .split_once("COOKIEFILE=")
+        .map(|(_, path)| unquote(path));
+    Ok((methods, cookie_file))
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unquote(value: &str) -> String {
+    let mut unquoted = String::new();
+    let mut chars = value.strip_prefix('"').unwrap_or(value).chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            '"' => break,
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+#[test]
+fn parse_protocol_info() {
+    let reply = vec![
+        s!("PROTOCOLINFO 1"),
+        s!(r#"AUTH METHODS=COOKIE,SAFECOOKIE COOKIEFILE="/run/tor/control \"auth\".cookie""#),
+        s!(r#"VERSION Tor="0.4.7.10""#),
+        s!("OK"),
+    ];
+    let (methods, cookie_file) = parse_auth_line(&reply).unwrap();
+    assert_eq!(methods, vec![s!("COOKIE"), s!("SAFECOOKIE")]);
+    assert_eq!(cookie_file, Some(s!(r#"/run/tor/control "auth".cookie"#)));
+    assert_eq!(unquote(&quote(r#"pass"wo\rd"#)), r#"pass"wo\rd"#);
+    assert!(parse_auth_line(&[s!("OK")]).is_err());
+}
@@ -8,11 +8,15 @@ use crate::bus::ctl::{
     BitcoinFundingInfo, CtlMsg, FundingInfo, InitMakerSwap, InitTakerSwap, MoneroFundingInfo,
     ProtoDeal, PubDeal, SwapKeys, WrappedKeyManager,
 };
-use crate::bus::info::{InfoMsg, MadeDeal, TookDeal, ViewableDeal};
-use crate::bus::p2p::{Commit, PeerMsg};
-use crate::bus::{CheckpointEntry, DealInfo, DealStatus, Failure, FailureCode};
-use crate::farcasterd::runtime::{launch_swapd, syncer_up, Runtime};
+use crate::bus::info::{InfoMsg, MadeDeal, NodeEvent, TookDeal, ViewableDeal};
+use crate::bus::p2p::{Commit, DealRefused, PeerMsg};
+use crate::bus::{CheckpointEntry, DealInfo, DealStatus, Failure, FailureCode, SwapParams};
+use crate::farcasterd::deal_validation::validate_deal;
+use crate::farcasterd::notifications::NotificationEvent;
+use crate::farcasterd::runtime::{launch_swapd, syncer_up, with_port, Runtime};
+use crate::farcasterd::stats::{StatsEvent, StatsEventKind};
 use crate::service::{SwapDetails, SwapLogging};
+use crate::swapd::TemporalSafety;
 use crate::LogStyle;
 use crate::{
     bus::{BusMsg, Outcome},
@@ -22,7 +26,10 @@ use crate::{
 };
 use farcaster_core::blockchain::Blockchain;
 use farcaster_core::role::{SwapRole, TradeRole};
-use farcaster_core::swap::{btcxmr::Deal, SwapId};
+use farcaster_core::swap::{
+    btcxmr::{Deal, DealParameters},
+    SwapId,
+};
 use farcaster_core::Uuid;
 use internet2::addr::{NodeAddr, NodeId};
 use microservices::esb::Handler;
@@ -124,6 +131,7 @@ pub struct MakeDeal {
     deal: Deal,
     arb_addr: bitcoin::Address,
     acc_addr: monero::Address,
+    swap_params: SwapParams,
 }
 
 pub struct TakerCommit {
@@ -132,12 +140,14 @@ pub struct TakerCommit {
     commit: Commit,
     target_bitcoin_address: bitcoin::Address,
     target_monero_address: monero::Address,
+    swap_params: SwapParams,
 }
 
 pub struct TakerConnect {
     deal: Deal,
     arb_addr: bitcoin::Address,
     acc_addr: monero::Address,
+    swap_params: SwapParams,
     source: ServiceId,
 }
 
@@ -145,6 +155,7 @@ pub struct TakeDeal {
     deal: Deal,
     arb_addr: bitcoin::Address,
     acc_addr: monero::Address,
+    swap_params: SwapParams,
     peerd: ServiceId,
 }
 
@@ -160,6 +171,7 @@ pub struct SwapdLaunched {
     key_manager: WrappedKeyManager,
     target_bitcoin_address: bitcoin::Address,
     target_monero_address: monero::Address,
+    sweep_bitcoin_address: Option<bitcoin::Address>,
 }
 
 pub struct RestoringSwapd {
@@ -167,6 +179,7 @@ pub struct RestoringSwapd {
     deal: Deal,
     trade_role: TradeRole,
     expected_counterparty_node_id: Option<NodeId>,
+    swap_params: SwapParams,
     arbitrating_syncer_up: Option<ServiceId>,
     accordant_syncer_up: Option<ServiceId>,
     swapd_up: bool,
@@ -354,7 +367,7 @@ impl TradeStateMachine {
                 Some((deal.clone(), TradeRole::Taker))
             }
             TradeStateMachine::TakerCommit(TakerCommit { deal, .. }) => {
-                Some((deal.clone(), TradeRole::Taker))
+                Some((deal.clone(), TradeRole::Maker))
             }
             TradeStateMachine::TakerConnect(TakerConnect { deal, .. }) => {
                 Some((deal.clone(), TradeRole::Taker))
@@ -422,6 +435,21 @@ impl TradeStateMachine {
         }
     }
 
+    pub fn counterparty_node_id(&self) -> Option<NodeId> {
+        match self {
+            TradeStateMachine::RestoringSwapd(RestoringSwapd {
+                expected_counterparty_node_id,
+                ..
+            }) => *expected_counterparty_node_id,
+            TradeStateMachine::SwapdRunning(SwapdRunning {
+                peerd: None,
+                expected_counterparty_node_id,
+                ..
+            }) => *expected_counterparty_node_id,
+            _ => self.get_connection().and_then(|peerd| peerd.node_id()),
+        }
+    }
+
     pub fn get_swap_id_with_matching_connection(&self, source: &ServiceId) -> Option<SwapId> {
         if let Some(peer) = self.get_connection() {
             if peer == *source {
@@ -470,6 +498,18 @@ impl TradeStateMachine {
         }
     }
 
+    /// Node id of the counterparty a restored swap without connection waits for
+    pub fn awaiting_reconnect_of(&self) -> Option<NodeId> {
+        match self {
+            TradeStateMachine::SwapdRunning(SwapdRunning {
+                peerd: None,
+                expected_counterparty_node_id,
+                ..
+            }) => *expected_counterparty_node_id,
+            _ => None,
+        }
+    }
+
     pub fn needs_funding(&self, blockchain: Blockchain) -> Option<FundingInfo> {
         match blockchain {
             Blockchain::Monero => self.needs_funding_monero().map(FundingInfo::Monero),
@@ -511,7 +551,7 @@ fn attempt_transition_to_make_deal(
             arbitrating_addr,
             accordant_addr,
             public_addr,
-            ..
+            swap_params,
         })) => {
             // validate deal parameters
             if let Err(e) = runtime.config.validate_deal_parameters(
@@ -527,8 +567,18 @@ fn attempt_transition_to_make_deal(
                 }))?;
                 return Ok(None);
             }
-            // start a listener on the bind_addr
-            let bind_addr = match runtime.config.get_bind_addr() {
+            if !swap_params.is_empty() {
+                if let Err(e) = validate_swap_params(runtime, &deal_parameters, &swap_params) {
+                    warn!("Swap parameters validation error: {}", e);
+                    event.complete_client_ctl(CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: e.to_string(),
+                    }))?;
+                    return Ok(None);
+                }
+            }
+            // start a listener on the bind_addr, or a listener with the deal's own node id
+            let ephemeral_ports = match runtime.config.get_ephemeral_ports() {
                 Err(err) => {
                     event.complete_ctl(CtlMsg::Failure(Failure {
                         code: FailureCode::Unknown,
@@ -536,9 +586,19 @@ fn attempt_transition_to_make_deal(
                     }))?;
                     return Ok(None);
                 }
-                Ok(bind_addr) => bind_addr,
+                Ok(ports) => ports,
+            };
+            let listening = match ephemeral_ports {
+                Some(ports) => runtime
+                    .listen_ephemeral(deal_parameters.uuid, ports)
+                    .map(|(node_id, port)| (node_id, Some(port))),
+                None => runtime
+                    .config
+                    .get_bind_addr()
+                    .and_then(|bind_addr| runtime.listen(bind_addr))
+                    .map(|node_id| (node_id, None)),
             };
-            match runtime.listen(bind_addr) {
+            match listening {
                 Err(err) => {
                     log_helper.log_warn(format!(
                         "Failed to start peerd listen, cannot make deal: {}",
@@ -550,7 +610,21 @@ fn attempt_transition_to_make_deal(
                     }))?;
                     Ok(None)
                 }
-                Ok(node_id) => {
+                Ok((node_id, ephemeral_port)) => {
+                    // the onion service replaces the public address given to make, and the
+                    // listener of an ephemeral node id its port
+                    let public_addr = match ephemeral_port.map(|port| with_port(public_addr, port))
+                    {
+                        Some(Ok(public_addr)) => public_addr,
+                        Some(Err(err)) => {
+                            event.complete_client_ctl(CtlMsg::Failure(Failure {
+                                code: FailureCode::Unknown,
+                                info: err.to_string(),
+                            }))?;
+                            return Ok(None);
+                        }
+                        None => runtime.onion_address().unwrap_or(public_addr),
+                    };
                     let deal = deal_parameters.to_v1(node_id.public_key(), public_addr);
                     let msg = s!("Deal registered, please share with taker.");
                     log_helper.log_info(format!(
@@ -565,8 +639,10 @@ fn attempt_transition_to_make_deal(
                             serialized_deal: deal.to_string(),
                             status: DealStatus::Open,
                             local_trade_role: TradeRole::Maker,
+                            ephemeral_node_id: ephemeral_port.is_some(),
                         }),
                     )?;
+                    runtime.notify_node_event(event.endpoints, NodeEvent::DealMade(deal.clone()));
                     event.complete_client_info(InfoMsg::MadeDeal(MadeDeal {
                         message: msg,
                         viewable_deal: ViewableDeal {
@@ -579,6 +655,7 @@ fn attempt_transition_to_make_deal(
                         deal,
                         arb_addr: arbitrating_addr,
                         acc_addr: accordant_addr,
+                        swap_params,
                     })))
                 }
             }
@@ -603,6 +680,7 @@ fn attempt_transition_to_taker_connect_or_take_deal(
             deal,
             bitcoin_address: arb_addr,
             monero_address: acc_addr,
+            swap_params,
         })) => {
             // validate deal parameters
             if let Err(e) = runtime.config.validate_deal_parameters(
@@ -618,6 +696,31 @@ fn attempt_transition_to_taker_connect_or_take_deal(
                 }))?;
                 return Ok(None);
             }
+            if !swap_params.is_empty() {
+                if let Err(e) = validate_swap_params(runtime, &deal.parameters, &swap_params) {
+                    warn!("Swap parameters validation error: {}", e);
+                    event.complete_client_ctl(CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: e.to_string(),
+                    }))?;
+                    return Ok(None);
+                }
+            }
+            let report = validate_deal(&runtime.config, &deal, runtime.price_oracle.as_ref());
+            if report.enforced && !report.passed() {
+                let failures = report
+                    .failures()
+                    .iter()
+                    .map(|check| check.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                log_helper.log_warn(format!("Deal validation failed: {}", failures));
+                event.complete_client_ctl(CtlMsg::Failure(Failure {
+                    code: FailureCode::Unknown,
+                    info: format!("Deal validation failed: {}", failures),
+                }))?;
+                return Ok(None);
+            }
             if runtime.consumed_deals_contains(&deal) || runtime.deals.contains(&deal) {
                 let msg = format!(
                     "{} already exists or was already taken, ignoring request",
@@ -666,6 +769,7 @@ fn attempt_transition_to_taker_connect_or_take_deal(
                             deal,
                             arb_addr,
                             acc_addr,
+                            swap_params,
                             peerd: peer_service_id,
                         })))
                     } else {
@@ -673,6 +777,7 @@ fn attempt_transition_to_taker_connect_or_take_deal(
                             deal,
                             arb_addr,
                             acc_addr,
+                            swap_params,
                             source: event.source,
                         })))
                     }
@@ -701,12 +806,20 @@ fn attempt_transition_to_restoring_swapd(
             deal,
             trade_role,
             expected_counterparty_node_id,
+            swap_params,
         })) => {
+            // restores initiated by farcasterd itself, i.e. auto restore and crashed swaps, have
+            // no client waiting for a reply
+            let from_client = event.source != ServiceId::Farcasterd;
             if let Err(err) = runtime.services_ready() {
-                event.complete_client_ctl(CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
-                    info: err.to_string(),
-                }))?;
+                if from_client {
+                    event.complete_client_ctl(CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: err.to_string(),
+                    }))?;
+                } else {
+                    log_helper.log_warn(format!("Cannot restore checkpoint: {}", err));
+                }
                 return Ok(None);
             }
 
@@ -715,10 +828,15 @@ fn attempt_transition_to_restoring_swapd(
                 .send_ctl_service(ServiceId::Swap(swap_id), CtlMsg::Hello)
                 .is_ok()
             {
-                event.complete_client_ctl(CtlMsg::Failure(Failure {
-                    code: FailureCode::Unknown,
-                    info: "Cannot restore a checkpoint into a running swap.".to_string(),
-                }))?;
+                let info = "Cannot restore a checkpoint into a running swap.".to_string();
+                if from_client {
+                    event.complete_client_ctl(CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info,
+                    }))?;
+                } else {
+                    log_helper.log_warn(info);
+                }
                 return Ok(None);
             }
 
@@ -738,29 +856,23 @@ fn attempt_transition_to_restoring_swapd(
                 (false, None)
             };
 
-            let swap_config = runtime.config.get_swap_config(
-                deal.parameters.arbitrating_blockchain.try_into()?,
-                deal.parameters.accordant_blockchain.try_into()?,
-                deal.parameters.network,
-            )?;
-
+            // use the parameters the swap was started with, the configuration may have changed
+            let swap_config = runtime
+                .config
+                .get_swap_config(
+                    deal.parameters.arbitrating_blockchain.try_into()?,
+                    deal.parameters.accordant_blockchain.try_into()?,
+                    deal.parameters.network,
+                )?
+                .with_params(&swap_params);
+
+            // a deal made with an ephemeral node id is listened to with that identity again
             let listening = if trade_role == TradeRole::Maker {
-                match runtime.config.get_bind_addr() {
-                    Ok(bind_addr) => {
-                        if let Err(err) = runtime.listen(bind_addr) {
-                            log_helper.log_warn(format!("failed to re-listen on restore: {}", err));
-                            false
-                        } else {
-                            true
-                        }
-                    }
-                    Err(err) => {
-                        log_helper.log_error(format!(
-                            "Failed to relisten on restore, bad bind address configuration: {}",
-                            err
-                        ));
-                        false
-                    }
+                if let Err(err) = runtime.listen_for_deal(&deal) {
+                    log_helper.log_warn(format!("failed to re-listen on restore: {}", err));
+                    false
+                } else {
+                    true
                 }
             } else {
                 false
@@ -772,6 +884,8 @@ fn attempt_transition_to_restoring_swapd(
                 deal.parameters.arbitrating_blockchain,
                 deal.parameters.network,
                 &runtime.config,
+                &runtime.supervisor,
+                runtime.tor_proxy,
             )?;
             let accordant_syncer_up = syncer_up(
                 &mut runtime.spawning_services,
@@ -779,16 +893,27 @@ fn attempt_transition_to_restoring_swapd(
                 deal.parameters.accordant_blockchain,
                 deal.parameters.network,
                 &runtime.config,
+                &runtime.supervisor,
+                runtime.tor_proxy,
             )?;
 
-            launch_swapd(trade_role, deal.clone(), swap_id, swap_config)?;
-            event.complete_client_info(InfoMsg::String("Restoring checkpoint.".to_string()))?;
+            launch_swapd(
+                trade_role,
+                deal.clone(),
+                swap_id,
+                swap_config,
+                &runtime.supervisor,
+            )?;
+            if from_client {
+                event.complete_client_info(InfoMsg::String("Restoring checkpoint.".to_string()))?;
+            }
 
             Ok(Some(TradeStateMachine::RestoringSwapd(RestoringSwapd {
                 swap_id,
                 deal,
                 trade_role,
                 expected_counterparty_node_id,
+                swap_params,
                 arbitrating_syncer_up,
                 accordant_syncer_up,
                 swapd_up: false,
@@ -817,10 +942,43 @@ fn attempt_transition_to_taker_committed(
         deal,
         arb_addr,
         acc_addr,
+        swap_params,
     } = make_deal;
     match (event.request.clone(), event.source.clone()) {
-        (BusMsg::P2p(PeerMsg::TakerCommit(taker_commit)), ServiceId::Peer(..)) => {
-            if deal == taker_commit.deal {
+        (BusMsg::P2p(PeerMsg::TakerCommit(taker_commit)), ServiceId::Peer(_, node_addr)) => {
+            if deal != taker_commit.deal {
+                log_helper.log_error(format!(
+                    "Received invalid TakerCommit for deal {}.",
+                    deal.id()
+                ));
+                Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                    deal,
+                    arb_addr,
+                    acc_addr,
+                    swap_params,
+                })))
+            } else if let Err(reason) = runtime.evaluate_taker(node_addr.id, &deal) {
+                log_helper.log_warn(format!(
+                    "Refusing TakerCommit from {} for deal {}: {}",
+                    node_addr.id,
+                    deal.id(),
+                    reason
+                ));
+                let source = event.source.clone();
+                event.send_msg_service(
+                    source,
+                    PeerMsg::DealRefused(DealRefused {
+                        swap_id: taker_commit.swap_id(),
+                        reason,
+                    }),
+                )?;
+                Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
+                    deal,
+                    arb_addr,
+                    acc_addr,
+                    swap_params,
+                })))
+            } else {
                 let source = event.source.clone();
                 log_helper.log_info(
                     "Received TakerCommit for swap - requesting walletd to create swap keys.",
@@ -836,6 +994,7 @@ fn attempt_transition_to_taker_committed(
                         serialized_deal: deal.to_string(),
                         status: DealStatus::InProgress,
                         local_trade_role: TradeRole::Maker,
+                        ephemeral_node_id: runtime.deal_secret_key(&deal).is_some(),
                     }),
                 )?;
                 Ok(Some(TradeStateMachine::TakerCommit(TakerCommit {
@@ -844,16 +1003,7 @@ fn attempt_transition_to_taker_committed(
                     commit: taker_commit.commit,
                     target_bitcoin_address: arb_addr,
                     target_monero_address: acc_addr,
-                })))
-            } else {
-                log_helper.log_error(format!(
-                    "Received invalid TakerCommit for deal {}.",
-                    deal.id()
-                ));
-                Ok(Some(TradeStateMachine::MakeDeal(MakeDeal {
-                    deal,
-                    arb_addr,
-                    acc_addr,
+                    swap_params,
                 })))
             }
         }
@@ -868,8 +1018,10 @@ fn attempt_transition_to_taker_committed(
                         serialized_deal: deal.to_string(),
                         status: DealStatus::Revoked,
                         local_trade_role: TradeRole::Maker,
+                        ephemeral_node_id: runtime.deal_secret_key(&deal).is_some(),
                     }),
                 )?;
+                runtime.stop_listening_for_deal(&deal)?;
                 event.complete_client_info(InfoMsg::String(
                     "Successfully revoked deal.".to_string(),
                 ))?;
@@ -882,6 +1034,7 @@ fn attempt_transition_to_taker_committed(
                     deal,
                     arb_addr,
                     acc_addr,
+                    swap_params,
                 })))
             }
         }
@@ -901,13 +1054,14 @@ fn attempt_transition_to_taker_committed(
                 deal,
                 arb_addr,
                 acc_addr,
+                swap_params,
             })))
         }
     }
 }
 
 fn attempt_transition_from_taker_commit_to_swapd_launched(
-    event: Event,
+    mut event: Event,
     runtime: &mut Runtime,
     taker_commit: TakerCommit,
     log_helper: LogHelper,
@@ -918,12 +1072,15 @@ fn attempt_transition_from_taker_commit_to_swapd_launched(
         commit,
         target_bitcoin_address,
         target_monero_address,
+        swap_params,
     } = taker_commit;
-    match event.request {
+    match &event.request {
         BusMsg::Ctl(CtlMsg::SwapKeys(swap_keys)) => {
             let swap_id = commit.swap_id();
             log_helper.log_info("Creating new swap.");
+            let swap_keys = swap_keys.clone();
             let tsm = transition_to_swapd_launched_tsm(
+                &mut event,
                 runtime,
                 ConsumedDealRole::Maker(commit),
                 swap_keys,
@@ -931,6 +1088,7 @@ fn attempt_transition_from_taker_commit_to_swapd_launched(
                 deal,
                 target_bitcoin_address,
                 target_monero_address,
+                swap_params,
                 swap_id,
                 log_helper,
             )?;
@@ -948,6 +1106,7 @@ fn attempt_transition_from_taker_commit_to_swapd_launched(
                 commit,
                 target_bitcoin_address,
                 target_monero_address,
+                swap_params,
             })))
         }
     }
@@ -963,13 +1122,14 @@ fn attempt_transition_to_take_deal(
         deal,
         arb_addr,
         acc_addr,
+        swap_params,
         source,
     } = taker_connect;
     match event.request {
         BusMsg::Ctl(CtlMsg::ConnectSuccess)
             if Some(node_addr_from_deal(&deal)) == event.source.node_addr() =>
         {
-            runtime.handle_new_connection(event.source.clone());
+            runtime.handle_new_connection(event.endpoints, event.source.clone());
             let deal_registered = "Deal registered".to_string();
             log_helper.log_info(format!(
                 "{}: {:#}",
@@ -992,6 +1152,7 @@ fn attempt_transition_to_take_deal(
                 deal,
                 arb_addr,
                 acc_addr,
+                swap_params,
                 peerd: event.source,
             })))
         }
@@ -1030,6 +1191,7 @@ fn attempt_transition_to_take_deal(
                 deal,
                 arb_addr,
                 acc_addr,
+                swap_params,
                 source,
             })))
         }
@@ -1046,20 +1208,24 @@ fn attempt_transition_from_take_deal_to_swapd_launched(
         deal,
         arb_addr,
         acc_addr,
+        swap_params,
         peerd,
     } = take_deal;
     match &event.request {
         BusMsg::Ctl(CtlMsg::SwapKeys(swap_keys)) => {
             let swap_id: SwapId = deal.id().into(); // The deal id is now used to track a swap
             log_helper.log_info("Creating new swap.");
+            let swap_keys = swap_keys.clone();
             let tsm = transition_to_swapd_launched_tsm(
+                &mut event,
                 runtime,
                 ConsumedDealRole::Taker,
-                swap_keys.clone(),
+                swap_keys,
                 peerd,
                 deal.clone(),
                 arb_addr,
                 acc_addr,
+                swap_params,
                 swap_id,
                 log_helper,
             )?;
@@ -1067,6 +1233,7 @@ fn attempt_transition_from_take_deal_to_swapd_launched(
                 ServiceId::Database,
                 CtlMsg::SetDealInfo(DealInfo {
                     serialized_deal: deal.to_string(),
+                    ephemeral_node_id: false,
                     deal,
                     status: DealStatus::InProgress,
                     local_trade_role: TradeRole::Taker,
@@ -1090,6 +1257,7 @@ fn attempt_transition_from_take_deal_to_swapd_launched(
                 deal,
                 arb_addr,
                 acc_addr,
+                swap_params,
                 peerd,
             })))
         }
@@ -1097,6 +1265,7 @@ fn attempt_transition_from_take_deal_to_swapd_launched(
 }
 
 fn transition_to_swapd_launched_tsm(
+    event: &mut Event,
     runtime: &mut Runtime,
     consumed_deal_role: ConsumedDealRole,
     swap_keys: SwapKeys,
@@ -1104,21 +1273,31 @@ fn transition_to_swapd_launched_tsm(
     deal: Deal,
     target_bitcoin_address: bitcoin::Address,
     target_monero_address: monero::Address,
+    swap_params: SwapParams,
     swap_id: SwapId,
     log_helper: LogHelper,
 ) -> Result<TradeStateMachine, Error> {
-    let swap_config = runtime.config.get_swap_config(
-        deal.parameters.arbitrating_blockchain.try_into()?,
-        deal.parameters.accordant_blockchain.try_into()?,
-        deal.parameters.network,
-    )?;
-    let SwapKeys { key_manager, .. } = swap_keys;
+    let swap_config = runtime
+        .config
+        .get_swap_config(
+            deal.parameters.arbitrating_blockchain.try_into()?,
+            deal.parameters.accordant_blockchain.try_into()?,
+            deal.parameters.network,
+        )?
+        .with_params(&swap_params);
+    let SwapKeys {
+        key_manager,
+        sweep_bitcoin_address,
+        ..
+    } = swap_keys;
     let arbitrating_syncer_up = syncer_up(
         &mut runtime.spawning_services,
         &mut runtime.registered_services,
         deal.parameters.arbitrating_blockchain,
         deal.parameters.network,
         &runtime.config,
+        &runtime.supervisor,
+        runtime.tor_proxy,
     )?;
     let accordant_syncer_up = syncer_up(
         &mut runtime.spawning_services,
@@ -1126,6 +1305,8 @@ fn transition_to_swapd_launched_tsm(
         deal.parameters.accordant_blockchain,
         deal.parameters.network,
         &runtime.config,
+        &runtime.supervisor,
+        runtime.tor_proxy,
     )?;
     log_helper.log_trace(format!(
         "launching swapd with swap_id: {}",
@@ -1133,11 +1314,23 @@ fn transition_to_swapd_launched_tsm(
     ));
 
     runtime.stats.incr_initiated();
+    runtime.notify_node_event(event.endpoints, NodeEvent::SwapStarted(swap_id));
+    record_stats(
+        event,
+        &log_helper,
+        StatsEvent::new(
+            swap_id,
+            &deal,
+            consumed_deal_role.clone().into(),
+            StatsEventKind::Initiated,
+        ),
+    );
     launch_swapd(
         consumed_deal_role.clone().into(),
         deal.clone(),
         swap_id,
         swap_config,
+        &runtime.supervisor,
     )?;
 
     Ok(TradeStateMachine::SwapdLaunched(SwapdLaunched {
@@ -1150,6 +1343,7 @@ fn transition_to_swapd_launched_tsm(
         key_manager,
         target_bitcoin_address,
         target_monero_address,
+        sweep_bitcoin_address,
         consumed_deal_role,
         peerd_reconnected: false,
     }))
@@ -1172,6 +1366,7 @@ fn attempt_transition_from_swapd_launched_to_swapd_running(
         mut peerd_reconnected,
         target_bitcoin_address,
         target_monero_address,
+        sweep_bitcoin_address,
         key_manager,
     } = swapd_launched;
     match (event.request.clone(), event.source.clone()) {
@@ -1230,6 +1425,7 @@ fn attempt_transition_from_swapd_launched_to_swapd_running(
                 key_manager,
                 target_bitcoin_address,
                 target_monero_address,
+                sweep_bitcoin_address,
                 commit: commit.clone(),
             }),
             ConsumedDealRole::Taker => CtlMsg::TakeSwap(InitTakerSwap {
@@ -1239,6 +1435,7 @@ fn attempt_transition_from_swapd_launched_to_swapd_running(
                 key_manager,
                 target_bitcoin_address,
                 target_monero_address,
+                sweep_bitcoin_address,
             }),
         };
         if peerd_reconnected {
@@ -1247,6 +1444,9 @@ fn attempt_transition_from_swapd_launched_to_swapd_running(
                 CtlMsg::PeerdReconnected(peerd.clone()),
             )?;
         }
+        if let Some(node_id) = peerd.node_id() {
+            runtime.record_peer_seen(event.endpoints, node_id, None, Some(swap_id))?;
+        }
         event.complete_ctl_service(ServiceId::Swap(swap_id), init_swap_req)?;
 
         Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
@@ -1277,6 +1477,7 @@ fn attempt_transition_from_swapd_launched_to_swapd_running(
             key_manager,
             target_bitcoin_address,
             target_monero_address,
+            sweep_bitcoin_address,
             arbitrating_syncer_up,
             accordant_syncer_up,
             swapd_up,
@@ -1297,6 +1498,7 @@ fn attempt_transition_from_restoring_swapd_to_swapd_running(
         deal,
         trade_role,
         expected_counterparty_node_id,
+        swap_params,
         mut arbitrating_syncer_up,
         mut accordant_syncer_up,
         mut swapd_up,
@@ -1322,7 +1524,7 @@ fn attempt_transition_from_restoring_swapd_to_swapd_running(
             if Some(node_addr_from_deal(&deal)) == source.node_addr()
                 && trade_role == TradeRole::Taker =>
         {
-            runtime.handle_new_connection(event.source.clone());
+            runtime.handle_new_connection(event.endpoints, event.source.clone());
             log_helper.log_info("Peerd connected for restored swap");
             peerd = Some(event.source.clone());
         }
@@ -1334,7 +1536,7 @@ fn attempt_transition_from_restoring_swapd_to_swapd_running(
             expect_connection = false;
         }
         (BusMsg::Ctl(CtlMsg::Hello), source) if trade_role == TradeRole::Maker => {
-            if let Ok(bind_addr) = runtime.config.get_bind_addr() {
+            if let Ok(bind_addr) = runtime.deal_bind_addr(&deal) {
                 if let Some(node_id) = expected_counterparty_node_id {
                     if source.node_addr() == Some(NodeAddr::new(node_id, bind_addr)) {
                         log_helper.log_info("Peerd connected for restored swap");
@@ -1355,11 +1557,25 @@ fn attempt_transition_from_restoring_swapd_to_swapd_running(
     ) {
         log_helper.log_info("Restoring swap");
         runtime.stats.incr_initiated();
+        runtime.notify_node_event(event.endpoints, NodeEvent::SwapStarted(swap_id));
 
         if let Some(peerd) = peerd.clone() {
             event.send_ctl_service(ServiceId::Swap(swap_id), CtlMsg::PeerdReconnected(peerd))?;
         }
 
+        // Dial the counterparty until it is connected, a maker can only dial a taker found in
+        // the address book
+        if let (None, Some(node_id)) = (&peerd, expected_counterparty_node_id) {
+            let deal_node_addr = node_addr_from_deal(&deal);
+            let deal_address = if trade_role == TradeRole::Taker && deal_node_addr.id == node_id {
+                Some(deal_node_addr.addr)
+            } else {
+                None
+            };
+            let secret_key = runtime.deal_secret_key(&deal);
+            runtime.start_peer_reconnect(event.endpoints, node_id, deal_address, secret_key)?;
+        }
+
         event.complete_ctl_service(
             ServiceId::Database,
             CtlMsg::RestoreCheckpoint(CheckpointEntry {
@@ -1367,6 +1583,7 @@ fn attempt_transition_from_restoring_swapd_to_swapd_running(
                 deal: deal.clone(),
                 trade_role,
                 expected_counterparty_node_id,
+                swap_params,
             }),
         )?;
 
@@ -1388,6 +1605,7 @@ fn attempt_transition_from_restoring_swapd_to_swapd_running(
             deal,
             trade_role,
             expected_counterparty_node_id,
+            swap_params,
             arbitrating_syncer_up,
             accordant_syncer_up,
             swapd_up,
@@ -1423,6 +1641,7 @@ fn attempt_transition_to_end(
         {
             let swap_service_id = ServiceId::Swap(swap_id);
             log_helper.log_debug("Letting swapd know of peer reconnection.");
+            runtime.record_swap_reconnect(swap_id);
             event.complete_ctl_service(swap_service_id, CtlMsg::PeerdReconnected(source))?;
             Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
                 peerd,
@@ -1444,6 +1663,7 @@ fn attempt_transition_to_end(
         {
             let swap_service_id = ServiceId::Swap(swap_id);
             log_helper.log_debug("Letting swapd know of peer reconnection.");
+            runtime.record_swap_reconnect(swap_id);
             event.complete_ctl_service(swap_service_id, CtlMsg::PeerdReconnected(source))?;
             Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
                 peerd,
@@ -1468,8 +1688,41 @@ fn attempt_transition_to_end(
                 runtime
                     .stats
                     .incr_awaiting_funding(&Blockchain::Bitcoin, swap_id);
+                runtime.notify(
+                    swap_id,
+                    trade_role,
+                    NotificationEvent::funding_required(&info),
+                );
+                runtime
+                    .notify_node_event(event.endpoints, NodeEvent::FundingRequired(info.clone()));
                 let network = address.network.into();
-                if let Some(auto_fund_config) = runtime.config.get_auto_funding_config(network) {
+                if runtime.hot_wallet_network == Some(network)
+                    && runtime.config.is_hot_wallet_auto_funding(network)
+                {
+                    log_helper.log_info("Attempting to auto-fund Bitcoin from the hot wallet");
+                    event.send_ctl_service(
+                        ServiceId::Wallet,
+                        CtlMsg::FundFromHotWallet(BitcoinFundingInfo {
+                            swap_id,
+                            address: address.clone(),
+                            amount,
+                        }),
+                    )?;
+                    Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
+                        peerd,
+                        deal,
+                        swap_id,
+                        arbitrating_syncer,
+                        accordant_syncer,
+                        funding_info: Some(info),
+                        auto_funded: true,
+                        clients_awaiting_connect_result,
+                        trade_role,
+                        expected_counterparty_node_id,
+                    })))
+                } else if let Some(auto_fund_config) =
+                    runtime.config.get_auto_funding_config(network)
+                {
                     log_helper.log_info("Attempting to auto-fund Bitcoin");
                     log_helper.log_debug(format!("Auto funding config: {:#?}", auto_fund_config));
 
@@ -1561,6 +1814,13 @@ fn attempt_transition_to_end(
                 runtime
                     .stats
                     .incr_awaiting_funding(&Blockchain::Monero, swap_id);
+                runtime.notify(
+                    swap_id,
+                    trade_role,
+                    NotificationEvent::funding_required(&info),
+                );
+                runtime
+                    .notify_node_event(event.endpoints, NodeEvent::FundingRequired(info.clone()));
                 let network = address.network.into();
                 if let Some(auto_fund_config) = runtime.config.get_auto_funding_config(network) {
                     log_helper.log_info("Attempting to auto-fund Monero");
@@ -1638,6 +1898,25 @@ fn attempt_transition_to_end(
             }
         },
 
+        (BusMsg::Ctl(CtlMsg::HotWalletFundingFailed(_, err)), _) => {
+            log_helper.log_error(format!(
+                "Auto-funding Bitcoin from the hot wallet failed, pushing to cli, use `swap-cli needs-funding Bitcoin` to retrieve address and amount: {}",
+                err
+            ));
+            Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
+                peerd,
+                deal,
+                swap_id,
+                arbitrating_syncer,
+                accordant_syncer,
+                funding_info,
+                auto_funded: false,
+                clients_awaiting_connect_result,
+                trade_role,
+                expected_counterparty_node_id,
+            })))
+        }
+
         (BusMsg::Ctl(CtlMsg::FundingCompleted(blockchain)), _) => {
             runtime.stats.incr_funded(&blockchain, &swap_id);
             log_helper.log_info(format!("Your {} funding completed", blockchain.label()));
@@ -1657,6 +1936,16 @@ fn attempt_transition_to_end(
 
         (BusMsg::Ctl(CtlMsg::FundingCanceled(blockchain)), _) => {
             runtime.stats.incr_funding_canceled(&blockchain, &swap_id);
+            record_stats(
+                &mut event,
+                &log_helper,
+                StatsEvent::new(
+                    swap_id,
+                    &deal,
+                    trade_role,
+                    StatsEventKind::FundingCanceled(blockchain),
+                ),
+            );
             log_helper.log_info(format!("Your {} funding was canceled.", blockchain.label()));
             Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
                 peerd,
@@ -1713,14 +2002,18 @@ fn attempt_transition_to_end(
             })))
         }
 
-        // A ConnectSuccess event can only come from a peerd connecting to a listener (maker)
+        // A ConnectSuccess event can only come from a peerd connecting to a listener (maker), or
+        // dialing the counterparty of a restored swap
         (BusMsg::Ctl(CtlMsg::ConnectSuccess), source)
-            if source.node_addr() == Some(node_addr_from_deal(&deal)) =>
+            if source.node_addr() == Some(node_addr_from_deal(&deal))
+                || (peerd.is_none()
+                    && source.node_id().is_some()
+                    && source.node_id() == expected_counterparty_node_id) =>
         {
             for client in clients_awaiting_connect_result.drain(..) {
                 event.send_client_ctl(client, CtlMsg::ConnectSuccess)?;
             }
-            runtime.handle_new_connection(source.clone());
+            runtime.handle_new_connection(event.endpoints, source.clone());
             Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
                 peerd: Some(source),
                 deal,
@@ -1735,9 +2028,13 @@ fn attempt_transition_to_end(
             })))
         }
 
-        // A ConnectFailed event can only come from a peerd connecting to a listener (maker)
+        // A ConnectFailed event can only come from a peerd connecting to a listener (maker), or
+        // dialing the counterparty of a restored swap
         (BusMsg::Ctl(CtlMsg::ConnectFailed), source)
-            if source.node_addr() == Some(node_addr_from_deal(&deal)) =>
+            if source.node_addr() == Some(node_addr_from_deal(&deal))
+                || (peerd.is_none()
+                    && source.node_id().is_some()
+                    && source.node_id() == expected_counterparty_node_id) =>
         {
             for client in clients_awaiting_connect_result.drain(..) {
                 event.send_client_ctl(
@@ -1750,7 +2047,7 @@ fn attempt_transition_to_end(
             }
             runtime.handle_failed_connection(event.endpoints, source.clone())?;
             Ok(Some(TradeStateMachine::SwapdRunning(SwapdRunning {
-                peerd: Some(source),
+                peerd,
                 deal,
                 swap_id,
                 arbitrating_syncer,
@@ -1793,15 +2090,41 @@ fn attempt_transition_to_end(
         (BusMsg::Ctl(CtlMsg::SwapOutcome(outcome)), source)
             if ServiceId::Swap(swap_id) == source =>
         {
+            record_stats(
+                &mut event,
+                &log_helper,
+                StatsEvent::new(
+                    swap_id,
+                    &deal,
+                    trade_role,
+                    StatsEventKind::ended(&deal, outcome.clone()),
+                ),
+            );
+            if outcome == Outcome::FailurePunish {
+                runtime.notify(swap_id, trade_role, NotificationEvent::Punished);
+            }
+            runtime.notify(
+                swap_id,
+                trade_role,
+                NotificationEvent::ended(&deal, outcome.clone()),
+            );
+            runtime.notify_node_event(
+                event.endpoints,
+                NodeEvent::SwapEnded(swap_id, outcome.clone()),
+            );
             event.send_ctl_service(
                 ServiceId::Database,
                 CtlMsg::SetDealInfo(DealInfo {
                     serialized_deal: deal.to_string(),
+                    ephemeral_node_id: runtime.deal_secret_key(&deal).is_some(),
                     deal,
                     status: DealStatus::Ended(outcome.clone()),
                     local_trade_role: trade_role,
                 }),
             )?;
+            if trade_role == TradeRole::Maker {
+                runtime.stop_listening_for_deal(&deal)?;
+            }
             runtime.clean_up_after_swap(&swap_id, event.endpoints)?;
             runtime.stats.incr_outcome(&outcome);
             match outcome {
@@ -1850,9 +2173,74 @@ fn attempt_transition_to_end(
     }
 }
 
+/// Record a swap stats event in databased, the stats are informational and a failure to record
+/// them does not stop the swap
+fn record_stats(event: &mut Event, log_helper: &LogHelper, stats_event: StatsEvent) {
+    if let Err(err) = event.send_ctl_service(ServiceId::Database, CtlMsg::RecordStats(stats_event))
+    {
+        log_helper.log_warn(format!("Failed to record the swap stats: {}", err));
+    }
+}
+
 fn node_addr_from_deal(deal: &Deal) -> NodeAddr {
     NodeAddr {
         id: NodeId::from(deal.node_id), // node_id is bitcoin::Pubkey
         addr: deal.peer_address,        // peer_address is InetSocketAddr
     }
 }
+
+/// Validate the per-swap parameters against the deal timelocks
+fn validate_swap_params(
+    runtime: &Runtime,
+    deal_parameters: &DealParameters,
+    swap_params: &SwapParams,
+) -> Result<(), Error> {
+    let swap_config = runtime
+        .config
+        .get_swap_config(
+            deal_parameters.arbitrating_blockchain.try_into()?,
+            deal_parameters.accordant_blockchain.try_into()?,
+            deal_parameters.network,
+        )?
+        .with_params(swap_params);
+    TemporalSafety::new(deal_parameters, &swap_config).valid_params()
+}
+
+#[test]
+fn taker_commit_consumes_deal_as_maker() {
+    use farcaster_core::bitcoin::BitcoinSegwitV0;
+    use farcaster_core::blockchain::FeePriority;
+    use farcaster_core::crypto::CommitmentEngine;
+    use farcaster_core::monero::Monero;
+    use farcaster_core::swap::btcxmr::{Bob, KeyManager};
+
+    let deal = Deal::from_str("Deal:Cke4ftrP5A781Vq85dgBQJNwYgBS4nuUV1LQM2fvVdFMNR4h5TrWhRR11111uMFuZTAsNgpdK8DiK11111TB9zym113GTvtvqfD1111114A4TTfFfmZoWyvpcjDBtTZCdWFSUWcRKYfEC3Y17hqaXZ3dWz11111111111111111111111111111111111111111AfZ113SEBTEspU3a").unwrap();
+    let target_bitcoin_address =
+        bitcoin::Address::from_str("bcrt1q3rc4sm3w9fr6a46n08znfjt7eu2yhhel6j8rsa").unwrap();
+    let target_monero_address = monero::Address::from_str("44CpGC77Kn6exUWYCUwfaUYmDeKn7MyRcNPikgeHBCz8M6LXUC3fGCWNMW7UACHyTL6QxzqKxvJbu5o2VESLzCaeNHNUkwv").unwrap();
+    let mut key_manager = KeyManager::new([1u8; 32], 1).unwrap();
+    let bob = Bob::new(
+        BitcoinSegwitV0::new(),
+        Monero,
+        target_bitcoin_address.clone(),
+        FeePriority::Low,
+    );
+    let commit = bob
+        .generate_parameters(&mut key_manager, &deal)
+        .unwrap()
+        .commit_bob(SwapId(Uuid::new()), &CommitmentEngine);
+
+    // the maker is the one receiving the taker commit, its in progress swaps count against the
+    // acceptance limits with the maker's swap role
+    let tsm = TradeStateMachine::TakerCommit(TakerCommit {
+        peerd: ServiceId::Peer(0, node_addr_from_deal(&deal)),
+        deal: deal.clone(),
+        commit: Commit::BobParameters(commit),
+        target_bitcoin_address,
+        target_monero_address,
+        swap_params: SwapParams::default(),
+    });
+    assert_eq!(tsm.trade_role(), Some(TradeRole::Maker));
+    assert_eq!(tsm.consumed_deal(), Some((deal.clone(), TradeRole::Maker)));
+    assert_eq!(tsm.swap_role(), Some(deal.swap_role(&TradeRole::Maker)));
+}
@@ -0,0 +1,319 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Authentication of the grpc clients with macaroon-style bearer tokens.
+//!
+//! A token is made of a random identifier, the permissions it grants and a signature chaining
+//! HMAC-SHA256 from the root key of grpcd over the identifier, then over the permissions caveat,
+//! as macaroons do. Only grpcd knows the root key, so a token cannot be forged nor its permissions
+//! extended. grpcd also keeps the list of the issued identifiers, removing an identifier revokes
+//! its token.
+//!
+//! The root key, the issued identifiers and the default tokens are stored in the `grpc` folder of
+//! the data directory. The default tokens are created on start when missing or revoked:
+//! `readonly.token` grants read, `trade.token` read and trade, and `admin.token` read, trade and
+//! funds. Clients send their token in the `authorization` header as `Bearer <token>`, for gRPC and
+//! grpc-web alike.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::rand::{thread_rng, RngCore};
+use strict_encoding::{NetworkDecode, NetworkEncode};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::bus::info::GrpcToken;
+use crate::Error;
+
+const GRPC_DIR: &str = "grpc";
+const ROOT_KEY_FILE: &str = "root.key";
+const ISSUED_TOKENS_FILE: &str = "tokens";
+const TOKEN_ID_LEN: usize = 16;
+const TOKEN_LEN: usize = TOKEN_ID_LEN + 1 + 32;
+
+/// Default tokens created in the grpc folder, with their permissions
+const DEFAULT_TOKENS: [(&str, &[Permission]); 3] = [
+    ("readonly.token", &[Permission::Read]),
+    ("trade.token", &[Permission::Read, Permission::Trade]),
+    (
+        "admin.token",
+        &[Permission::Read, Permission::Trade, Permission::Funds],
+    ),
+];
+
+/// Permission granted by a token, every grpc call requires one of them
+#[derive(
+    Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, NetworkEncode, NetworkDecode,
+)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum Permission {
+    /// Query the node, its deals and swaps
+    #[display("read")]
+    Read,
+    /// Make, take, revoke deals and manage the swaps
+    #[display("trade")]
+    Trade,
+    /// Move funds out of the node, i.e. sweep addresses
+    #[display("funds")]
+    Funds,
+}
+
+impl Permission {
+    fn flag(self) -> u8 {
+        match self {
+            Permission::Read => 1,
+            Permission::Trade => 1 << 1,
+            Permission::Funds => 1 << 2,
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = PermissionParseError;
+    fn from_str(input: &str) -> Result<Permission, Self::Err> {
+        match input {
+            "read" | "Read" => Ok(Permission::Read),
+            "trade" | "Trade" => Ok(Permission::Trade),
+            "funds" | "Funds" => Ok(Permission::Funds),
+            _ => Err(PermissionParseError::Invalid),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum PermissionParseError {
+    /// The provided value can't be parsed as a permission, expected read, trade or funds
+    Invalid,
+}
+
+/// Permissions granted to a grpc request, inserted in its extensions by the [`Authenticator`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Permissions(u8);
+
+impl Permissions {
+    /// Permissions of the requests when authentication is disabled
+    pub fn all() -> Self {
+        Permissions::from(&[Permission::Read, Permission::Trade, Permission::Funds][..])
+    }
+
+    pub fn contains(&self, permission: Permission) -> bool {
+        self.0 & permission.flag() != 0
+    }
+
+    fn to_vec(self) -> Vec<Permission> {
+        [Permission::Read, Permission::Trade, Permission::Funds]
+            .iter()
+            .copied()
+            .filter(|permission| self.contains(*permission))
+            .collect()
+    }
+}
+
+impl From<&[Permission]> for Permissions {
+    fn from(permissions: &[Permission]) -> Self {
+        Permissions(permissions.iter().fold(0, |flags, p| flags | p.flag()))
+    }
+}
+
+/// Root key and issued tokens of grpcd
+#[derive(Debug)]
+pub struct TokenStore {
+    dir: PathBuf,
+    root_key: [u8; 32],
+    issued: BTreeMap<String, Permissions>,
+}
+
+impl TokenStore {
+    /// Load the token store from the data directory, the root key is created on first use and the
+    /// default tokens are created if missing or revoked
+    pub fn open(data_dir: &Path) -> Result<Self, Error> {
+        let dir = data_dir.join(GRPC_DIR);
+        fs::create_dir_all(&dir)?;
+
+        let root_key_path = dir.join(ROOT_KEY_FILE);
+        let root_key = if root_key_path.exists() {
+            <[u8; 32]>::from_hex(fs::read_to_string(&root_key_path)?.trim())
+                .map_err(|err| Error::Farcaster(format!("Invalid grpc root key: {}", err)))?
+        } else {
+            let mut root_key = [0u8; 32];
+            thread_rng().fill_bytes(&mut root_key);
+            write_private(&root_key_path, &root_key.to_hex())?;
+            root_key
+        };
+
+        let issued_path = dir.join(ISSUED_TOKENS_FILE);
+        let issued = if issued_path.exists() {
+            fs::read_to_string(&issued_path)?
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(id, flags)| Ok((id.to_string(), Permissions(u8::from_str(flags)?))))
+                .collect::<Result<_, std::num::ParseIntError>>()
+                .map_err(|err| Error::Farcaster(format!("Invalid grpc tokens file: {}", err)))?
+        } else {
+            BTreeMap::new()
+        };
+
+        let mut store = TokenStore {
+            dir,
+            root_key,
+            issued,
+        };
+        for (file, permissions) in DEFAULT_TOKENS {
+            let path = store.dir.join(file);
+            let valid = path.exists() && store.verify(fs::read_to_string(&path)?.trim()).is_some();
+            if !valid {
+                let token = store.create(permissions)?;
+                write_private(&path, &token.token.unwrap_or_default())?;
+                info!("Created grpc token {}", path.display());
+            }
+        }
+        Ok(store)
+    }
+
+    /// Issue a new token granting the permissions
+    pub fn create(&mut self, permissions: &[Permission]) -> Result<GrpcToken, Error> {
+        let mut id = [0u8; TOKEN_ID_LEN];
+        thread_rng().fill_bytes(&mut id);
+        let permissions = Permissions::from(permissions);
+        let mut token = Vec::with_capacity(TOKEN_LEN);
+        token.extend_from_slice(&id);
+        token.push(permissions.0);
+        token.extend_from_slice(&self.sign(&id, permissions));
+
+        self.issued.insert(id.to_hex(), permissions);
+        self.save()?;
+        Ok(GrpcToken {
+            id: id.to_hex(),
+            permissions: permissions.to_vec(),
+            token: Some(token.to_hex()),
+        })
+    }
+
+    /// Revoke the token with the identifier, returns false if no such token was issued
+    pub fn revoke(&mut self, id: &str) -> Result<bool, Error> {
+        if self.issued.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// The issued tokens, without their secret part
+    pub fn list(&self) -> Vec<GrpcToken> {
+        self.issued
+            .iter()
+            .map(|(id, permissions)| GrpcToken {
+                id: id.clone(),
+                permissions: permissions.to_vec(),
+                token: None,
+            })
+            .collect()
+    }
+
+    /// Returns the permissions granted by the token if it was issued by grpcd and not revoked
+    pub fn verify(&self, token: &str) -> Option<Permissions> {
+        let token = Vec::<u8>::from_hex(token).ok()?;
+        if token.len() != TOKEN_LEN {
+            return None;
+        }
+        let (id, rest) = token.split_at(TOKEN_ID_LEN);
+        let permissions = Permissions(rest[0]);
+        let signature = self.sign(id, permissions);
+        // compare in constant time
+        let diff = signature
+            .iter()
+            .zip(&rest[1..])
+            .fold(0, |diff, (a, b)| diff | (a ^ b));
+        match self.issued.get(&id.to_hex()) {
+            Some(issued) if diff == 0 && *issued == permissions => Some(permissions),
+            _ => None,
+        }
+    }
+
+    /// Chain the HMAC from the root key over the identifier then the permissions caveat
+    fn sign(&self, id: &[u8], permissions: Permissions) -> [u8; 32] {
+        let mut engine = HmacEngine::<sha256::Hash>::new(&self.root_key);
+        engine.input(id);
+        let signature = Hmac::<sha256::Hash>::from_engine(engine);
+        let mut engine = HmacEngine::<sha256::Hash>::new(&signature[..]);
+        engine.input(format!("permissions = {}", permissions.0).as_bytes());
+        Hmac::<sha256::Hash>::from_engine(engine).into_inner()
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let issued: String = self
+            .issued
+            .iter()
+            .map(|(id, permissions)| format!("{} {}\n", id, permissions.0))
+            .collect();
+        write_private(&self.dir.join(ISSUED_TOKENS_FILE), &issued)
+    }
+}
+
+/// Writes the file readable only by the owner
+fn write_private(path: &Path, content: &str) -> Result<(), Error> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/// Interceptor checking the bearer token of every grpc request and inserting the granted
+/// [`Permissions`] in the request extensions. Every request is granted all permissions when
+/// authentication is disabled.
+#[derive(Clone, Debug)]
+pub struct Authenticator(pub Option<Arc<Mutex<TokenStore>>>);
+
+impl Interceptor for Authenticator {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let permissions = match &self.0 {
+            None => Permissions::all(),
+            Some(store) => {
+                let token = request
+                    .metadata()
+                    .get("authorization")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.strip_prefix("Bearer "))
+                    .ok_or_else(|| Status::unauthenticated("Missing bearer token"))?;
+                store
+                    .lock()
+                    .expect("grpc token store lock poisoned")
+                    .verify(token.trim())
+                    .ok_or_else(|| Status::unauthenticated("Invalid or revoked token"))?
+            }
+        };
+        // the token must not show up in the logged requests
+        request.metadata_mut().remove("authorization");
+        request.extensions_mut().insert(permissions);
+        Ok(request)
+    }
+}
+
+/// Fails if the request was not granted the permission by the [`Authenticator`]
+pub fn authorize<T>(request: &Request<T>, permission: Permission) -> Result<(), Status> {
+    match request.extensions().get::<Permissions>() {
+        Some(granted) if granted.contains(permission) => Ok(()),
+        _ => Err(Status::permission_denied(format!(
+            "The token does not grant the {} permission",
+            permission
+        ))),
+    }
+}
@@ -4,6 +4,8 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::path::PathBuf;
+
 /// Grpcd daemon; part of Farcaster Node
 #[derive(Parser, Clone, PartialEq, Eq, Debug)]
 #[clap(name = "grpcd", bin_name = "grpcd", author, version)]
@@ -20,10 +22,19 @@ pub struct Opts {
     /// Ip that the grpc server is accepting connections on
     #[clap(long)]
     pub grpc_ip: String,
+
+    /// Do not require the grpc clients to authenticate with a token, every client is granted all
+    /// permissions
+    #[clap(long)]
+    pub no_auth: bool,
 }
 
 impl Opts {
     pub fn process(&mut self) {
         self.shared.process();
     }
+
+    pub fn absolute_data_dir_path(&self) -> PathBuf {
+        PathBuf::from(shellexpand::tilde(&self.shared.data_dir.to_string_lossy()).to_string())
+    }
 }
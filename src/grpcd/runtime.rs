@@ -5,11 +5,15 @@
 // https://opensource.org/licenses/MIT.
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
 use farcaster_core::bitcoin::{fee::SatPerKvB, timelock::CSVTimelock};
 use farcaster_core::blockchain::{Blockchain, FeeStrategy, Network};
@@ -24,21 +28,29 @@ use internet2::{
 use microservices::esb;
 use microservices::ZMQ_CONTEXT;
 use tokio::runtime::Builder;
+use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::oneshot::error::RecvError;
 use tokio::sync::Mutex;
+use tonic::codegen::futures_core::Stream;
 use tonic::{transport::Server, Request as GrpcRequest, Response as GrpcResponse, Status};
 use uuid::Uuid;
 
 use crate::bus::bridge::BridgeMsg;
 use crate::bus::ctl::{FundingInfo, ProtoDeal, PubDeal};
 use crate::bus::info::AddressBalance;
-use crate::bus::info::{Address, DealStatusSelector, ProgressEvent};
+use crate::bus::info::{Address, DealStatusSelector, NodeEvent, ProgressEvent};
 use crate::bus::{ctl::CtlMsg, info::InfoMsg, info::SwapInfo};
 use crate::bus::{
-    AddressSecretKey, DealStatus, Failure, HealthCheckSelector, OptionDetails, Outcome,
+    AddressSecretKey, DealStatus, Failure, FailureCode, HealthCheckSelector, OptionDetails,
+    Outcome, SwapParams,
 };
 use crate::bus::{BusMsg, ServiceBus};
+use crate::farcasterd::acceptance_policy::AcceptancePolicyReport;
+use crate::farcasterd::deal_validation;
+use crate::farcasterd::stats;
+use crate::grpcd::auth::{authorize, Authenticator, Permission, TokenStore};
 use crate::grpcd::runtime::farcaster::NetworkSelector;
+use crate::service::exit_service;
 use crate::service::Endpoints;
 use crate::swapd::StateReport;
 use crate::syncerd::{Health, SweepAddressAddendum, SweepBitcoinAddress, SweepMoneroAddress};
@@ -167,6 +179,33 @@ impl From<Outcome> for farcaster::Outcome {
     }
 }
 
+impl From<SwapParams> for farcaster::SwapParams {
+    fn from(p: SwapParams) -> farcaster::SwapParams {
+        farcaster::SwapParams {
+            arb_finality: p.arb_finality.map(u32::from),
+            arb_safety: p.arb_safety.map(u32::from),
+            acc_finality: p.acc_finality.map(u32::from),
+        }
+    }
+}
+
+impl TryFrom<farcaster::SwapParams> for SwapParams {
+    type Error = Status;
+
+    fn try_from(p: farcaster::SwapParams) -> Result<SwapParams, Status> {
+        let parse = |value: Option<u32>, name: &str| {
+            value
+                .map(|v| u8::try_from(v).map_err(|_| Status::invalid_argument(name)))
+                .transpose()
+        };
+        Ok(SwapParams {
+            arb_finality: parse(p.arb_finality, "arbitrating finality")?,
+            arb_safety: parse(p.arb_safety, "arbitrating safety")?,
+            acc_finality: parse(p.acc_finality, "accordant finality")?,
+        })
+    }
+}
+
 impl From<Deal> for DeserializedDeal {
     fn from(deal: Deal) -> DeserializedDeal {
         DeserializedDeal {
@@ -207,12 +246,18 @@ impl From<DealStatus> for farcaster::DealStatus {
 }
 
 impl DealInfo {
-    fn new(deal: Deal, local_trade_role: TradeRole, status: DealStatus) -> DealInfo {
+    fn new(
+        deal: Deal,
+        local_trade_role: TradeRole,
+        status: DealStatus,
+        ephemeral_node_id: bool,
+    ) -> DealInfo {
         DealInfo {
             serialized_deal: deal.to_string(),
             deserialized_deal: Some(deal.into()),
             local_trade_role: farcaster::TradeRole::from(local_trade_role).into(),
             deal_status: farcaster::DealStatus::from(status).into(),
+            ephemeral_node_id,
         }
     }
 }
@@ -250,10 +295,81 @@ impl From<StateReport> for farcaster::State {
             blocks_until_safe_monero_buy_sweep: state_report
                 .blocks_until_safe_monero_buy_sweep
                 .map(farcaster::state::BlocksUntilSafeMoneroBuySweep::BuyMoneroBlocks),
+            lock_fee: state_report.lock_fee.map(|fee| fee.to_string()),
+            cancel_fee: state_report.cancel_fee.map(|fee| fee.to_string()),
+            refund_fee: state_report.refund_fee.map(|fee| fee.to_string()),
+            buy_fee: state_report.buy_fee.map(|fee| fee.to_string()),
+            punish_fee: state_report.punish_fee.map(|fee| fee.to_string()),
+            sweep_fee: state_report.sweep_fee.map(|fee| fee.to_string()),
         }
     }
 }
 
+impl From<ProgressEvent> for farcaster::Progress {
+    fn from(p: ProgressEvent) -> farcaster::Progress {
+        let progress = match p {
+            ProgressEvent::Message(m) => farcaster::progress::Progress::Message(m),
+            ProgressEvent::StateUpdate(su) => farcaster::progress::Progress::StateUpdate(su.into()),
+            ProgressEvent::StateTransition(st) => {
+                farcaster::progress::Progress::StateTransition(farcaster::StateTransition {
+                    old_state: Some(st.old_state.into()),
+                    new_state: Some(st.new_state.into()),
+                })
+            }
+            ProgressEvent::Failure(Failure { info, .. }) => {
+                farcaster::progress::Progress::Failure(info)
+            }
+            ProgressEvent::Success(OptionDetails(s)) => {
+                farcaster::progress::Progress::Success(s.unwrap_or_default())
+            }
+        };
+        farcaster::Progress {
+            progress: Some(progress),
+        }
+    }
+}
+
+impl From<NodeEvent> for farcaster::NodeEvent {
+    fn from(e: NodeEvent) -> farcaster::NodeEvent {
+        let syncer = |blockchain: Blockchain, network: Network| SyncerService {
+            blockchain: farcaster::Blockchain::from(blockchain).into(),
+            network: farcaster::Network::from(network).into(),
+        };
+        let event = match e {
+            NodeEvent::DealMade(deal) => node_event::Event::DealMade(DealMade {
+                deal: deal.to_string(),
+                deserialized_deal: Some(deal.into()),
+            }),
+            NodeEvent::FundingRequired(info) => node_event::Event::FundingRequired(info.into()),
+            NodeEvent::SwapStarted(swap_id) => node_event::Event::SwapStarted(swap_id.to_string()),
+            NodeEvent::SwapEnded(swap_id, outcome) => node_event::Event::SwapEnded(SwapEnded {
+                swap_id: swap_id.to_string(),
+                outcome: farcaster::Outcome::from(outcome).into(),
+            }),
+            NodeEvent::PeerConnected(node_addr) => {
+                node_event::Event::PeerConnected(node_addr.to_string())
+            }
+            NodeEvent::PeerDisconnected(node_addr) => {
+                node_event::Event::PeerDisconnected(node_addr.to_string())
+            }
+            NodeEvent::SyncerConnected(blockchain, network) => {
+                node_event::Event::SyncerConnected(syncer(blockchain, network))
+            }
+            NodeEvent::SyncerDisconnected(blockchain, network) => {
+                node_event::Event::SyncerDisconnected(syncer(blockchain, network))
+            }
+            NodeEvent::SyncerHealth(blockchain, network, health) => {
+                node_event::Event::SyncerHealth(SyncerHealth {
+                    blockchain: farcaster::Blockchain::from(blockchain).into(),
+                    network: farcaster::Network::from(network).into(),
+                    health: health.to_string(),
+                })
+            }
+        };
+        farcaster::NodeEvent { event: Some(event) }
+    }
+}
+
 impl From<farcaster::NetworkSelector> for HealthCheckSelector {
     fn from(s: farcaster::NetworkSelector) -> Self {
         match s {
@@ -271,6 +387,35 @@ impl From<farcaster::NetworkSelector> for HealthCheckSelector {
     }
 }
 
+impl From<crate::farcasterd::supervisor::CrashReport> for CrashReport {
+    fn from(c: crate::farcasterd::supervisor::CrashReport) -> Self {
+        CrashReport {
+            service: c.service,
+            crashes: c.crashes,
+            restarts: c.restarts,
+            last_exit_status: c.last_exit_status,
+        }
+    }
+}
+
+impl From<crate::bus::info::PeerInfo> for PeerInfo {
+    fn from(p: crate::bus::info::PeerInfo) -> Self {
+        PeerInfo {
+            peer: String::new(),
+            uptime: p.uptime.as_secs(),
+            connection_uptime: p.connection_uptime.as_secs(),
+            reconnects: p.reconnects,
+            messages_sent: p.messages_sent as u64,
+            messages_received: p.messages_received as u64,
+            bytes_sent: p.bytes_sent,
+            bytes_received: p.bytes_received,
+            rtt: p.rtt.map(|rtt| rtt.as_millis() as u64),
+            average_rtt: p.average_rtt.map(|rtt| rtt.as_millis() as u64),
+            limit_violations: p.limit_violations,
+        }
+    }
+}
+
 impl From<crate::farcasterd::stats::Stats> for Stats {
     fn from(s: crate::farcasterd::stats::Stats) -> Self {
         Stats {
@@ -293,6 +438,85 @@ impl From<crate::farcasterd::stats::Stats> for Stats {
             funded_btc: s.funded_btc,
             funding_canceled_xmr: s.funding_canceled_xmr,
             funding_canceled_btc: s.funding_canceled_btc,
+            peer_limit_violations: s.peer_limit_violations,
+        }
+    }
+}
+
+impl From<AcceptancePolicyReport> for AcceptancePolicyResponse {
+    fn from(p: AcceptancePolicyReport) -> Self {
+        AcceptancePolicyResponse {
+            id: 0,
+            max_concurrent_swaps: p.max_concurrent_swaps,
+            max_concurrent_swaps_per_peer: p.max_concurrent_swaps_per_peer,
+            max_btc_exposure: p.max_btc_exposure.map(|amount| amount.as_sat()),
+            max_xmr_exposure: p.max_xmr_exposure.map(|amount| amount.as_pico()),
+            allow_list_enabled: p.allowed_node_ids.is_some(),
+            allowed_node_ids: p
+                .allowed_node_ids
+                .iter()
+                .flatten()
+                .map(|node_id| node_id.to_string())
+                .collect(),
+            denied_node_ids: p
+                .denied_node_ids
+                .iter()
+                .map(|node_id| node_id.to_string())
+                .collect(),
+            trading_hours: p.trading_hours,
+            concurrent_swaps: p.concurrent_swaps,
+            btc_exposure: p.btc_exposure.as_sat(),
+            xmr_exposure: p.xmr_exposure.as_pico(),
+            refused: p.refused,
+        }
+    }
+}
+
+impl From<deal_validation::ValidationCheck> for farcaster::ValidationCheck {
+    fn from(c: deal_validation::ValidationCheck) -> Self {
+        let status: farcaster::CheckStatus = match c.status {
+            deal_validation::CheckStatus::Passed => farcaster::CheckStatus::Passed,
+            deal_validation::CheckStatus::Failed => farcaster::CheckStatus::Failed,
+            deal_validation::CheckStatus::Skipped => farcaster::CheckStatus::Skipped,
+        };
+        farcaster::ValidationCheck {
+            check: c.check,
+            status: status.into(),
+            details: c.details,
+        }
+    }
+}
+
+impl From<farcaster::StatsGrouping> for stats::StatsGrouping {
+    fn from(t: farcaster::StatsGrouping) -> stats::StatsGrouping {
+        match t {
+            farcaster::StatsGrouping::Day => stats::StatsGrouping::Day,
+            farcaster::StatsGrouping::Month => stats::StatsGrouping::Month,
+            farcaster::StatsGrouping::Total => stats::StatsGrouping::Total,
+        }
+    }
+}
+
+impl From<stats::StatsBucket> for farcaster::StatsBucket {
+    fn from(b: stats::StatsBucket) -> Self {
+        farcaster::StatsBucket {
+            period: b.period,
+            network: farcaster::Network::from(b.network).into(),
+            swap_role: farcaster::SwapRole::from(b.swap_role).into(),
+            initiated: b.initiated,
+            success: b.success,
+            refund: b.refund,
+            punish: b.punish,
+            abort: b.abort,
+            funding_canceled_btc: b.funding_canceled_btc,
+            funding_canceled_xmr: b.funding_canceled_xmr,
+            volume_btc: b.volume_btc.as_sat(),
+            volume_xmr: b.volume_xmr.as_pico(),
+            fees_btc: b.fees_btc.as_sat(),
+            mean_success_secs: b.mean_success_secs,
+            mean_refund_secs: b.mean_refund_secs,
+            mean_punish_secs: b.mean_punish_secs,
+            mean_abort_secs: b.mean_abort_secs,
         }
     }
 }
@@ -308,9 +532,16 @@ impl IdCounter {
     }
 }
 
+/// Maximum number of messages pushed on a subscription waiting to be streamed to the client
+const SUBSCRIPTION_BUFFER: usize = 100;
+
 pub struct FarcasterService {
     tokio_tx_request: tokio::sync::mpsc::Sender<(u64, BusMsg)>,
+    // Unsubscribe requests are sent from the drop of a subscription, which cannot wait for room
+    // in the request channel
+    tokio_tx_unsubscribe: tokio::sync::mpsc::UnboundedSender<(u64, BusMsg)>,
     pending_requests: Arc<Mutex<HashMap<u64, tokio::sync::oneshot::Sender<BusMsg>>>>,
+    subscriptions: Arc<Mutex<HashMap<u64, tokio::sync::mpsc::Sender<BusMsg>>>>,
     id_counter: Arc<Mutex<IdCounter>>,
 }
 
@@ -334,6 +565,45 @@ impl FarcasterService {
         Ok(oneshot_rx)
     }
 
+    /// Subscribe to the messages pushed by farcasterd, the subscription request and all pushed
+    /// messages share the same grpcd client id. The messages are converted into the items of the
+    /// returned stream, farcasterd is requested to unsubscribe once the stream is dropped.
+    async fn subscribe<T>(
+        &self,
+        request_id: u32,
+        subscribe: InfoMsg,
+        unsubscribe: InfoMsg,
+        convert: fn(u32, BusMsg) -> Option<Result<T, Status>>,
+    ) -> Result<Subscription<T>, Status> {
+        let mut id_counter = self.id_counter.lock().await;
+        let id = id_counter.increment();
+        drop(id_counter);
+
+        // register the subscription first, farcasterd may push messages as soon as subscribed
+        let (tx, rx) = tokio::sync::mpsc::channel::<BusMsg>(SUBSCRIPTION_BUFFER);
+        let mut subscriptions = self.subscriptions.lock().await;
+        subscriptions.insert(id, tx);
+        drop(subscriptions);
+
+        let request = BusMsg::Bridge(BridgeMsg::Info {
+            request: subscribe,
+            service_id: ServiceId::Farcasterd,
+        });
+        if let Err(error) = self.tokio_tx_request.send((id, request)).await {
+            self.subscriptions.lock().await.remove(&id);
+            return Err(Status::internal(error.to_string()));
+        }
+
+        Ok(Subscription {
+            id,
+            request_id,
+            rx,
+            convert,
+            unsubscribe,
+            tokio_tx_unsubscribe: self.tokio_tx_unsubscribe.clone(),
+        })
+    }
+
     async fn check_health(
         &self,
         blockchain: Blockchain,
@@ -353,6 +623,76 @@ impl FarcasterService {
     }
 }
 
+/// Stream of the messages pushed by farcasterd on a subscription
+pub struct Subscription<T> {
+    id: u64,
+    request_id: u32,
+    rx: tokio::sync::mpsc::Receiver<BusMsg>,
+    convert: fn(u32, BusMsg) -> Option<Result<T, Status>>,
+    unsubscribe: InfoMsg,
+    tokio_tx_unsubscribe: tokio::sync::mpsc::UnboundedSender<(u64, BusMsg)>,
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = Result<T, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.rx.poll_recv(cx) {
+                Poll::Ready(Some(msg)) => {
+                    // messages without a stream item are skipped
+                    if let Some(item) = (self.convert)(self.request_id, msg) {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        // The client disconnected or the stream ended, clean up the subscription in farcasterd.
+        // The channel in the subscriptions is closed and removed by the response loop.
+        let request = BusMsg::Bridge(BridgeMsg::Info {
+            request: self.unsubscribe.clone(),
+            service_id: ServiceId::Farcasterd,
+        });
+        if let Err(err) = self.tokio_tx_unsubscribe.send((self.id, request)) {
+            error!(
+                "Failed to unsubscribe grpc client {}: {}",
+                ServiceId::GrpcdClient(self.id),
+                err
+            );
+        }
+    }
+}
+
+fn progress_item(id: u32, msg: BusMsg) -> Option<Result<SubscribeProgressResponse, Status>> {
+    let progress: ProgressEvent = match msg {
+        BusMsg::Info(InfoMsg::Progress(progress)) => progress.into(),
+        BusMsg::Info(InfoMsg::Success(success)) => ProgressEvent::Success(success),
+        BusMsg::Info(InfoMsg::Failure(failure)) => ProgressEvent::Failure(failure),
+        _ => return None,
+    };
+    Some(Ok(SubscribeProgressResponse {
+        id,
+        progress: Some(progress.into()),
+    }))
+}
+
+fn event_item(id: u32, msg: BusMsg) -> Option<Result<SubscribeEventsResponse, Status>> {
+    match msg {
+        BusMsg::Info(InfoMsg::NodeEvent(event)) => Some(Ok(SubscribeEventsResponse {
+            id,
+            event: Some(event.into()),
+        })),
+        _ => None,
+    }
+}
+
 fn process_error_response<T>(msg: Result<BusMsg, RecvError>) -> Result<GrpcResponse<T>, Status> {
     match msg {
         Err(error) => Err(Status::internal(error.to_string())),
@@ -368,6 +708,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<InfoRequest>,
     ) -> Result<GrpcResponse<InfoResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         debug!("Received a grpc info request: {:?}", request);
         let oneshot_rx = self
             .process_request(BusMsg::Bridge(BridgeMsg::Info {
@@ -390,6 +731,7 @@ impl Farcaster for FarcasterService {
                     swaps: info.swaps.iter().map(|swap| swap.to_string()).collect(),
                     deals: info.deals.iter().map(|deal| deal.to_string()).collect(),
                     stats: Some(info.stats.into()),
+                    crashes: info.crashes.into_iter().map(|c| c.into()).collect(),
                 };
                 Ok(GrpcResponse::new(reply))
             }
@@ -401,6 +743,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<PeersRequest>,
     ) -> Result<GrpcResponse<PeersResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         debug!("Received a grpc peers request: {:?}", request);
         let oneshot_rx = self
             .process_request(BusMsg::Bridge(BridgeMsg::Info {
@@ -410,9 +753,27 @@ impl Farcaster for FarcasterService {
             .await?;
         match oneshot_rx.await {
             Ok(BusMsg::Info(InfoMsg::PeerList(peers))) => {
+                let mut peer_infos = vec![];
+                for peer in peers.iter() {
+                    let oneshot_rx = self
+                        .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                            request: InfoMsg::GetPeerInfo(*peer),
+                            service_id: ServiceId::Farcasterd,
+                        }))
+                        .await?;
+                    // a peer disconnected in the meantime is skipped
+                    match oneshot_rx.await {
+                        Ok(BusMsg::Info(InfoMsg::PeerInfo(info))) => peer_infos.push(PeerInfo {
+                            peer: peer.to_string(),
+                            ..info.into()
+                        }),
+                        res => debug!("No info for peer {}: {:?}", peer, res),
+                    }
+                }
                 let reply = farcaster::PeersResponse {
                     id: request.into_inner().id,
                     peers: peers.iter().map(|peer| format!("{}", peer)).collect(),
+                    peer_infos,
                 };
                 Ok(GrpcResponse::new(reply))
             }
@@ -424,6 +785,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<SwapInfoRequest>,
     ) -> Result<GrpcResponse<SwapInfoResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         debug!("Received a grpc swap info request: {:?}", request);
         let SwapInfoRequest {
             id,
@@ -480,6 +842,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<ListDealsRequest>,
     ) -> Result<GrpcResponse<ListDealsResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         debug!("Received a grpc request: {:?}", request);
         let ListDealsRequest {
             id,
@@ -508,7 +871,9 @@ impl Farcaster for FarcasterService {
                             network_selector == NetworkSelector::AllNetworks
                                 || Some(d.deal.parameters.network) == network_selector.into()
                         })
-                        .map(|d| DealInfo::new(d.deal, d.local_trade_role, d.status))
+                        .map(|d| {
+                            DealInfo::new(d.deal, d.local_trade_role, d.status, d.ephemeral_node_id)
+                        })
                         .collect(),
                 };
                 Ok(GrpcResponse::new(reply))
@@ -522,7 +887,9 @@ impl Farcaster for FarcasterService {
                             network_selector == NetworkSelector::AllNetworks
                                 || Some(d.deal.parameters.network) == network_selector.into()
                         })
-                        .map(|d| DealInfo::new(d.deal, d.local_trade_role, d.status))
+                        .map(|d| {
+                            DealInfo::new(d.deal, d.local_trade_role, d.status, d.ephemeral_node_id)
+                        })
                         .collect(),
                 };
                 Ok(GrpcResponse::new(reply))
@@ -537,6 +904,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<DealInfoRequest>,
     ) -> Result<GrpcResponse<DealInfoResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         debug!("Received a grpc deal info request: {:?}", request);
         let DealInfoRequest {
             id,
@@ -553,10 +921,44 @@ impl Farcaster for FarcasterService {
         Ok(GrpcResponse::new(reply))
     }
 
+    async fn validate_deal(
+        &self,
+        request: GrpcRequest<ValidateDealRequest>,
+    ) -> Result<GrpcResponse<ValidateDealResponse>, Status> {
+        authorize(&request, Permission::Read)?;
+        debug!("Received a grpc validate deal request: {:?}", request);
+        let ValidateDealRequest {
+            id,
+            deal: string_deal,
+        } = request.into_inner();
+        let deal =
+            Deal::from_str(&string_deal).map_err(|_| Status::invalid_argument("deal malformed"))?;
+
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::ValidateDeal(deal),
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::DealValidationReport(report))) => {
+                let reply = ValidateDealResponse {
+                    id,
+                    passed: report.passed(),
+                    enforced: report.enforced,
+                    checks: report.checks.into_iter().map(|c| c.into()).collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
     async fn checkpoints(
         &self,
         request: GrpcRequest<CheckpointsRequest>,
     ) -> Result<GrpcResponse<CheckpointsResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         debug!("Received a grpc checkpoints request: {:?}", request);
         let CheckpointsRequest {
             id,
@@ -625,6 +1027,7 @@ impl Farcaster for FarcasterService {
                                 DealStatus::InProgress,
                             )),
                             trade_role: farcaster::TradeRole::from(entry.trade_role) as i32,
+                            swap_params: Some(entry.swap_params.into()),
                         })
                         .collect(),
                 };
@@ -638,6 +1041,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<RestoreCheckpointRequest>,
     ) -> Result<GrpcResponse<RestoreCheckpointResponse>, Status> {
+        authorize(&request, Permission::Trade)?;
         debug!("Received a grpc restore checkpoints request: {:?}", request);
         let RestoreCheckpointRequest {
             id,
@@ -682,6 +1086,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<FundingAddressesRequest>,
     ) -> Result<GrpcResponse<FundingAddressesResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         let FundingAddressesRequest {
             id,
             blockchain: grpc_blockchain,
@@ -754,6 +1159,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<MakeRequest>,
     ) -> Result<GrpcResponse<MakeResponse>, Status> {
+        authorize(&request, Permission::Trade)?;
         debug!("Received a grpc make request: {:?}", request);
         let MakeRequest {
             id,
@@ -770,6 +1176,7 @@ impl Farcaster for FarcasterService {
             maker_role: grpc_swap_role,
             public_ip_addr: str_public_ip_addr,
             public_port,
+            swap_params: grpc_swap_params,
         } = request.into_inner();
 
         let network: Network = farcaster::Network::from_i32(grpc_network)
@@ -802,6 +1209,11 @@ impl Farcaster for FarcasterService {
                 )
             })?;
 
+        let swap_params = grpc_swap_params
+            .map(SwapParams::try_from)
+            .transpose()?
+            .unwrap_or_default();
+
         let deal_parameters = DealParameters {
             uuid: Uuid::new_v4().into(),
             network,
@@ -820,6 +1232,7 @@ impl Farcaster for FarcasterService {
             public_addr,
             arbitrating_addr,
             accordant_addr,
+            swap_params,
         };
 
         let oneshot_rx = self
@@ -845,6 +1258,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<RevokeDealRequest>,
     ) -> Result<GrpcResponse<RevokeDealResponse>, Status> {
+        authorize(&request, Permission::Trade)?;
         debug!("Received a grpc revoke deal request: {:?}", request);
         let RevokeDealRequest { id, deal: str_deal } = request.into_inner();
 
@@ -869,6 +1283,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<AbortSwapRequest>,
     ) -> Result<GrpcResponse<AbortSwapResponse>, Status> {
+        authorize(&request, Permission::Trade)?;
         debug!("Received a grpc abort swap request: {:?}", request);
         let AbortSwapRequest {
             id,
@@ -896,6 +1311,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<ProgressRequest>,
     ) -> Result<GrpcResponse<ProgressResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         debug!("Received a grpc progress request: {:?}", request);
         let ProgressRequest {
             id,
@@ -915,36 +1331,7 @@ impl Farcaster for FarcasterService {
             Ok(BusMsg::Info(InfoMsg::SwapProgress(mut progress))) => {
                 let reply = ProgressResponse {
                     id,
-                    progress: progress
-                        .progress
-                        .drain(..)
-                        .map(|p| match p {
-                            ProgressEvent::Message(m) => farcaster::Progress {
-                                progress: Some(farcaster::progress::Progress::Message(m)),
-                            },
-                            ProgressEvent::StateUpdate(su) => farcaster::Progress {
-                                progress: Some(farcaster::progress::Progress::StateUpdate(
-                                    su.into(),
-                                )),
-                            },
-                            ProgressEvent::StateTransition(st) => farcaster::Progress {
-                                progress: Some(farcaster::progress::Progress::StateTransition(
-                                    farcaster::StateTransition {
-                                        old_state: Some(st.old_state.into()),
-                                        new_state: Some(st.new_state.into()),
-                                    },
-                                )),
-                            },
-                            ProgressEvent::Failure(Failure { info, .. }) => farcaster::Progress {
-                                progress: Some(farcaster::progress::Progress::Failure(info)),
-                            },
-                            ProgressEvent::Success(OptionDetails(s)) => farcaster::Progress {
-                                progress: Some(farcaster::progress::Progress::Success(
-                                    s.unwrap_or_default(),
-                                )),
-                            },
-                        })
-                        .collect(),
+                    progress: progress.progress.drain(..).map(|p| p.into()).collect(),
                 };
 
                 Ok(GrpcResponse::new(reply))
@@ -953,10 +1340,58 @@ impl Farcaster for FarcasterService {
         }
     }
 
+    type SubscribeProgressStream = Subscription<SubscribeProgressResponse>;
+
+    async fn subscribe_progress(
+        &self,
+        request: GrpcRequest<SubscribeProgressRequest>,
+    ) -> Result<GrpcResponse<Self::SubscribeProgressStream>, Status> {
+        authorize(&request, Permission::Read)?;
+        debug!("Received a grpc subscribe progress request: {:?}", request);
+        let SubscribeProgressRequest {
+            id,
+            swap_id: str_swap_id,
+        } = request.into_inner();
+        let swap_id =
+            SwapId::from_str(&str_swap_id).map_err(|_| Status::invalid_argument("swap id"))?;
+
+        let subscription = self
+            .subscribe(
+                id,
+                InfoMsg::SubscribeProgress(swap_id),
+                InfoMsg::UnsubscribeProgress(swap_id),
+                progress_item,
+            )
+            .await?;
+        Ok(GrpcResponse::new(subscription))
+    }
+
+    type SubscribeEventsStream = Subscription<SubscribeEventsResponse>;
+
+    async fn subscribe_events(
+        &self,
+        request: GrpcRequest<SubscribeEventsRequest>,
+    ) -> Result<GrpcResponse<Self::SubscribeEventsStream>, Status> {
+        authorize(&request, Permission::Read)?;
+        debug!("Received a grpc subscribe events request: {:?}", request);
+        let SubscribeEventsRequest { id } = request.into_inner();
+
+        let subscription = self
+            .subscribe(
+                id,
+                InfoMsg::SubscribeEvents,
+                InfoMsg::UnsubscribeEvents,
+                event_item,
+            )
+            .await?;
+        Ok(GrpcResponse::new(subscription))
+    }
+
     async fn connect_swap(
         &self,
         request: GrpcRequest<ConnectSwapRequest>,
     ) -> Result<GrpcResponse<ConnectSwapResponse>, Status> {
+        authorize(&request, Permission::Trade)?;
         let ConnectSwapRequest {
             id,
             swap_id: str_swap_id,
@@ -988,6 +1423,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<NeedsFundingRequest>,
     ) -> Result<GrpcResponse<NeedsFundingResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         debug!("Received a grpc needs funding request: {:?}", request);
         let NeedsFundingRequest {
             id,
@@ -1043,6 +1479,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<HealthCheckRequest>,
     ) -> Result<GrpcResponse<HealthCheckResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         let HealthCheckRequest {
             id,
             selector: grpc_selector,
@@ -1112,6 +1549,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<SweepAddressRequest>,
     ) -> Result<GrpcResponse<SweepAddressResponse>, Status> {
+        authorize(&request, Permission::Funds)?;
         debug!("Received a grpc sweep address request: {:?}", request);
         let SweepAddressRequest {
             id,
@@ -1204,12 +1642,14 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<TakeRequest>,
     ) -> Result<GrpcResponse<TakeResponse>, Status> {
+        authorize(&request, Permission::Trade)?;
         debug!("Received a grpc take request: {:?}", request);
         let TakeRequest {
             id,
             deal: str_deal,
             bitcoin_address: str_bitcoin_address,
             monero_address: str_monero_address,
+            swap_params: grpc_swap_params,
         } = request.into_inner();
 
         let bitcoin_address = bitcoin::Address::from_str(&str_bitcoin_address)
@@ -1217,6 +1657,10 @@ impl Farcaster for FarcasterService {
         let monero_address = monero::Address::from_str(&str_monero_address)
             .map_err(|_| Status::invalid_argument("accordant_address"))?;
         let deal = Deal::from_str(&str_deal).map_err(|_| Status::invalid_argument("deal"))?;
+        let swap_params = grpc_swap_params
+            .map(SwapParams::try_from)
+            .transpose()?
+            .unwrap_or_default();
 
         let oneshot_rx = self
             .process_request(BusMsg::Bridge(BridgeMsg::Ctl {
@@ -1224,6 +1668,7 @@ impl Farcaster for FarcasterService {
                     deal,
                     bitcoin_address,
                     monero_address,
+                    swap_params,
                 }),
                 service_id: ServiceId::Farcasterd,
             }))
@@ -1242,6 +1687,7 @@ impl Farcaster for FarcasterService {
         &self,
         request: GrpcRequest<GetBalanceRequest>,
     ) -> Result<GrpcResponse<GetBalanceResponse>, Status> {
+        authorize(&request, Permission::Read)?;
         let GetBalanceRequest {
             id,
             address: str_address,
@@ -1281,20 +1727,90 @@ impl Farcaster for FarcasterService {
             res => process_error_response(res),
         }
     }
+
+    async fn acceptance_policy(
+        &self,
+        request: GrpcRequest<AcceptancePolicyRequest>,
+    ) -> Result<GrpcResponse<AcceptancePolicyResponse>, Status> {
+        authorize(&request, Permission::Read)?;
+        debug!("Received a grpc acceptance policy request: {:?}", request);
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::GetAcceptancePolicy,
+                service_id: ServiceId::Farcasterd,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::AcceptancePolicy(policy))) => {
+                let reply = farcaster::AcceptancePolicyResponse {
+                    id: request.into_inner().id,
+                    ..policy.into()
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
+
+    async fn stats(
+        &self,
+        request: GrpcRequest<StatsRequest>,
+    ) -> Result<GrpcResponse<StatsResponse>, Status> {
+        authorize(&request, Permission::Read)?;
+        debug!("Received a grpc stats request: {:?}", request);
+        let StatsRequest {
+            id,
+            since,
+            group_by: grpc_group_by,
+        } = request.into_inner();
+        let since = since
+            .map(|since| stats::StatsDay::from_str(&since))
+            .transpose()
+            .map_err(|_| Status::invalid_argument("since malformed, expected YYYY-MM-DD"))?;
+        let group_by: stats::StatsGrouping = farcaster::StatsGrouping::from_i32(grpc_group_by)
+            .ok_or_else(|| Status::invalid_argument("group_by"))?
+            .into();
+
+        let oneshot_rx = self
+            .process_request(BusMsg::Bridge(BridgeMsg::Info {
+                request: InfoMsg::GetStats(stats::StatsQuery { since, group_by }),
+                service_id: ServiceId::Database,
+            }))
+            .await?;
+        match oneshot_rx.await {
+            Ok(BusMsg::Info(InfoMsg::StatsList(mut buckets))) => {
+                let reply = StatsResponse {
+                    id,
+                    buckets: buckets.drain(..).map(|b| b.into()).collect(),
+                };
+                Ok(GrpcResponse::new(reply))
+            }
+            res => process_error_response(res),
+        }
+    }
 }
 
 pub struct GrpcServer {
     grpc_port: u16,
     grpc_ip: String,
+    authenticator: Authenticator,
 }
 
 fn request_loop(
     mut tokio_rx_request: tokio::sync::mpsc::Receiver<(u64, BusMsg)>,
+    mut tokio_rx_unsubscribe: tokio::sync::mpsc::UnboundedReceiver<(u64, BusMsg)>,
     tx_request: zmq::Socket,
 ) -> tokio::task::JoinHandle<Result<(), Error>> {
     tokio::task::spawn(async move {
         let mut session = LocalSession::with_zmq_socket(ZmqSocketType::Push, tx_request);
-        while let Some((id, request)) = tokio_rx_request.recv().await {
+        loop {
+            let (id, request) = tokio::select! {
+                Some(unsubscribe) = tokio_rx_unsubscribe.recv() => unsubscribe,
+                request = tokio_rx_request.recv() => match request {
+                    Some(request) => request,
+                    None => break,
+                },
+            };
             debug!("sending request over grpc bridge: {:?}", request);
             let grpc_client_address: Vec<u8> = ServiceId::GrpcdClient(id).into();
             let grpc_address: Vec<u8> = ServiceId::Grpcd.into();
@@ -1318,6 +1834,7 @@ fn request_loop(
 fn response_loop(
     mpsc_rx_response: Receiver<(u64, BusMsg)>,
     pending_requests_lock: Arc<Mutex<HashMap<u64, tokio::sync::oneshot::Sender<BusMsg>>>>,
+    subscriptions_lock: Arc<Mutex<HashMap<u64, tokio::sync::mpsc::Sender<BusMsg>>>>,
 ) -> tokio::task::JoinHandle<Result<(), Error>> {
     tokio::task::spawn(async move {
         loop {
@@ -1331,8 +1848,23 @@ fn response_loop(
                                 "Error encountered while sending response to Grpc server handle: The client probably disconnected."
                             );
                         }
+                        continue;
+                    }
+                    drop(pending_requests);
+
+                    let mut subscriptions = subscriptions_lock.lock().await;
+                    // drop the subscriptions whose stream ended
+                    subscriptions.retain(|_, sender| !sender.is_closed());
+                    if let Some(sender) = subscriptions.get(&id) {
+                        if let Err(TrySendError::Full(request)) = sender.try_send(request) {
+                            warn!(
+                                "Dropping {} pushed to grpc client {}: the client does not keep up with the stream",
+                                request,
+                                ServiceId::GrpcdClient(id)
+                            );
+                        }
                     } else {
-                        error!("id {} not found in pending grpc requests", id);
+                        debug!("id {} not found in pending grpc requests", id);
                     }
                 }
                 Err(TryRecvError::Disconnected) => {
@@ -1350,12 +1882,13 @@ fn response_loop(
 
 fn server_loop(
     service: FarcasterService,
+    authenticator: Authenticator,
     addr: SocketAddr,
 ) -> tokio::task::JoinHandle<Result<(), Error>> {
     tokio::task::spawn(async move {
         let web_service = tonic_web::config()
             .allow_all_origins()
-            .enable(FarcasterServer::new(service));
+            .enable(FarcasterServer::with_interceptor(service, authenticator));
 
         if let Err(err) = Server::builder()
             .accept_http1(true)
@@ -1382,6 +1915,7 @@ impl GrpcServer {
             .parse()
             .expect("invalid grpc server bind address");
         info!("Binding grpc to address: {}", addr);
+        let authenticator = self.authenticator.clone();
 
         std::thread::spawn(move || {
             // We panic on the async runtime failing, because this is indicative
@@ -1393,20 +1927,31 @@ impl GrpcServer {
                 .expect("failed to build new tokio runtime");
             rt.block_on(async {
                 let (tokio_tx_request, tokio_rx_request) = tokio::sync::mpsc::channel(1000);
+                let (tokio_tx_unsubscribe, tokio_rx_unsubscribe) =
+                    tokio::sync::mpsc::unbounded_channel();
 
                 let pending_requests: Arc<
                     Mutex<HashMap<u64, tokio::sync::oneshot::Sender<BusMsg>>>,
                 > = Arc::new(Mutex::new(map![]));
-                let request_handle = request_loop(tokio_rx_request, tx_request);
-                let response_handle = response_loop(rx_response, Arc::clone(&pending_requests));
+                let subscriptions: Arc<Mutex<HashMap<u64, tokio::sync::mpsc::Sender<BusMsg>>>> =
+                    Arc::new(Mutex::new(map![]));
+                let request_handle =
+                    request_loop(tokio_rx_request, tokio_rx_unsubscribe, tx_request);
+                let response_handle = response_loop(
+                    rx_response,
+                    Arc::clone(&pending_requests),
+                    Arc::clone(&subscriptions),
+                );
 
                 let service = FarcasterService {
                     id_counter: Arc::new(Mutex::new(IdCounter(0))),
                     tokio_tx_request,
+                    tokio_tx_unsubscribe,
                     pending_requests,
+                    subscriptions,
                 };
 
-                let server_handle = server_loop(service, addr);
+                let server_handle = server_loop(service, authenticator, addr);
 
                 // this drives the tokio execution
                 let res = tokio::try_join!(request_handle, response_handle, server_handle);
@@ -1443,7 +1988,22 @@ impl GrpcServer {
 
 type IdBusMsgPair = (u64, BusMsg);
 
-pub fn run(config: ServiceConfig, grpc_port: u16, grpc_ip: String) -> Result<(), Error> {
+pub fn run(
+    config: ServiceConfig,
+    grpc_port: u16,
+    grpc_ip: String,
+    data_dir: PathBuf,
+    auth: bool,
+) -> Result<(), Error> {
+    let token_store = if auth {
+        Some(Arc::new(std::sync::Mutex::new(TokenStore::open(
+            &data_dir,
+        )?)))
+    } else {
+        warn!("Authentication of the grpc clients is disabled, every client is granted all permissions");
+        None
+    };
+
     let (tx_response, rx_response): (Sender<IdBusMsgPair>, Receiver<IdBusMsgPair>) =
         std::sync::mpsc::channel();
 
@@ -1455,6 +2015,7 @@ pub fn run(config: ServiceConfig, grpc_port: u16, grpc_ip: String) -> Result<(),
     let mut server = GrpcServer {
         grpc_port,
         grpc_ip: grpc_ip.clone(),
+        authenticator: Authenticator(token_store.clone()),
     };
     server.run(rx_response, tx_request)?;
 
@@ -1463,6 +2024,7 @@ pub fn run(config: ServiceConfig, grpc_port: u16, grpc_ip: String) -> Result<(),
         tx_response,
         grpc_port,
         grpc_ip,
+        token_store,
     };
 
     let mut service = Service::service(config, runtime)?;
@@ -1476,6 +2038,7 @@ pub struct Runtime {
     tx_response: Sender<(u64, BusMsg)>,
     grpc_port: u16,
     grpc_ip: String,
+    token_store: Option<Arc<std::sync::Mutex<TokenStore>>>,
 }
 
 impl CtlServer for Runtime {}
@@ -1518,7 +2081,7 @@ impl esb::Handler<ServiceBus> for Runtime {
 impl Runtime {
     fn handle_ctl(
         &mut self,
-        _endpoints: &mut Endpoints,
+        endpoints: &mut Endpoints,
         source: ServiceId,
         request: CtlMsg,
     ) -> Result<(), Error> {
@@ -1527,6 +2090,54 @@ impl Runtime {
                 debug!("Received Hello from {}", source);
             }
 
+            CtlMsg::Terminate if source == ServiceId::Farcasterd => {
+                info!("Terminating {}", ServiceId::Grpcd);
+                exit_service(0);
+            }
+
+            CtlMsg::CreateGrpcToken(permissions) => {
+                let token = self
+                    .lock_token_store()
+                    .and_then(|mut store| store.create(&permissions));
+                match token {
+                    Ok(token) => {
+                        info!("Created grpc token {}", token.id);
+                        self.send_client_info(endpoints, source, InfoMsg::GrpcToken(token))?;
+                    }
+                    Err(err) => {
+                        self.send_client_ctl(
+                            endpoints,
+                            source,
+                            CtlMsg::Failure(Failure {
+                                code: FailureCode::Unknown,
+                                info: err.to_string(),
+                            }),
+                        )?;
+                    }
+                }
+            }
+
+            CtlMsg::RevokeGrpcToken(id) => {
+                let revoked = self
+                    .lock_token_store()
+                    .and_then(|mut store| store.revoke(&id));
+                let reply = match revoked {
+                    Ok(true) => {
+                        info!("Revoked grpc token {}", id);
+                        CtlMsg::Success(OptionDetails::with(format!("Revoked grpc token {}", id)))
+                    }
+                    Ok(false) => CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: format!("No grpc token {} is issued", id),
+                    }),
+                    Err(err) => CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: err.to_string(),
+                    }),
+                };
+                self.send_client_ctl(endpoints, source, reply)?;
+            }
+
             req => {
                 if let ServiceId::GrpcdClient(id) = source {
                     self.tx_response
@@ -1543,10 +2154,26 @@ impl Runtime {
 
     fn handle_info(
         &mut self,
-        _endpoints: &mut Endpoints,
+        endpoints: &mut Endpoints,
         source: ServiceId,
         request: InfoMsg,
     ) -> Result<(), Error> {
+        if let InfoMsg::ListGrpcTokens = request {
+            let tokens = self.lock_token_store().map(|store| store.list());
+            return match tokens {
+                Ok(tokens) => {
+                    self.send_client_info(endpoints, source, InfoMsg::GrpcTokenList(tokens.into()))
+                }
+                Err(err) => self.send_client_ctl(
+                    endpoints,
+                    source,
+                    CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: err.to_string(),
+                    }),
+                ),
+            };
+        }
         if let ServiceId::GrpcdClient(id) = source {
             self.tx_response
                 .send((id, BusMsg::Info(request)))
@@ -1558,6 +2185,15 @@ impl Runtime {
         Ok(())
     }
 
+    fn lock_token_store(&self) -> Result<std::sync::MutexGuard<'_, TokenStore>, Error> {
+        self.token_store
+            .as_ref()
+            .map(|store| store.lock().expect("grpc token store lock poisoned"))
+            .ok_or_else(|| {
+                Error::Farcaster("Authentication of the grpc clients is disabled".to_string())
+            })
+    }
+
     fn handle_bridge(
         &mut self,
         endpoints: &mut Endpoints,
@@ -1585,6 +2221,7 @@ impl Runtime {
                 let mut server = GrpcServer {
                     grpc_port: self.grpc_port,
                     grpc_ip: self.grpc_ip.clone(),
+                    authenticator: Authenticator(self.token_store.clone()),
                 };
                 server.run(rx_response, tx_request)?;
                 self.tx_response = tx_response;
@@ -50,6 +50,8 @@ pub mod opts;
 
 #[cfg(feature = "node")]
 pub mod databased;
+#[cfg(feature = "embedded")]
+pub mod embedded;
 #[cfg(feature = "node")]
 pub mod farcasterd;
 #[cfg(feature = "node")]
@@ -5,10 +5,11 @@
 // https://opensource.org/licenses/MIT.
 
 use clap::ValueHint;
+use std::io::BufRead;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::{fs, io};
+use std::{env, fs, io};
 
 use internet2::addr::ServiceAddr;
 
@@ -32,6 +33,11 @@ pub const FARCASTER_SYNC_SOCKET_NAME: &str = "{data_dir}/sync";
 
 pub const FARCASTER_KEY_FILE: &str = "{data_dir}/key.dat";
 
+/// Marks the end of the command line arguments of a launched service, the following ones carry
+/// secrets and are handed over on the service stdin, one per line, instead of the command line
+/// visible to every local user.
+pub const ARGS_FROM_STDIN: &str = "--args-from-stdin";
+
 /// Shared options used by different binaries
 #[derive(Parser, Clone, PartialEq, Eq, Debug)]
 pub struct Opts {
@@ -129,6 +135,19 @@ pub struct TokenString {
     pub token: String,
 }
 
+/// Command line arguments of the service, completed with the ones read from stdin if launched
+/// with [`ARGS_FROM_STDIN`]
+pub fn service_args() -> Vec<String> {
+    let mut args: Vec<String> = env::args().collect();
+    if let Some(pos) = args.iter().position(|arg| arg == ARGS_FROM_STDIN) {
+        args.remove(pos);
+        for line in io::stdin().lock().lines() {
+            args.push(line.expect("Unable to read the arguments from stdin"));
+        }
+    }
+    args
+}
+
 impl FromStr for TokenString {
     type Err = io::Error;
 
@@ -144,10 +163,15 @@ impl Opts {
         let env = env_logger::Env::new().default_filter_or("error,farcaster_node=info");
         // standard environment variable set to "true" when running in CI environments
         let is_test = matches!(std::env::var("CI"), Ok(v) if v == "true");
-        env_logger::from_env(env)
+        // the logger is already set up when several services share the process, e.g. in an
+        // embedded node
+        if env_logger::from_env(env)
             .is_test(is_test)
             .try_init()
-            .expect("Failed to initialize loggger!");
+            .is_err()
+        {
+            trace!("Logger already initialized");
+        }
 
         let mut me = self.clone();
 
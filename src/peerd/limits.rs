@@ -0,0 +1,132 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Protection of peerd against remote peers flooding it or holding connections open.
+//!
+//! Every connection limits the rate of the messages received and the size of the pong a remote
+//! ping can request; the offending messages are dropped. A listener limits the simultaneous
+//! connections from the same IP and closes the connections on which no swap starts within the
+//! idle timeout. The swaps in flight per node id are limited by farcasterd, which knows all the
+//! swaps running with a peer. See [`crate::config::PeerLimits`] for the configured values.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Minimum interval between two reports of the violations of a connection
+const VIOLATION_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A limit broken by a remote peer
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display)]
+pub enum Violation {
+    #[display("more than {0} messages per second")]
+    MessageRate(u32),
+    #[display("ping requesting a pong of {0} bytes")]
+    PongSize(u16),
+    #[display("no swap started within {0} seconds")]
+    Idle(u64),
+}
+
+/// Counts the messages received during the current second
+#[derive(Debug)]
+pub struct RateLimiter {
+    max_per_sec: u32,
+    window_start: Instant,
+    received: u32,
+}
+
+impl RateLimiter {
+    pub fn new(max_per_sec: u32) -> Self {
+        RateLimiter {
+            max_per_sec,
+            window_start: Instant::now(),
+            received: 0,
+        }
+    }
+
+    /// Register a received message, returns false if it exceeds the rate
+    pub fn allow(&mut self) -> bool {
+        if self.window_start.elapsed() >= Duration::from_secs(1) {
+            self.window_start = Instant::now();
+            self.received = 0;
+        }
+        self.received = self.received.saturating_add(1);
+        self.received <= self.max_per_sec
+    }
+}
+
+/// Violations of a connection not reported yet. Reports are throttled so that a flooding remote
+/// does not flood the logs and farcasterd in turn.
+#[derive(Debug, Default)]
+pub struct PendingViolations {
+    count: u32,
+    last: Option<Violation>,
+    reported_at: Option<Instant>,
+}
+
+impl PendingViolations {
+    /// Count a violation, returns the violations to report if the last report is old enough
+    pub fn add(&mut self, violation: Violation) -> Option<(u32, Violation)> {
+        self.count = self.count.saturating_add(1);
+        self.last = Some(violation);
+        match self.reported_at {
+            Some(at) if at.elapsed() < VIOLATION_REPORT_INTERVAL => None,
+            _ => self.take(),
+        }
+    }
+
+    /// Returns the number of pending violations and the last one, if any
+    pub fn take(&mut self) -> Option<(u32, Violation)> {
+        let last = self.last.take()?;
+        self.reported_at = Some(Instant::now());
+        Some((std::mem::take(&mut self.count), last))
+    }
+}
+
+/// Simultaneous connections per remote IP, shared by the threads of a listener
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionsPerIp(Arc<Mutex<HashMap<IpAddr, u32>>>);
+
+impl ConnectionsPerIp {
+    /// Register a connection from the IP, returns None if the IP already reached the maximum. The
+    /// connection is released when the returned slot is dropped.
+    pub fn acquire(&self, ip: IpAddr, max: u32) -> Option<ConnectionSlot> {
+        let mut connections = self.0.lock().expect("connections lock poisoned");
+        let count = connections.entry(ip).or_insert(0);
+        if *count >= max {
+            return None;
+        }
+        *count += 1;
+        Some(ConnectionSlot {
+            connections: self.clone(),
+            ip,
+        })
+    }
+}
+
+/// A connection counted in [`ConnectionsPerIp`]
+#[derive(Debug)]
+pub struct ConnectionSlot {
+    connections: ConnectionsPerIp,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut connections = self
+            .connections
+            .0
+            .lock()
+            .expect("connections lock poisoned");
+        if let Some(count) = connections.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.ip);
+            }
+        }
+    }
+}
@@ -4,11 +4,14 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+mod limits;
 #[cfg(feature = "shell")]
 mod opts;
 mod runtime;
+mod socks;
 
 #[cfg(feature = "shell")]
 pub use opts::{Opts, PeerKeyOpts};
 pub use runtime::run_from_connect;
 pub use runtime::run_from_listener;
+pub use runtime::run_threaded_listener;
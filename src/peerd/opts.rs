@@ -7,7 +7,10 @@
 use clap::{ArgGroup, ValueHint};
 use std::net::IpAddr;
 
+use crate::bus::p2p::Init;
+use crate::config::PeerLimits;
 use crate::opts::TokenString;
+use farcaster_core::blockchain::Network;
 use internet2::addr::{LocalNode, NodeAddr};
 
 /// Peer network connection daemon; part of Farcaster Node
@@ -59,6 +62,38 @@ pub struct Opts {
     #[clap(short, long, default_value = "9735")]
     pub port: u16,
 
+    /// Spawn a thread instead of forking a child process for every incoming connection
+    ///
+    /// Only used together with `--listen`.
+    #[clap(long, requires = "listen")]
+    pub use_threads: bool,
+
+    /// Network the node swaps on, announced to the remote peer in the handshake
+    ///
+    /// Can be given several times, a peer without any network in common is rejected.
+    #[clap(long = "network")]
+    pub networks: Vec<Network>,
+
+    /// Maximum number of messages per second received from the remote, the excess is dropped
+    #[clap(long, default_value = "20")]
+    pub max_messages_per_sec: u32,
+
+    /// Maximum size in bytes of the pong a remote ping can request, larger pings are dropped
+    #[clap(long, default_value = "1024")]
+    pub max_pong_size: u16,
+
+    /// Maximum number of simultaneous incoming connections from the same IP
+    ///
+    /// Only used together with `--listen`.
+    #[clap(long, default_value = "8")]
+    pub max_connections_per_ip: u32,
+
+    /// Seconds after which an incoming connection on which no swap started is closed
+    ///
+    /// Only used together with `--listen`.
+    #[clap(long, default_value = "300")]
+    pub idle_timeout: u64,
+
     /// Node key configuration
     #[clap(flatten)]
     pub peer_key_opts: PeerKeyOpts,
@@ -77,6 +112,22 @@ impl Opts {
     pub fn process(&mut self) {
         self.shared.process();
     }
+
+    /// Handshake data announced to the remote peers
+    pub fn local_init(&self) -> Init {
+        Init::local(self.networks.clone())
+    }
+
+    /// Limits applied to the remote peers, the swaps in flight are limited by farcasterd
+    pub fn peer_limits(&self) -> PeerLimits {
+        PeerLimits {
+            max_messages_per_sec: self.max_messages_per_sec,
+            max_pong_size: self.max_pong_size,
+            max_connections_per_ip: self.max_connections_per_ip,
+            idle_timeout: self.idle_timeout,
+            ..PeerLimits::default()
+        }
+    }
 }
 
 /// Node key configuration
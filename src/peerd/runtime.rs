@@ -7,9 +7,14 @@
 use farcaster_core::swap::SwapId;
 use internet2::addr::LocalNode;
 use microservices::peer::RecvMessage;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::thread::spawn;
-use std::time::{Duration, SystemTime};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant, SystemTime};
 use uuid::Uuid;
 
 use amplify::Bipolar;
@@ -17,7 +22,7 @@ use bitcoin::secp256k1::rand::{self, Rng, RngCore};
 use internet2::{addr::InetSocketAddr, CreateUnmarshaller, Unmarshall, Unmarshaller};
 use internet2::{
     addr::NodeAddr,
-    presentation, transport,
+    presentation, session, transport,
     zeromq::{Carrier, ZmqSocketType},
     TypedEnum,
 };
@@ -26,21 +31,51 @@ use microservices::node::TryService;
 use microservices::peer::{self, PeerConnection, PeerSender, SendMessage};
 use microservices::ZMQ_CONTEXT;
 
-use crate::bus::p2p::Receipt;
+use crate::bus::p2p::{FeatureSet, Init};
 use crate::bus::{
     ctl::CtlMsg,
     info::{InfoMsg, PeerInfo},
     p2p::PeerMsg,
     BusMsg, ServiceBus,
 };
+use crate::config::PeerLimits;
+use crate::peerd::limits::{ConnectionsPerIp, PendingViolations, RateLimiter, Violation};
+use crate::peerd::socks;
+use crate::service::{exit_service, mark_service_thread};
 use crate::{CtlServer, Endpoints, Error, LogStyle, Service, ServiceConfig, ServiceId};
 
+/// Interval between two checks for incoming connections of the threaded listener
+const LISTENER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Connection established by a taker, with the handshake data of the maker
+type ConnectedPeer = (PeerSender, std::sync::mpsc::Sender<()>, Init, FeatureSet);
+
 pub fn start_connect_peer_listener_runtime(
     remote_node_addr: NodeAddr,
+    tor_proxy: Option<SocketAddr>,
     local_node: LocalNode,
+    local_init: &Init,
+    limits: PeerLimits,
     peerd_service_id: ServiceId,
-) -> Result<(PeerSender, std::sync::mpsc::Sender<()>), Error> {
-    let connection = PeerConnection::connect_brontozaur(local_node, remote_node_addr)?;
+    bridge: &str,
+) -> Result<ConnectedPeer, Error> {
+    let connection = match tor_proxy {
+        Some(proxy) => {
+            let forwarded = socks::forward_through_proxy(proxy, &remote_node_addr.addr)?;
+            debug!(
+                "Connecting to remote peer {} through the proxy {}",
+                remote_node_addr, proxy
+            );
+            PeerConnection::connect_brontozaur(
+                local_node,
+                NodeAddr {
+                    id: remote_node_addr.id,
+                    addr: InetSocketAddr::socket(forwarded.ip(), forwarded.port()),
+                },
+            )?
+        }
+        None => PeerConnection::connect_brontozaur(local_node, remote_node_addr)?,
+    };
     debug!("Connected to remote peer: {}", remote_node_addr);
 
     debug!("Splitting connection into receiver and sender parts");
@@ -56,19 +91,29 @@ pub fn start_connect_peer_listener_runtime(
         "sent message with local node id {} to the maker",
         local_node.node_id()
     );
+    peer_sender.send_message(PeerMsg::Init(local_init.clone()))?;
     let unmarshaller: Unmarshaller<PeerMsg> = PeerMsg::create_unmarshaller();
     let msg: &PeerMsg = &*peer_receiver.recv_message(&unmarshaller)?;
-    match msg {
-        PeerMsg::Pong(id) => {
-            debug!("Received the following pong from the maker {:?}", id);
+    let remote_init = match msg {
+        PeerMsg::Init(init) => {
+            debug!("Received the following init from the maker {}", init);
+            init.clone()
+        }
+        // nodes older than the protocol negotiation answer the identity with a pong
+        PeerMsg::Pong(_) => {
+            return Err(Error::Farcaster(format!(
+                "Incompatible peer {}: remote does not negotiate the protocol version",
+                remote_node_addr
+            )));
         }
         _ => {
             return Err(Error::Peer(presentation::Error::UnknownDataType));
         }
     };
+    let features = negotiate(local_init, &remote_init, &remote_node_addr)?;
 
     let tx = ZMQ_CONTEXT.socket(zmq::PUSH)?;
-    tx.connect("inproc://bridge")?;
+    tx.connect(bridge)?;
 
     let (thread_flag_tx, _thread_flag_rx) = std::sync::mpsc::channel();
 
@@ -89,6 +134,11 @@ pub fn start_connect_peer_listener_runtime(
         )?,
         _thread_flag_rx,
         awaiting_pong: false,
+        limits,
+        rate_limiter: RateLimiter::new(limits.max_messages_per_sec),
+        violations: none!(),
+        // a taker connection is never idle, the swaps it starts are driven by the taker
+        idle_since: None,
     };
     let unmarshaller: Unmarshaller<PeerMsg> = PeerMsg::create_unmarshaller();
     let peer_receiver_runtime = peer::Listener::<PeerReceiverRuntime, PeerMsg>::with(
@@ -107,21 +157,49 @@ pub fn start_connect_peer_listener_runtime(
         }
     });
 
-    Ok((peer_sender, thread_flag_tx))
+    Ok((peer_sender, thread_flag_tx, remote_init, features))
+}
+
+/// Check the handshake data of the remote, the connection is closed if it is not compatible
+fn negotiate(
+    local_init: &Init,
+    remote_init: &Init,
+    remote: &dyn Display,
+) -> Result<FeatureSet, Error> {
+    match local_init.negotiate(remote_init) {
+        Ok(features) => {
+            debug!(
+                "Negotiated protocol with {}: v{}, features {}",
+                remote, remote_init.protocol_version, features
+            );
+            Ok(features)
+        }
+        Err(reason) => {
+            warn!("Rejecting incompatible peer {}: {}", remote, reason);
+            Err(Error::Farcaster(format!(
+                "Incompatible peer {}: {}",
+                remote, reason
+            )))
+        }
+    }
 }
 
 pub fn run_from_connect(
     config: ServiceConfig,
     remote_node_addr: NodeAddr,
+    tor_proxy: Option<SocketAddr>,
     local_socket: Option<InetSocketAddr>,
     local_node: LocalNode,
+    local_init: Init,
+    limits: PeerLimits,
 ) -> Result<(), Error> {
+    let id = Uuid::new_v4().as_u128();
+    let bridge = bridge_endpoint(id);
     debug!("Opening bridge between runtime and peer receiver threads");
     let rx = ZMQ_CONTEXT.socket(zmq::PULL)?;
-    rx.bind("inproc://bridge")?;
+    rx.bind(&bridge)?;
 
     let (thread_flag_tx, _thread_flag_rx) = std::sync::mpsc::channel();
-    let id = Uuid::new_v4().as_u128();
 
     debug!(
         "Starting main service runtime with identity: {}",
@@ -130,14 +208,29 @@ pub fn run_from_connect(
     let runtime = Runtime {
         identity: ServiceId::Peer(id, remote_node_addr),
         remote_node_addr: Some(remote_node_addr),
+        tor_proxy,
         local_socket,
         local_node,
+        local_init,
+        remote_init: None,
+        features: FeatureSet::default(),
+        limits,
         peer_sender: None, // As connector we create the sender on is_ready
         forked_from_listener: false,
+        bridge,
         started: SystemTime::now(),
+        connected_since: SystemTime::now(),
+        reconnects: 0,
         messages_sent: 0,
         messages_received: 0,
+        bytes_sent: 0,
+        bytes_received: 0,
+        limit_violations: 0,
         awaited_pong: None,
+        ping_sent_at: None,
+        rtt: None,
+        rtt_total: Duration::from_secs(0),
+        pongs: 0,
         thread_flag_tx,
         unchecked_msg_cache: empty!(),
     };
@@ -154,6 +247,8 @@ pub fn run_from_listener(
     remote_node_addr: Option<NodeAddr>,
     local_socket: Option<InetSocketAddr>,
     local_node: LocalNode,
+    local_init: Init,
+    limits: PeerLimits,
 ) -> Result<(), Error> {
     debug!("Splitting connection into receiver and sender parts");
     let (mut peer_receiver, mut peer_sender) = connection.split();
@@ -164,9 +259,7 @@ pub fn run_from_listener(
     // TODO: It is privacy/security critical that once the
     // connection is encrypted, this should be replaced by a proper handshake.
     let unmarshaller: Unmarshaller<PeerMsg> = PeerMsg::create_unmarshaller();
-    let msg: Arc<PeerMsg> = peer_receiver
-        .recv_message(&unmarshaller)
-        .expect("failed to receive identity message from taker");
+    let msg: Arc<PeerMsg> = peer_receiver.recv_message(&unmarshaller)?;
     let id = match *msg {
         PeerMsg::Identity(id) => {
             debug!("Received the following local node id from the taker {}", id);
@@ -174,9 +267,28 @@ pub fn run_from_listener(
         }
         _ => None,
     };
-    peer_sender
-        .send_message(PeerMsg::Pong(vec![0]))
-        .expect("Failed to send handshake pong");
+    // the init is sent before receiving the taker's one: a taker older than the protocol
+    // negotiation awaits an answer to its identity and fails on the init instead of hanging, an
+    // incompatible taker can report the reason
+    peer_sender.send_message(PeerMsg::Init(local_init.clone()))?;
+    let msg: Arc<PeerMsg> = peer_receiver.recv_message(&unmarshaller)?;
+    let remote_init = match &*msg {
+        PeerMsg::Init(init) => {
+            debug!("Received the following init from the taker {}", init);
+            init.clone()
+        }
+        _ => {
+            return Err(Error::Farcaster(format!(
+                "Incompatible peer {:?}: remote does not negotiate the protocol version",
+                id
+            )));
+        }
+    };
+    let features = negotiate(
+        &local_init,
+        &remote_init,
+        &id.map_or_else(|| s!("unknown"), |id| id.to_string()),
+    )?;
     let peerd_id = Uuid::new_v4().as_u128();
     let internal_identity = ServiceId::Peer(
         peerd_id,
@@ -186,11 +298,12 @@ pub fn run_from_listener(
         },
     );
 
+    let bridge = bridge_endpoint(peerd_id);
     debug!("Opening bridge between runtime and peer receiver threads");
     let rx = ZMQ_CONTEXT.socket(zmq::PULL)?;
-    rx.bind("inproc://bridge")?;
+    rx.bind(&bridge)?;
     let tx = ZMQ_CONTEXT.socket(zmq::PUSH)?;
-    tx.connect("inproc://bridge")?;
+    tx.connect(&bridge)?;
 
     let (thread_flag_tx, _thread_flag_rx) = std::sync::mpsc::channel();
 
@@ -211,6 +324,10 @@ pub fn run_from_listener(
         )?,
         _thread_flag_rx,
         awaiting_pong: false,
+        limits,
+        rate_limiter: RateLimiter::new(limits.max_messages_per_sec),
+        violations: none!(),
+        idle_since: Some(Instant::now()),
     };
     let unmarshaller: Unmarshaller<PeerMsg> = PeerMsg::create_unmarshaller();
     let peer_receiver_runtime = peer::Listener::<PeerReceiverRuntime, PeerMsg>::with(
@@ -235,14 +352,29 @@ pub fn run_from_listener(
     let runtime = Runtime {
         identity: internal_identity,
         remote_node_addr,
+        tor_proxy: None,
         local_socket,
         local_node,
+        local_init,
+        remote_init: Some(remote_init),
+        features,
+        limits,
         peer_sender: Some(peer_sender),
         forked_from_listener: true,
+        bridge,
         started: SystemTime::now(),
+        connected_since: SystemTime::now(),
+        reconnects: 0,
         messages_sent: 0,
         messages_received: 0,
+        bytes_sent: 0,
+        bytes_received: 0,
+        limit_violations: 0,
         awaited_pong: None,
+        ping_sent_at: None,
+        rtt: None,
+        rtt_total: Duration::from_secs(0),
+        pongs: 0,
         thread_flag_tx,
         unchecked_msg_cache: empty!(),
     };
@@ -252,6 +384,97 @@ pub fn run_from_listener(
     unreachable!()
 }
 
+/// Listen for incoming connections and run the peerd of every accepted connection in a new
+/// thread instead of a forked child process. Returns once `stop` is set.
+pub fn run_threaded_listener(
+    config: ServiceConfig,
+    inet_addr: InetSocketAddr,
+    local_node: LocalNode,
+    local_init: Init,
+    limits: PeerLimits,
+    stop: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    debug!("Binding TCP socket {}", inet_addr);
+    let listener = TcpListener::bind(SocketAddr::try_from(inet_addr)?)?;
+    // non-blocking accept lets the listener check the stop flag regularly
+    listener.set_nonblocking(true)?;
+
+    let connections = ConnectionsPerIp::default();
+    debug!("Running threaded TCP listener event loop");
+    while !stop.load(Ordering::Relaxed) {
+        let (stream, remote_socket_addr) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                sleep(LISTENER_POLL_INTERVAL);
+                continue;
+            }
+            Err(err) => {
+                error!("Error accepting incoming peer connection: {}", err);
+                continue;
+            }
+        };
+        debug!("New connection from {}", remote_socket_addr);
+        let slot = match connections.acquire(remote_socket_addr.ip(), limits.max_connections_per_ip)
+        {
+            Some(slot) => slot,
+            None => {
+                warn!(
+                    "Refusing connection from {}: more than {} connections from this IP",
+                    remote_socket_addr, limits.max_connections_per_ip
+                );
+                continue;
+            }
+        };
+
+        let config = config.clone();
+        let local_init = local_init.clone();
+        spawn(move || {
+            // a terminated peerd only stops its own thread, releasing its connection slot
+            mark_service_thread();
+            let _slot = slot;
+            let res = stream
+                .set_nonblocking(false)
+                .and_then(|_| stream.set_read_timeout(Some(Duration::from_secs(30))))
+                .map_err(Error::from)
+                .and_then(|_| {
+                    debug!("Establishing session with the remote");
+                    let session = session::BrontozaurSession::with(
+                        stream,
+                        local_node.private_key(),
+                        inet_addr,
+                    )?;
+                    debug!(
+                        "Session successfully established with {}",
+                        remote_socket_addr
+                    );
+                    run_from_listener(
+                        config,
+                        PeerConnection::with(session),
+                        None,
+                        Some(inet_addr),
+                        local_node,
+                        local_init,
+                        limits,
+                    )
+                });
+            if let Err(err) = res {
+                error!(
+                    "Peer connection with {} failed: {}",
+                    remote_socket_addr, err
+                );
+            }
+        });
+    }
+    debug!("Stopped listening on {}", inet_addr);
+    Ok(())
+}
+
+/// Endpoint of the bridge between the runtime and the peer receiver thread, unique per peerd as
+/// several of them may run in the same process
+fn bridge_endpoint(peerd_id: u128) -> String {
+    format!("inproc://peerdbridge-{:x}", peerd_id)
+}
+
 pub struct BridgeHandler;
 
 impl esb::Handler<ServiceBus> for BridgeHandler {
@@ -286,6 +509,12 @@ pub struct PeerReceiverRuntime {
     bridge: esb::Controller<ServiceBus, BusMsg, BridgeHandler>,
     awaiting_pong: bool,
     _thread_flag_rx: std::sync::mpsc::Receiver<()>,
+    limits: PeerLimits,
+    rate_limiter: RateLimiter,
+    violations: PendingViolations,
+    // Start of a connection accepted by a listener until a swap message is received, the
+    // connection is closed after the idle timeout
+    idle_since: Option<Instant>,
 }
 
 impl PeerReceiverRuntime {
@@ -306,6 +535,37 @@ impl PeerReceiverRuntime {
             Ok(())
         }
     }
+
+    /// Log the violations of the limits by the remote and report them to the runtime
+    fn report_violations(&mut self, violations: Option<(u32, Violation)>) -> Result<(), Error> {
+        if let Some((count, last)) = violations {
+            warn!(
+                "{} | {} violations of the peer limits by the remote, last: {}",
+                self.internal_identity, count, last
+            );
+            self.send_over_bridge(Arc::new(PeerMsg::LimitViolations(count)))?;
+        }
+        Ok(())
+    }
+
+    /// Fails once a connection accepted by a listener stayed without any swap message for the
+    /// idle timeout
+    fn check_idle(&mut self) -> Result<(), Error> {
+        match self.idle_since {
+            Some(since) if since.elapsed() >= self.limits.idle_timeout() => {
+                let violations = self
+                    .violations
+                    .add(Violation::Idle(self.limits.idle_timeout))
+                    .or_else(|| self.violations.take());
+                self.report_violations(violations)?;
+                Err(Error::Farcaster(format!(
+                    "No swap started within {} seconds, closing the idle connection",
+                    self.limits.idle_timeout
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 impl peer::Handler<PeerMsg> for PeerReceiverRuntime {
@@ -316,6 +576,22 @@ impl peer::Handler<PeerMsg> for PeerReceiverRuntime {
         message: <Unmarshaller<PeerMsg> as Unmarshall>::Data,
     ) -> Result<(), Self::Error> {
         trace!("FWP message details: {:?}", message);
+        if message.is_protocol() {
+            self.idle_since = None;
+        }
+        self.check_idle()?;
+        if !self.rate_limiter.allow() {
+            let violations = self
+                .violations
+                .add(Violation::MessageRate(self.limits.max_messages_per_sec));
+            return self.report_violations(violations);
+        }
+        if let PeerMsg::Ping(pong_size) = *message {
+            if pong_size > self.limits.max_pong_size {
+                let violations = self.violations.add(Violation::PongSize(pong_size));
+                return self.report_violations(violations);
+            }
+        }
         if let PeerMsg::Pong(_) = *Arc::clone(&message) {
             if self.awaiting_pong {
                 self.awaiting_pong = false;
@@ -338,6 +614,13 @@ impl peer::Handler<PeerMsg> for PeerReceiverRuntime {
         debug!("Underlying peer interface requested to handle {}", err);
         match err {
             Error::Peer(presentation::Error::Transport(transport::Error::TimedOut)) => {
+                let violations = self.violations.take();
+                self.report_violations(violations)?;
+                if let Err(err) = self.check_idle() {
+                    error!("{}", err);
+                    self.send_over_bridge(Arc::new(PeerMsg::PeerReceiverRuntimeShutdown))?;
+                    return Err(err);
+                }
                 trace!("Time to ping the remote peer");
                 if self.awaiting_pong {
                     error!(
@@ -370,17 +653,36 @@ impl peer::Handler<PeerMsg> for PeerReceiverRuntime {
 pub struct Runtime {
     identity: ServiceId,
     remote_node_addr: Option<NodeAddr>,
+    tor_proxy: Option<SocketAddr>,
     local_socket: Option<InetSocketAddr>,
     local_node: LocalNode,
+    local_init: Init,
+    // Handshake data of the remote, None until a connecting peerd is connected
+    remote_init: Option<Init>,
+    // Features supported by both nodes
+    features: FeatureSet,
+    limits: PeerLimits,
 
     peer_sender: Option<PeerSender>,
     // TODO: make this an enum instead with a descriptive distinction of listening and connecting to a listener
     forked_from_listener: bool,
+    bridge: String,
 
     started: SystemTime,
+    // Start of the current connection, reset when a connecting peerd reconnects
+    connected_since: SystemTime,
+    reconnects: u32,
     messages_sent: usize,
     messages_received: usize,
+    bytes_sent: u64,
+    bytes_received: u64,
+    limit_violations: u64,
     awaited_pong: Option<u16>,
+    // Round-trip times of the pings answered by the remote
+    ping_sent_at: Option<Instant>,
+    rtt: Option<Duration>,
+    rtt_total: Duration,
+    pongs: u32,
 
     unchecked_msg_cache: Vec<((SwapId, internet2::TypeId), PeerMsg)>,
 
@@ -400,10 +702,14 @@ impl esb::Handler<ServiceBus> for Runtime {
     fn on_ready(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
         // log iff taker
         if !self.forked_from_listener {
-            let (peer_sender, thread_flag_tx) = match start_connect_peer_listener_runtime(
+            let connected = match start_connect_peer_listener_runtime(
                 self.remote_node_addr.expect("Checked for connecter"),
+                self.tor_proxy,
                 self.local_node,
+                &self.local_init,
+                self.limits,
                 self.identity(),
+                &self.bridge,
             ) {
                 Ok(val) => {
                     debug!(
@@ -434,9 +740,13 @@ impl esb::Handler<ServiceBus> for Runtime {
                     return Ok(());
                 }
             };
+            let (peer_sender, thread_flag_tx, remote_init, features) = connected;
 
             self.peer_sender = Some(peer_sender);
             self.thread_flag_tx = thread_flag_tx;
+            self.remote_init = Some(remote_init);
+            self.features = features;
+            self.connected_since = SystemTime::now();
             info!(
                 "{} with the remote peer {}",
                 "Successfully connected".bright_blue_bold(),
@@ -496,7 +806,7 @@ impl Runtime {
             self.identity(),
             &message.get_type()
         );
-        self.messages_sent += 1;
+        self.count_sent(&message);
         while let Err(err) = self
             .peer_sender
             .as_mut()
@@ -594,7 +904,7 @@ impl Runtime {
                     std::process::id()
                 );
 
-                std::process::exit(0);
+                exit_service(0);
             }
 
             _ => {
@@ -634,10 +944,30 @@ impl Runtime {
                         .duration_since(SystemTime::UNIX_EPOCH)
                         .unwrap_or_else(|_| Duration::from_secs(0))
                         .as_secs(),
+                    connection_uptime: SystemTime::now()
+                        .duration_since(self.connected_since)
+                        .unwrap_or_else(|_| Duration::from_secs(0)),
+                    reconnects: self.reconnects,
                     messages_sent: self.messages_sent,
                     messages_received: self.messages_received,
+                    bytes_sent: self.bytes_sent,
+                    bytes_received: self.bytes_received,
+                    rtt: self.rtt,
+                    average_rtt: if self.pongs > 0 {
+                        Some(self.rtt_total / self.pongs)
+                    } else {
+                        None
+                    },
+                    limit_violations: self.limit_violations,
                     forked_from_listener: self.forked_from_listener,
                     awaits_pong: self.awaited_pong.is_some(),
+                    protocol_version: self.remote_init.as_ref().map(|init| init.protocol_version),
+                    features: self.features,
+                    networks: self
+                        .remote_init
+                        .as_ref()
+                        .map(|init| init.networks.clone())
+                        .unwrap_or_default(),
                 };
                 self.send_client_info(endpoints, source, InfoMsg::PeerInfo(info))?;
             }
@@ -664,8 +994,12 @@ impl Runtime {
         loop {
             match start_connect_peer_listener_runtime(
                 self.remote_node_addr.expect("Checked for connnecter"),
+                self.tor_proxy,
                 self.local_node,
+                &self.local_init,
+                self.limits,
                 self.identity(),
+                &self.bridge,
             ) {
                 Err(err) => {
                     attempt += 1;
@@ -677,7 +1011,7 @@ impl Runtime {
                     );
                     std::thread::sleep(std::time::Duration::from_secs(attempt));
                 }
-                Ok((peer_sender, thread_flag_tx)) => {
+                Ok((peer_sender, thread_flag_tx, remote_init, features)) => {
                     info!(
                         "{} | Reconnect success after {} attempts",
                         self.identity(),
@@ -685,20 +1019,27 @@ impl Runtime {
                     );
                     self.peer_sender = Some(peer_sender);
                     self.thread_flag_tx = thread_flag_tx;
+                    self.remote_init = Some(remote_init);
+                    self.features = features;
+                    self.reconnects += 1;
+                    self.connected_since = SystemTime::now();
+                    // the ping in flight, if any, was lost with the connection
+                    self.ping_sent_at = None;
                     break;
                 }
             }
         }
-        for (_, cached_msg) in self.unchecked_msg_cache.iter() {
+        for (_, cached_msg) in self.unchecked_msg_cache.clone() {
             info!(
                 "{} | re-emitting cached message after reconnect: {}",
                 cached_msg.swap_id(),
                 cached_msg
             );
+            self.count_sent(&cached_msg);
             self.peer_sender
                 .as_mut()
                 .expect("should be connected")
-                .send_message(cached_msg.clone())?;
+                .send_message(cached_msg)?;
         }
         endpoints.send_to(
             ServiceBus::Ctl,
@@ -718,7 +1059,11 @@ impl Runtime {
     ) -> Result<(), Error> {
         debug!("{} | BRIDGE RPC request: {}", self.identity(), request);
 
-        self.messages_received += 1;
+        // the other messages are sent by the receiver thread
+        if request.on_receiver_whitelist() {
+            self.messages_received += 1;
+            self.bytes_received += request.serialize().len() as u64;
+        }
 
         match &request {
             PeerMsg::PingPeer => self.ping()?,
@@ -742,6 +1087,19 @@ impl Runtime {
                         self.identity()
                     ),
                 }
+                if let (Some(_), Some(sent_at)) = (self.awaited_pong, self.ping_sent_at.take()) {
+                    let rtt = sent_at.elapsed();
+                    trace!("{} | Round-trip time: {:?}", self.identity(), rtt);
+                    self.rtt = Some(rtt);
+                    self.rtt_total += rtt;
+                    self.pongs += 1;
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        ServiceId::Farcasterd,
+                        BusMsg::Ctl(CtlMsg::PeerRoundTrip(rtt)),
+                    )?;
+                }
                 self.awaited_pong = None;
             }
 
@@ -791,6 +1149,16 @@ impl Runtime {
                 }
             }
 
+            PeerMsg::LimitViolations(count) => {
+                self.limit_violations += *count as u64;
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::Ctl(CtlMsg::PeerLimitViolations(*count)),
+                )?;
+            }
+
             PeerMsg::MsgReceipt(receipt) => {
                 debug!("{} | received receipt: {:?}", request.swap_id(), receipt);
 
@@ -798,12 +1166,20 @@ impl Runtime {
                     .iter()
                     .position(|(key, _)| key.0 == receipt.swap_id && key.1 == receipt.msg_type)
                     .map(|pos| self.unchecked_msg_cache.remove(pos));
+
+                // the message must not be resent after a restart either
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Database,
+                    BusMsg::Ctl(CtlMsg::PeerMessageAcknowledged(receipt.clone())),
+                )?;
             }
 
             // swap initiation message
             PeerMsg::TakerCommit(_) => {
                 let swap_id = request.swap_id();
-                let msg_type = request.get_type();
+                let receipt = request.msg_id();
                 debug!(
                     "{} | PID {} | Received the {} protocol message, forwarding to farcasterd",
                     swap_id.swap_id(),
@@ -818,11 +1194,7 @@ impl Runtime {
                 )?;
 
                 // send a receipt back to the remote peer
-                self.handle_msg(
-                    endpoints,
-                    source,
-                    PeerMsg::MsgReceipt(Receipt { swap_id, msg_type }),
-                )?;
+                self.handle_msg(endpoints, source, PeerMsg::MsgReceipt(receipt))?;
             }
 
             msg => {
@@ -842,13 +1214,12 @@ impl Runtime {
                 )?;
 
                 // send a receipt back to the remote peer
+                let receipt = PeerMsg::MsgReceipt(request.msg_id());
+                self.count_sent(&receipt);
                 self.peer_sender
                     .as_mut()
                     .expect("should be connected")
-                    .send_message(PeerMsg::MsgReceipt(Receipt {
-                        swap_id: request.swap_id(),
-                        msg_type: request.get_type(),
-                    }))?;
+                    .send_message(receipt)?;
             }
         }
         Ok(())
@@ -861,12 +1232,14 @@ impl Runtime {
         let mut noise = vec![0u8; len as usize];
         rng.fill_bytes(&mut noise);
         let pong_size = rng.gen_range(4, 32);
-        self.messages_sent += 1;
+        let ping = PeerMsg::Ping(pong_size);
+        self.count_sent(&ping);
         self.peer_sender
             .as_mut()
             .expect("should be connected")
-            .send_message(PeerMsg::Ping(pong_size))?;
+            .send_message(ping)?;
         self.awaited_pong = Some(pong_size);
+        self.ping_sent_at = Some(Instant::now());
         Ok(())
     }
 
@@ -880,11 +1253,18 @@ impl Runtime {
             .iter()
             .map(|_| rng.gen())
             .collect();
-        self.messages_sent += 1;
+        let pong = PeerMsg::Pong(noise);
+        self.count_sent(&pong);
         self.peer_sender
             .as_mut()
             .expect("should be connected")
-            .send_message(PeerMsg::Pong(noise))?;
+            .send_message(pong)?;
         Ok(())
     }
+
+    /// Count a message sent to the remote peer in the connection statistics
+    fn count_sent(&mut self, message: &PeerMsg) {
+        self.messages_sent += 1;
+        self.bytes_sent += message.serialize().len() as u64;
+    }
 }
@@ -0,0 +1,189 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Outgoing peer connections through a SOCKS5 proxy, used to reach makers advertising an onion
+//! address. The brontozaur session only connects to socket addresses, so the proxied connection
+//! is exposed on a loopback port forwarding it both ways. Any local process can connect to that
+//! port, the forwarder only accepts the connection opened by peerd itself.
+
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::io::{self, Read, Write};
+#[cfg(target_os = "linux")]
+use std::net::SocketAddrV4;
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::thread::spawn;
+use std::time::Duration;
+
+use internet2::addr::InetSocketAddr;
+
+use crate::Error;
+
+/// Time allowed to the proxy to open the connection with the remote peer
+const PROXY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Connects to the remote address through the SOCKS5 proxy and returns the loopback address
+/// forwarding to it
+pub fn forward_through_proxy(
+    proxy: SocketAddr,
+    remote: &InetSocketAddr,
+) -> Result<SocketAddr, Error> {
+    let remote = remote.to_string();
+    let (host, port) = remote
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .ok_or_else(|| Error::Farcaster(format!("Invalid remote peer address {}", remote)))?;
+    let upstream =
+        connect(proxy, host.trim_matches(|c| c == '[' || c == ']'), port).map_err(|err| {
+            Error::Farcaster(format!(
+                "Unable to connect to {} through the proxy {}: {}",
+                remote, proxy, err
+            ))
+        })?;
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let local = listener.local_addr()?;
+    spawn(move || loop {
+        match listener.accept() {
+            Ok((downstream, _)) if is_own_connection(&downstream) => {
+                pipe(downstream, upstream);
+                break;
+            }
+            Ok((_, peer)) => warn!(
+                "Refused connection from {} to the proxied peer connection, it does not belong \
+                to peerd",
+                peer
+            ),
+            Err(err) => {
+                error!("Unable to forward the proxied peer connection: {}", err);
+                break;
+            }
+        }
+    });
+    Ok(local)
+}
+
+/// Returns true if the other end of a loopback connection is a socket of this process, i.e. its
+/// socket inode listed in `/proc/net/tcp` is one of the file descriptors of the process
+#[cfg(target_os = "linux")]
+fn is_own_connection(stream: &TcpStream) -> bool {
+    let (local, peer) = match (stream.local_addr(), stream.peer_addr()) {
+        (Ok(SocketAddr::V4(local)), Ok(SocketAddr::V4(peer))) => (local, peer),
+        _ => return false,
+    };
+    // addresses are listed as the hexadecimal IPv4 in host byte order and port
+    let proc_addr = |addr: SocketAddrV4| {
+        format!(
+            "{:08X}:{:04X}",
+            u32::from_ne_bytes(addr.ip().octets()),
+            addr.port()
+        )
+    };
+    let (remote_end, local_end) = (proc_addr(peer), proc_addr(local));
+    let inode = fs::read_to_string("/proc/net/tcp").ok().and_then(|table| {
+        table.lines().skip(1).find_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [_, local, remote, _, _, _, _, _, _, inode, ..]
+                    if *local == remote_end && *remote == local_end =>
+                {
+                    Some(format!("socket:[{}]", inode))
+                }
+                _ => None,
+            }
+        })
+    });
+    let inode = match inode {
+        Some(inode) => inode,
+        None => return false,
+    };
+    fs::read_dir("/proc/self/fd")
+        .map(|fds| {
+            fds.filter_map(|fd| fs::read_link(fd.ok()?.path()).ok())
+                .any(|target| target.to_string_lossy() == inode)
+        })
+        .unwrap_or(false)
+}
+
+/// The owner of a loopback connection cannot be checked on this platform, the first connection
+/// is accepted
+#[cfg(not(target_os = "linux"))]
+fn is_own_connection(_: &TcpStream) -> bool {
+    true
+}
+
+/// Opens a connection to the host through the SOCKS5 proxy, the proxy resolves the host name
+fn connect(proxy: SocketAddr, host: &str, port: u16) -> io::Result<TcpStream> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    if host.len() > u8::MAX as usize {
+        return Err(invalid("host name too long for SOCKS5"));
+    }
+    let mut stream = TcpStream::connect_timeout(&proxy, PROXY_TIMEOUT)?;
+    stream.set_read_timeout(Some(PROXY_TIMEOUT))?;
+
+    // greeting without authentication
+    stream.write_all(&[5, 1, 0])?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply)?;
+    if reply != [5, 0] {
+        return Err(invalid("SOCKS5 proxy refused the unauthenticated greeting"));
+    }
+
+    // connect request with a domain name address
+    let mut request = vec![5, 1, 0, 3, host.len() as u8];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request)?;
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply)?;
+    if reply[1] != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("SOCKS5 proxy failed to connect with code {}", reply[1]),
+        ));
+    }
+    // skip the bound address and port
+    let address_len = match reply[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        _ => return Err(invalid("SOCKS5 proxy replied with an unknown address type")),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound)?;
+    stream.set_read_timeout(None)?;
+    Ok(stream)
+}
+
+/// Forwards the data between the two streams until one of them is closed
+fn pipe(mut downstream: TcpStream, mut upstream: TcpStream) {
+    let (mut downstream_reader, mut upstream_writer) =
+        match (downstream.try_clone(), upstream.try_clone()) {
+            (Ok(downstream_reader), Ok(upstream_writer)) => (downstream_reader, upstream_writer),
+            _ => {
+                error!("Unable to forward the proxied peer connection");
+                return;
+            }
+        };
+    spawn(move || {
+        let _ = io::copy(&mut downstream_reader, &mut upstream_writer);
+        let _ = upstream_writer.shutdown(Shutdown::Both);
+    });
+    let _ = io::copy(&mut upstream, &mut downstream);
+    let _ = downstream.shutdown(Shutdown::Both);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn own_loopback_connection_is_recognized() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (accepted, _) = listener.accept().unwrap();
+    assert!(is_own_connection(&accepted));
+}
@@ -334,6 +334,33 @@ where
     }
 }
 
+#[cfg(feature = "node")]
+thread_local! {
+    static SERVICE_THREAD: std::cell::Cell<bool> = std::cell::Cell::new(false);
+}
+
+/// Unwinding payload used by [`exit_service`] to stop a service running as a thread
+#[cfg(feature = "node")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ServiceExitCode(pub i32);
+
+/// Mark the current thread as running a service sharing its process with other services, e.g.
+/// inside an embedded node
+#[cfg(feature = "node")]
+pub fn mark_service_thread() {
+    SERVICE_THREAD.with(|flag| flag.set(true));
+}
+
+/// Terminate the current service. A service running as a thread unwinds its own thread with a
+/// [`ServiceExitCode`] payload instead of exiting the whole process.
+#[cfg(feature = "node")]
+pub fn exit_service(code: i32) -> ! {
+    if SERVICE_THREAD.with(|flag| flag.get()) {
+        std::panic::resume_unwind(Box::new(ServiceExitCode(code)))
+    }
+    std::process::exit(code)
+}
+
 pub type Endpoints = esb::EndpointList<ServiceBus>;
 
 pub trait TryToServiceId {
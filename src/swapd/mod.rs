@@ -19,3 +19,4 @@ pub use runtime::run;
 pub use runtime::CheckpointSwapd;
 pub use state_report::StateReport;
 pub use swap_state::SwapStateMachine;
+pub use temporal_safety::TemporalSafety;
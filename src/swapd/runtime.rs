@@ -10,11 +10,12 @@ use super::{
     temporal_safety::TemporalSafety,
     StateReport,
 };
+use crate::service::exit_service;
 use crate::syncerd::types::{Event, TransactionConfirmations};
-use crate::syncerd::{Abort, Task, TaskTarget};
+use crate::syncerd::{Abort, FeeChoice, FeeSource, Task, TaskTarget};
 use crate::{
     bus::ctl::{Checkpoint, CtlMsg},
-    bus::info::{InfoMsg, SwapInfo},
+    bus::info::{InfoMsg, PeerMessages, SwapInfo},
     bus::p2p::PeerMsg,
     bus::sync::SyncMsg,
     bus::{BusMsg, ServiceBus},
@@ -29,11 +30,13 @@ use crate::{
 use crate::{CtlServer, Error, LogStyle, Service, ServiceConfig, ServiceId};
 
 use std::any::Any;
+use std::collections::HashSet;
+use std::fmt::Display;
 use std::time::{Duration, SystemTime};
 
 use bitcoin::Txid;
 use farcaster_core::{
-    blockchain::Blockchain,
+    blockchain::{Blockchain, FeeStrategy},
     role::{SwapRole, TradeRole},
     swap::btcxmr::{Deal, DealParameters},
     swap::SwapId,
@@ -41,6 +44,7 @@ use farcaster_core::{
 };
 
 use internet2::addr::{NodeAddr, NodeId};
+use internet2::TypedEnum;
 use microservices::esb::{self, Handler};
 use strict_encoding::{StrictDecode, StrictEncode};
 
@@ -113,6 +117,8 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
         confirmations: none!(),
         broadcasted_txs: none!(),
         failed_broadcasted_txs: none!(),
+        tx_fees: none!(),
+        sweep_fee: None,
     };
 
     let state_report = StateReport::new("Start".to_string(), &temporal_safety, &syncer_state);
@@ -129,13 +135,17 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
         syncer_state,
         temporal_safety,
         enquirer: None,
-        pending_peer_request: none!(),
+        sweep_bitcoin_address: None,
+        received_peer_messages: none!(),
         deal,
         local_trade_role,
         local_swap_role,
         latest_state_report: state_report,
         swap_state_machine,
         unhandled_peer_message: None, // The last message we received and was not handled by the state machine
+        checkpointed: false,
+        draining: false,
+        drained: false,
     };
     let broker = false;
     Service::run(config, runtime, broker)
@@ -148,18 +158,22 @@ pub struct Runtime {
     pub connected: bool,
     pub started: SystemTime,
     pub enquirer: Option<ServiceId>,
+    pub sweep_bitcoin_address: Option<bitcoin::Address>, // Destination of Bob's funding address sweep, the refund address if none
     pub syncer_state: SyncerState,
     pub temporal_safety: TemporalSafety,
-    pub pending_peer_request: Vec<PeerMsg>, // Peer requests that failed and are waiting for reconnection
+    pub received_peer_messages: HashSet<internet2::TypeId>, // Types of the peer messages already handled, resent duplicates are dropped
     pub deal: Deal,
     pub local_trade_role: TradeRole,
     pub local_swap_role: SwapRole,
     pub latest_state_report: StateReport,
     pub swap_state_machine: SwapStateMachine,
     pub unhandled_peer_message: Option<PeerMsg>,
+    pub checkpointed: bool, // Set once the swap is checkpointed, either by this instance or restored from a checkpoint
+    pub draining: bool,     // Set by farcasterd when the node drains before shutting down
+    pub drained: bool,      // Set once drained, the swap stops progressing until terminated
 }
 
-#[derive(Debug, Clone, Display, StrictEncode, StrictDecode)]
+#[derive(Debug, Clone, Display, StrictEncode)]
 #[display("checkpoint-swapd")]
 pub struct CheckpointSwapd {
     pub state: SwapStateMachine,
@@ -172,6 +186,27 @@ pub struct CheckpointSwapd {
     pub local_trade_role: TradeRole,
     pub connected_counterparty_node_id: Option<NodeId>,
     pub deal: Deal,
+    pub sweep_bitcoin_address: Option<bitcoin::Address>,
+}
+
+// Swaps checkpointed before the hot wallet was introduced sweep to the refund address
+impl StrictDecode for CheckpointSwapd {
+    fn strict_decode<D: std::io::Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
+        Ok(CheckpointSwapd {
+            state: StrictDecode::strict_decode(&mut d)?,
+            pending_msg: StrictDecode::strict_decode(&mut d)?,
+            enquirer: StrictDecode::strict_decode(&mut d)?,
+            xmr_addr_addendum: StrictDecode::strict_decode(&mut d)?,
+            temporal_safety: StrictDecode::strict_decode(&mut d)?,
+            txids: StrictDecode::strict_decode(&mut d)?,
+            pending_broadcasts: StrictDecode::strict_decode(&mut d)?,
+            local_trade_role: StrictDecode::strict_decode(&mut d)?,
+            connected_counterparty_node_id: StrictDecode::strict_decode(&mut d)?,
+            deal: StrictDecode::strict_decode(&mut d)?,
+            sweep_bitcoin_address: Option::<bitcoin::Address>::strict_decode(&mut d)
+                .unwrap_or_default(),
+        })
+    }
 }
 
 impl CtlServer for Runtime {}
@@ -206,15 +241,22 @@ impl esb::Handler<ServiceBus> for Runtime {
         source: ServiceId,
         request: BusMsg,
     ) -> Result<(), Self::Error> {
+        // Once drained the swap stops progressing, only termination and info requests are handled
+        if self.drained && !matches!(request, BusMsg::Ctl(CtlMsg::Terminate) | BusMsg::Info(_)) {
+            self.log_debug(format!("Drained, ignoring {} from {}", request, source));
+            return Ok(());
+        }
         match (bus, request) {
             // Peer-to-peer message bus, only accept peer message
             (ServiceBus::Msg, BusMsg::P2p(req)) => {
                 self.handle_msg(endpoints, source, req)?;
+                self.try_drain(endpoints)?;
                 self.report_potential_state_change(endpoints)
             }
             // Control bus for issuing control commands, only accept Ctl message
             (ServiceBus::Ctl, BusMsg::Ctl(req)) => {
                 self.handle_ctl(endpoints, source, req)?;
+                self.try_drain(endpoints)?;
                 self.report_potential_state_change(endpoints)
             }
             // Info command bus, only accept Info message
@@ -222,6 +264,7 @@ impl esb::Handler<ServiceBus> for Runtime {
             // Syncer event bus for blockchain tasks and events, only accept Sync message
             (ServiceBus::Sync, BusMsg::Sync(req)) => {
                 self.handle_sync(endpoints, source, req)?;
+                self.try_drain(endpoints)?;
                 self.report_potential_state_change(endpoints)
             }
             // All other pairs are not supported
@@ -238,7 +281,19 @@ impl esb::Handler<ServiceBus> for Runtime {
 }
 
 impl Runtime {
+    /// Send a protocol message to the counterparty. The message is kept by databased until the
+    /// counterparty acknowledges it, and resent after reconnects and restores until then.
     pub fn send_peer(&mut self, endpoints: &mut Endpoints, msg: PeerMsg) -> Result<(), Error> {
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Ctl(CtlMsg::StorePeerMessage(msg.clone())),
+        )?;
+        self.transmit_peer(endpoints, msg)
+    }
+
+    fn transmit_peer(&mut self, endpoints: &mut Endpoints, msg: PeerMsg) -> Result<(), Error> {
         self.log_trace(format!(
             "sending peer message {} to {}",
             msg, self.peer_service
@@ -263,11 +318,22 @@ impl Runtime {
                 ServiceId::Farcasterd,
                 BusMsg::Ctl(CtlMsg::PeerdUnreachable(self.peer_service.clone())),
             )?;
-            self.pending_peer_request.push(msg);
         }
         Ok(())
     }
 
+    /// Ask databased for the messages not acknowledged by the counterparty and the messages
+    /// already received, answered with a PeerMessages message
+    fn request_peer_messages(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        endpoints.send_to(
+            ServiceBus::Info,
+            self.identity(),
+            ServiceId::Database,
+            BusMsg::Info(InfoMsg::GetPeerMessages(self.swap_id())),
+        )?;
+        Ok(())
+    }
+
     pub fn swap_id(&self) -> SwapId {
         match self.identity {
             ServiceId::Swap(swap_id) => swap_id,
@@ -288,6 +354,11 @@ impl Runtime {
             tx_label.label(),
             tx.txid().tx_hash()
         ));
+        if tx_label != TxLabel::Lock && !self.syncer_state.tx_fees.contains_key(&tx_label) {
+            let fee = self.deal_fee();
+            self.report_fee(endpoints, tx_label, &fee);
+            self.syncer_state.tx_fees.insert(tx_label, fee);
+        }
         let task = self.syncer_state.broadcast(tx, tx_label);
         Ok(endpoints.send_to(
             ServiceBus::Sync,
@@ -297,6 +368,28 @@ impl Runtime {
         )?)
     }
 
+    /// Log the fee chosen for a transaction and add it to the swap timeline
+    pub fn report_fee(&mut self, endpoints: &mut Endpoints, tx: impl Display, fee: &FeeChoice) {
+        let msg = format!("{} transaction fee: {}", tx, fee);
+        self.log_info(&msg);
+        self.report_progress_message_log_fail(endpoints, msg);
+    }
+
+    // Fee rate of the arbitrating transactions other than lock, set by the deal fee strategy at
+    // low priority
+    fn deal_fee(&self) -> FeeChoice {
+        let sats_per_kvbyte = match &self.deal.parameters.fee_strategy {
+            FeeStrategy::Fixed(fee_rate) => fee_rate.as_sat(),
+            FeeStrategy::Range { min_inc, .. } => min_inc.as_sat(),
+        };
+        FeeChoice {
+            sats_per_kvbyte,
+            target: None,
+            source: FeeSource::Deal,
+            clamped: None,
+        }
+    }
+
     fn handle_msg(
         &mut self,
         endpoints: &mut Endpoints,
@@ -324,6 +417,12 @@ impl Runtime {
             return Err(Error::Farcaster(msg));
         }
 
+        // The counterparty resends the messages we did not acknowledge before a restart
+        if self.received_peer_messages.contains(&request.get_type()) {
+            self.log_debug(format!("Ignoring duplicate peer message {}", request));
+            return Ok(());
+        }
+
         match request {
             // bob and alice
             PeerMsg::Abort(_) => {
@@ -358,7 +457,12 @@ impl Runtime {
             }
             CtlMsg::Terminate if source == ServiceId::Farcasterd => {
                 self.log_info(format!("Terminating {}", self.identity()).label());
-                std::process::exit(0);
+                exit_service(0);
+            }
+
+            CtlMsg::Drain if source == ServiceId::Farcasterd => {
+                self.log_info("Draining, waiting for a safe point to stop at");
+                self.draining = true;
             }
 
             CtlMsg::Disconnected => {
@@ -377,18 +481,14 @@ impl Runtime {
                 self.log_info(format!("Peer {} reconnected", service_id));
                 self.peer_service = service_id;
                 self.connected = true;
-                for msg in self.pending_peer_request.clone().iter() {
-                    self.send_peer(endpoints, msg.clone())?;
-                }
-                self.pending_peer_request.clear();
+                self.request_peer_messages(endpoints)?;
             }
 
             CtlMsg::FailedPeerMessage(msg) => {
                 self.log_warn(format!(
-                    "Sending the peer message {} failed. Adding to pending peer requests",
+                    "Sending the peer message {} failed, it will be resent once reconnected",
                     msg
                 ));
-                self.pending_peer_request.push(msg);
             }
 
             CtlMsg::Checkpoint(Checkpoint { swap_id: _, state }) => {
@@ -401,11 +501,14 @@ impl Runtime {
                     xmr_addr_addendum,
                     local_trade_role,
                     state,
+                    sweep_bitcoin_address,
                     ..
                 } = state;
                 self.log_info("Restoring swap");
                 self.swap_state_machine = state;
+                self.checkpointed = true;
                 self.enquirer = enquirer;
+                self.sweep_bitcoin_address = sweep_bitcoin_address;
                 self.temporal_safety = temporal_safety;
                 // We need to update the peerd for the pending requests in case of reconnect
                 self.local_trade_role = local_trade_role;
@@ -459,6 +562,7 @@ impl Runtime {
                 if let Some(msg) = pending_msg {
                     self.send_peer(endpoints, msg)?;
                 }
+                self.request_peer_messages(endpoints)?;
             }
 
             req => {
@@ -499,6 +603,23 @@ impl Runtime {
                 self.send_client_info(endpoints, source, InfoMsg::SwapInfo(info))?;
             }
 
+            InfoMsg::PeerMessages(PeerMessages {
+                outbox, received, ..
+            }) if source == ServiceId::Database => {
+                self.received_peer_messages.extend(received);
+                if self.connected {
+                    for msg in outbox {
+                        self.log_debug(format!("Resending unacknowledged peer message {}", msg));
+                        self.transmit_peer(endpoints, msg)?;
+                    }
+                } else if !outbox.is_empty() {
+                    self.log_debug(format!(
+                        "{} unacknowledged peer messages will be resent once reconnected",
+                        outbox.len()
+                    ));
+                }
+            }
+
             req => {
                 self.log_error(format!(
                     "BusMsg {} is not supported by the INFO interface",
@@ -699,6 +820,17 @@ impl Runtime {
             self.swap_state_machine.clone(),
         )? {
             self.swap_state_machine = ssm;
+            // Remember the handled peer message to drop it if it is resent
+            if let BusMsg::P2p(peer_msg) = &msg {
+                if self.received_peer_messages.insert(peer_msg.get_type()) {
+                    endpoints.send_to(
+                        ServiceBus::Ctl,
+                        self.identity(),
+                        ServiceId::Database,
+                        BusMsg::Ctl(CtlMsg::PeerMessageReceived(peer_msg.msg_id())),
+                    )?;
+                }
+            }
             // On SwapEnd, report immediately to ensure the progress message goes out before the swap is terminated, then let farcasterd know of the outcome.
             if let SwapStateMachine::SwapEnd(outcome) = &self.swap_state_machine {
                 let outcome = outcome.clone(); // so we don't borrow self anymore
@@ -759,6 +891,7 @@ impl Runtime {
         pending_msg: Option<PeerMsg>,
         next_state: SwapStateMachine,
     ) -> Result<(), Error> {
+        self.checkpointed = true;
         endpoints.send_to(
             ServiceBus::Ctl,
             self.identity(),
@@ -776,12 +909,48 @@ impl Runtime {
                     local_trade_role: self.local_trade_role,
                     connected_counterparty_node_id: self.peer_service.node_id(),
                     deal: self.deal.clone(),
+                    sweep_bitcoin_address: self.sweep_bitcoin_address.clone(),
                 },
             })),
         )?;
         Ok(())
     }
 
+    /// While draining, notify farcasterd as soon as the swap can be terminated and resumed
+    /// later from its checkpoint: a checkpoint exists and no broadcast is in flight. A swap
+    /// without checkpoint cannot be resumed, it is aborted instead.
+    fn try_drain(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        if !self.draining || self.drained {
+            return Ok(());
+        }
+        match self.swap_state_machine {
+            // await the init or checkpoint request of a just launched swapd
+            SwapStateMachine::StartTaker(_) | SwapStateMachine::StartMaker(_) => {}
+            // already aborted, the swap ends once the funding is swept
+            SwapStateMachine::BobAbortAwaitingBitcoinSweep | SwapStateMachine::SwapEnd(_) => {}
+            _ if !self.checkpointed => {
+                self.log_info("Not checkpointed yet, aborting the swap instead of draining it");
+                self.execute_state_machine(
+                    endpoints,
+                    BusMsg::Ctl(CtlMsg::AbortSwap),
+                    ServiceId::Farcasterd,
+                )?;
+            }
+            _ if self.syncer_state.tasks.broadcasting_txs.is_empty() => {
+                self.log_info("Drained, awaiting termination");
+                self.drained = true;
+                endpoints.send_to(
+                    ServiceBus::Ctl,
+                    self.identity(),
+                    ServiceId::Farcasterd,
+                    BusMsg::Ctl(CtlMsg::Drained),
+                )?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
     pub fn abort_all_syncer_tasks(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
         let abort_all = Task::Abort(Abort {
             task_target: TaskTarget::AllTasks,
@@ -9,6 +9,7 @@ use strict_encoding::{NetworkDecode, NetworkEncode};
 
 use crate::bus::{Progress, StateTransition};
 use crate::swapd::temporal_safety::SWEEP_MONERO_THRESHOLD;
+use crate::syncerd::FeeChoice;
 
 use super::{syncer_client::SyncerState, temporal_safety::TemporalSafety};
 
@@ -36,6 +37,12 @@ pub struct StateReport {
     pub blocks_until_punish_possible: Option<i64>,
     pub blocks_until_safe_buy: Option<u32>,
     pub blocks_until_safe_monero_buy_sweep: Option<u32>,
+    pub lock_fee: Option<FeeChoice>,
+    pub cancel_fee: Option<FeeChoice>,
+    pub refund_fee: Option<FeeChoice>,
+    pub buy_fee: Option<FeeChoice>,
+    pub punish_fee: Option<FeeChoice>,
+    pub sweep_fee: Option<FeeChoice>,
 }
 
 impl StateReport {
@@ -78,6 +85,12 @@ impl StateReport {
             blocks_until_safe_monero_buy_sweep: syncer_state
                 .get_confs(TxLabel::AccLock)
                 .map(|c| SWEEP_MONERO_THRESHOLD.saturating_sub(c)),
+            lock_fee: syncer_state.tx_fees.get(&TxLabel::Lock).cloned(),
+            cancel_fee: syncer_state.tx_fees.get(&TxLabel::Cancel).cloned(),
+            refund_fee: syncer_state.tx_fees.get(&TxLabel::Refund).cloned(),
+            buy_fee: syncer_state.tx_fees.get(&TxLabel::Buy).cloned(),
+            punish_fee: syncer_state.tx_fees.get(&TxLabel::Punish).cloned(),
+            sweep_fee: syncer_state.sweep_fee.clone(),
         }
     }
 
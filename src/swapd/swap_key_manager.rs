@@ -430,15 +430,18 @@ impl BobSwapKeyManager {
         self.funding_tx.get_address().ok()
     }
 
+    /// Sweep the funding address to the given address, or to the refund address if none
     pub fn process_get_sweep_bitcoin_address(
         &mut self,
         source_address: bitcoin::Address,
+        sweep_bitcoin_address: Option<bitcoin::Address>,
     ) -> Result<SweepBitcoinAddress, Error> {
         let BobSwapKeyManager {
             key_manager, bob, ..
         } = self;
         let source_secret_key = key_manager.get_or_derive_bitcoin_key(ArbitratingKeyId::Lock)?;
-        let destination_address = bob.refund_address.clone();
+        let destination_address =
+            sweep_bitcoin_address.unwrap_or_else(|| bob.refund_address.clone());
         Ok(SweepBitcoinAddress {
             source_secret_key,
             source_address,
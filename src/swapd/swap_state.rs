@@ -22,6 +22,7 @@ use microservices::esb::Handler;
 use monero::ViewPair;
 use strict_encoding::{StrictDecode, StrictEncode};
 
+use crate::farcasterd::stats::{StatsEvent, StatsEventKind};
 use crate::{
     bus::ctl::BitcoinFundingInfo,
     syncerd::{bitcoin_syncer::p2wpkh_signed_tx_fee, AddressTransaction},
@@ -31,7 +32,7 @@ use crate::{bus::p2p::Reveal, swapd::temporal_safety::SWEEP_MONERO_THRESHOLD};
 use crate::{
     bus::{
         ctl::{CtlMsg, InitMakerSwap, InitTakerSwap},
-        p2p::{Commit, PeerMsg, TakerCommit},
+        p2p::{Commit, DealRefused, PeerMsg, TakerCommit},
         BusMsg, Failure, FailureCode,
     },
     event::{Event, StateMachine},
@@ -359,6 +360,8 @@ pub struct AliceReveal {
 #[derive(Clone, Debug, StrictEncode, StrictDecode)]
 pub struct BobFeeEstimated {
     required_funding_amount: bitcoin::Amount,
+    // fee of the lock transaction included in the funding amount, from the syncer fee estimation
+    lock_fee: bitcoin::Amount,
     remote_params: Parameters,
     swap_key_manager: BobSwapKeyManager,
 }
@@ -624,6 +627,7 @@ fn attempt_transition_to_init_taker(
             ref key_manager,
             ref target_bitcoin_address,
             target_monero_address,
+            ref sweep_bitcoin_address,
         })) => {
             if ServiceId::Swap(swap_id) != runtime.identity {
                 runtime.log_error(format!(
@@ -646,6 +650,7 @@ fn attempt_transition_to_init_taker(
                 runtime.connected = true;
             }
             runtime.enquirer = Some(report_to.clone());
+            runtime.sweep_bitcoin_address = sweep_bitcoin_address.clone();
 
             match swap_role {
                 SwapRole::Bob => {
@@ -749,6 +754,7 @@ fn attempt_transition_to_init_maker(
             swap_id: _,
             target_bitcoin_address,
             target_monero_address,
+            sweep_bitcoin_address,
             commit: remote_commit,
         })) => {
             // start watching block height changes
@@ -763,6 +769,7 @@ fn attempt_transition_to_init_maker(
                 runtime.connected = true;
             }
             runtime.enquirer = Some(report_to);
+            runtime.sweep_bitcoin_address = sweep_bitcoin_address;
 
             match swap_role {
                 SwapRole::Bob => {
@@ -885,6 +892,14 @@ fn try_bob_init_taker_to_bob_taker_maker_commit(
             // just cancel the swap, no additional logic required
             handle_bob_abort_swap(event, runtime, swap_key_manager)
         }
+        BusMsg::P2p(PeerMsg::DealRefused(DealRefused { reason, .. })) => {
+            runtime.log_error(format!(
+                "Taken deal {} was refused by the maker ({}), aborting this swap.",
+                runtime.deal.id().swap_id(),
+                reason,
+            ));
+            handle_bob_abort_swap(event, runtime, swap_key_manager)
+        }
         BusMsg::P2p(PeerMsg::MakerCommit(Commit::AliceParameters(remote_commit))) => {
             runtime.log_debug("Received remote maker commitment");
             let reveal = swap_key_manager.create_reveal_from_local_params(runtime)?;
@@ -918,6 +933,14 @@ fn try_alice_init_taker_to_alice_taker_maker_commit(
             // just cancel the swap, no additional logic required
             handle_abort_swap(event, runtime)
         }
+        BusMsg::P2p(PeerMsg::DealRefused(DealRefused { reason, .. })) => {
+            runtime.log_error(format!(
+                "Taken deal {} was refused by the maker ({}), aborting this swap.",
+                runtime.deal.id().swap_id(),
+                reason,
+            ));
+            handle_abort_swap(event, runtime)
+        }
         BusMsg::P2p(PeerMsg::MakerCommit(Commit::BobParameters(remote_commit))) => {
             runtime.log_debug("Received remote maker commitment");
             let reveal = swap_key_manager.create_reveal_from_local_params(runtime)?;
@@ -995,15 +1018,14 @@ fn try_bob_reveal_to_bob_fee_estimated(
     } = bob_reveal;
     match &event.request {
         BusMsg::Sync(SyncMsg::Event(SyncEvent::FeeEstimation(FeeEstimation {
-            fee_estimations:
-                FeeEstimations::BitcoinFeeEstimation {
-                    high_priority_sats_per_kvbyte,
-                    ..
-                },
+            fee_estimations: FeeEstimations::BitcoinFeeEstimation { lock, .. },
             ..
         }))) => {
-            // FIXME handle low priority as well
-            runtime.log_info(format!("Fee: {} sat/kvB", high_priority_sats_per_kvbyte));
+            runtime.report_fee(event.endpoints, TxLabel::Lock, lock);
+            runtime
+                .syncer_state
+                .tx_fees
+                .insert(TxLabel::Lock, lock.clone());
             runtime.log_debug("Sending funding info to farcasterd");
             let funding_address = swap_key_manager
                 .funding_address()
@@ -1013,7 +1035,7 @@ fn try_bob_reveal_to_bob_fee_estimated(
             let vsize = 94;
             let nr_inputs = 1;
             let total_fees = bitcoin::Amount::from_sat(p2wpkh_signed_tx_fee(
-                *high_priority_sats_per_kvbyte,
+                lock.sats_per_kvbyte,
                 vsize,
                 nr_inputs,
             ));
@@ -1048,6 +1070,7 @@ fn try_bob_reveal_to_bob_fee_estimated(
                 remote_params,
                 swap_key_manager,
                 required_funding_amount,
+                lock_fee: total_fees,
             })))
         }
         BusMsg::Ctl(CtlMsg::AbortSwap) => handle_bob_abort_swap(event, runtime, swap_key_manager),
@@ -1064,6 +1087,7 @@ fn try_bob_fee_estimated_to_bob_funded(
         remote_params,
         mut swap_key_manager,
         required_funding_amount,
+        lock_fee,
     } = bob_reveal;
     match &event.request {
         BusMsg::Sync(SyncMsg::Event(SyncEvent::AddressTransaction(AddressTransaction {
@@ -1097,6 +1121,20 @@ fn try_bob_fee_estimated_to_bob_funded(
                     ServiceId::Farcasterd,
                     CtlMsg::FundingCompleted(Blockchain::Bitcoin),
                 )?;
+                if let Err(err) = event.send_ctl_service(
+                    ServiceId::Database,
+                    CtlMsg::RecordStats(StatsEvent::new(
+                        runtime.swap_id,
+                        &runtime.deal,
+                        runtime.local_trade_role,
+                        StatsEventKind::FundingFee(lock_fee),
+                    )),
+                ) {
+                    runtime.log_warn(format!(
+                        "Failed to record the lock fee in the stats: {}",
+                        err
+                    ));
+                }
             }
 
             // process tx with swap_key_manager
@@ -1507,9 +1545,13 @@ fn try_awaiting_sweep_to_swap_end(
             Ok(Some(SwapStateMachine::SwapEnd(Outcome::FailureAbort)))
         }
 
-        BusMsg::Sync(SyncMsg::Event(SyncEvent::SweepSuccess(SweepSuccess { id, .. })))
-            if runtime.syncer_state.tasks.sweeping_addr == Some(id) =>
-        {
+        BusMsg::Sync(SyncMsg::Event(SyncEvent::SweepSuccess(SweepSuccess {
+            id, ref fee, ..
+        }))) if runtime.syncer_state.tasks.sweeping_addr == Some(id) => {
+            if let Some(fee) = fee {
+                runtime.report_fee(event.endpoints, "Sweep", fee);
+                runtime.syncer_state.sweep_fee = Some(fee.clone());
+            }
             event.send_client_ctl(
                 ServiceId::Farcasterd,
                 CtlMsg::FundingCanceled(Blockchain::Bitcoin),
@@ -2383,7 +2425,10 @@ fn handle_bob_abort_swap(
     let funding_address = swap_key_manager
         .funding_address()
         .expect("Am Bob, so have funding address");
-    let sweep_btc = swap_key_manager.process_get_sweep_bitcoin_address(funding_address)?;
+    let sweep_btc = swap_key_manager.process_get_sweep_bitcoin_address(
+        funding_address,
+        runtime.sweep_bitcoin_address.clone(),
+    )?;
     runtime.log_info(format!(
         "Sweeping source (funding) address: {} to destination address: {}",
         sweep_btc.source_address.addr(),
@@ -8,8 +8,8 @@ use crate::{
     bus::ServiceBus,
     service::{Endpoints, LogStyle, SwapDetails, SwapLogging},
     syncerd::{
-        Abort, AddressAddendum, BroadcastTransaction, BtcAddressAddendum, GetTx, SweepAddress,
-        SweepAddressAddendum, SweepBitcoinAddress, SweepMoneroAddress, TaskTarget,
+        Abort, AddressAddendum, BroadcastTransaction, BtcAddressAddendum, FeeChoice, GetTx,
+        SweepAddress, SweepAddressAddendum, SweepBitcoinAddress, SweepMoneroAddress, TaskTarget,
         TransactionBroadcasted, TxFilter, Txid, WatchAddress, WatchEstimateFee, WatchHeight,
         WatchTransaction, XmrAddressAddendum,
     },
@@ -67,6 +67,10 @@ pub struct SyncerState {
     pub awaiting_funding: bool,
     pub broadcasted_txs: HashMap<TxLabel, bitcoin::Transaction>,
     pub failed_broadcasted_txs: HashMap<TxLabel, bitcoin::Transaction>,
+    // Fees chosen for the transactions of the swap, the lock fee is only set for Bob
+    pub tx_fees: HashMap<TxLabel, FeeChoice>,
+    // Fee chosen for the sweep of Bob's funding address on abort
+    pub sweep_fee: Option<FeeChoice>,
 }
 
 impl SwapLogging for SyncerState {
@@ -4,8 +4,12 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use crate::bus::SwapParams;
+use crate::config::ParsedSwapConfig;
 use crate::Error;
 use farcaster_core::blockchain::Blockchain;
+use farcaster_core::swap::btcxmr::DealParameters;
+use std::convert::TryFrom;
 use strict_encoding::{StrictDecode, StrictEncode};
 
 /// Represent a blockchain height
@@ -37,6 +41,26 @@ pub struct TemporalSafety {
 }
 
 impl TemporalSafety {
+    /// Create the temporal parameters of a swap from the deal timelocks and the swap configuration
+    pub fn new(deal_parameters: &DealParameters, swap_config: &ParsedSwapConfig) -> Self {
+        TemporalSafety {
+            cancel_timelock: deal_parameters.cancel_timelock.as_u32(),
+            punish_timelock: deal_parameters.punish_timelock.as_u32(),
+            safety: swap_config.arbitrating.safety.into(),
+            arb_finality: swap_config.arbitrating.finality.into(),
+            acc_finality: swap_config.accordant.finality.into(),
+        }
+    }
+
+    /// Returns the finality and safety parameters used by the swap
+    pub fn swap_params(&self) -> SwapParams {
+        SwapParams {
+            arb_finality: u8::try_from(self.arb_finality).ok(),
+            arb_safety: u8::try_from(self.safety).ok(),
+            acc_finality: u8::try_from(self.acc_finality).ok(),
+        }
+    }
+
     /// Validate if temporal parameters are coherent
     pub fn valid_params(&self) -> Result<(), Error> {
         let finality = self.arb_finality;
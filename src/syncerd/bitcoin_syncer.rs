@@ -7,7 +7,10 @@
 use crate::bus::info::Address;
 use crate::bus::sync::{BridgeEvent, SyncMsg};
 use crate::bus::{AddressSecretKey, BusMsg};
+use crate::config::FeePolicy;
 use crate::error::SyncerError;
+use crate::farcasterd::metrics::RpcLatency;
+use crate::syncerd::fee_policy::{choose_fee, floor_at_relay_fee, histogram_fee_rate};
 use crate::syncerd::opts::Opts;
 use crate::syncerd::runtime::SyncerdTask;
 use crate::syncerd::runtime::Synclet;
@@ -16,7 +19,9 @@ use crate::syncerd::syncer_state::{GetTxServiceIdPair, SyncerState};
 use crate::syncerd::types::{AddressAddendum, SweepAddressAddendum, Task};
 use crate::syncerd::BtcAddressAddendum;
 use crate::syncerd::Event;
+use crate::syncerd::FeeChoice;
 use crate::syncerd::FeeEstimations;
+use crate::syncerd::FeeSource;
 use crate::syncerd::GetTx;
 use crate::syncerd::Health;
 use crate::syncerd::TaskTarget;
@@ -42,7 +47,7 @@ use internet2::TypedEnum;
 use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::{Receiver, TryRecvError};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Receiver as TokioReceiver;
 use tokio::sync::mpsc::Sender as TokioSender;
 use tokio::sync::Mutex;
@@ -59,6 +64,7 @@ pub struct ElectrumRpc {
     block_hash: BlockHash,
     addresses: HashMap<BtcAddressAddendum, (Option<Hex32Bytes>, TxFilter)>,
     ping_count: u8,
+    latency: RpcLatency,
 }
 
 #[derive(Debug)]
@@ -91,10 +97,13 @@ impl ElectrumRpc {
     fn new(
         electrum_server: &str,
         proxy_address: Option<String>,
+        latency: RpcLatency,
     ) -> Result<Self, electrum_client::Error> {
         debug!("creating ElectrumRpc client");
         let client = create_electrum_client(electrum_server, proxy_address)?;
+        let start = Instant::now();
         let header = client.block_headers_subscribe()?;
+        latency.observe(start.elapsed());
         debug!("New ElectrumRpc at height {}", header.height);
 
         Ok(Self {
@@ -103,12 +112,21 @@ impl ElectrumRpc {
             height: header.height as u64,
             block_hash: header.header.block_hash(),
             ping_count: 0,
+            latency,
         })
     }
 
+    /// Run a call against the electrum server and record its latency
+    fn timed<T>(&self, call: impl FnOnce(&Client) -> T) -> T {
+        let start = Instant::now();
+        let res = call(&self.client);
+        self.latency.observe(start.elapsed());
+        res
+    }
+
     fn ping(&mut self) -> Result<(), Error> {
         if self.ping_count % PING_WAIT == 0 {
-            self.client.ping()?;
+            self.timed(|client| client.ping())?;
             self.ping_count = 0;
         }
         self.ping_count += 1;
@@ -235,15 +253,14 @@ impl ElectrumRpc {
                 }
             };
             // Get the full transaction
-            match self.client.transaction_get(tx_id) {
+            match self.timed(|client| client.transaction_get(tx_id)) {
                 Ok(tx) => {
                     debug!("Updated tx: {}", tx_id);
                     // Look for history of the first output (maybe last is generally less likely
                     // to be used multiple times, so more efficient?!). If the history call
                     // fails or the transaction is not found in the history it is treated as unconfirmed.
                     let height = match self
-                        .client
-                        .script_get_history(&tx.output[0].script_pubkey)
+                        .timed(|client| client.script_get_history(&tx.output[0].script_pubkey))
                         .map_err(SyncerError::Electrum)
                         .and_then(|mut history| {
                             history
@@ -279,7 +296,9 @@ impl ElectrumRpc {
                         1.. => {
                             // SAFETY: safe cast as it strictly greater than 0
                             let confirm_height = height as usize;
-                            let block = match self.client.block_header(confirm_height) {
+                            let block_header =
+                                self.timed(|client| client.block_header(confirm_height));
+                            let block = match block_header {
                                 Ok(block) => block,
                                 Err(err) => {
                                     debug!(
@@ -305,7 +324,8 @@ impl ElectrumRpc {
                         }
                     };
 
-                    let current_block_height = match self.client.block_headers_subscribe() {
+                    let top_header = self.timed(|client| client.block_headers_subscribe());
+                    let current_block_height = match top_header {
                         // SAFETY: safe cast u64 from usize
                         Ok(block) => block.height as u64,
                         Err(err) => {
@@ -471,7 +491,8 @@ fn sweep_address(
     dest_address: bitcoin::Address,
     client: &Client,
     network: bitcoin::Network,
-) -> Result<Vec<Txid>, Error> {
+    fee_policy: &FeePolicy,
+) -> Result<(Vec<Txid>, Option<FeeChoice>), Error> {
     match source_address.address_type() {
         Some(bitcoin::AddressType::P2wpkh) => {}
         Some(address_type) => {
@@ -493,7 +514,7 @@ fn sweep_address(
             "No sweepable outputs detected for address: {}",
             source_address
         );
-        return Ok(vec![]);
+        return Ok((vec![], None));
     }
 
     let in_amount = unspent_txs
@@ -522,14 +543,16 @@ fn sweep_address(
         }],
     };
 
-    // TODO (maybe): make blocks_until_confirmation or fee_btc_per_kvb configurable by user (see FeeStrategy)
-    let blocks_until_confirmation = 2;
-    let fee_sat_per_kvb = (client
-        // because near == far (target) low and high fee are equal
-        .estimate_priority_fee(blocks_until_confirmation, blocks_until_confirmation)?
-        .high_fee
-        * 1.0e8)
-        .ceil() as u64;
+    let fee_choice = choose_fee(
+        fee_policy,
+        fee_policy.sweep_target,
+        client.estimate_target_fee(fee_policy.sweep_target)?,
+    );
+    debug!(
+        "sweeping address {} with fee {}",
+        source_address, fee_choice
+    );
+    let fee_sat_per_kvb = fee_choice.sats_per_kvbyte;
     let fee = p2wpkh_signed_tx_fee(fee_sat_per_kvb, unsigned_tx.vsize(), unspent_txs.len());
 
     // 546 is the dust limit for a p2pkh output. This covers both cases for when
@@ -539,7 +562,7 @@ fn sweep_address(
             "Amount is too close to being dust for address: {}, with total in amount {} and total fee {} ({} satoshi/kvb)",
             source_address, in_amount, fee, fee_sat_per_kvb,
         );
-        return Ok(vec![]);
+        return Ok((vec![], None));
     }
     unsigned_tx.output[0].value = in_amount - fee;
     let mut psbt = bitcoin::util::psbt::PartiallySignedTransaction::from_unsigned_tx(unsigned_tx)
@@ -574,7 +597,7 @@ fn sweep_address(
     let tx_hash =
         client.transaction_broadcast_raw(&bitcoin::consensus::serialize(&finalized_signed_tx))?;
 
-    Ok(vec![tx_hash.into()])
+    Ok((vec![tx_hash.into()], Some(fee_choice)))
 }
 
 async fn run_syncerd_bridge_event_sender(
@@ -608,6 +631,7 @@ async fn run_syncerd_task_receiver(
     transaction_get_tx: TokioSender<(GetTx, ServiceId)>,
     balance_get_tx: TokioSender<BalanceServiceIdPair>,
     terminate_tx: TokioSender<()>,
+    rpc_latency: RpcLatency,
 ) {
     tokio::spawn(async move {
         loop {
@@ -714,15 +738,18 @@ async fn run_syncerd_task_receiver(
                         }
                         Task::HealthCheck(HealthCheck { id }) => {
                             debug!("performing health check");
-                            let health =
-                                match ElectrumRpc::new(&electrum_server, proxy_address.clone())
-                                    .and_then(|client| {
-                                        client.client.ping()?;
-                                        Ok(())
-                                    }) {
-                                    Err(err) => Health::FaultyElectrum(err.to_string()),
-                                    Ok(_) => Health::Healthy,
-                                };
+                            let health = match ElectrumRpc::new(
+                                &electrum_server,
+                                proxy_address.clone(),
+                                rpc_latency.clone(),
+                            )
+                            .and_then(|client| {
+                                client.timed(|client| client.ping())?;
+                                Ok(())
+                            }) {
+                                Err(err) => Health::FaultyElectrum(err.to_string()),
+                                Ok(_) => Health::Healthy,
+                            };
                             let mut state_guard = state.lock().await;
                             state_guard
                                 .health_result(id, health, syncerd_task.source)
@@ -748,10 +775,15 @@ fn address_polling(
     state: Arc<Mutex<SyncerState>>,
     electrum_server: String,
     proxy_address: Option<String>,
+    rpc_latency: RpcLatency,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn(async move {
         loop {
-            let mut rpc = match ElectrumRpc::new(&electrum_server, proxy_address.clone()) {
+            let mut rpc = match ElectrumRpc::new(
+                &electrum_server,
+                proxy_address.clone(),
+                rpc_latency.clone(),
+            ) {
                 Ok(client) => client,
                 Err(err) => {
                     error!(
@@ -834,11 +866,16 @@ fn height_polling(
     electrum_server: String,
     proxy_address: Option<String>,
     transaction_broadcast_tx: TokioSender<(BroadcastTransaction, ServiceId)>,
+    rpc_latency: RpcLatency,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn(async move {
         // outer loop ensures the polling restarts if there is an error
         loop {
-            let mut rpc = match ElectrumRpc::new(&electrum_server, proxy_address.clone()) {
+            let mut rpc = match ElectrumRpc::new(
+                &electrum_server,
+                proxy_address.clone(),
+                rpc_latency.clone(),
+            ) {
                 Ok(client) => client,
                 Err(err) => {
                     error!(
@@ -924,11 +961,16 @@ fn unseen_transaction_polling(
     state: Arc<Mutex<SyncerState>>,
     electrum_server: String,
     proxy_address: Option<String>,
+    rpc_latency: RpcLatency,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn(async move {
         // outer loop ensures the polling restarts if there is an error
         loop {
-            let rpc = match ElectrumRpc::new(&electrum_server, proxy_address.clone()) {
+            let rpc = match ElectrumRpc::new(
+                &electrum_server,
+                proxy_address.clone(),
+                rpc_latency.clone(),
+            ) {
                 Ok(client) => client,
                 Err(err) => {
                     error!(
@@ -1004,43 +1046,50 @@ fn transaction_broadcasting(
     })
 }
 
-/// Result of querying electrum to get a low priority and high priority fee rate.
-struct FeeByPriority {
-    low_fee: f64,
-    high_fee: f64,
-}
-
-/// Extend electrum client capabilities and query fee for low and high priority.
+/// Extend electrum client capabilities and query fee for a confirmation target.
 trait GenericEstimateFee {
-    /// Query electrum for estimate fee for low and high priority or fall back on node's relay fee.
-    fn estimate_priority_fee(
+    /// Query electrum for the fee rate in sats per kvB for the confirmation target or fall back
+    /// on the mempool fee histogram, returns None if none is available. The rate is never below
+    /// the relay fee of the server.
+    fn estimate_target_fee(
         &self,
-        near_target: usize,
-        far_target: usize,
-    ) -> Result<FeeByPriority, electrum_client::Error>;
+        target: u16,
+    ) -> Result<Option<(u64, FeeSource)>, electrum_client::Error>;
 }
 
 impl GenericEstimateFee for Client {
-    fn estimate_priority_fee(
+    fn estimate_target_fee(
         &self,
-        near_target: usize,
-        far_target: usize,
-    ) -> Result<FeeByPriority, electrum_client::Error> {
-        let low_fee;
-        let mut high_fee = self.estimate_fee(near_target)?;
-        if high_fee == -1.0 {
-            // None returned internally between node and electrum, fallback on relay_fee
-            high_fee = self.relay_fee()?;
-            low_fee = high_fee;
-        } else {
-            // Shortcut in case we want only 1 fee and near == far
-            if far_target != near_target {
-                low_fee = self.estimate_fee(far_target)?;
-            } else {
-                low_fee = high_fee
-            }
+        target: u16,
+    ) -> Result<Option<(u64, FeeSource)>, electrum_client::Error> {
+        let relay_fee = (self.relay_fee()? * 1.0e8).ceil() as u64;
+        let fee = self.estimate_fee(target as usize)?;
+        if fee > 0.0 {
+            return Ok(Some(floor_at_relay_fee(
+                ((fee * 1.0e8).ceil() as u64, FeeSource::Estimate),
+                relay_fee,
+            )));
         }
-        Ok(FeeByPriority { low_fee, high_fee })
+        // -1 is returned when the node does not have enough data, fallback on the histogram
+        let histogram = self.raw_call("mempool.get_fee_histogram", vec![])?;
+        let histogram: Option<Vec<(f64, u64)>> = histogram.as_array().and_then(|entries| {
+            entries
+                .iter()
+                .map(|entry| {
+                    let entry = entry.as_array()?;
+                    Some((entry.get(0)?.as_f64()?, entry.get(1)?.as_u64()?))
+                })
+                .collect()
+        });
+        Ok(histogram.map(|histogram| {
+            floor_at_relay_fee(
+                (
+                    (histogram_fee_rate(&histogram, target) * 1000.0).ceil() as u64,
+                    FeeSource::Histogram,
+                ),
+                relay_fee,
+            )
+        }))
     }
 }
 
@@ -1048,32 +1097,53 @@ fn estimate_fee_polling(
     electrum_server: String,
     proxy_address: Option<String>,
     state: Arc<Mutex<SyncerState>>,
+    fee_policy: FeePolicy,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn(async move {
-        let high_priority_target = 2;
-        let low_priority_target = 6;
         loop {
             debug!("creating fee polling electrum client");
-            if let Ok(client) = create_electrum_client(&electrum_server, proxy_address.clone()) {
-                loop {
-                    match client.estimate_priority_fee(high_priority_target, low_priority_target) {
-                        Ok(FeeByPriority { low_fee, high_fee }) => {
-                            let mut state_guard = state.lock().await;
+            let client = create_electrum_client(&electrum_server, proxy_address.clone());
+            loop {
+                let estimates = client
+                    .as_ref()
+                    .map_err(|err| err.to_string())
+                    .and_then(|client| {
+                        let estimate = |target| {
+                            client
+                                .estimate_target_fee(target)
+                                .map_err(|err| err.to_string())
+                        };
+                        Ok((
+                            estimate(fee_policy.lock_target)?,
+                            estimate(fee_policy.sweep_target)?,
+                        ))
+                    });
+                let mut state_guard = state.lock().await;
+                match estimates {
+                    Ok((lock, sweep)) => {
+                        state_guard
+                            .fee_estimated(FeeEstimations::BitcoinFeeEstimation {
+                                lock: choose_fee(&fee_policy, fee_policy.lock_target, lock),
+                                sweep: choose_fee(&fee_policy, fee_policy.sweep_target, sweep),
+                            })
+                            .await;
+                    }
+                    Err(err) => {
+                        error!("Failed to retrieve fee estimation: {}", err);
+                        // keep the last estimation if any, otherwise use the static fallback
+                        if state_guard.fee_estimation.is_none() {
                             state_guard
                                 .fee_estimated(FeeEstimations::BitcoinFeeEstimation {
-                                    high_priority_sats_per_kvbyte: (high_fee * 1.0e8).ceil() as u64,
-                                    low_priority_sats_per_kvbyte: (low_fee * 1.0e8).ceil() as u64,
+                                    lock: choose_fee(&fee_policy, fee_policy.lock_target, None),
+                                    sweep: choose_fee(&fee_policy, fee_policy.sweep_target, None),
                                 })
                                 .await;
-                            drop(state_guard);
-                        }
-                        Err(err) => {
-                            error!("Failed to retrieve fee estimation: {}", err);
-                            break;
                         }
+                        break;
                     }
-                    tokio::time::sleep(std::time::Duration::from_secs(20)).await;
                 }
+                drop(state_guard);
+                tokio::time::sleep(std::time::Duration::from_secs(20)).await;
             }
             tokio::time::sleep(std::time::Duration::from_secs(20)).await;
         }
@@ -1085,6 +1155,7 @@ fn sweep_polling(
     electrum_server: String,
     proxy_address: Option<String>,
     network: bitcoin::Network,
+    fee_policy: FeePolicy,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn(async move {
         loop {
@@ -1105,16 +1176,17 @@ fn sweep_polling(
                             if let SweepAddressAddendum::Bitcoin(addendum) =
                                 sweep_address_task.addendum.clone()
                             {
-                                let sweep_address_txids = sweep_address(
+                                let (sweep_address_txids, fee) = sweep_address(
                                     addendum.source_secret_key,
                                     addendum.source_address,
                                     addendum.destination_address,
                                     &client,
                                     network,
+                                    &fee_policy,
                                 )
                                 .unwrap_or_else(|err| {
                                     warn!("error polling sweep address {}, retrying", err);
-                                    vec![]
+                                    (vec![], None)
                                 });
                                 debug!(
                                     "sweep address transaction: {:?}",
@@ -1122,7 +1194,9 @@ fn sweep_polling(
                                 );
                                 let mut state_guard = state.lock().await;
                                 if !sweep_address_txids.is_empty() {
-                                    state_guard.success_sweep(id, sweep_address_txids).await;
+                                    state_guard
+                                        .success_sweep(id, sweep_address_txids, fee)
+                                        .await;
                                 } else if !sweep_address_task.retry {
                                     state_guard.fail_sweep(id).await;
                                 }
@@ -1208,6 +1282,7 @@ fn balance_fetcher(
                                 address: Address::Monero(address),
                                 id: get_balance.id,
                                 balance: 0,
+                                used: false,
                                 err: Some(
                                     "Sent monero address balance to bitcoin syncer".to_string(),
                                 ),
@@ -1226,16 +1301,20 @@ fn balance_fetcher(
 
             match create_electrum_client(&electrum_server, proxy_address.clone()).and_then(
                 |transaction_client| {
-                    transaction_client.script_get_balance(&address.script_pubkey())
+                    let script = address.script_pubkey();
+                    let balance = transaction_client.script_get_balance(&script)?;
+                    let history = transaction_client.script_get_history(&script)?;
+                    Ok((balance, !history.is_empty()))
                 },
             ) {
-                Ok(balance) => {
+                Ok((balance, used)) => {
                     tx_event
                         .send(BridgeEvent {
                             event: Event::AddressBalance(AddressBalance {
                                 id: get_balance.id,
                                 address: Address::Bitcoin(address.clone()),
                                 balance: balance.unconfirmed.unsigned_abs() + balance.confirmed,
+                                used,
                                 err: None,
                             }),
                             source,
@@ -1255,6 +1334,7 @@ fn balance_fetcher(
                                 id: get_balance.id,
                                 address: Address::Bitcoin(address.clone()),
                                 balance: 0,
+                                used: false,
                                 err: Some(e.to_string()),
                             }),
                             source,
@@ -1286,8 +1366,10 @@ impl Synclet for BitcoinSyncer {
         syncer_address: Vec<u8>,
         opts: &Opts,
         network: Network,
+        rpc_latency: RpcLatency,
     ) -> Result<(), Error> {
         let btc_network = network.into();
+        let fee_policy = opts.fee_policy()?;
         let proxy_address = opts.shared.tor_proxy.map(|address| address.to_string());
         debug!("bitcoin synclet using proxy: {:?}", proxy_address);
 
@@ -1335,6 +1417,7 @@ impl Synclet for BitcoinSyncer {
                         transaction_get_tx,
                         balance_get_tx,
                         terminate_tx,
+                        rpc_latency.clone(),
                     )
                     .await;
                     run_syncerd_bridge_event_sender(tx, event_rx, syncer_address).await;
@@ -1343,6 +1426,7 @@ impl Synclet for BitcoinSyncer {
                         Arc::clone(&state),
                         electrum_server.clone(),
                         proxy_address.clone(),
+                        rpc_latency.clone(),
                     );
 
                     let height_handle = height_polling(
@@ -1350,12 +1434,14 @@ impl Synclet for BitcoinSyncer {
                         electrum_server.clone(),
                         proxy_address.clone(),
                         transaction_broadcast_tx,
+                        rpc_latency.clone(),
                     );
 
                     let unseen_transaction_handle = unseen_transaction_polling(
                         Arc::clone(&state),
                         electrum_server.clone(),
                         proxy_address.clone(),
+                        rpc_latency,
                     );
 
                     let transaction_broadcast_handle = transaction_broadcasting(
@@ -1383,6 +1469,7 @@ impl Synclet for BitcoinSyncer {
                         electrum_server.clone(),
                         proxy_address.clone(),
                         Arc::clone(&state),
+                        fee_policy,
                     );
 
                     let sweep_handle = sweep_polling(
@@ -1390,6 +1477,7 @@ impl Synclet for BitcoinSyncer {
                         electrum_server.clone(),
                         proxy_address.clone(),
                         btc_network,
+                        fee_policy,
                     );
 
                     let terminate_handle = terminate_polling(terminate_rx);
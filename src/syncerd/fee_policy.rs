@@ -0,0 +1,114 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use crate::config::FeePolicy;
+use crate::syncerd::types::{FeeBound, FeeChoice, FeeSource};
+
+/// Maximum number of virtual bytes in a block
+const BLOCK_VSIZE: u64 = 1_000_000;
+
+/// Choose the fee rate for the confirmation target given the estimated rate in sats per kvB, if
+/// any. Without estimation the fallback rate of the policy is used, the chosen rate is always
+/// within the policy bounds.
+pub fn choose_fee(
+    policy: &FeePolicy,
+    target: u16,
+    estimate: Option<(u64, FeeSource)>,
+) -> FeeChoice {
+    let (sats_per_kvbyte, source) =
+        estimate.unwrap_or((policy.fallback_fee_rate * 1000, FeeSource::Fallback));
+    let min = policy.min_fee_rate * 1000;
+    let max = policy.max_fee_rate * 1000;
+    let (sats_per_kvbyte, clamped) = if sats_per_kvbyte < min {
+        (min, Some(FeeBound::Min))
+    } else if sats_per_kvbyte > max {
+        (max, Some(FeeBound::Max))
+    } else {
+        (sats_per_kvbyte, None)
+    };
+    FeeChoice {
+        sats_per_kvbyte,
+        target: Some(target),
+        source,
+        clamped,
+    }
+}
+
+/// Raise an estimated fee rate in sats per kvB to the relay fee of the server, transactions paying
+/// less are not relayed.
+pub fn floor_at_relay_fee(estimate: (u64, FeeSource), relay_fee: u64) -> (u64, FeeSource) {
+    if estimate.0 < relay_fee {
+        (relay_fee, FeeSource::RelayFee)
+    } else {
+        estimate
+    }
+}
+
+/// Returns the fee rate in sat/vB required to be mined within the target given a mempool fee
+/// histogram, i.e. a list of fee rates in sat/vB and the virtual size of the transactions paying
+/// them, sorted by decreasing fee rate. Zero is returned if the whole mempool is mined within the
+/// target, the rate must then be raised to the relay fee.
+pub fn histogram_fee_rate(histogram: &[(f64, u64)], target: u16) -> f64 {
+    let budget = BLOCK_VSIZE * target as u64;
+    let mut vsize = 0;
+    for (fee_rate, size) in histogram {
+        vsize += size;
+        if vsize >= budget {
+            return *fee_rate;
+        }
+    }
+    // the whole mempool is mined within the target, any fee rate is enough
+    0.0
+}
+
+#[test]
+fn fee_choice_from_policy() {
+    let policy = FeePolicy {
+        lock_target: 2,
+        sweep_target: 6,
+        min_fee_rate: 2,
+        max_fee_rate: 100,
+        fallback_fee_rate: 20,
+    };
+    let fee = choose_fee(&policy, 2, Some((15_000, FeeSource::Estimate)));
+    assert_eq!(fee.sats_per_kvbyte, 15_000);
+    assert_eq!(fee.clamped, None);
+    let fee = choose_fee(&policy, 2, Some((1_000, FeeSource::Estimate)));
+    assert_eq!(fee.sats_per_kvbyte, 2_000);
+    assert_eq!(fee.clamped, Some(FeeBound::Min));
+    let fee = choose_fee(&policy, 2, Some((250_000, FeeSource::Histogram)));
+    assert_eq!(fee.sats_per_kvbyte, 100_000);
+    assert_eq!(fee.clamped, Some(FeeBound::Max));
+    let fee = choose_fee(&policy, 6, None);
+    assert_eq!(fee.sats_per_kvbyte, 20_000);
+    assert_eq!(fee.source, FeeSource::Fallback);
+
+    let histogram = vec![
+        (50.0, 400_000),
+        (20.0, 800_000),
+        (10.0, 1_000_000),
+        (5.0, 500_000),
+    ];
+    assert_eq!(histogram_fee_rate(&histogram, 1), 20.0);
+    assert_eq!(histogram_fee_rate(&histogram, 2), 10.0);
+    assert_eq!(histogram_fee_rate(&histogram, 6), 0.0);
+
+    assert_eq!(
+        floor_at_relay_fee((0, FeeSource::Histogram), 1_000),
+        (1_000, FeeSource::RelayFee)
+    );
+    assert_eq!(
+        floor_at_relay_fee((10_000, FeeSource::Histogram), 1_000),
+        (10_000, FeeSource::Histogram)
+    );
+    let fee = choose_fee(
+        &policy,
+        2,
+        Some(floor_at_relay_fee((0, FeeSource::Histogram), 1_000)),
+    );
+    assert_eq!(fee.sats_per_kvbyte, 2_000);
+    assert_eq!(fee.source, FeeSource::RelayFee);
+}
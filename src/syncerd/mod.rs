@@ -5,6 +5,7 @@
 // https://opensource.org/licenses/MIT.
 
 pub mod bitcoin_syncer;
+pub mod fee_policy;
 pub mod monero_syncer;
 pub mod syncer_state;
 pub mod types;
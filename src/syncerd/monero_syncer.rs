@@ -8,6 +8,7 @@ use crate::bus::info::Address;
 use crate::bus::sync::{BridgeEvent, SyncMsg};
 use crate::bus::{AddressSecretKey, BusMsg};
 use crate::error::{Error, SyncerError};
+use crate::farcasterd::metrics::RpcLatency;
 use crate::service::LogStyle;
 use crate::syncerd::opts::Opts;
 use crate::syncerd::runtime::SyncerdTask;
@@ -36,6 +37,7 @@ use std::fs;
 use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, TryRecvError};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::mpsc::Receiver as TokioReceiver;
 use tokio::sync::mpsc::Sender as TokioSender;
 use tokio::sync::Mutex;
@@ -48,6 +50,7 @@ pub struct MoneroRpc {
     daemon_json_rpc: monero_rpc::DaemonJsonRpcClient,
     daemon_rpc: monero_rpc::DaemonRpcClient,
     block_hash: Vec<u8>,
+    latency: RpcLatency,
 }
 
 #[derive(Debug)]
@@ -79,34 +82,43 @@ fn create_rpc_client(rpc_url: String, proxy_url: Option<String>) -> monero_rpc::
 }
 
 impl MoneroRpc {
-    fn new(node_rpc_url: String, proxy_url: Option<String>) -> Self {
+    fn new(node_rpc_url: String, proxy_url: Option<String>, latency: RpcLatency) -> Self {
         MoneroRpc {
             daemon_json_rpc: create_rpc_client(node_rpc_url.clone(), proxy_url.clone()).daemon(),
             daemon_rpc: create_rpc_client(node_rpc_url, proxy_url).daemon_rpc(),
             height: 0,
             block_hash: vec![0],
+            latency,
         }
     }
 
     async fn get_height(&mut self) -> Result<u64, Error> {
-        let count: u64 = self.daemon_json_rpc.get_block_count().await?.into();
+        let start = Instant::now();
+        let count = self.daemon_json_rpc.get_block_count().await;
+        self.latency.observe(start.elapsed());
+        let count: u64 = count?.into();
         Ok(count - 1)
     }
 
     async fn get_block_hash(&mut self, height: u64) -> Result<Vec<u8>, Error> {
         let selector = GetBlockHeaderSelector::Height(height);
-        let header = self.daemon_json_rpc.get_block_header(selector).await?;
-        Ok(header.hash.0.to_vec())
+        let start = Instant::now();
+        let header = self.daemon_json_rpc.get_block_header(selector).await;
+        self.latency.observe(start.elapsed());
+        Ok(header?.hash.0.to_vec())
     }
 
     async fn get_transactions(
         &mut self,
         monero_txids: Vec<monero::Hash>,
     ) -> Result<Vec<Transaction>, Error> {
+        let start = Instant::now();
         let txs = self
             .daemon_rpc
             .get_transactions(monero_txids, Some(true), Some(true))
-            .await?;
+            .await;
+        self.latency.observe(start.elapsed());
+        let txs = txs?;
 
         let block_height = self.get_height().await?;
 
@@ -599,9 +611,10 @@ fn address_polling(
     syncer_servers: MoneroSyncerServers,
     wallet_mutex: Arc<Mutex<monero_rpc::WalletClient>>,
     proxy_address: Option<String>,
+    rpc_latency: RpcLatency,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn(async move {
-        let mut rpc = MoneroRpc::new(syncer_servers.monero_daemon, proxy_address);
+        let mut rpc = MoneroRpc::new(syncer_servers.monero_daemon, proxy_address, rpc_latency);
         loop {
             let state_guard = state.lock().await;
             let mut addresses = state_guard.addresses.clone();
@@ -698,9 +711,10 @@ fn height_polling(
     state: Arc<Mutex<SyncerState>>,
     syncer_servers: MoneroSyncerServers,
     proxy_address: Option<String>,
+    rpc_latency: RpcLatency,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn(async move {
-        let mut rpc = MoneroRpc::new(syncer_servers.monero_daemon, proxy_address);
+        let mut rpc = MoneroRpc::new(syncer_servers.monero_daemon, proxy_address, rpc_latency);
         loop {
             let block_notif = match rpc.check_block().await {
                 Ok(notif) => Some(notif),
@@ -790,7 +804,7 @@ fn sweep_polling(
                     });
                     let mut state_guard = state.lock().await;
                     if !sweep_address_txs.is_empty() {
-                        state_guard.success_sweep(id, sweep_address_txs).await;
+                        state_guard.success_sweep(id, sweep_address_txs, None).await;
                     } else if !sweep_address_task.retry {
                         state_guard.fail_sweep(id).await;
                     }
@@ -806,9 +820,10 @@ fn unseen_transaction_polling(
     state: Arc<Mutex<SyncerState>>,
     syncer_servers: MoneroSyncerServers,
     proxy_address: Option<String>,
+    rpc_latency: RpcLatency,
 ) -> tokio::task::JoinHandle<()> {
     tokio::task::spawn(async move {
-        let mut rpc = MoneroRpc::new(syncer_servers.monero_daemon, proxy_address);
+        let mut rpc = MoneroRpc::new(syncer_servers.monero_daemon, proxy_address, rpc_latency);
         loop {
             let state_guard = state.lock().await;
             let unseen_transactions = state_guard.unseen_transactions.clone();
@@ -957,6 +972,8 @@ fn balance_fetcher(
                                         id: get_balance.id,
                                         address: Address::Monero(address),
                                         balance: balance.as_pico(),
+                                        // spent outputs are not tracked for monero addresses
+                                        used: balance.as_pico() > 0,
                                         err: None,
                                     }),
                                     source,
@@ -971,6 +988,7 @@ fn balance_fetcher(
                                         id: get_balance.id,
                                         address: Address::Monero(address),
                                         balance: 0,
+                                        used: false,
                                         err: Some(e.to_string()),
                                     }),
                                     source,
@@ -988,6 +1006,7 @@ fn balance_fetcher(
                                 address: Address::Bitcoin(address),
                                 id: get_balance.id,
                                 balance: 0,
+                                used: false,
                                 err: Some(
                                     "Sent bitcoin address balance to monero syncer".to_string(),
                                 ),
@@ -1025,6 +1044,7 @@ impl Synclet for MoneroSyncer {
         syncer_address: Vec<u8>,
         opts: &Opts,
         network: Network,
+        rpc_latency: RpcLatency,
     ) -> Result<(), Error> {
         let network = network.into();
         if let Some(daemon) = &opts.monero_daemon {
@@ -1085,6 +1105,7 @@ impl Synclet for MoneroSyncer {
                             syncer_servers.clone(),
                             Arc::clone(&wallet_mutex),
                             proxy_address.clone(),
+                            rpc_latency.clone(),
                         );
 
                         // transaction polling is done in the same loop
@@ -1092,12 +1113,14 @@ impl Synclet for MoneroSyncer {
                             Arc::clone(&state),
                             syncer_servers.clone(),
                             proxy_address.clone(),
+                            rpc_latency.clone(),
                         );
 
                         let unseen_transaction_handle = unseen_transaction_polling(
                             Arc::clone(&state),
                             syncer_servers.clone(),
                             proxy_address.clone(),
+                            rpc_latency,
                         );
 
                         let sweep_handle = sweep_polling(
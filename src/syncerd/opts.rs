@@ -4,6 +4,8 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use crate::config::FeePolicy;
+use crate::Error;
 use farcaster_core::blockchain::{Blockchain, Network};
 use std::str::FromStr;
 
@@ -53,10 +55,44 @@ pub struct Opts {
     /// Wallet directory use by the monero-wallet-rpc
     #[clap(long)]
     pub monero_wallet_dir_path: Option<String>,
+
+    /// Confirmation target in blocks of the Bitcoin lock transaction fee
+    #[clap(long)]
+    pub lock_fee_target: Option<u16>,
+
+    /// Confirmation target in blocks of the Bitcoin sweep transactions fee
+    #[clap(long)]
+    pub sweep_fee_target: Option<u16>,
+
+    /// Minimum Bitcoin fee rate in sat/vB
+    #[clap(long)]
+    pub min_fee_rate: Option<u64>,
+
+    /// Maximum Bitcoin fee rate in sat/vB
+    #[clap(long)]
+    pub max_fee_rate: Option<u64>,
+
+    /// Bitcoin fee rate in sat/vB used when no fee can be estimated
+    #[clap(long)]
+    pub fallback_fee_rate: Option<u64>,
 }
 
 impl Opts {
     pub fn process(&mut self) {
         self.shared.process();
     }
+
+    /// Returns the fee policy with the default values for the unset arguments
+    pub fn fee_policy(&self) -> Result<FeePolicy, Error> {
+        let default = FeePolicy::default();
+        let policy = FeePolicy {
+            lock_target: self.lock_fee_target.unwrap_or(default.lock_target),
+            sweep_target: self.sweep_fee_target.unwrap_or(default.sweep_target),
+            min_fee_rate: self.min_fee_rate.unwrap_or(default.min_fee_rate),
+            max_fee_rate: self.max_fee_rate.unwrap_or(default.max_fee_rate),
+            fallback_fee_rate: self.fallback_fee_rate.unwrap_or(default.fallback_fee_rate),
+        };
+        policy.validate()?;
+        Ok(policy)
+    }
 }
@@ -10,6 +10,8 @@ use crate::bus::{
     sync::SyncMsg,
     BusMsg, ServiceBus,
 };
+use crate::farcasterd::metrics::{RpcLatency, SyncerMetrics};
+use crate::service::exit_service;
 use crate::service::Endpoints;
 use crate::syncerd::bitcoin_syncer::BitcoinSyncer;
 use crate::syncerd::monero_syncer::MoneroSyncer;
@@ -36,6 +38,7 @@ pub trait Synclet {
         syncer_address: Vec<u8>,
         opts: &Opts,
         network: Network,
+        rpc_latency: RpcLatency,
     ) -> Result<(), Error>;
 }
 
@@ -58,10 +61,13 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
     info!("Creating new {} ({}) syncer", &blockchain, &network);
     let (tx, rx): (Sender<SyncerdTask>, Receiver<SyncerdTask>) = std::sync::mpsc::channel();
 
+    // one syncer per blockchain and network, the bridge must not collide with the other syncers
+    // running in the same process when the node is embedded
+    let bridge = format!("inproc://syncerdbridge-{}-{}", blockchain, network);
     let tx_event = ZMQ_CONTEXT.socket(zmq::PAIR)?;
     let rx_event = ZMQ_CONTEXT.socket(zmq::PAIR)?;
-    rx_event.bind("inproc://syncerdbridge")?;
-    tx_event.connect("inproc://syncerdbridge")?;
+    rx_event.bind(&bridge)?;
+    tx_event.connect(&bridge)?;
 
     let syncer: Box<dyn Synclet> = match blockchain {
         Blockchain::Monero => Box::new(MoneroSyncer::new()),
@@ -74,10 +80,17 @@ pub fn run(config: ServiceConfig, opts: Opts) -> Result<(), Error> {
         tasks: none!(),
         syncer,
         tx,
+        rpc_latency: RpcLatency::default(),
+        height: 0,
     };
-    runtime
-        .syncer
-        .run(rx, tx_event, runtime.identity().into(), &opts, network)?;
+    runtime.syncer.run(
+        rx,
+        tx_event,
+        runtime.identity().into(),
+        &opts,
+        network,
+        runtime.rpc_latency.clone(),
+    )?;
     let mut service = Service::service(config, runtime)?;
     service.add_bridge_service_bus(rx_event)?;
     service.run_loop()?;
@@ -90,6 +103,8 @@ pub struct Runtime {
     started: SystemTime,
     tasks: HashSet<SyncerdTask>,
     tx: Sender<SyncerdTask>,
+    rpc_latency: RpcLatency,
+    height: u64,
 }
 
 impl CtlServer for Runtime {}
@@ -151,7 +166,7 @@ impl Runtime {
             (CtlMsg::Terminate, ServiceId::Farcasterd) => {
                 // terminate all runtimes
                 info!("Received terminate on {}", self.identity());
-                std::process::exit(0);
+                exit_service(0);
             }
 
             (req, source) => {
@@ -243,6 +258,11 @@ impl Runtime {
         debug!("Syncerd BRIDGE RPC request: {}", request);
         match request {
             SyncMsg::BridgeEvent(syncerd_bridge_event) => {
+                if let Event::HeightChanged(HeightChanged { height, .. }) =
+                    syncerd_bridge_event.event
+                {
+                    self.report_metrics(endpoints, height)?;
+                }
                 endpoints.send_to(
                     ServiceBus::Sync,
                     self.identity(),
@@ -257,4 +277,23 @@ impl Runtime {
         }
         Ok(())
     }
+
+    /// Report the syncer metrics to farcasterd when a new block is seen
+    fn report_metrics(&mut self, endpoints: &mut Endpoints, height: u64) -> Result<(), Error> {
+        if height <= self.height {
+            return Ok(());
+        }
+        self.height = height;
+        endpoints.send_to(
+            ServiceBus::Ctl,
+            self.identity(),
+            ServiceId::Farcasterd,
+            BusMsg::Ctl(CtlMsg::SyncerMetrics(SyncerMetrics {
+                height,
+                tasks: self.tasks.len() as u64,
+                rpc_latency: self.rpc_latency.snapshot(),
+            })),
+        )?;
+        Ok(())
+    }
 }
@@ -624,7 +624,12 @@ impl SyncerState {
         send_event(&self.tx_event, &mut events).await;
     }
 
-    pub async fn success_sweep(&mut self, id: &InternalId, txids: Vec<Txid>) {
+    pub async fn success_sweep(
+        &mut self,
+        id: &InternalId,
+        txids: Vec<Txid>,
+        fee: Option<FeeChoice>,
+    ) {
         if let Some(sweep_address) = self.sweep_addresses.get(id) {
             send_event(
                 &self.tx_event,
@@ -632,6 +637,7 @@ impl SyncerState {
                     Event::SweepSuccess(SweepSuccess {
                         id: sweep_address.id,
                         txids,
+                        fee,
                     }),
                     self.tasks_sources
                         .get(id)
@@ -1158,7 +1164,11 @@ async fn syncer_state_sweep_addresses() {
     assert_eq!(state.tasks_sources.len(), 1);
     assert_eq!(state.sweep_addresses.len(), 1);
     state
-        .success_sweep(&InternalId(2), vec![monero::Hash::new(vec![0]).into()])
+        .success_sweep(
+            &InternalId(2),
+            vec![monero::Hash::new(vec![0]).into()],
+            None,
+        )
         .await;
     assert_eq!(state.lifetimes.len(), 0);
     assert_eq!(state.tasks_sources.len(), 0);
@@ -435,6 +435,8 @@ impl fmt::Display for TransactionBroadcasted {
 pub struct SweepSuccess {
     pub id: TaskId,
     pub txids: Vec<Txid>,
+    /// Fee chosen for a Bitcoin sweep, none for Monero
+    pub fee: Option<FeeChoice>,
 }
 
 impl fmt::Display for SweepSuccess {
@@ -468,14 +470,89 @@ pub struct FeeEstimation {
 
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
 #[display(Debug)]
-// the sats per kvB is because we need u64 for Eq, PartialEq and Hash
 pub enum FeeEstimations {
     BitcoinFeeEstimation {
-        high_priority_sats_per_kvbyte: u64,
-        low_priority_sats_per_kvbyte: u64,
+        /// Fee chosen for the lock transaction
+        lock: FeeChoice,
+        /// Fee chosen for the sweep transactions
+        sweep: FeeChoice,
     },
 }
 
+/// Where a fee rate comes from
+#[derive(Copy, Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum FeeSource {
+    /// Estimated by the server for the confirmation target
+    #[display("estimatefee")]
+    Estimate,
+    /// Computed from the server's mempool fee histogram
+    #[display("mempool fee histogram")]
+    Histogram,
+    /// Static fee rate of the fee policy
+    #[display("static fallback")]
+    Fallback,
+    /// Relay fee of the server, estimates below it are raised to it
+    #[display("relay fee floor")]
+    RelayFee,
+    /// Fee strategy of the deal, used by the arbitrating transactions at low priority
+    #[display("deal fee strategy")]
+    Deal,
+}
+
+/// Fee rate bound of the fee policy
+#[derive(Copy, Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum FeeBound {
+    #[display("minimum")]
+    Min,
+    #[display("maximum")]
+    Max,
+}
+
+/// A fee rate chosen by the fee policy and the reasons it was chosen. The rate is in sats per kvB
+/// because we need u64 for Eq, PartialEq and Hash
+#[derive(Clone, Debug, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct FeeChoice {
+    pub sats_per_kvbyte: u64,
+    /// Confirmation target in blocks, none if the rate follows the deal fee strategy
+    pub target: Option<u16>,
+    pub source: FeeSource,
+    /// Set if the rate was clamped to one of the fee policy bounds
+    pub clamped: Option<FeeBound>,
+}
+
+impl fmt::Display for FeeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} sat/vB ({}",
+            self.sats_per_kvbyte as f64 / 1000.0,
+            self.source
+        )?;
+        if let Some(target) = self.target {
+            write!(f, " for a {} blocks target", target)?;
+        }
+        if let Some(bound) = self.clamped {
+            write!(f, ", clamped to the {} fee rate", bound)?;
+        }
+        write!(f, ")")
+    }
+}
+
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Eq, PartialEq, Hash)]
 #[display(Debug)]
 pub struct HealthResult {
@@ -505,6 +582,8 @@ pub struct AddressBalance {
     pub id: TaskId,
     pub address: Address,
     pub balance: u64,
+    /// Whether the address ever received funds, even if spent since
+    pub used: bool,
     pub err: Option<String>,
 }
 
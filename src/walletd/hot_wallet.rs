@@ -0,0 +1,434 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use bitcoin::secp256k1::{Message, SECP256K1};
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey};
+use bitcoin::util::ecdsa::EcdsaSig;
+use bitcoin::{
+    Address, EcdsaSighashType, OutPoint, PrivateKey, PublicKey, Script, Transaction, TxIn, TxOut,
+    Txid, Witness,
+};
+use farcaster_core::bitcoin::segwitv0::signature_hash;
+use farcaster_core::bitcoin::transaction::TxInRef;
+
+use crate::syncerd::bitcoin_syncer::p2wpkh_signed_tx_fee;
+use crate::Error;
+
+/// Number of consecutive unused addresses derived, and watched, after the last used address of
+/// each keychain
+pub const GAP_LIMIT: u32 = 20;
+
+/// Dust limit of a p2pkh output, no output below this value is created
+const DUST_LIMIT: u64 = 546;
+
+/// Keychains of a BIP84 account: external for receiving, internal for change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keychain {
+    Receive,
+    Change,
+}
+
+impl Keychain {
+    fn index(&self) -> u32 {
+        match self {
+            Keychain::Receive => 0,
+            Keychain::Change => 1,
+        }
+    }
+}
+
+/// An unspent output of the hot wallet
+#[derive(Debug, Clone)]
+pub struct Utxo {
+    pub outpoint: OutPoint,
+    pub txout: TxOut,
+    pub keychain: Keychain,
+    pub index: u32,
+    pub confirmed: bool,
+}
+
+#[derive(Debug, Clone)]
+struct WalletTx {
+    tx: Transaction,
+    confirmed: bool,
+}
+
+/// BIP84 hot wallet derived from the wallet seed of walletd. Addresses are derived up to the gap
+/// limit after the last used one, the transactions paying or spending from them are reported by
+/// the Bitcoin syncer and nothing else than the seed needs to be persisted.
+pub struct HotWallet {
+    network: bitcoin::Network,
+    account: ExtendedPrivKey,
+    receive: Vec<Address>,
+    change: Vec<Address>,
+    scripts: HashMap<Script, (Keychain, u32)>,
+    used: HashSet<Script>,
+    // number of receive addresses handed out since start
+    revealed: u32,
+    // addresses derived but not yet returned by `take_new_addresses`
+    new_addresses: Vec<Address>,
+    transactions: HashMap<Txid, WalletTx>,
+}
+
+impl HotWallet {
+    pub fn new(seed: &[u8], network: bitcoin::Network) -> Result<Self, Error> {
+        let master = ExtendedPrivKey::new_master(network, seed)?;
+        let coin_type = if network == bitcoin::Network::Bitcoin {
+            0
+        } else {
+            1
+        };
+        let path = DerivationPath::from_str(&format!("m/84'/{}'/0'", coin_type))?;
+        let mut wallet = HotWallet {
+            network,
+            account: master.derive_priv(SECP256K1, &path)?,
+            receive: vec![],
+            change: vec![],
+            scripts: HashMap::new(),
+            used: HashSet::new(),
+            revealed: 0,
+            new_addresses: vec![],
+            transactions: HashMap::new(),
+        };
+        wallet.fill_gap()?;
+        Ok(wallet)
+    }
+
+    pub fn network(&self) -> bitcoin::Network {
+        self.network
+    }
+
+    /// Returns the addresses derived since the last call, they must be watched
+    pub fn take_new_addresses(&mut self) -> Vec<Address> {
+        self.new_addresses.drain(..).collect()
+    }
+
+    /// Returns all the derived addresses
+    pub fn addresses(&self) -> Vec<Address> {
+        self.receive
+            .iter()
+            .chain(self.change.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the transactions of the wallet not confirmed yet
+    pub fn unconfirmed_txids(&self) -> Vec<Txid> {
+        self.transactions
+            .iter()
+            .filter(|(_, wallet_tx)| !wallet_tx.confirmed)
+            .map(|(txid, _)| *txid)
+            .collect()
+    }
+
+    pub fn is_mine(&self, script: &Script) -> bool {
+        self.scripts.contains_key(script)
+    }
+
+    /// Returns a receive address not handed out since start and following the last used one
+    pub fn receive_address(&mut self) -> Result<Address, Error> {
+        let index = self.next_unused(Keychain::Receive).max(self.revealed);
+        while self.receive.len() as u32 <= index {
+            self.derive(Keychain::Receive)?;
+        }
+        self.revealed = index + 1;
+        Ok(self.receive[index as usize].clone())
+    }
+
+    fn change_address(&mut self) -> Result<Address, Error> {
+        let index = self.next_unused(Keychain::Change);
+        while self.change.len() as u32 <= index {
+            self.derive(Keychain::Change)?;
+        }
+        Ok(self.change[index as usize].clone())
+    }
+
+    /// Adds or updates a transaction paying to or spending from the wallet and derives the
+    /// addresses needed to keep the gap limit
+    pub fn add_transaction(&mut self, tx: Transaction, confirmed: bool) -> Result<(), Error> {
+        for output in tx.output.iter() {
+            if self.is_mine(&output.script_pubkey) {
+                self.used.insert(output.script_pubkey.clone());
+            }
+        }
+        let wallet_tx = self.transactions.entry(tx.txid()).or_insert(WalletTx {
+            tx,
+            confirmed: false,
+        });
+        wallet_tx.confirmed |= confirmed;
+        self.fill_gap()
+    }
+
+    pub fn set_confirmed(&mut self, txid: &Txid) {
+        if let Some(wallet_tx) = self.transactions.get_mut(txid) {
+            wallet_tx.confirmed = true;
+        }
+    }
+
+    /// Removes a transaction created by the wallet that failed to be broadcasted, its inputs are
+    /// spendable again
+    pub fn remove_transaction(&mut self, txid: &Txid) {
+        self.transactions.remove(txid);
+    }
+
+    pub fn utxos(&self) -> Vec<Utxo> {
+        let spent: HashSet<OutPoint> = self
+            .transactions
+            .values()
+            .flat_map(|wallet_tx| wallet_tx.tx.input.iter().map(|input| input.previous_output))
+            .collect();
+        let mut utxos = vec![];
+        for (txid, wallet_tx) in self.transactions.iter() {
+            for (vout, txout) in wallet_tx.tx.output.iter().enumerate() {
+                let outpoint = OutPoint::new(*txid, vout as u32);
+                if let Some((keychain, index)) = self.scripts.get(&txout.script_pubkey) {
+                    if !spent.contains(&outpoint) {
+                        utxos.push(Utxo {
+                            outpoint,
+                            txout: txout.clone(),
+                            keychain: *keychain,
+                            index: *index,
+                            confirmed: wallet_tx.confirmed,
+                        });
+                    }
+                }
+            }
+        }
+        utxos
+    }
+
+    /// Returns the confirmed and unconfirmed balances in satoshis
+    pub fn balance(&self) -> (u64, u64) {
+        self.utxos()
+            .iter()
+            .fold((0, 0), |(confirmed, unconfirmed), utxo| {
+                if utxo.confirmed {
+                    (confirmed + utxo.txout.value, unconfirmed)
+                } else {
+                    (confirmed, unconfirmed + utxo.txout.value)
+                }
+            })
+    }
+
+    /// Creates and signs a transaction paying the amount to the address. Inputs are selected
+    /// largest first, confirmed ones before unconfirmed ones, and the change returns to a change
+    /// address unless it is dust. The transaction is added to the wallet so its inputs are not
+    /// selected again.
+    pub fn create_transaction(
+        &mut self,
+        address: &Address,
+        amount: u64,
+        fee_sat_per_kvb: u64,
+    ) -> Result<Transaction, Error> {
+        if amount <= DUST_LIMIT {
+            return Err(Error::Farcaster(format!(
+                "Amount of {} sat is below the dust limit",
+                amount
+            )));
+        }
+        let change_address = self.change_address()?;
+        let mut utxos = self.utxos();
+        utxos.sort_by(|a, b| {
+            b.confirmed
+                .cmp(&a.confirmed)
+                .then(b.txout.value.cmp(&a.txout.value))
+        });
+        let available: u64 = utxos.iter().map(|utxo| utxo.txout.value).sum();
+
+        let mut utxos = utxos.into_iter();
+        let mut selected = vec![];
+        let mut in_amount = 0;
+        let mut tx = loop {
+            let mut tx = Transaction {
+                version: 2,
+                lock_time: 0,
+                input: selected
+                    .iter()
+                    .map(|utxo: &Utxo| TxIn {
+                        previous_output: utxo.outpoint,
+                        script_sig: Script::default(),
+                        // signal replaceability
+                        sequence: 0xFFFFFFFD,
+                        witness: Witness::new(),
+                    })
+                    .collect(),
+                output: vec![
+                    TxOut {
+                        value: amount,
+                        script_pubkey: address.script_pubkey(),
+                    },
+                    TxOut {
+                        value: 0,
+                        script_pubkey: change_address.script_pubkey(),
+                    },
+                ],
+            };
+            let fee = p2wpkh_signed_tx_fee(fee_sat_per_kvb, tx.vsize(), selected.len());
+            if !selected.is_empty() && in_amount >= amount + fee {
+                let change = in_amount - amount - fee;
+                if change > DUST_LIMIT {
+                    tx.output[1].value = change;
+                } else {
+                    // the dust change is left to the fee
+                    tx.output.pop();
+                }
+                break tx;
+            }
+            match utxos.next() {
+                Some(utxo) => {
+                    in_amount += utxo.txout.value;
+                    selected.push(utxo);
+                }
+                None => {
+                    return Err(Error::Farcaster(format!(
+                        "Insufficient funds in the hot wallet: {} sat available, {} sat and the fee required",
+                        available, amount
+                    )));
+                }
+            }
+        };
+
+        let mut witnesses = vec![];
+        for (index, utxo) in selected.iter().enumerate() {
+            let private_key = self.private_key(utxo.keychain, utxo.index)?;
+            let public_key = PublicKey::from_private_key(SECP256K1, &private_key);
+            let script_code = Script::new_p2pkh(&public_key.pubkey_hash());
+            let sighash = signature_hash(
+                TxInRef::new(&tx, index),
+                &script_code,
+                utxo.txout.value,
+                EcdsaSighashType::All,
+            );
+            let message = Message::from_slice(&sighash)?;
+            let signature =
+                EcdsaSig::sighash_all(SECP256K1.sign_ecdsa(&message, &private_key.inner));
+            witnesses.push(Witness::from_vec(vec![
+                signature.to_vec(),
+                public_key.to_bytes(),
+            ]));
+        }
+        for (input, witness) in tx.input.iter_mut().zip(witnesses) {
+            input.witness = witness;
+        }
+        self.add_transaction(tx.clone(), false)?;
+        Ok(tx)
+    }
+
+    fn keychain(&self, keychain: Keychain) -> &Vec<Address> {
+        match keychain {
+            Keychain::Receive => &self.receive,
+            Keychain::Change => &self.change,
+        }
+    }
+
+    fn private_key(&self, keychain: Keychain, index: u32) -> Result<PrivateKey, Error> {
+        let path = [
+            ChildNumber::from_normal_idx(keychain.index())?,
+            ChildNumber::from_normal_idx(index)?,
+        ];
+        Ok(self.account.derive_priv(SECP256K1, &path)?.to_priv())
+    }
+
+    fn derive(&mut self, keychain: Keychain) -> Result<Address, Error> {
+        let index = self.keychain(keychain).len() as u32;
+        let private_key = self.private_key(keychain, index)?;
+        let public_key = PublicKey::from_private_key(SECP256K1, &private_key);
+        let address = Address::p2wpkh(&public_key, self.network)?;
+        self.scripts
+            .insert(address.script_pubkey(), (keychain, index));
+        match keychain {
+            Keychain::Receive => self.receive.push(address.clone()),
+            Keychain::Change => self.change.push(address.clone()),
+        }
+        self.new_addresses.push(address.clone());
+        Ok(address)
+    }
+
+    /// Index following the last used address of the keychain
+    fn next_unused(&self, keychain: Keychain) -> u32 {
+        self.keychain(keychain)
+            .iter()
+            .rposition(|address| self.used.contains(&address.script_pubkey()))
+            .map_or(0, |index| index as u32 + 1)
+    }
+
+    fn fill_gap(&mut self) -> Result<(), Error> {
+        for keychain in [Keychain::Receive, Keychain::Change] {
+            while (self.keychain(keychain).len() as u32) < self.next_unused(keychain) + GAP_LIMIT {
+                self.derive(keychain)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn hot_wallet_bip84_derivation() {
+    // BIP84 test vector seed
+    let seed = hex::decode("5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4").unwrap();
+    let mut wallet = HotWallet::new(&seed, bitcoin::Network::Bitcoin).unwrap();
+    assert_eq!(wallet.take_new_addresses().len(), 2 * GAP_LIMIT as usize);
+    assert_eq!(
+        wallet.receive_address().unwrap().to_string(),
+        "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+    );
+    assert_eq!(
+        wallet.receive_address().unwrap().to_string(),
+        "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g"
+    );
+    assert_eq!(
+        wallet.change_address().unwrap().to_string(),
+        "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"
+    );
+}
+
+#[test]
+fn hot_wallet_send() {
+    let mut wallet = HotWallet::new(&[7u8; 32], bitcoin::Network::Regtest).unwrap();
+    let address = wallet.receive_address().unwrap();
+    wallet.take_new_addresses();
+    let funding = Transaction {
+        version: 2,
+        lock_time: 0,
+        input: vec![],
+        output: vec![TxOut {
+            value: 100_000,
+            script_pubkey: address.script_pubkey(),
+        }],
+    };
+    wallet.add_transaction(funding.clone(), true).unwrap();
+    assert_eq!(wallet.balance(), (100_000, 0));
+    // the first receive address is used, one more address keeps the gap
+    assert_eq!(wallet.take_new_addresses().len(), 1);
+
+    let destination = HotWallet::new(&[8u8; 32], bitcoin::Network::Regtest)
+        .unwrap()
+        .receive_address()
+        .unwrap();
+    assert!(wallet
+        .create_transaction(&destination, 200_000, 1_000)
+        .is_err());
+    let tx = wallet
+        .create_transaction(&destination, 30_000, 1_000)
+        .unwrap();
+    assert_eq!(tx.input.len(), 1);
+    assert_eq!(
+        tx.input[0].previous_output,
+        OutPoint::new(funding.txid(), 0)
+    );
+    assert_eq!(tx.input[0].witness.len(), 2);
+    assert_eq!(tx.output[0].value, 30_000);
+    assert!(wallet.is_mine(&tx.output[1].script_pubkey));
+    let fee = 100_000 - 30_000 - tx.output[1].value;
+    assert!(fee > 100 && fee < 200);
+    // only the change is left, unconfirmed
+    assert_eq!(wallet.balance(), (0, tx.output[1].value));
+    wallet.remove_transaction(&tx.txid());
+    assert_eq!(wallet.balance(), (100_000, 0));
+}
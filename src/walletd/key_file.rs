@@ -0,0 +1,127 @@
+// Copyright 2020-2022 Farcaster Devs & LNP/BP Standards Association
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Encryption at rest of the walletd key file. An encrypted key file is the magic bytes, the
+//! scrypt cost and salt used to derive the key from the passphrase, the nonce and the
+//! XChaCha20-Poly1305 ciphertext of the strict encoded node secrets.
+
+use std::fmt;
+
+use bitcoin::secp256k1::rand::{thread_rng, RngCore};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+
+use crate::Error;
+
+/// Prefix of encrypted key files, plaintext key files start with the key file path length
+const MAGIC: &[u8; 8] = b"FCKEYv01";
+
+/// scrypt cost parameter log2(N), 32 MiB of memory with r = 8
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+/// Key derived from the passphrase, kept to encrypt the key file again on every change without
+/// running scrypt each time
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyFileCipher {
+    log_n: u8,
+    salt: [u8; SALT_LEN],
+    key: [u8; 32],
+}
+
+impl fmt::Debug for KeyFileCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyFileCipher")
+            .field("log_n", &self.log_n)
+            .finish()
+    }
+}
+
+impl KeyFileCipher {
+    /// Derives a new key with a random salt from the passphrase
+    pub fn new(passphrase: &str) -> Result<Self, Error> {
+        if passphrase.is_empty() {
+            return Err(Error::Farcaster(
+                "The key file passphrase cannot be empty".to_string(),
+            ));
+        }
+        let mut salt = [0u8; SALT_LEN];
+        thread_rng().fill_bytes(&mut salt);
+        Self::derive(passphrase, SCRYPT_LOG_N, salt)
+    }
+
+    fn derive(passphrase: &str, log_n: u8, salt: [u8; SALT_LEN]) -> Result<Self, Error> {
+        let params = scrypt::Params::new(log_n, SCRYPT_R, SCRYPT_P)
+            .map_err(|err| Error::Farcaster(format!("Invalid key file scrypt cost: {}", err)))?;
+        let mut key = [0u8; 32];
+        scrypt::scrypt(passphrase.as_bytes(), &salt, &params, &mut key)
+            .expect("32 bytes is a valid scrypt output length");
+        Ok(KeyFileCipher { log_n, salt, key })
+    }
+
+    /// Whether the passphrase is the one this key was derived from
+    pub fn is_passphrase(&self, passphrase: &str) -> bool {
+        Self::derive(passphrase, self.log_n, self.salt)
+            .map(|cipher| cipher.key == self.key)
+            .unwrap_or(false)
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LEN];
+        thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&self.key))
+            .encrypt(XNonce::from_slice(&nonce), plaintext)
+            .expect("encryption of a key file does not fail");
+        let mut data = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        data.extend_from_slice(MAGIC);
+        data.push(self.log_n);
+        data.extend_from_slice(&self.salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        data
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Decrypts an encrypted key file, returning the plaintext and the key to encrypt it again
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<(Vec<u8>, KeyFileCipher), Error> {
+    if !is_encrypted(data) || data.len() < HEADER_LEN {
+        return Err(Error::Farcaster("Not an encrypted key file".to_string()));
+    }
+    let log_n = data[MAGIC.len()];
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(&data[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN]);
+    let nonce = XNonce::from_slice(&data[HEADER_LEN - NONCE_LEN..HEADER_LEN]);
+    let cipher = KeyFileCipher::derive(passphrase, log_n, salt)?;
+    let plaintext = XChaCha20Poly1305::new(Key::from_slice(&cipher.key))
+        .decrypt(nonce, &data[HEADER_LEN..])
+        .map_err(|_| {
+            Error::Farcaster("Unable to decrypt the key file, wrong passphrase".to_string())
+        })?;
+    Ok((plaintext, cipher))
+}
+
+#[test]
+fn key_file_encryption_roundtrip() {
+    let cipher = KeyFileCipher::new("correct horse battery staple").unwrap();
+    let data = cipher.encrypt(b"node secrets");
+    assert!(is_encrypted(&data));
+    assert!(decrypt(&data, "wrong passphrase").is_err());
+    let (plaintext, decrypted_cipher) = decrypt(&data, "correct horse battery staple").unwrap();
+    assert_eq!(plaintext, b"node secrets");
+    assert_eq!(decrypted_cipher, cipher);
+    assert!(cipher.is_passphrase("correct horse battery staple"));
+    assert!(!cipher.is_passphrase("wrong passphrase"));
+    assert!(KeyFileCipher::new("").is_err());
+}
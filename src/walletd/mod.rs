@@ -4,10 +4,16 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+pub mod hot_wallet;
+#[cfg(feature = "shell")]
+mod key_file;
 #[cfg(feature = "shell")]
 mod opts;
 pub mod runtime;
 
 #[cfg(feature = "shell")]
-pub use opts::{KeyOpts, NodeSecrets, Opts};
+pub use opts::{
+    read_mnemonic, KeyOpts, KeyPassphrase, NodeSecrets, Opts, FARCASTER_KEY_PASSPHRASE,
+    FARCASTER_MNEMONIC,
+};
 pub use runtime::run;
@@ -5,16 +5,37 @@
 // https://opensource.org/licenses/MIT.
 
 use crate::opts::FARCASTER_KEY_FILE;
+use crate::walletd::key_file::{self, KeyFileCipher};
+use crate::Error;
+use bip39::Mnemonic;
 use clap::ValueHint;
+use farcaster_core::blockchain::Network;
+use std::convert::{Infallible, TryInto};
+#[cfg(unix)]
+use std::os::unix::{
+    fs::{OpenOptionsExt, PermissionsExt},
+    io::FromRawFd,
+};
 use std::path::PathBuf;
-use std::{fs, io::Read};
+use std::str::FromStr;
+use std::{
+    env, fs,
+    io::{self, BufRead, Read, Write},
+};
 
 use crate::opts::TokenString;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
 use bitcoin::secp256k1::{
     rand::{rngs::ThreadRng, thread_rng},
     PublicKey, Secp256k1, SecretKey,
 };
-use strict_encoding::{StrictDecode, StrictEncode};
+use strict_encoding::{strict_deserialize, strict_serialize, StrictDecode, StrictEncode};
+
+/// Environment variable read for the mnemonic on restore, it is prompted on stdin otherwise
+pub const FARCASTER_MNEMONIC: &str = "FARCASTER_MNEMONIC";
+
+/// Environment variable read for the key file passphrase
+pub const FARCASTER_KEY_PASSPHRASE: &str = "FARCASTER_KEY_PASSPHRASE";
 
 /// Walletd daemon; part of Farcaster Node
 #[derive(Parser, Clone, PartialEq, Eq, Debug)]
@@ -28,6 +49,23 @@ pub struct Opts {
     #[clap(flatten)]
     pub wallet_token: TokenString,
 
+    /// Network of the Bitcoin hot wallet, no hot wallet is managed if absent
+    #[clap(long)]
+    pub hot_wallet_network: Option<Network>,
+
+    /// Create the key file from a 24 words mnemonic, read from the FARCASTER_MNEMONIC
+    /// environment variable or prompted on stdin. The key file must not exist yet. The funds
+    /// left on past funding addresses are recovered once the hot wallet syncer is up, no swap can
+    /// start until then. Without hot wallet, swaps start right away. New swap keys skip far
+    /// ahead of the ones used before in both cases.
+    #[clap(long)]
+    pub restore_from_mnemonic: bool,
+
+    /// Key file passphrase handed over by farcasterd on stdin, the key options are not read
+    /// if given
+    #[clap(long, hide = true)]
+    pub key_passphrase: Option<KeyPassphrase>,
+
     /// These params can be read also from the configuration file, not just
     /// command-line args or environment variables
     #[clap(flatten)]
@@ -39,6 +77,32 @@ impl Opts {
         self.shared.process();
         self.key_opts.process(&self.shared);
     }
+
+    /// Reads the key file passphrase, from farcasterd if it handed it over
+    pub fn passphrase(&self) -> Result<Option<String>, Error> {
+        match &self.key_passphrase {
+            Some(passphrase) => Ok(Some(passphrase.0.clone())),
+            None => self.key_opts.passphrase(),
+        }
+    }
+}
+
+/// Key file passphrase given on the command line, not shown in logs
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyPassphrase(pub String);
+
+impl FromStr for KeyPassphrase {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(KeyPassphrase(s.to_string()))
+    }
+}
+
+impl std::fmt::Debug for KeyPassphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KeyPassphrase(..)")
+    }
 }
 
 /// Node key configuration
@@ -46,8 +110,11 @@ impl Opts {
 pub struct KeyOpts {
     /// Node key file
     ///
-    /// Location for the file containing node private Secp256k1 key
-    /// (unencrypted)
+    /// Location for the file containing node private Secp256k1 key, encrypted with the
+    /// passphrase if one is given. The passphrase is read from the FARCASTER_KEY_PASSPHRASE
+    /// environment variable, the passphrase file descriptor or the terminal, in that order.
+    /// farcasterd reads it once and hands it over to walletd on stdin. An existing unencrypted
+    /// key file is encrypted once a passphrase is given.
     #[clap(
         short,
         long,
@@ -56,6 +123,14 @@ pub struct KeyOpts {
         value_hint = ValueHint::FilePath
     )]
     pub key_file: String,
+
+    /// File descriptor to read the key file passphrase from, up to the first newline
+    #[clap(long)]
+    pub passphrase_fd: Option<i32>,
+
+    /// Prompt for the key file passphrase on the terminal
+    #[clap(long)]
+    pub passphrase_prompt: bool,
 }
 
 #[derive(StrictEncode, StrictDecode, Clone, PartialEq, Eq, Debug)]
@@ -68,7 +143,7 @@ impl Counter {
 }
 
 /// Hold secret keys and seeds
-#[derive(StrictEncode, StrictDecode, Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct NodeSecrets {
     /// local key file
     pub key_file: String,
@@ -78,43 +153,207 @@ pub struct NodeSecrets {
     pub wallet_seed: [u8; 32],
     /// wallet last derivation index
     pub wallet_counter: Counter,
+    /// key file encryption, not stored in the key file
+    cipher: Option<KeyFileCipher>,
+}
+
+impl StrictEncode for NodeSecrets {
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, strict_encoding::Error> {
+        Ok(self.key_file.strict_encode(&mut e)?
+            + self.peerd_secret_key.strict_encode(&mut e)?
+            + self.wallet_seed.strict_encode(&mut e)?
+            + self.wallet_counter.strict_encode(&mut e)?)
+    }
+}
+
+impl StrictDecode for NodeSecrets {
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
+        Ok(NodeSecrets {
+            key_file: StrictDecode::strict_decode(&mut d)?,
+            peerd_secret_key: StrictDecode::strict_decode(&mut d)?,
+            wallet_seed: StrictDecode::strict_decode(&mut d)?,
+            wallet_counter: StrictDecode::strict_decode(&mut d)?,
+            cipher: None,
+        })
+    }
 }
 
 impl NodeSecrets {
-    pub fn new(key_file: String) -> Self {
+    pub fn new(key_file: String, passphrase: Option<String>) -> Result<Self, Error> {
         if PathBuf::from(key_file.clone()).exists() {
-            NodeSecrets::strict_decode(fs::File::open(key_file.clone()).unwrap_or_else(|_| {
-                panic!(
-                    "Unable to open key file {}; please check that the user \
-                    running the deamon has necessary permissions",
-                    key_file
-                )
-            }))
-            .expect("Unable to read node code file format")
+            let data = read_key_file(&key_file)?;
+            if key_file::is_encrypted(&data) {
+                let (plaintext, cipher) = unlock_key_file(&key_file, &data, passphrase.as_deref())?;
+                let mut node_secrets = Self::decode(&key_file, plaintext)?;
+                node_secrets.cipher = Some(cipher);
+                Ok(node_secrets)
+            } else {
+                let mut node_secrets = Self::decode(&key_file, data)?;
+                match passphrase {
+                    Some(passphrase) => {
+                        node_secrets.cipher = Some(KeyFileCipher::new(&passphrase)?);
+                        node_secrets.save();
+                        info!("Key file {} is now encrypted with the passphrase", key_file);
+                    }
+                    None => warn!(
+                        "Key file {} is not encrypted; please provide a passphrase to encrypt it",
+                        key_file
+                    ),
+                }
+                Ok(node_secrets)
+            }
         } else {
             let mut rng = thread_rng();
-            let peer_private_key = SecretKey::new(&mut rng);
             let wallet_seed = Self::create_seed(&mut rng);
             let node_secrets = Self {
-                key_file: key_file.clone(),
-                peerd_secret_key: peer_private_key,
+                key_file,
+                peerd_secret_key: Self::derive_peerd_secret_key(&wallet_seed),
                 wallet_seed,
                 wallet_counter: Counter(0),
+                cipher: passphrase
+                    .map(|passphrase| KeyFileCipher::new(&passphrase))
+                    .transpose()?,
             };
+            node_secrets.save();
+            Ok(node_secrets)
+        }
+    }
+
+    fn decode(key_file: &str, data: Vec<u8>) -> Result<Self, Error> {
+        strict_deserialize::<NodeSecrets>(data).map_err(|err| {
+            Error::Farcaster(format!(
+                "Unable to read the format of key file {}: {}",
+                key_file, err
+            ))
+        })
+    }
+
+    /// Creates the key file from a mnemonic. The file is marked as restored until walletd
+    /// recovered the wallet counter, see [`NodeSecrets::complete_restore`].
+    pub fn restore(
+        key_file: String,
+        mnemonic: &Mnemonic,
+        passphrase: Option<String>,
+    ) -> Result<Self, Error> {
+        if PathBuf::from(key_file.clone()).exists() {
+            return Err(Error::Farcaster(format!(
+                "Key file {} already exists, move it away to restore from a mnemonic",
+                key_file
+            )));
+        }
+        let wallet_seed: [u8; 32] = mnemonic.to_entropy().try_into().map_err(|_| {
+            Error::Farcaster("The mnemonic must have 24 words to restore from".to_string())
+        })?;
+        let node_secrets = Self {
+            peerd_secret_key: Self::derive_peerd_secret_key(&wallet_seed),
+            key_file,
+            wallet_seed,
+            wallet_counter: Counter(0),
+            cipher: passphrase
+                .map(|passphrase| KeyFileCipher::new(&passphrase))
+                .transpose()?,
+        };
+        node_secrets.save();
+        fs::File::create(node_secrets.restore_file())?;
+        Ok(node_secrets)
+    }
+
+    /// Whether the key file was restored and the wallet counter is not recovered yet
+    pub fn restore_pending(&self) -> bool {
+        PathBuf::from(self.restore_file()).exists()
+    }
+
+    /// Recovers the wallet counter after a restore, swap keys are derived after the last used
+    /// index from now on
+    pub fn complete_restore(&mut self, last_wallet_index: u32) -> Result<(), Error> {
+        self.wallet_counter.0 = self.wallet_counter.0.max(last_wallet_index);
+        self.save();
+        fs::remove_file(self.restore_file())?;
+        Ok(())
+    }
+
+    /// The wallet seed as a 24 words mnemonic
+    pub fn mnemonic(&self) -> Mnemonic {
+        Mnemonic::from_entropy(&self.wallet_seed).expect("wallet_seed has 32 bytes")
+    }
+
+    /// Seed of the hot wallet, the BIP39 seed of the mnemonic without passphrase so that any
+    /// BIP84 wallet recovers the hot wallet funds
+    pub fn hot_wallet_seed(&self) -> [u8; 64] {
+        self.mnemonic().to_seed("")
+    }
+
+    /// Whether the node id is restored from the mnemonic, key files created before mnemonics
+    /// were introduced hold an unrelated peerd key
+    pub fn is_node_id_restorable(&self) -> bool {
+        self.peerd_secret_key == Self::derive_peerd_secret_key(&self.wallet_seed)
+    }
+
+    fn derive_peerd_secret_key(wallet_seed: &[u8; 32]) -> SecretKey {
+        let mut engine = sha256::Hash::engine();
+        engine.input(b"farcaster:peerd_secret_key");
+        engine.input(wallet_seed);
+        SecretKey::from_slice(&sha256::Hash::from_engine(engine)[..])
+            .expect("a hash is a valid secret key with overwhelming probability")
+    }
 
-            let key_file_handle = fs::File::create(&key_file).unwrap_or_else(|_| {
-                panic!(
-                    "Unable to create key file '{}'; please check that path exists",
-                    key_file
-                )
-            });
-            node_secrets
-                .strict_encode(key_file_handle)
-                .expect("Unable to save generated node secrets");
-            node_secrets
+    fn restore_file(&self) -> String {
+        format!("{}.restore", self.key_file)
+    }
+
+    /// Encrypts the key file with a new passphrase, the current one must be given if the key
+    /// file is already encrypted
+    pub fn change_passphrase(&mut self, current: Option<&str>, new: &str) -> Result<(), Error> {
+        self.check_passphrase(current)?;
+        self.cipher = Some(KeyFileCipher::new(new)?);
+        self.save();
+        Ok(())
+    }
+
+    /// Checks the passphrase the key file is encrypted with, any passphrase is accepted if the
+    /// key file is not encrypted
+    pub fn check_passphrase(&self, passphrase: Option<&str>) -> Result<(), Error> {
+        match (&self.cipher, passphrase) {
+            (Some(cipher), Some(passphrase)) if cipher.is_passphrase(passphrase) => Ok(()),
+            (None, _) => Ok(()),
+            _ => Err(Error::Farcaster(
+                "The key file passphrase is wrong".to_string(),
+            )),
         }
     }
 
+    fn save(&self) {
+        let plaintext = strict_serialize(self).expect("Memory-based encoding does not fail");
+        let data = match &self.cipher {
+            Some(cipher) => cipher.encrypt(&plaintext),
+            None => plaintext,
+        };
+        // write the new secrets next to the key file and rename them over it, so an interrupted
+        // save never leaves a truncated key file behind
+        let tmp_file = format!("{}.tmp", self.key_file);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut tmp_file_handle = options.open(&tmp_file).unwrap_or_else(|_| {
+            panic!(
+                "Unable to create key file '{}'; please check that path exists",
+                tmp_file
+            )
+        });
+        // the mode above only applies when the file is created, only the user running the
+        // daemon can read the key file
+        #[cfg(unix)]
+        tmp_file_handle
+            .set_permissions(fs::Permissions::from_mode(0o600))
+            .expect("Unable to restrict key file permissions");
+        tmp_file_handle
+            .write_all(&data)
+            .and_then(|_| tmp_file_handle.sync_all())
+            .expect("Unable to save node secrets");
+        fs::rename(&tmp_file, &self.key_file).expect("Unable to save node secrets");
+    }
+
     pub fn node_id(&self) -> PublicKey {
         PublicKey::from_secret_key(&Secp256k1::new(), &self.peerd_secret_key)
     }
@@ -139,14 +378,7 @@ impl NodeSecrets {
 
     pub fn increment_wallet_counter(&mut self) -> u32 {
         self.wallet_counter.increment();
-        let key_file_handle = fs::File::create(&self.key_file).unwrap_or_else(|_| {
-            panic!(
-                "Unable to create key file '{}'; please check that path exists",
-                self.key_file
-            )
-        });
-        self.strict_encode(key_file_handle)
-            .expect("Unable to save incremented wallet counter");
+        self.save();
         self.wallet_counter.0
     }
 
@@ -159,4 +391,163 @@ impl KeyOpts {
     pub fn process(&mut self, shared: &crate::opts::Opts) {
         shared.process_dir(&mut self.key_file);
     }
+
+    /// Reads the key file passphrase, none if no source is given
+    pub fn passphrase(&self) -> Result<Option<String>, Error> {
+        if let Ok(passphrase) = env::var(FARCASTER_KEY_PASSPHRASE) {
+            return Ok(Some(passphrase));
+        }
+        if let Some(fd) = self.passphrase_fd {
+            return read_passphrase_fd(fd).map(Some);
+        }
+        if self.passphrase_prompt {
+            return Ok(Some(rpassword::prompt_password("Key file passphrase: ")?));
+        }
+        Ok(None)
+    }
+
+    /// Checks that the key file, if it exists, is unlocked by the passphrase
+    pub fn check_passphrase(&self, passphrase: Option<&str>) -> Result<(), Error> {
+        if PathBuf::from(&self.key_file).exists() {
+            let data = read_key_file(&self.key_file)?;
+            if key_file::is_encrypted(&data) {
+                unlock_key_file(&self.key_file, &data, passphrase)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_key_file(key_file: &str) -> Result<Vec<u8>, Error> {
+    fs::read(key_file).map_err(|err| {
+        Error::Farcaster(format!(
+            "Unable to open key file {}: {}; please check that the user running the daemon has \
+            necessary permissions",
+            key_file, err
+        ))
+    })
+}
+
+fn unlock_key_file(
+    key_file: &str,
+    data: &[u8],
+    passphrase: Option<&str>,
+) -> Result<(Vec<u8>, KeyFileCipher), Error> {
+    let passphrase = passphrase.ok_or_else(|| {
+        Error::Farcaster(format!(
+            "Key file {} is encrypted; please provide its passphrase",
+            key_file
+        ))
+    })?;
+    key_file::decrypt(data, passphrase)
+        .map_err(|err| Error::Farcaster(format!("Unable to unlock key file {}: {}", key_file, err)))
+}
+
+#[cfg(unix)]
+fn read_passphrase_fd(fd: i32) -> Result<String, Error> {
+    // the descriptor is handed over by the parent process, it is closed once read
+    let file = unsafe { fs::File::from_raw_fd(fd) };
+    let mut passphrase = String::new();
+    io::BufReader::new(file).read_line(&mut passphrase)?;
+    Ok(passphrase.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+#[cfg(not(unix))]
+fn read_passphrase_fd(_fd: i32) -> Result<String, Error> {
+    Err(Error::Farcaster(
+        "Reading the passphrase from a file descriptor is only supported on unix".to_string(),
+    ))
+}
+
+/// Reads the mnemonic to restore from, from [`FARCASTER_MNEMONIC`] if set or stdin otherwise
+pub fn read_mnemonic() -> Result<Mnemonic, Error> {
+    let phrase = match env::var(FARCASTER_MNEMONIC) {
+        Ok(phrase) => phrase,
+        Err(_) => {
+            eprint!("Mnemonic to restore the node from: ");
+            io::stderr().flush()?;
+            let mut phrase = String::new();
+            io::stdin().read_line(&mut phrase)?;
+            phrase
+        }
+    };
+    let words = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    Ok(Mnemonic::parse_normalized(&words)?)
+}
+
+#[test]
+fn node_secrets_mnemonic_roundtrip() {
+    let dir = env::temp_dir().join(format!("farcaster-mnemonic-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let key_file = dir.join("key").to_string_lossy().to_string();
+
+    let node_secrets = NodeSecrets::new(key_file.clone(), None).unwrap();
+    assert!(node_secrets.is_node_id_restorable());
+    assert!(!node_secrets.restore_pending());
+    let mnemonic = node_secrets.mnemonic();
+    assert_eq!(mnemonic.word_count(), 24);
+    assert!(NodeSecrets::restore(key_file.clone(), &mnemonic, None).is_err());
+
+    fs::remove_file(&key_file).unwrap();
+    let mut restored = NodeSecrets::restore(key_file.clone(), &mnemonic, None).unwrap();
+    assert_eq!(restored.wallet_seed, node_secrets.wallet_seed);
+    assert_eq!(restored.node_id(), node_secrets.node_id());
+    assert!(restored.restore_pending());
+    restored.complete_restore(3).unwrap();
+    assert!(!restored.restore_pending());
+    assert_eq!(
+        NodeSecrets::new(key_file, None).unwrap().wallet_counter,
+        Counter(3)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn node_secrets_encrypted_key_file() {
+    let dir = env::temp_dir().join(format!("farcaster-key-file-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let key_file = dir.join("key").to_string_lossy().to_string();
+
+    // a plaintext key file is migrated once a passphrase is given
+    let node_secrets = NodeSecrets::new(key_file.clone(), None).unwrap();
+    assert!(!key_file::is_encrypted(&fs::read(&key_file).unwrap()));
+    let mut migrated = NodeSecrets::new(key_file.clone(), Some("first".to_string())).unwrap();
+    assert!(key_file::is_encrypted(&fs::read(&key_file).unwrap()));
+    assert_eq!(migrated.wallet_seed, node_secrets.wallet_seed);
+
+    migrated.increment_wallet_counter();
+    assert!(migrated.change_passphrase(Some("wrong"), "second").is_err());
+    migrated.change_passphrase(Some("first"), "second").unwrap();
+    assert!(NodeSecrets::new(key_file.clone(), None).is_err());
+    assert!(NodeSecrets::new(key_file.clone(), Some("first".to_string())).is_err());
+    let unlocked = NodeSecrets::new(key_file, Some("second".to_string())).unwrap();
+    assert!(unlocked.check_passphrase(Some("second")).is_ok());
+    assert!(unlocked.check_passphrase(None).is_err());
+    assert_eq!(unlocked.wallet_seed, node_secrets.wallet_seed);
+    assert_eq!(unlocked.wallet_counter, Counter(1));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn node_secrets_save_restricts_key_file() {
+    let dir = env::temp_dir().join(format!("farcaster-key-save-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let key_file = dir.join("key").to_string_lossy().to_string();
+
+    // permissions loosened after creation are restricted again on the next save
+    let mut node_secrets = NodeSecrets::new(key_file.clone(), None).unwrap();
+    fs::set_permissions(&key_file, fs::Permissions::from_mode(0o644)).unwrap();
+    node_secrets.increment_wallet_counter();
+    let mode = fs::metadata(&key_file).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert!(!std::path::Path::new(&format!("{}.tmp", key_file)).exists());
+    assert_eq!(
+        NodeSecrets::new(key_file, None).unwrap().wallet_counter,
+        Counter(1)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
 }
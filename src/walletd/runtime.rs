@@ -4,27 +4,91 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::collections::HashMap;
+
 use crate::bus::{
-    ctl::{CtlMsg, GetKeys, Keys, SwapKeys, Token, WrappedKeyManager},
-    BusMsg, ServiceBus,
+    ctl::{
+        BitcoinFundingInfo, ChangeKeyPassphrase, CtlMsg, GetKeys, HotWalletSend, Keys, SwapKeys,
+        Token, WrappedKeyManager,
+    },
+    info::{ExportMnemonic, HotWalletBalance, InfoMsg, WalletMnemonic},
+    sync::SyncMsg,
+    AddressSecretKey, BitcoinSecretKeyInfo, BusMsg, Failure, FailureCode, OptionDetails,
+    ServiceBus,
 };
 
+use crate::service::exit_service;
 use crate::service::Endpoints;
+use crate::syncerd::{
+    AddressAddendum, AddressBalance, AddressTransaction, BroadcastTransaction, BtcAddressAddendum,
+    Event as SyncEvent, FeeChoice, FeeEstimation, FeeEstimations, GetAddressBalance, Task, TaskId,
+    TransactionBroadcasted, TransactionConfirmations, TxFilter, WatchAddress, WatchEstimateFee,
+    WatchTransaction,
+};
+use crate::walletd::hot_wallet::HotWallet;
 use crate::walletd::NodeSecrets;
 use crate::{CtlServer, Error, Service, ServiceConfig, ServiceId};
 
+use bitcoin::consensus::{deserialize, serialize};
+use farcaster_core::bitcoin::segwitv0::FundingTx;
+use farcaster_core::blockchain::Network;
+use farcaster_core::crypto::{ArbitratingKeyId, GenerateKey};
 use farcaster_core::swap::btcxmr::KeyManager;
+use farcaster_core::swap::SwapId;
+use farcaster_core::transaction::Fundable;
 use microservices::esb::{self, Handler};
 
+/// Number of consecutive unused swap key indices after which a restore scan stops, indices of
+/// swaps without a Bitcoin funding address look unused
+pub const RESTORE_GAP_LIMIT: u32 = 20;
+
+/// Number of swap key indices skipped after the last index found used on restore. Only Bob's
+/// funding addresses reveal an index, indices used as Alice after it cannot be found and must not
+/// be derived again. Indices cost nothing to skip, a `u32` leaves room for over 400k restores,
+/// and no node is expected to run ten thousand swaps as Alice in a row, so this bounds the key
+/// reuse risk well past any realistic usage
+pub const RESTORE_SKIP_AHEAD: u32 = 10_000;
+
 pub fn run(
     config: ServiceConfig,
     wallet_token: Token,
-    node_secrets: NodeSecrets,
+    mut node_secrets: NodeSecrets,
+    hot_wallet_network: Option<Network>,
 ) -> Result<(), Error> {
+    let hot_wallet = hot_wallet_network
+        .map(|network| HotWallet::new(&node_secrets.hot_wallet_seed(), network.into()))
+        .transpose()?;
+    let restore_scan = match &hot_wallet {
+        Some(hot_wallet) if node_secrets.restore_pending() => {
+            info!(
+                "Key file restored from mnemonic, recovering the swap keys on {}",
+                hot_wallet.network()
+            );
+            Some(RestoreScan::new(hot_wallet.network()))
+        }
+        None if node_secrets.restore_pending() => {
+            warn!(
+                "Key file restored from mnemonic but no hot wallet is enabled, the funding \
+                addresses of the previous swaps are not scanned, swap keys are derived after \
+                index {}",
+                RESTORE_SKIP_AHEAD
+            );
+            node_secrets.complete_restore(RESTORE_SKIP_AHEAD)?;
+            None
+        }
+        _ => None,
+    };
     let runtime = Runtime {
         identity: ServiceId::Wallet,
         wallet_token,
         node_secrets,
+        hot_wallet,
+        syncer: None,
+        syncer_task_counter: 0,
+        watched_txs: HashMap::new(),
+        fee_estimate: None,
+        pending_sends: HashMap::new(),
+        restore_scan,
     };
 
     Service::run(config, runtime, false)
@@ -34,6 +98,56 @@ pub struct Runtime {
     identity: ServiceId,
     wallet_token: Token,
     node_secrets: NodeSecrets,
+    hot_wallet: Option<HotWallet>,
+    // Bitcoin syncer of the hot wallet, set by farcasterd once up
+    syncer: Option<ServiceId>,
+    syncer_task_counter: u32,
+    watched_txs: HashMap<TaskId, bitcoin::Txid>,
+    fee_estimate: Option<FeeChoice>,
+    pending_sends: HashMap<TaskId, PendingSend>,
+    restore_scan: Option<RestoreScan>,
+}
+
+/// Recovery of the swap key indices used before a restore from mnemonic. Bob's funding address
+/// of every index is checked with the hot wallet syncer until a gap of unused indices.
+struct RestoreScan {
+    network: bitcoin::Network,
+    next_index: u32,
+    last_used: u32,
+    pending: HashMap<TaskId, u32>,
+}
+
+impl RestoreScan {
+    fn new(network: bitcoin::Network) -> Self {
+        RestoreScan {
+            network,
+            next_index: 1,
+            last_used: 0,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Indices to check next, each is pending until its result is received
+    fn next_indices(&mut self) -> Vec<u32> {
+        let indices: Vec<u32> = (self.next_index..=self.last_used + RESTORE_GAP_LIMIT).collect();
+        self.next_index = self.next_index.max(self.last_used + RESTORE_GAP_LIMIT + 1);
+        indices
+    }
+
+    fn is_complete(&self) -> bool {
+        self.pending.is_empty() && self.next_index > self.last_used + RESTORE_GAP_LIMIT
+    }
+}
+
+/// A hot wallet transaction being broadcasted and who to report the result to
+struct PendingSend {
+    txid: bitcoin::Txid,
+    origin: SendOrigin,
+}
+
+enum SendOrigin {
+    Client(ServiceId),
+    Funding(SwapId),
 }
 
 impl CtlServer for Runtime {}
@@ -56,6 +170,10 @@ impl esb::Handler<ServiceBus> for Runtime {
         match (bus, request) {
             // Control bus for issuing control commands, only accept Ctl message
             (ServiceBus::Ctl, BusMsg::Ctl(req)) => self.handle_ctl(endpoints, source, req),
+            // Info bus for client queries on the hot wallet
+            (ServiceBus::Info, BusMsg::Info(req)) => self.handle_info(endpoints, source, req),
+            // Sync bus for the events of the hot wallet syncer
+            (ServiceBus::Sync, BusMsg::Sync(req)) => self.handle_sync(endpoints, source, req),
             // All other pairs are not supported
             (bus, req) => Err(Error::NotSupported(bus, req.to_string())),
         }
@@ -81,15 +199,39 @@ impl Runtime {
                 debug!("Received Hello from {}", source);
             }
 
+            CtlMsg::Terminate if source == ServiceId::Farcasterd => {
+                info!("Terminating {}", self.identity());
+                exit_service(0);
+            }
+
             CtlMsg::CreateSwapKeys(deal, wallet_token) => {
                 if wallet_token != self.wallet_token {
                     return Err(Error::InvalidToken);
                 }
+                if self.node_secrets.restore_pending() {
+                    // deriving keys of an index used before the restore would reuse them
+                    return Err(Error::Farcaster(
+                        "Swap keys cannot be created before the restore scan completes".to_string(),
+                    ));
+                }
                 let wallet_index = self.node_secrets.increment_wallet_counter();
                 let key_manager = KeyManager::new(self.node_secrets.wallet_seed, wallet_index)?;
+                // the funds swept by the swap return to the hot wallet if on the deal's network
+                let sweep_bitcoin_address = match self.hot_wallet.as_mut() {
+                    Some(hot_wallet)
+                        if hot_wallet.network()
+                            == bitcoin::Network::from(deal.parameters.network) =>
+                    {
+                        let address = hot_wallet.receive_address()?;
+                        self.watch_new_addresses(endpoints)?;
+                        Some(address)
+                    }
+                    _ => None,
+                };
                 let swap_keys = SwapKeys {
                     key_manager: WrappedKeyManager(key_manager),
                     deal,
+                    sweep_bitcoin_address,
                 };
                 endpoints.send_to(
                     ServiceBus::Ctl,
@@ -115,6 +257,101 @@ impl Runtime {
                 )?;
             }
 
+            CtlMsg::HotWalletSyncer(syncer) if source == ServiceId::Farcasterd => {
+                if let Some(hot_wallet) = self.hot_wallet.as_mut() {
+                    info!("Hot wallet syncer {} is up, watching the wallet", syncer);
+                    self.syncer = Some(syncer);
+                    // the syncer might have been relaunched, subscribe to everything again
+                    let addresses = hot_wallet.addresses();
+                    let txids = hot_wallet.unconfirmed_txids();
+                    hot_wallet.take_new_addresses();
+                    self.watched_txs.clear();
+                    let id = self.next_task_id();
+                    self.send_task(
+                        endpoints,
+                        Task::WatchEstimateFee(WatchEstimateFee {
+                            id,
+                            lifetime: u64::MAX,
+                        }),
+                    )?;
+                    for address in addresses {
+                        self.watch_address(endpoints, address)?;
+                    }
+                    for txid in txids {
+                        self.watch_transaction(endpoints, txid)?;
+                    }
+                }
+                if let Some(scan) = self.restore_scan.as_mut() {
+                    // results of the previous syncer are lost, check its pending indices again
+                    if let Some(index) = scan.pending.values().min() {
+                        scan.next_index = *index;
+                    }
+                    scan.pending.clear();
+                    self.continue_restore_scan(endpoints)?;
+                }
+            }
+
+            CtlMsg::FundFromHotWallet(BitcoinFundingInfo {
+                swap_id,
+                address,
+                amount,
+            }) if source == ServiceId::Farcasterd => {
+                info!(
+                    "{} | Funding {} with {} from the hot wallet",
+                    swap_id, address, amount
+                );
+                if let Err(err) = self.send_from_hot_wallet(
+                    endpoints,
+                    &address,
+                    amount,
+                    SendOrigin::Funding(swap_id),
+                ) {
+                    error!("{} | Hot wallet funding failed: {}", swap_id, err);
+                    self.send_ctl(
+                        endpoints,
+                        ServiceId::Farcasterd,
+                        BusMsg::Ctl(CtlMsg::HotWalletFundingFailed(swap_id, err.to_string())),
+                    )?;
+                }
+            }
+
+            CtlMsg::HotWalletSend(HotWalletSend { address, amount }) => {
+                if let Err(err) = self.send_from_hot_wallet(
+                    endpoints,
+                    &address,
+                    amount,
+                    SendOrigin::Client(source.clone()),
+                ) {
+                    self.send_client_ctl(
+                        endpoints,
+                        source,
+                        CtlMsg::Failure(Failure {
+                            code: FailureCode::Unknown,
+                            info: err.to_string(),
+                        }),
+                    )?;
+                }
+            }
+
+            CtlMsg::ChangeKeyPassphrase(ChangeKeyPassphrase { current, new }) => {
+                let reply = match self
+                    .node_secrets
+                    .change_passphrase(current.as_deref(), &new)
+                {
+                    Ok(()) => {
+                        info!("Key file encrypted with the new passphrase");
+                        CtlMsg::Success(OptionDetails::with(
+                            "Key file encrypted with the new passphrase, use it on next start",
+                        ))
+                    }
+                    Err(err) => CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: err.to_string(),
+                    }),
+                };
+                self.send_client_ctl(endpoints, source, reply)?;
+            }
+
             req => {
                 error!(
                     "BusMsg {} is not supported by the CTL interface",
@@ -125,4 +362,416 @@ impl Runtime {
 
         Ok(())
     }
+
+    fn handle_info(
+        &mut self,
+        endpoints: &mut Endpoints,
+        source: ServiceId,
+        request: InfoMsg,
+    ) -> Result<(), Error> {
+        if let InfoMsg::ExportMnemonic(ExportMnemonic { passphrase }) = request {
+            if let Err(err) = self.node_secrets.check_passphrase(passphrase.as_deref()) {
+                return self.send_client_ctl(
+                    endpoints,
+                    source,
+                    CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: err.to_string(),
+                    }),
+                );
+            }
+            let mnemonic = WalletMnemonic {
+                mnemonic: self.node_secrets.mnemonic().to_string(),
+                node_id_restorable: self.node_secrets.is_node_id_restorable(),
+            };
+            return self.send_client_info(endpoints, source, InfoMsg::WalletMnemonic(mnemonic));
+        }
+        let hot_wallet = match self.hot_wallet.as_mut() {
+            Some(hot_wallet) => hot_wallet,
+            None => {
+                return self.send_client_ctl(
+                    endpoints,
+                    source,
+                    CtlMsg::Failure(Failure {
+                        code: FailureCode::Unknown,
+                        info: "The hot wallet is not enabled".to_string(),
+                    }),
+                );
+            }
+        };
+        match request {
+            InfoMsg::GetHotWalletBalance => {
+                let (confirmed, unconfirmed) = hot_wallet.balance();
+                let balance = HotWalletBalance {
+                    network: hot_wallet.network().into(),
+                    confirmed: bitcoin::Amount::from_sat(confirmed),
+                    unconfirmed: bitcoin::Amount::from_sat(unconfirmed),
+                    utxos: hot_wallet.utxos().len() as u32,
+                };
+                self.send_client_info(endpoints, source, InfoMsg::HotWalletBalance(balance))?;
+            }
+
+            InfoMsg::GetHotWalletAddress => {
+                let address = hot_wallet.receive_address()?;
+                self.watch_new_addresses(endpoints)?;
+                self.send_client_info(endpoints, source, InfoMsg::HotWalletAddress(address))?;
+            }
+
+            req => {
+                error!(
+                    "BusMsg {} is not supported by the INFO interface",
+                    req.to_string()
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_sync(
+        &mut self,
+        endpoints: &mut Endpoints,
+        _source: ServiceId,
+        request: SyncMsg,
+    ) -> Result<(), Error> {
+        let event = match request {
+            SyncMsg::Event(event) => event,
+            req => {
+                error!(
+                    "BusMsg {} is not supported by the SYNC interface",
+                    req.to_string()
+                );
+                return Ok(());
+            }
+        };
+        if let SyncEvent::AddressBalance(address_balance) = event {
+            return self.handle_restore_scan(endpoints, address_balance);
+        }
+        let hot_wallet = match self.hot_wallet.as_mut() {
+            Some(hot_wallet) => hot_wallet,
+            None => return Ok(()),
+        };
+        match event {
+            SyncEvent::AddressTransaction(AddressTransaction { tx, .. }) => {
+                let tx: bitcoin::Transaction =
+                    deserialize(&tx.iter().flatten().copied().collect::<Vec<u8>>())?;
+                let txid = tx.txid();
+                debug!("Hot wallet transaction {} seen", txid);
+                hot_wallet.add_transaction(tx, false)?;
+                if hot_wallet.unconfirmed_txids().contains(&txid) {
+                    self.watch_transaction(endpoints, txid)?;
+                }
+                self.watch_new_addresses(endpoints)?;
+            }
+
+            SyncEvent::TransactionConfirmations(TransactionConfirmations {
+                id,
+                confirmations: Some(confirmations),
+                ..
+            }) if confirmations > 0 => {
+                if let Some(txid) = self.watched_txs.remove(&id) {
+                    debug!("Hot wallet transaction {} confirmed", txid);
+                    hot_wallet.set_confirmed(&txid);
+                }
+            }
+
+            SyncEvent::FeeEstimation(FeeEstimation {
+                fee_estimations: FeeEstimations::BitcoinFeeEstimation { lock, .. },
+                ..
+            }) => {
+                self.fee_estimate = Some(lock);
+            }
+
+            SyncEvent::TransactionBroadcasted(TransactionBroadcasted { id, error, .. }) => {
+                if let Some(PendingSend { txid, origin }) = self.pending_sends.remove(&id) {
+                    match (error, origin) {
+                        (None, SendOrigin::Client(client)) => {
+                            info!("Hot wallet transaction {} broadcasted", txid);
+                            self.watch_transaction(endpoints, txid)?;
+                            self.send_client_ctl(
+                                endpoints,
+                                client,
+                                CtlMsg::Success(OptionDetails::with(format!(
+                                    "Transaction {} broadcasted",
+                                    txid
+                                ))),
+                            )?;
+                        }
+                        (None, SendOrigin::Funding(swap_id)) => {
+                            info!(
+                                "{} | Funded from the hot wallet with txid: {}",
+                                swap_id, txid
+                            );
+                            self.watch_transaction(endpoints, txid)?;
+                        }
+                        (Some(err), origin) => {
+                            hot_wallet.remove_transaction(&txid);
+                            let info = format!("Failed to broadcast transaction {}: {}", txid, err);
+                            error!("{}", info);
+                            match origin {
+                                SendOrigin::Client(client) => self.send_client_ctl(
+                                    endpoints,
+                                    client,
+                                    CtlMsg::Failure(Failure {
+                                        code: FailureCode::Unknown,
+                                        info,
+                                    }),
+                                )?,
+                                SendOrigin::Funding(swap_id) => self.send_ctl(
+                                    endpoints,
+                                    ServiceId::Farcasterd,
+                                    BusMsg::Ctl(CtlMsg::HotWalletFundingFailed(swap_id, info)),
+                                )?,
+                            }
+                        }
+                    }
+                }
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Creates, signs and broadcasts a transaction from the hot wallet, the result is reported to
+    /// the origin once broadcasted
+    fn send_from_hot_wallet(
+        &mut self,
+        endpoints: &mut Endpoints,
+        address: &bitcoin::Address,
+        amount: bitcoin::Amount,
+        origin: SendOrigin,
+    ) -> Result<(), Error> {
+        let hot_wallet = self
+            .hot_wallet
+            .as_mut()
+            .ok_or_else(|| Error::Farcaster("The hot wallet is not enabled".to_string()))?;
+        if address.network != hot_wallet.network() {
+            return Err(Error::Farcaster(format!(
+                "Address {} is not on the hot wallet network {}",
+                address,
+                hot_wallet.network()
+            )));
+        }
+        let fee = self.fee_estimate.as_ref().ok_or_else(|| {
+            Error::Farcaster("No fee estimation received yet from the syncer".to_string())
+        })?;
+        if self.syncer.is_none() {
+            return Err(Error::Farcaster(
+                "The hot wallet syncer is not up yet".to_string(),
+            ));
+        }
+        let tx = hot_wallet.create_transaction(address, amount.as_sat(), fee.sats_per_kvbyte)?;
+        debug!(
+            "Hot wallet transaction {} created with fee {}",
+            tx.txid(),
+            fee
+        );
+        let id = self.next_task_id();
+        self.pending_sends.insert(
+            id,
+            PendingSend {
+                txid: tx.txid(),
+                origin,
+            },
+        );
+        self.watch_new_addresses(endpoints)?;
+        self.send_task(
+            endpoints,
+            Task::BroadcastTransaction(BroadcastTransaction {
+                id,
+                tx: serialize(&tx),
+                broadcast_after_height: None,
+            }),
+        )
+    }
+
+    fn handle_restore_scan(
+        &mut self,
+        endpoints: &mut Endpoints,
+        AddressBalance {
+            id,
+            address,
+            balance,
+            used,
+            err,
+        }: AddressBalance,
+    ) -> Result<(), Error> {
+        let (index, network) = match self.restore_scan.as_mut() {
+            Some(scan) => match scan.pending.remove(&id) {
+                Some(index) => (index, scan.network),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+        if let Some(err) = err {
+            // the key file stays marked as restored, the scan starts over on next launch
+            error!(
+                "Restore scan failed on swap key index {}: {}, restart the node to retry",
+                index, err
+            );
+            self.restore_scan = None;
+            return Ok(());
+        }
+        if used {
+            debug!(
+                "Swap key index {} used by funding address {}",
+                index, address
+            );
+            if let Some(scan) = self.restore_scan.as_mut() {
+                scan.last_used = scan.last_used.max(index);
+            }
+        }
+        if balance > 0 {
+            info!(
+                "Found {} on funding address {} of swap key index {}, it can be swept with \
+                swap-cli sweep-bitcoin-address",
+                bitcoin::Amount::from_sat(balance),
+                address,
+                index
+            );
+            let address_secret_key = self.funding_address_secret_key(index, network)?;
+            self.send_ctl(
+                endpoints,
+                ServiceId::Database,
+                BusMsg::Ctl(CtlMsg::SetAddressSecretKey(address_secret_key)),
+            )?;
+        }
+        self.continue_restore_scan(endpoints)
+    }
+
+    /// Checks the next swap key indices of the restore scan, or completes it once a gap of
+    /// unused indices is reached
+    fn continue_restore_scan(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        if self.syncer.is_none() {
+            // indices are only marked pending once their task can be sent, the scan continues
+            // when the syncer is up
+            return Ok(());
+        }
+        let (indices, network) = match self.restore_scan.as_mut() {
+            Some(scan) if scan.is_complete() => {
+                let last_used = scan.last_used;
+                self.restore_scan = None;
+                let wallet_index = last_used + RESTORE_SKIP_AHEAD;
+                self.node_secrets.complete_restore(wallet_index)?;
+                info!(
+                    "Restore scan completed, last funding address used by swap key index {}, swap \
+                    keys are derived after index {}",
+                    last_used, wallet_index
+                );
+                return Ok(());
+            }
+            Some(scan) => (scan.next_indices(), scan.network),
+            None => return Ok(()),
+        };
+        for index in indices {
+            let address_secret_key = self.funding_address_secret_key(index, network)?;
+            let id = self.next_task_id();
+            if let Some(scan) = self.restore_scan.as_mut() {
+                scan.pending.insert(id, index);
+            }
+            self.send_task(
+                endpoints,
+                Task::GetAddressBalance(GetAddressBalance {
+                    id,
+                    address_secret_key,
+                }),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Bob's funding address of a swap key index and its secret key, as created by swapd
+    fn funding_address_secret_key(
+        &self,
+        index: u32,
+        network: bitcoin::Network,
+    ) -> Result<AddressSecretKey, Error> {
+        let mut key_manager = KeyManager::new(self.node_secrets.wallet_seed, index)?;
+        let funding_tx = FundingTx::initialize(
+            key_manager.get_pubkey(ArbitratingKeyId::Lock)?,
+            network.into(),
+        )?;
+        Ok(AddressSecretKey::Bitcoin {
+            address: funding_tx.get_address()?,
+            secret_key_info: BitcoinSecretKeyInfo {
+                swap_id: None,
+                secret_key: key_manager.get_or_derive_bitcoin_key(ArbitratingKeyId::Lock)?,
+            },
+        })
+    }
+
+    fn next_task_id(&mut self) -> TaskId {
+        self.syncer_task_counter += 1;
+        TaskId(self.syncer_task_counter)
+    }
+
+    fn send_task(&mut self, endpoints: &mut Endpoints, task: Task) -> Result<(), Error> {
+        if let Some(syncer) = self.syncer.clone() {
+            endpoints.send_to(
+                ServiceBus::Sync,
+                self.identity(),
+                syncer,
+                BusMsg::Sync(SyncMsg::Task(task)),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Watches the addresses the hot wallet derived since the last call
+    fn watch_new_addresses(&mut self, endpoints: &mut Endpoints) -> Result<(), Error> {
+        if self.syncer.is_none() {
+            // watched once the syncer is up
+            return Ok(());
+        }
+        let addresses = self
+            .hot_wallet
+            .as_mut()
+            .map(|hot_wallet| hot_wallet.take_new_addresses())
+            .unwrap_or_default();
+        for address in addresses {
+            self.watch_address(endpoints, address)?;
+        }
+        Ok(())
+    }
+
+    fn watch_address(
+        &mut self,
+        endpoints: &mut Endpoints,
+        address: bitcoin::Address,
+    ) -> Result<(), Error> {
+        let id = self.next_task_id();
+        self.send_task(
+            endpoints,
+            Task::WatchAddress(WatchAddress {
+                id,
+                lifetime: u64::MAX,
+                addendum: AddressAddendum::Bitcoin(BtcAddressAddendum { address }),
+                include_tx: true,
+                filter: TxFilter::All,
+            }),
+        )
+    }
+
+    fn watch_transaction(
+        &mut self,
+        endpoints: &mut Endpoints,
+        txid: bitcoin::Txid,
+    ) -> Result<(), Error> {
+        if self.watched_txs.values().any(|watched| *watched == txid) {
+            return Ok(());
+        }
+        let id = self.next_task_id();
+        self.watched_txs.insert(id, txid);
+        self.send_task(
+            endpoints,
+            Task::WatchTransaction(WatchTransaction {
+                id,
+                lifetime: u64::MAX,
+                hash: txid.into(),
+                confirmation_bound: 1,
+            }),
+        )
+    }
 }
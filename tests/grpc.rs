@@ -88,6 +88,7 @@ async fn grpc_server_functional_test() {
         maker_role: farcaster::SwapRole::Bob.into(),
         public_ip_addr: "127.0.0.1".to_string(),
         public_port: 7067,
+        swap_params: None,
     };
     let request = tonic::Request::new(make_request.clone());
     let response = farcaster_client_1.make(request).await;
@@ -140,6 +141,7 @@ async fn grpc_server_functional_test() {
         deal,
         bitcoin_address: btc_address.to_string(),
         monero_address: xmr_address.to_string(),
+        swap_params: None,
     };
     let request = tonic::Request::new(take_request.clone());
     let response = farcaster_client_2.take(request).await;
@@ -196,6 +198,7 @@ async fn grpc_server_functional_test() {
         deal,
         bitcoin_address: btc_address.to_string(),
         monero_address: xmr_address.to_string(),
+        swap_params: None,
     };
     let request = tonic::Request::new(take_request.clone());
     let response = farcaster_client_2.take(request).await;
@@ -671,6 +671,125 @@ async fn swap_alice_maker() {
     kill_all();
 }
 
+#[tokio::test]
+#[timeout(600000)]
+#[ignore]
+async fn swap_services_do_not_expose_secrets() {
+    setup_logging();
+    let bitcoin_rpc = Arc::new(bitcoin_setup());
+    let (_, monero_wallet) = monero_setup().await;
+
+    let (_, data_dir_maker, _, data_dir_taker) = launch_farcasterd_pair().await;
+
+    // spawns the listening and connecting peerds and both swapds
+    make_and_take_deal(
+        data_dir_maker.clone(),
+        data_dir_taker.clone(),
+        "Bob".to_string(),
+        Arc::clone(&bitcoin_rpc),
+        Arc::clone(&monero_wallet),
+        bitcoin::Amount::from_str("1 BTC").unwrap(),
+        monero::Amount::from_str_with_denomination("1 XMR").unwrap(),
+    )
+    .await;
+
+    let peerd_secret_keys: Vec<String> = [&data_dir_maker, &data_dir_taker]
+        .iter()
+        .map(|data_dir| {
+            let key_file = format!("{}/key.dat", data_dir[1]);
+            farcaster_node::walletd::NodeSecrets::new(key_file, None)
+                .unwrap()
+                .peerd_secret_key
+                .display_secret()
+                .to_string()
+        })
+        .collect();
+
+    let sys = System::new_all();
+    let services: Vec<&sysinfo::Process> = sys
+        .get_processes()
+        .values()
+        .filter(|process| {
+            [
+                "farcasterd",
+                "peerd",
+                "swapd",
+                "walletd",
+                "syncerd",
+                "databased",
+                "grpcd",
+            ]
+            .contains(&process.name())
+        })
+        .collect();
+    assert!(services.iter().any(|process| process.name() == "peerd"));
+    assert!(services.iter().any(|process| process.name() == "swapd"));
+    for process in services {
+        let exposed = process.cmd().join(" ") + " " + &process.environ().join(" ");
+        for secret_arg in ["--peer-secret-key", "--token", "--deal"] {
+            assert!(
+                !exposed.contains(secret_arg),
+                "{} exposes {} in its command line or environment",
+                process.name(),
+                secret_arg
+            );
+        }
+        for secret_key in peerd_secret_keys.iter() {
+            assert!(
+                !exposed.contains(secret_key.as_str()),
+                "{} exposes the node secret key in its command line or environment",
+                process.name()
+            );
+        }
+    }
+
+    kill_all();
+}
+
+#[tokio::test]
+#[timeout(600000)]
+#[ignore]
+async fn swap_drain_aborts_unfunded_swap() {
+    setup_logging();
+    let bitcoin_rpc = Arc::new(bitcoin_setup());
+    let (_, monero_wallet) = monero_setup().await;
+
+    let (mut farcasterd_maker, data_dir_maker, mut farcasterd_taker, data_dir_taker) =
+        launch_farcasterd_pair().await;
+
+    let (_xmr_dest_wallet_name, _bitcoin_address, swap_id) = make_and_take_deal(
+        data_dir_maker.clone(),
+        data_dir_taker.clone(),
+        "Bob".to_string(),
+        Arc::clone(&bitcoin_rpc),
+        Arc::clone(&monero_wallet),
+        bitcoin::Amount::from_str("1 BTC").unwrap(),
+        monero::Amount::from_str_with_denomination("1 XMR").unwrap(),
+    )
+    .await;
+
+    bitcoin_rpc
+        .generate_to_address(1, &reusable_btc_address())
+        .unwrap();
+
+    // run until bob has the btc funding address, but do not fund it
+    retry_until_bitcoin_funding_address(
+        swap_id,
+        needs_funding_args(data_dir_maker.clone(), "bitcoin".to_string()),
+    )
+    .await;
+
+    // neither side checkpointed the swap yet, both abort it and shut down
+    for data_dir in [data_dir_maker, data_dir_taker] {
+        let res = run("../swap-cli", shutdown_drain_args(data_dir)).unwrap();
+        info!("res: {:?}", res);
+    }
+    retry_until_exited(&mut farcasterd_maker).await;
+    retry_until_exited(&mut farcasterd_taker).await;
+
+    kill_all();
+}
+
 #[derive(Debug, Clone)]
 struct SwapParams {
     data_dir_bob: Vec<String>,
@@ -3052,6 +3171,13 @@ fn restore_checkpoint_args(data_dir: Vec<String>, swap_id: SwapId) -> Vec<String
         .collect()
 }
 
+fn shutdown_drain_args(data_dir: Vec<String>) -> Vec<String> {
+    data_dir
+        .into_iter()
+        .chain(vec!["shutdown".to_string(), "--drain".to_string()])
+        .collect()
+}
+
 fn cli_output_to_node_info(stdout: Vec<String>) -> NodeInfo {
     debug!("{:?}", stdout);
     serde_yaml::from_str(
@@ -3274,6 +3400,16 @@ async fn retry_until_funding_info_cleared(swap_id: SwapId, args: Vec<String>) {
     panic!("timeout before any bitcoin funding address could be retrieved");
 }
 
+async fn retry_until_exited(farcasterd: &mut process::Child) {
+    for _ in 0..ALLOWED_RETRIES {
+        if farcasterd.try_wait().unwrap().is_some() {
+            return;
+        }
+        tokio::time::sleep(time::Duration::from_secs(1)).await;
+    }
+    panic!("timeout before farcasterd exited");
+}
+
 async fn retry_until_monero_funding_address(
     swap_id: SwapId,
     args: Vec<String>,
@@ -173,17 +173,13 @@ pub fn fee_estimation_received(request: BusMsg) {
         BusMsg::Sync(SyncMsg::BridgeEvent(BridgeEvent {
             event:
                 Event::FeeEstimation(FeeEstimation {
-                    fee_estimations:
-                        FeeEstimations::BitcoinFeeEstimation {
-                            high_priority_sats_per_kvbyte,
-                            low_priority_sats_per_kvbyte,
-                        },
+                    fee_estimations: FeeEstimations::BitcoinFeeEstimation { lock, sweep },
                     ..
                 }),
             ..
         })) => {
-            assert!(high_priority_sats_per_kvbyte >= 1000);
-            assert!(low_priority_sats_per_kvbyte >= 1000);
+            assert!(lock.sats_per_kvbyte >= 1000);
+            assert!(sweep.sats_per_kvbyte >= 1000);
         }
         _ => {
             panic!("expected syncerd bridge event");